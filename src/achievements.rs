@@ -0,0 +1,283 @@
+//! Achievements for things done in local games, like taking cities early or beating the
+//! strongest bot. Which ones are unlocked is saved between runs.
+
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use serde_json;
+
+use generals::{Cell, GameEvent, GameState, Position, Team};
+
+/// The bot that `Achievement::BeatSearch` is for, which is the strongest one.
+const HARDEST_BOT: &str = "search";
+/// The number of cities to take, and the tick to take them by, for `EarlyCities`.
+const EARLY_CITIES: usize = 3;
+const EARLY_TICKS: usize = 50;
+/// The number of players a game needs for `Conqueror`.
+const CONQUEROR_PLAYERS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Achievement {
+    FirstWin,
+    EarlyCities,
+    /// Win without the enemy ever taking the cell our King started on.
+    KeptStart,
+    BeatSearch,
+    Conqueror,
+}
+
+/// Every achievement, in the order of the gallery.
+pub const ALL: [Achievement; 5] = [Achievement::FirstWin,
+                                   Achievement::EarlyCities,
+                                   Achievement::KeptStart,
+                                   Achievement::BeatSearch,
+                                   Achievement::Conqueror];
+
+impl Achievement {
+    /// The name it is saved under.
+    pub fn id(&self) -> &'static str {
+        match *self {
+            Achievement::FirstWin => "first_win",
+            Achievement::EarlyCities => "early_cities",
+            Achievement::KeptStart => "kept_start",
+            Achievement::BeatSearch => "beat_search",
+            Achievement::Conqueror => "conqueror",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match *self {
+            Achievement::FirstWin => "First blood",
+            Achievement::EarlyCities => "Land grab",
+            Achievement::KeptStart => "Home guard",
+            Achievement::BeatSearch => "Outthought",
+            Achievement::Conqueror => "Conqueror",
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match *self {
+            Achievement::FirstWin => "Win a game".to_string(),
+            Achievement::EarlyCities => {
+                format!("Capture {} cities before turn {}", EARLY_CITIES, EARLY_TICKS)
+            }
+            Achievement::KeptStart => "Win without losing your starting city".to_string(),
+            Achievement::BeatSearch => format!("Defeat the {} bot", HARDEST_BOT),
+            Achievement::Conqueror => {
+                format!("Win a free-for-all of {} or more players", CONQUEROR_PLAYERS)
+            }
+        }
+    }
+}
+
+/// The achievements unlocked so far.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Unlocked {
+    ids: BTreeSet<String>,
+}
+
+impl Unlocked {
+    /// Load the achievements saved at `path`. No file means none are unlocked.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match File::open(path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file)).map_err(io::Error::from),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Unlocked::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(file, self).map_err(io::Error::from)
+    }
+
+    pub fn has(&self, achievement: Achievement) -> bool {
+        self.ids.contains(achievement.id())
+    }
+
+    /// Unlock `achievement`. Returns whether it was locked before.
+    pub fn unlock(&mut self, achievement: Achievement) -> bool {
+        self.ids.insert(achievement.id().to_string())
+    }
+}
+
+/// Follows a game from the point of view of one team, to tell which achievements it earned.
+pub struct Tracker {
+    team: Team,
+    /// Where our King started.
+    start: Option<Position>,
+    cities: usize,
+    /// Whether an enemy ever took `start`.
+    lost_start: bool,
+}
+
+impl Tracker {
+    /// Track `team` in `game`, which has not started yet.
+    pub fn new(game: &GameState, team: Team) -> Self {
+        let start = game.board
+            .iter()
+            .find(|&(_, cell)| match cell {
+                Cell::King(t, _) => t == team,
+                _ => false,
+            })
+            .map(|(pos, _)| pos);
+        Tracker {
+            team,
+            start,
+            cities: 0,
+            lost_start: false,
+        }
+    }
+
+    /// Look at `event`, which happened in tick `tick`. Returns the achievement it earned, if
+    /// any.
+    pub fn event(&mut self, event: &GameEvent, tick: usize) -> Option<Achievement> {
+        match *event {
+            GameEvent::CityCaptured { team, .. } if team == self.team => {
+                self.cities += 1;
+                if self.cities == EARLY_CITIES && tick < EARLY_TICKS {
+                    return Some(Achievement::EarlyCities);
+                }
+            }
+            GameEvent::Captured { pos, from: Some(from), .. } |
+            GameEvent::CityCaptured { pos, from: Some(from), .. } |
+            GameEvent::KingCaptured { pos, victim: from, .. }
+                if from == self.team && Some(pos) == self.start => {
+                self.lost_start = true;
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// The achievements earned by how the game ended, where `opponents` are the names of the
+    /// bots we played against.
+    pub fn finish(&self, game: &GameState, opponents: &[String]) -> Vec<Achievement> {
+        if game.winner() != Some(self.team) {
+            return Vec::new();
+        }
+        let mut earned = vec![Achievement::FirstWin];
+        if !self.lost_start {
+            earned.push(Achievement::KeptStart);
+        }
+        if opponents.iter().any(|name| name == HARDEST_BOT) {
+            earned.push(Achievement::BeatSearch);
+        }
+        if game.num_players >= CONQUEROR_PLAYERS {
+            earned.push(Achievement::Conqueror);
+        }
+        earned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use generals::Board;
+    use super::*;
+
+    /// A 2-player game on a 4x1 board, with our King at the left end and the enemy one at the
+    /// right.
+    fn game() -> GameState {
+        let cells = vec![Cell::King(0, 1), Cell::Open, Cell::Open, Cell::King(1, 1)];
+        GameState::new(Board::from_cells(4, cells).unwrap(), 2)
+    }
+
+    fn city(team: Team) -> GameEvent {
+        GameEvent::CityCaptured {
+            team,
+            pos: Position(1, 0),
+            from: None,
+        }
+    }
+
+    #[test]
+    fn three_cities_early_are_early_cities() {
+        let mut tracker = Tracker::new(&game(), 0);
+        assert_eq!(tracker.event(&city(0), 10), None);
+        assert_eq!(tracker.event(&city(1), 11), None);
+        assert_eq!(tracker.event(&city(0), 20), None);
+        assert_eq!(tracker.event(&city(0), 49), Some(Achievement::EarlyCities));
+        assert_eq!(tracker.event(&city(0), 49), None);
+    }
+
+    #[test]
+    fn the_third_city_on_tick_50_is_too_late() {
+        let mut tracker = Tracker::new(&game(), 0);
+        tracker.event(&city(0), 10);
+        tracker.event(&city(0), 20);
+        assert_eq!(tracker.event(&city(0), EARLY_TICKS), None);
+    }
+
+    #[test]
+    fn losing_the_starting_city_is_not_keeping_it() {
+        let mut game = game();
+        let mut tracker = Tracker::new(&game, 0);
+        let lost = GameEvent::Captured {
+            team: 1,
+            pos: Position(0, 0),
+            from: Some(0),
+        };
+        tracker.event(&lost, 5);
+        game.forfeit(1);
+        let earned = tracker.finish(&game, &[]);
+        assert!(earned.contains(&Achievement::FirstWin));
+        assert!(!earned.contains(&Achievement::KeptStart));
+    }
+
+    #[test]
+    fn losing_other_cells_keeps_the_starting_city() {
+        let mut game = game();
+        let mut tracker = Tracker::new(&game, 0);
+        let next_to_it = GameEvent::Captured {
+            team: 1,
+            pos: Position(1, 0),
+            from: Some(0),
+        };
+        tracker.event(&next_to_it, 5);
+        game.forfeit(1);
+        assert!(tracker.finish(&game, &[]).contains(&Achievement::KeptStart));
+    }
+
+    #[test]
+    fn only_the_hardest_bot_is_beat_search() {
+        let mut game = game();
+        let tracker = Tracker::new(&game, 0);
+        game.forfeit(1);
+        let earned = tracker.finish(&game, &["random".to_string()]);
+        assert!(!earned.contains(&Achievement::BeatSearch));
+        let earned = tracker.finish(&game, &[HARDEST_BOT.to_string()]);
+        assert!(earned.contains(&Achievement::BeatSearch));
+    }
+
+    #[test]
+    fn losing_earns_nothing() {
+        let mut game = game();
+        let tracker = Tracker::new(&game, 0);
+        game.forfeit(0);
+        assert!(tracker.finish(&game, &[HARDEST_BOT.to_string()]).is_empty());
+    }
+
+    #[test]
+    fn unlocked_survives_saving_and_loading() {
+        let dir = env::temp_dir().join(format!("generals-achievements-{}", process::id()));
+        let path = dir.join("achievements.json");
+        assert!(Unlocked::load(&path).unwrap().ids.is_empty());
+        let mut unlocked = Unlocked::default();
+        assert!(unlocked.unlock(Achievement::KeptStart));
+        assert!(!unlocked.unlock(Achievement::KeptStart));
+        unlocked.save(&path).unwrap();
+        let loaded = Unlocked::load(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(loaded.has(Achievement::KeptStart));
+        assert!(!loaded.has(Achievement::FirstWin));
+    }
+}
@@ -0,0 +1,163 @@
+//! `--export-data <replay or event log> [--format csv|json] [--out PATH]`
+//!
+//! Turn a finished game into one row per team and tick, for looking at balance in a
+//! spreadsheet or with pandas. Replays are played back, so their rows have the scores of every
+//! tick. Event logs from `--log-events` only say what happened, so their rows have no scores.
+//! A log can hold several games, like all the matches of a server, so rows say which game
+//! they are from. The one game of a replay is game 0.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use serde_json;
+
+use eventlog;
+use generals::{GameEvent, Team};
+use replay::Replay;
+use sim::parse_flag;
+
+/// What one team had after a tick, and what it did during it.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Row {
+    pub game: u64,
+    pub tick: usize,
+    pub team: Team,
+    /// The scores, which only replays have.
+    pub army: Option<usize>,
+    pub land: Option<usize>,
+    pub cities: Option<usize>,
+    /// Cells taken, including fortresses and Kings.
+    pub captures: usize,
+    pub city_captures: usize,
+    pub kills: usize,
+    pub moves: usize,
+}
+
+const CSV_HEADER: &str = "game,tick,team,army,land,cities,captures,city_captures,kills,moves";
+
+/// Count `event` in the row of the team that did it, out of `rows` indexed by team.
+fn count(rows: &mut Vec<Row>, game: u64, tick: usize, event: &GameEvent) {
+    let team = match *event {
+        GameEvent::Moved { team, .. } |
+        GameEvent::Captured { team, .. } |
+        GameEvent::CityCaptured { team, .. } |
+        GameEvent::KingCaptured { team, .. } => team,
+    };
+    while rows.len() <= team {
+        let t = rows.len();
+        rows.push(Row { game, tick, team: t, ..Row::default() });
+    }
+    let row = &mut rows[team];
+    match *event {
+        GameEvent::Moved { .. } => row.moves += 1,
+        GameEvent::Captured { .. } => row.captures += 1,
+        GameEvent::CityCaptured { .. } => row.city_captures += 1,
+        GameEvent::KingCaptured { .. } => row.kills += 1,
+    }
+}
+
+/// The rows of every tick of `replay`.
+pub fn replay_rows(replay: &Replay) -> Vec<Row> {
+    let mut game = replay.state_at(0);
+    let mut rows = Vec::new();
+    for frame in replay.frames.iter() {
+        frame.restore(&mut game);
+        let events = game.tick();
+        let tick = game.tick_number;
+        let mut tick_rows = game.scores()
+            .iter()
+            .map(|s| {
+                Row {
+                    tick,
+                    team: s.team,
+                    army: Some(s.army),
+                    land: Some(s.land),
+                    cities: Some(s.cities),
+                    ..Row::default()
+                }
+            })
+            .collect::<Vec<_>>();
+        for event in events.iter() {
+            count(&mut tick_rows, 0, tick, event);
+        }
+        rows.extend(tick_rows);
+    }
+    rows
+}
+
+/// The rows of the ticks in an event log, from its `(game, tick, event)` triples. Teams get a
+/// row in every tick they did something in.
+pub fn log_rows(events: &[(u64, usize, GameEvent)]) -> Vec<Row> {
+    let mut rows = Vec::new();
+    let mut i = 0;
+    while i < events.len() {
+        let (game, tick) = (events[i].0, events[i].1);
+        let mut tick_rows = Vec::new();
+        while i < events.len() && (events[i].0, events[i].1) == (game, tick) {
+            count(&mut tick_rows, game, tick, &events[i].2);
+            i += 1;
+        }
+        rows.extend(tick_rows.into_iter().filter(|r| {
+            r.moves + r.captures + r.city_captures + r.kills > 0
+        }));
+    }
+    rows
+}
+
+fn field(value: Option<usize>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+pub fn write_csv<W: Write>(out: &mut W, rows: &[Row]) -> io::Result<()> {
+    writeln!(out, "{}", CSV_HEADER)?;
+    for r in rows {
+        writeln!(out,
+                 "{},{},{},{},{},{},{},{},{},{}",
+                 r.game,
+                 r.tick,
+                 r.team,
+                 field(r.army),
+                 field(r.land),
+                 field(r.cities),
+                 r.captures,
+                 r.city_captures,
+                 r.kills,
+                 r.moves)?;
+    }
+    Ok(())
+}
+
+pub fn write_json<W: Write>(out: &mut W, rows: &[Row]) -> io::Result<()> {
+    serde_json::to_writer(out, rows).map_err(io::Error::from)
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let i = args.iter()
+        .position(|a| a == "--export-data")
+        .ok_or("missing --export-data")?;
+    let input = args.get(i + 1).ok_or("--export-data needs a replay or event log")?;
+    let format = parse_flag(args, "--format", "csv".to_string())?;
+    let out = parse_flag(args, "--out", format!("game.{}", format))?;
+    if format != "csv" && format != "json" {
+        return Err(format!("Unknown format '{}', try csv or json", format));
+    }
+
+    // Anything that is not a replay is read as an event log.
+    let rows = match Replay::load(input) {
+        Ok(replay) => replay_rows(&replay),
+        Err(_) => {
+            let events = eventlog::read_events(input)
+                .map_err(|e| format!("{} is neither a replay nor an event log: {}", input, e))?;
+            log_rows(&events)
+        }
+    };
+    let mut file = BufWriter::new(File::create(&out).map_err(|e| e.to_string())?);
+    let written = if format == "csv" {
+        write_csv(&mut file, &rows)
+    } else {
+        write_json(&mut file, &rows)
+    };
+    written.and_then(|_| file.flush()).map_err(|e| format!("Failed to write {}: {}", out, e))?;
+    println!("Wrote {} rows to {}", rows.len(), out);
+    Ok(())
+}
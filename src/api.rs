@@ -0,0 +1,146 @@
+//! The interface for embedding the game engine in other programs.
+//!
+//! Everything exported from this module follows semver: breaking changes to it bump the major
+//! version. `GameEvent` may get new variants in minor versions, so match on it with a wildcard
+//! arm. Anything not reachable from here is internal and may change at any time.
+
+use rand;
+
+use bot::{self, BotPlayer};
+use frontend::{self, Command};
+use generals::GameState;
+
+pub use error::GeneralsError;
+pub use generals::{Board, BoardDelta, Cell, Direction, GameEvent, MapSettings, Move, PlayerView,
+                   Position, Score, Team, Tile, ViewDelta};
+
+/// Everything needed to set up a new game.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameConfig {
+    pub num_players: usize,
+    pub map: MapSettings,
+    /// Seed for the map and the bots. `None` picks one at random.
+    pub seed: Option<u32>,
+    /// Teams played by built-in bots, along with the bot name. See `bot::NAMES`.
+    pub bots: Vec<(Team, String)>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            num_players: 2,
+            map: MapSettings::default(),
+            seed: None,
+            bots: Vec::new(),
+        }
+    }
+}
+
+/// The largest board `Game::new` makes, in cells across.
+pub const MAX_BOARD_SIZE: usize = 1024;
+
+/// The full state of the game at some tick, without anything specific to a renderer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    pub tick_number: usize,
+    pub dimens: (i32, i32),
+    /// Rows of cells, indexed `cells[y][x]`.
+    pub cells: Vec<Vec<Cell>>,
+    pub scores: Vec<Score>,
+    pub winner: Option<Team>,
+}
+
+/// A callback that is given every event of a game, see `Game::subscribe`.
+type Subscriber = Box<dyn FnMut(&GameEvent)>;
+
+/// A running game.
+pub struct Game {
+    state: GameState,
+    bots: Vec<BotPlayer>,
+    subscribers: Vec<Subscriber>,
+}
+
+impl Game {
+    pub fn new(config: GameConfig) -> Result<Game, GeneralsError> {
+        config.map.check(config.num_players, MAX_BOARD_SIZE)?;
+        let seed = config.seed.unwrap_or_else(rand::random);
+        let board = Board::generate(&config.map,
+                                    config.num_players,
+                                    &mut ::generals::seeded_rng(seed));
+        let mut bots = Vec::new();
+        for &(team, ref name) in config.bots.iter() {
+            if team >= config.num_players {
+                return Err(GeneralsError::UnknownTeam(team));
+            }
+            let bot = bot::seeded(name, seed.wrapping_add(team as u32))
+                .ok_or_else(|| GeneralsError::UnknownBot(name.clone()))?;
+            bots.push(BotPlayer::new(team, bot));
+        }
+        Ok(Game {
+            state: GameState::new(board, config.num_players),
+            bots,
+            subscribers: Vec::new(),
+        })
+    }
+
+    /// Let the bots move, advance the game by one tick, and notify all subscribers of what
+    /// happened.
+    pub fn tick(&mut self) -> Vec<GameEvent> {
+        for bot in self.bots.iter_mut() {
+            bot.act(&mut self.state);
+        }
+        let events = self.state.tick();
+        for subscriber in self.subscribers.iter_mut() {
+            for event in events.iter() {
+                subscriber(event);
+            }
+        }
+        events
+    }
+
+    /// Append a move to the queue of `team`.
+    pub fn submit_move(&mut self, team: Team, mv: Move) -> Result<(), GeneralsError> {
+        frontend::apply(&mut self.state, team, Command::Move(mv))
+    }
+
+    pub fn clear_moves(&mut self, team: Team) -> Result<(), GeneralsError> {
+        frontend::apply(&mut self.state, team, Command::ClearMoves)
+    }
+
+    /// Call `f` with every event that happens from now on.
+    pub fn subscribe<F>(&mut self, f: F)
+    where
+        F: FnMut(&GameEvent) + 'static,
+    {
+        self.subscribers.push(Box::new(f));
+    }
+
+    /// What changed on the board during the last tick.
+    pub fn last_delta(&self) -> &BoardDelta {
+        self.state.last_delta()
+    }
+
+    pub fn snapshot(&self) -> GameSnapshot {
+        GameSnapshot {
+            tick_number: self.state.tick_number,
+            dimens: self.state.dimens,
+            cells: self.state.board.rows().collect(),
+            scores: self.state.scores(),
+            winner: self.state.winner(),
+        }
+    }
+
+    /// What `team` can see of the game.
+    pub fn view(&self, team: Team) -> Result<PlayerView, GeneralsError> {
+        self.state.player_state(team)?;
+        Ok(self.state.player_view(team))
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.state.is_over()
+    }
+
+    pub fn winner(&self) -> Option<Team> {
+        self.state.winner()
+    }
+}
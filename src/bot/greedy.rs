@@ -0,0 +1,155 @@
+use generals::*;
+use super::{is_passable, owned_cells, Bot};
+
+/// How far from our King an enemy army has to be before we get worried.
+const THREAT_DISTANCE: i32 = 4;
+/// How far around a cell we look for unexplored tiles.
+const EXPLORE_RADIUS: i32 = 2;
+
+const KING_VALUE: i64 = 10_000;
+const CITY_VALUE: i64 = 500;
+const ENEMY_LAND_VALUE: i64 = 40;
+const OPEN_VALUE: i64 = 20;
+const FOG_VALUE: i64 = 10;
+const UNEXPLORED_VALUE: i64 = 5;
+
+/// Scores every possible move and picks the best one. Captures cities whenever it can afford
+/// them, expands towards unexplored territory, and pulls armies home when the King is in danger.
+pub struct Greedy;
+
+/// What the bot knows about the game, for scoring moves.
+struct Situation {
+    team: Team,
+    king: Option<Position>,
+    /// The King is threatened by a large enemy army.
+    threatened: bool,
+    /// We are much stronger than anyone else, so go for their land.
+    aggressive: bool,
+}
+
+/// The number of fogged tiles around `pos`.
+fn unexplored(view: &PlayerView, Position(x, y): Position) -> i64 {
+    let mut n = 0;
+    for dy in -EXPLORE_RADIUS..EXPLORE_RADIUS + 1 {
+        for dx in -EXPLORE_RADIUS..EXPLORE_RADIUS + 1 {
+            match view.try_get(x + dx, y + dy) {
+                Some(Tile::Fog) | Some(Tile::Obstacle) => n += 1,
+                _ => {}
+            }
+        }
+    }
+    n
+}
+
+fn find_king(view: &PlayerView, owned: &[(Position, usize)]) -> Option<(Position, usize)> {
+    owned
+        .iter()
+        .find(|&&(Position(x, y), _)| matches!(view.get(x, y), Tile::Visible(Cell::King(_, _))))
+        .cloned()
+}
+
+/// Whether any visible enemy army close to the King is large enough to take it.
+fn is_threatened(view: &PlayerView, king: Position, king_units: usize) -> bool {
+    for (y, row) in view.tiles.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            let pos = Position(x as i32, y as i32);
+            if let Tile::Visible(ref cell) = *tile {
+                let enemy = cell.owner().is_some_and(|t| t != view.team);
+                if enemy && cell.units().unwrap_or(0) > king_units &&
+                   pos.distance(king) <= THREAT_DISTANCE {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+impl Greedy {
+    fn situation(&self, view: &PlayerView, owned: &[(Position, usize)]) -> Situation {
+        let king = find_king(view, owned);
+        let threatened = king.map(|(pos, n)| is_threatened(view, pos, n))
+            .unwrap_or(false);
+        let our_army = view.scores[view.team].army;
+        let strongest_enemy = view.scores
+            .iter()
+            .filter(|s| s.team != view.team && !s.dead)
+            .map(|s| s.army)
+            .max()
+            .unwrap_or(0);
+        Situation {
+            team: view.team,
+            king: king.map(|(pos, _)| pos),
+            threatened,
+            aggressive: our_army > 2 * strongest_enemy,
+        }
+    }
+
+    fn score(&self, view: &PlayerView, sit: &Situation, from: Position, units: usize,
+             dir: Direction) -> i64 {
+        let to = from + dir;
+        // We always leave one unit behind.
+        let moving = units - 1;
+        let mut score = match view.get(to.0, to.1) {
+            Tile::Visible(Cell::King(t, n)) if t != sit.team => {
+                if moving > n { KING_VALUE } else { -1 }
+            }
+            Tile::Visible(Cell::Fortress(owner, n)) if owner != Some(sit.team) => {
+                if moving > n { CITY_VALUE } else { -1 }
+            }
+            Tile::Visible(Cell::Captured(t, n)) if t != sit.team => {
+                if moving <= n {
+                    -1
+                } else if sit.aggressive {
+                    2 * ENEMY_LAND_VALUE
+                } else {
+                    ENEMY_LAND_VALUE
+                }
+            }
+            Tile::Visible(Cell::Open) => OPEN_VALUE + UNEXPLORED_VALUE * unexplored(view, to),
+            Tile::Fog => FOG_VALUE + UNEXPLORED_VALUE * unexplored(view, to),
+            Tile::Visible(_) => {
+                // Moving within our own land: only worth it for large armies heading towards
+                // the frontier.
+                if moving < 10 {
+                    return -1;
+                }
+                UNEXPLORED_VALUE * (unexplored(view, to) - unexplored(view, from))
+            }
+            Tile::Obstacle => return i64::MIN,
+        };
+        if sit.threatened {
+            if let Some(king) = sit.king {
+                let closer = (from.distance(king) - to.distance(king)) as i64;
+                score += closer * moving as i64;
+            }
+        }
+        score
+    }
+}
+
+impl Bot for Greedy {
+    fn choose_moves(&mut self, view: &PlayerView) -> Vec<Move> {
+        if !view.moves.is_empty() {
+            return Vec::new();
+        }
+        let owned = owned_cells(view);
+        let sit = self.situation(view, &owned);
+        let mut best: Option<(i64, Move)> = None;
+        for &(from, units) in owned.iter().filter(|&&(_, n)| n > 1) {
+            for &dir in DIRECTIONS.iter() {
+                if !is_passable(view, from + dir) {
+                    continue;
+                }
+                let score = self.score(view, &sit, from, units, dir);
+                if best.map(|(s, _)| score > s).unwrap_or(true) {
+                    best = Some((score, (from, dir)));
+                }
+            }
+        }
+        match best {
+            Some((score, m)) if score > 0 => vec![m],
+            _ => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use rand;
+
+use frontend::Command;
+use generals::*;
+use profile::{self, Section};
+
+mod greedy;
+mod random;
+mod script;
+mod search;
+pub use self::greedy::Greedy;
+pub use self::random::RandomExpander;
+pub use self::script::Script;
+pub use self::search::{Budget, Search};
+
+/// A computer controlled player.
+pub trait Bot {
+    /// Moves to append to the bots move queue. This is called once before every tick, and the
+    /// bot only gets to see the fogged view of its own team.
+    fn choose_moves(&mut self, view: &PlayerView) -> Vec<Move>;
+}
+
+/// The names of the built-in bots, as accepted by `by_name`.
+pub const NAMES: &[&str] = &["idle", "random", "greedy", "search"];
+
+/// How long the search bot thinks per move, unless it is seeded.
+pub const DEFAULT_SEARCH_MS: usize = 20;
+/// How many rollouts the seeded search bot plays per move.
+pub const DEFAULT_SEARCH_ROLLOUTS: usize = 200;
+
+static SEARCH_MS: AtomicUsize = AtomicUsize::new(DEFAULT_SEARCH_MS);
+static SEARCH_ROLLOUTS: AtomicUsize = AtomicUsize::new(DEFAULT_SEARCH_ROLLOUTS);
+
+/// Let search bots made from now on think for `ms` milliseconds per move.
+pub fn set_search_time(ms: usize) {
+    SEARCH_MS.store(ms, Ordering::Relaxed);
+}
+
+/// Let seeded search bots made from now on play `rollouts` rollouts per move.
+pub fn set_search_rollouts(rollouts: usize) {
+    SEARCH_ROLLOUTS.store(rollouts, Ordering::Relaxed);
+}
+
+/// The bot used to fill empty player slots.
+pub const DEFAULT_BOT: &str = "random";
+
+/// Construct a built-in bot by its name. The search bot thinks for as long as
+/// `set_search_time` says.
+pub fn by_name(name: &str) -> Option<Box<dyn Bot>> {
+    let ms = SEARCH_MS.load(Ordering::Relaxed) as u64;
+    with_budget(name, rand::random(), Budget::Time(Duration::from_millis(ms)))
+}
+
+/// Like `by_name`, but bots that use randomness are seeded with `seed`, so that games can be
+/// reproduced. The search bot plays as many rollouts as `set_search_rollouts` says instead of
+/// watching the clock, so its moves don't depend on how fast the machine is.
+pub fn seeded(name: &str, seed: u32) -> Option<Box<dyn Bot>> {
+    let rollouts = SEARCH_ROLLOUTS.load(Ordering::Relaxed);
+    with_budget(name, seed, Budget::Rollouts(rollouts))
+}
+
+fn with_budget(name: &str, seed: u32, budget: Budget) -> Option<Box<dyn Bot>> {
+    match name {
+        "idle" => Some(Box::new(Idle)),
+        "random" => Some(Box::new(RandomExpander::new(seeded_rng(seed)))),
+        "greedy" => Some(Box::new(Greedy)),
+        "search" => Some(Box::new(Search::new(seeded_rng(seed), budget))),
+        _ => None,
+    }
+}
+
+/// The cells controlled by the viewing team, along with their unit counts.
+pub fn owned_cells(view: &PlayerView) -> Vec<(Position, usize)> {
+    let mut owned = Vec::new();
+    for (y, row) in view.tiles.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            if let Tile::Visible(ref cell) = *tile {
+                if cell.owner() == Some(view.team) {
+                    owned.push((Position(x as i32, y as i32), cell.units().unwrap_or(0)));
+                }
+            }
+        }
+    }
+    owned
+}
+
+/// Whether units can move onto `pos`, as far as the viewer can tell. Unknown obstacles are
+/// assumed to be mountains.
+pub fn is_passable(view: &PlayerView, pos: Position) -> bool {
+    let Position(x, y) = pos;
+    !matches!(view.try_get(x, y),
+              None | Some(Tile::Obstacle) | Some(Tile::Visible(Cell::Mountain)))
+}
+
+/// A bot that never does anything.
+pub struct Idle;
+
+impl Bot for Idle {
+    fn choose_moves(&mut self, _view: &PlayerView) -> Vec<Move> {
+        Vec::new()
+    }
+}
+
+/// A bot in control of a team in a game.
+pub struct BotPlayer {
+    pub team: Team,
+    bot: Box<dyn Bot>,
+}
+
+impl BotPlayer {
+    pub fn new(team: Team, bot: Box<dyn Bot>) -> Self {
+        BotPlayer { team, bot }
+    }
+
+    /// Let the bot look at the game, and push its moves to the teams queue.
+    pub fn act(&mut self, game: &mut GameState) {
+        let _timer = profile::start(Section::Bots);
+        if game.player_states[self.team].dead {
+            return;
+        }
+        let view = game.player_view(self.team);
+        let moves = self.bot.choose_moves(&view);
+        for &mv in moves.iter() {
+            game.announce_command(self.team, &Command::Move(mv));
+        }
+        game.player_mut(self.team).moves.extend(moves);
+    }
+}
@@ -0,0 +1,81 @@
+use rand::{Rng, XorShiftRng};
+
+use generals::*;
+use super::{is_passable, owned_cells, Bot};
+
+/// One in this many turns the bot consolidates instead of expanding.
+const CONSOLIDATE_ODDS: u32 = 6;
+
+/// Expands into random neighbouring open cells, and now and then moves an army one step
+/// towards its King.
+pub struct RandomExpander {
+    rng: XorShiftRng,
+}
+
+impl RandomExpander {
+    pub fn new(rng: XorShiftRng) -> Self {
+        RandomExpander { rng }
+    }
+
+    fn expand(&mut self, view: &PlayerView, owned: &[(Position, usize)]) -> Option<Move> {
+        let mut expansions = Vec::new();
+        for &(pos, _) in owned.iter().filter(|&&(_, n)| n > 1) {
+            for &dir in DIRECTIONS.iter() {
+                let Position(x, y) = pos + dir;
+                if let Some(Tile::Visible(Cell::Open)) = view.try_get(x, y) {
+                    expansions.push((pos, dir));
+                }
+            }
+        }
+        self.rng.choose(&expansions).cloned()
+    }
+
+    fn consolidate(&mut self, view: &PlayerView, owned: &[(Position, usize)]) -> Option<Move> {
+        let Position(kx, ky) = owned
+            .iter()
+            .find(|&&(Position(x, y), _)| {
+                matches!(view.get(x, y), Tile::Visible(Cell::King(_, _)))
+            })?
+            .0;
+        let armies = owned
+            .iter()
+            .filter(|&&(Position(x, y), n)| n > 1 && (x, y) != (kx, ky))
+            .map(|&(pos, _)| pos)
+            .collect::<Vec<_>>();
+        let from = *self.rng.choose(&armies)?;
+        let mut dirs = Vec::new();
+        if kx > from.0 {
+            dirs.push(Direction::Right);
+        } else if kx < from.0 {
+            dirs.push(Direction::Left);
+        }
+        if ky > from.1 {
+            dirs.push(Direction::Down);
+        } else if ky < from.1 {
+            dirs.push(Direction::Up);
+        }
+        self.rng.shuffle(&mut dirs);
+        dirs.into_iter()
+            .map(|dir| (from, dir))
+            .find(|&(pos, dir)| is_passable(view, pos + dir))
+    }
+}
+
+impl Bot for RandomExpander {
+    fn choose_moves(&mut self, view: &PlayerView) -> Vec<Move> {
+        // Wait for the previous move to go through before deciding on the next one.
+        if !view.moves.is_empty() {
+            return Vec::new();
+        }
+        let owned = owned_cells(view);
+        let consolidated = if self.rng.gen_weighted_bool(CONSOLIDATE_ODDS) {
+            self.consolidate(view, &owned)
+        } else {
+            None
+        };
+        consolidated
+            .or_else(|| self.expand(view, &owned))
+            .into_iter()
+            .collect()
+    }
+}
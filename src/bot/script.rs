@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use generals::*;
+use super::Bot;
+
+/// Plays the moves of a script file instead of thinking, for reproducing a game exactly. Every
+/// line of the file is a move, queued on the given tick:
+///
+/// ```text
+/// # tick x y direction
+/// 0 3 4 right
+/// 2 4 4 down
+/// ```
+pub struct Script {
+    /// Sorted by tick.
+    moves: Vec<(usize, Move)>,
+    next: usize,
+}
+
+fn parse_direction(s: &str) -> Option<Direction> {
+    match s {
+        "up" => Some(Direction::Up),
+        "down" => Some(Direction::Down),
+        "left" => Some(Direction::Left),
+        "right" => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+impl Script {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Script::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut moves = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            let mv = match words[..] {
+                [tick, x, y, dir] => {
+                    match (tick.parse(), x.parse(), y.parse(), parse_direction(dir)) {
+                        (Ok(tick), Ok(x), Ok(y), Some(dir)) => Some((tick, (Position(x, y), dir))),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            moves.push(mv.ok_or_else(|| format!("line {}: expected 'tick x y direction'", i + 1))?);
+        }
+        moves.sort_by_key(|&(tick, _)| tick);
+        Ok(Script { moves, next: 0 })
+    }
+}
+
+impl Bot for Script {
+    fn choose_moves(&mut self, view: &PlayerView) -> Vec<Move> {
+        let mut moves = Vec::new();
+        while let Some(&(tick, mv)) = self.moves.get(self.next) {
+            if tick > view.tick_number {
+                break;
+            }
+            moves.push(mv);
+            self.next += 1;
+        }
+        moves
+    }
+}
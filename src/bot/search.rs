@@ -0,0 +1,128 @@
+use std::time::{Duration, Instant};
+
+use rand::{Rng, XorShiftRng};
+
+use generals::*;
+use super::Bot;
+
+/// How many ticks each rollout simulates.
+const ROLLOUT_DEPTH: usize = 12;
+/// Stop searching after this many rollouts, even if there is time left.
+pub const MAX_ROLLOUTS: usize = 2000;
+
+const LAND_VALUE: f64 = 2.0;
+const CITY_VALUE: f64 = 25.0;
+const ARMY_VALUE: f64 = 0.5;
+const KILL_VALUE: f64 = 1000.0;
+const DEATH_VALUE: f64 = -1_000_000.0;
+
+/// How much the search bot searches per move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    /// Search for this long, or until `MAX_ROLLOUTS` rollouts. The moves depend on how fast
+    /// the machine is.
+    Time(Duration),
+    /// Play this many rollouts, however long they take.
+    Rollouts(usize),
+}
+
+/// Flat Monte Carlo search: every possible next move is tried in random rollouts on a copy of
+/// the game, and the move with the best average outcome wins. Since the bot can't see through
+/// the fog, the rollouts are played on the best guess from its view, with idle opponents.
+pub struct Search {
+    rng: XorShiftRng,
+    budget: Budget,
+}
+
+impl Search {
+    pub fn new(rng: XorShiftRng, budget: Budget) -> Self {
+        Search { rng, budget }
+    }
+
+    /// Play `first` in `game`, starting from `root`, followed by random moves, and evaluate the
+    /// result.
+    fn rollout(&mut self, game: &mut GameState, root: &Snapshot, team: Team, first: Move) -> f64 {
+        game.restore(root);
+        game.player_mut(team).moves.push_back(first);
+        for _ in 0..ROLLOUT_DEPTH {
+            if game.player_states[team].queue_len() == 0 {
+                let moves = candidate_moves(game, team);
+                if let Some(&m) = self.rng.choose(&moves) {
+                    game.player_mut(team).moves.push_back(m);
+                }
+            }
+            game.tick();
+            if game.player_states[team].dead {
+                break;
+            }
+        }
+        evaluate(game, team)
+    }
+}
+
+/// All moves `team` can make from cells with units to spare, except into mountains.
+fn candidate_moves(game: &GameState, team: Team) -> Vec<Move> {
+    let mut moves = Vec::new();
+    for (pos, cell) in game.board.cells_owned_by(team) {
+        if cell.units().unwrap_or(0) <= 1 {
+            continue;
+        }
+        for &dir in DIRECTIONS.iter() {
+            let Position(x, y) = pos + dir;
+            if game.board.try_get(x, y).is_some_and(|cell| cell.is_passable()) {
+                moves.push((pos, dir));
+            }
+        }
+    }
+    moves
+}
+
+fn evaluate(game: &GameState, team: Team) -> f64 {
+    let scores = game.scores();
+    if scores[team].dead {
+        return DEATH_VALUE;
+    }
+    let kills = scores.iter().filter(|s| s.team != team && s.dead).count();
+    let me = scores[team];
+    me.land as f64 * LAND_VALUE + me.cities as f64 * CITY_VALUE + me.army as f64 * ARMY_VALUE +
+        kills as f64 * KILL_VALUE
+}
+
+impl Bot for Search {
+    fn choose_moves(&mut self, view: &PlayerView) -> Vec<Move> {
+        if !view.moves.is_empty() {
+            return Vec::new();
+        }
+        let root = GameState::from_view(view);
+        let candidates = candidate_moves(&root, view.team);
+        if candidates.is_empty() {
+            return Vec::new();
+        }
+        let mut totals = vec![0.0; candidates.len()];
+        let mut counts = vec![0; candidates.len()];
+        let snapshot = root.snapshot();
+        let mut game = root;
+        let start = Instant::now();
+        let mut n = 0;
+        let budget = self.budget;
+        let more = |n: usize| match budget {
+            Budget::Time(time) => n < MAX_ROLLOUTS && start.elapsed() < time,
+            Budget::Rollouts(rollouts) => n < rollouts,
+        };
+        // Try every candidate at least once, then keep going round robin while the budget
+        // allows.
+        while n < candidates.len() || more(n) {
+            let i = n % candidates.len();
+            totals[i] += self.rollout(&mut game, &snapshot, view.team, candidates[i]);
+            counts[i] += 1;
+            n += 1;
+        }
+        let mut best = 0;
+        for i in 1..candidates.len() {
+            if totals[i] / counts[i] as f64 > totals[best] / counts[best] as f64 {
+                best = i;
+            }
+        }
+        vec![candidates[best]]
+    }
+}
@@ -0,0 +1,145 @@
+//! The single player campaign: bundled scenarios played in order, against bots that get
+//! stronger as it goes. A mission unlocks when the one before it is won.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use serde_json;
+
+use generals::Team;
+use scenario::Scenario;
+
+pub struct Mission {
+    /// The name of the bundled scenario.
+    pub scenario: &'static str,
+    /// The bot playing every other team, instead of the ones in the scenario.
+    pub bot: &'static str,
+}
+
+pub const MISSIONS: &[Mission] = &[
+    Mission { scenario: "first-strike", bot: "idle" },
+    Mission { scenario: "land-grab", bot: "random" },
+    Mission { scenario: "hold-the-pass", bot: "greedy" },
+    Mission { scenario: "last-stand", bot: "search" },
+];
+
+impl Mission {
+    /// The scenario of the mission, with its bots swapped for the bot of the mission.
+    pub fn scenario(&self) -> Scenario {
+        let mut scenario = Scenario::find(self.scenario).expect("bundled scenarios are valid");
+        scenario.bots = vec![self.bot.to_string(); scenario.num_players - 1];
+        scenario
+    }
+}
+
+/// How far a player got in the campaign.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Progress {
+    /// The number of missions won, which are always the first ones.
+    pub completed: usize,
+}
+
+impl Progress {
+    /// Load the progress saved at `path`. No file means no progress yet.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match File::open(path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file)).map_err(io::Error::from),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Progress::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(file, self).map_err(io::Error::from)
+    }
+
+    /// Whether mission `i` can be played: every mission before it has been won.
+    pub fn is_unlocked(&self, i: usize) -> bool {
+        i <= self.completed && i < MISSIONS.len()
+    }
+
+    /// Record that mission `i` was won. Returns whether that unlocked a new mission.
+    pub fn complete(&mut self, i: usize) -> bool {
+        if i != self.completed {
+            return false;
+        }
+        self.completed += 1;
+        self.completed < MISSIONS.len()
+    }
+
+    /// Record how mission `i` ended, when the player played `team` and `winner` won. Returns
+    /// `None` if the player lost, or else whether the win unlocked a new mission.
+    pub fn record(&mut self, i: usize, winner: Option<Team>, team: Team) -> Option<bool> {
+        if winner != Some(team) {
+            return None;
+        }
+        Some(self.complete(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use bot;
+    use super::*;
+
+    #[test]
+    fn missions_unlock_one_at_a_time() {
+        let mut progress = Progress::default();
+        assert!(progress.is_unlocked(0));
+        assert!(!progress.is_unlocked(1));
+        assert!(!progress.complete(1));
+        assert!(progress.complete(0));
+        assert!(progress.is_unlocked(1));
+        // Winning a mission again doesn't skip ahead.
+        assert!(!progress.complete(0));
+        assert!(!progress.is_unlocked(2));
+    }
+
+    #[test]
+    fn only_wins_of_the_players_team_count() {
+        let mut progress = Progress::default();
+        assert_eq!(progress.record(0, Some(0), 1), None);
+        assert_eq!(progress.record(0, None, 1), None);
+        assert_eq!(progress.completed, 0);
+        assert_eq!(progress.record(0, Some(1), 1), Some(true));
+        assert_eq!(progress.completed, 1);
+    }
+
+    #[test]
+    fn winning_the_last_mission_unlocks_nothing() {
+        let mut progress = Progress { completed: MISSIONS.len() - 1 };
+        assert!(!progress.complete(MISSIONS.len() - 1));
+        assert_eq!(progress.completed, MISSIONS.len());
+        assert!(!progress.is_unlocked(MISSIONS.len()));
+    }
+
+    #[test]
+    fn every_mission_loads_with_its_bots() {
+        for mission in MISSIONS.iter() {
+            let scenario = mission.scenario();
+            assert!(bot::NAMES.contains(&mission.bot));
+            assert_eq!(scenario.bots.len(), scenario.num_players - 1);
+            assert!(scenario.bots.iter().all(|name| name == mission.bot));
+        }
+    }
+
+    #[test]
+    fn progress_survives_saving_and_loading() {
+        let dir = env::temp_dir().join(format!("generals-campaign-{}", process::id()));
+        let path = dir.join("campaign.json");
+        assert_eq!(Progress::load(&path).unwrap().completed, 0);
+        Progress { completed: 2 }.save(&path).unwrap();
+        let loaded = Progress::load(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(loaded.completed, 2);
+    }
+}
@@ -0,0 +1,149 @@
+//! The configuration file of the client, `generals/config.toml` in the config directory of the
+//! platform. It is written with the defaults on the first run, and again whenever the settings
+//! change in the game, so it can be edited by hand as well.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use dirs;
+use toml;
+
+use ui::keymap::Keymap;
+use ui::settings::Settings;
+
+const CONFIG_DIR: &str = "generals";
+const CONFIG_FILE: &str = "config.toml";
+const CAMPAIGN_FILE: &str = "campaign.json";
+const DAILY_FILE: &str = "daily.json";
+const MATCHES_FILE: &str = "matches.jsonl";
+const ACHIEVEMENTS_FILE: &str = "achievements.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The name to play under on servers.
+    pub name: Option<String>,
+    /// The secret that proves to servers that `name` is ours, so that nobody else can play
+    /// ranked games under it. Made up on the first run.
+    pub key: Option<String>,
+    /// The team color to play in, as an index into the palette, whatever team we are.
+    pub color: Option<usize>,
+    pub window: WindowConfig,
+    /// How local games are set up, unless the command line says otherwise.
+    pub game: GameConfig,
+    pub settings: Settings,
+    /// Key bindings, from action names to SDL key names, like `clear_moves = "Q"`.
+    pub keys: BTreeMap<String, String>,
+    /// Whether the tutorial was finished or skipped. Until then, the client starts with it.
+    pub tutorial_done: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    /// Side length of the board. `None` scales it with the number of players.
+    pub size: Option<usize>,
+    pub players: usize,
+    /// The bots playing the other teams, in order.
+    pub bots: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            name: None,
+            key: None,
+            color: None,
+            window: WindowConfig::default(),
+            game: GameConfig::default(),
+            settings: Settings::default(),
+            keys: Keymap::default().bindings(),
+            tutorial_done: false,
+        }
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig {
+            width: 1600,
+            height: 1600,
+        }
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            size: None,
+            players: 2,
+            bots: Vec::new(),
+        }
+    }
+}
+
+/// Where the config file is, or would be. Platforms without a config directory get it in the
+/// working directory.
+pub fn path() -> PathBuf {
+    match dirs::config_dir() {
+        Some(dir) => dir.join(CONFIG_DIR).join(CONFIG_FILE),
+        None => PathBuf::from(CONFIG_FILE),
+    }
+}
+
+/// Where the campaign progress is saved, next to the config file.
+pub fn campaign_path() -> PathBuf {
+    path().with_file_name(CAMPAIGN_FILE)
+}
+
+/// Where the best results of the daily challenges are saved, next to the config file.
+pub fn daily_path() -> PathBuf {
+    path().with_file_name(DAILY_FILE)
+}
+
+/// Where the history of finished games is kept, next to the config file.
+pub fn matches_path() -> PathBuf {
+    path().with_file_name(MATCHES_FILE)
+}
+
+/// Where the unlocked achievements are saved, next to the config file.
+pub fn achievements_path() -> PathBuf {
+    path().with_file_name(ACHIEVEMENTS_FILE)
+}
+
+impl Config {
+    /// Load the config file, or write one with the defaults if there is none.
+    pub fn load() -> io::Result<Self> {
+        let mut text = String::new();
+        match File::open(path()) {
+            Ok(mut file) => file.read_to_string(&mut text)?,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                let config = Config::default();
+                config.save()?;
+                println!("Wrote the default config to {}", path().display());
+                return Ok(config);
+            }
+            Err(e) => return Err(e),
+        };
+        toml::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        File::create(path)?.write_all(text.as_bytes())
+    }
+}
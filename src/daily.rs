@@ -0,0 +1,140 @@
+//! The daily challenge: a map, opponent and board size that follow from the date, so that
+//! everyone plays the same game on the same day. The best result of each day is kept locally.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json;
+
+use generals::{Generation, MapSettings};
+
+/// The opponents of the challenge, one for each day of the week in turn.
+const BOTS: &[&str] = &["greedy", "greedy", "search", "greedy", "search", "greedy", "search"];
+
+/// Days since the Unix epoch, in UTC.
+pub type Day = u64;
+
+pub fn today() -> Day {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / (24 * 60 * 60))
+        .unwrap_or(0)
+}
+
+/// `day` as `YYYY-MM-DD`.
+pub fn date(day: Day) -> String {
+    // Howard Hinnant's civil_from_days, for days since 1970-01-01.
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// The game of one day. It is always a 1v1, played as team 0.
+#[derive(Debug, Clone)]
+pub struct Challenge {
+    pub day: Day,
+    pub generation: Generation,
+    /// The bot playing team 1.
+    pub bot: &'static str,
+}
+
+impl Challenge {
+    pub fn new(day: Day) -> Self {
+        // Mix the day up, so that neighbouring days don't get similar seeds.
+        let seed = (day.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32) as u32;
+        let map = MapSettings {
+            size: Some(16 + 4 * (day % 5) as usize),
+            ..MapSettings::default()
+        };
+        Challenge {
+            day,
+            generation: Generation::new(seed, map),
+            bot: BOTS[(day % BOTS.len() as u64) as usize],
+        }
+    }
+
+    pub fn today() -> Self {
+        Challenge::new(today())
+    }
+}
+
+/// How a daily challenge went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyResult {
+    pub won: bool,
+    pub ticks: usize,
+    /// The land we had at the end.
+    pub land: usize,
+}
+
+impl DailyResult {
+    /// Winning beats losing. Faster wins are better, and so are losses that held out longer
+    /// with more land.
+    pub fn is_better_than(&self, other: &DailyResult) -> bool {
+        match (self.won, other.won) {
+            (true, false) => true,
+            (false, true) => false,
+            (true, true) => self.ticks < other.ticks,
+            (false, false) => (self.ticks, self.land) > (other.ticks, other.land),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        if self.won {
+            format!("won in {} ticks", self.ticks)
+        } else {
+            format!("lost after {} ticks with {} land", self.ticks, self.land)
+        }
+    }
+}
+
+/// The best result of every day that was played.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DailyRecords {
+    best: BTreeMap<Day, DailyResult>,
+}
+
+impl DailyRecords {
+    /// Load the records saved at `path`. No file means nothing was played yet.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match File::open(path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file)).map_err(io::Error::from),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(DailyRecords::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(file, self).map_err(io::Error::from)
+    }
+
+    pub fn best(&self, day: Day) -> Option<DailyResult> {
+        self.best.get(&day).cloned()
+    }
+
+    /// Keep `result` if it is the best of `day` so far. Returns whether it was.
+    pub fn record(&mut self, day: Day, result: DailyResult) -> bool {
+        match self.best.get(&day) {
+            Some(best) if !result.is_better_than(best) => false,
+            _ => {
+                self.best.insert(day, result);
+                true
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use serde_json;
+
+use generals::{Cell, Move, Position, Team};
+
+/// What can go wrong in the engine. Anything that comes from outside, like moves sent by
+/// clients or files on disk, ends up here rather than in a panic, so that a bad message or
+/// file can't take a server down.
+#[derive(Debug)]
+pub enum GeneralsError {
+    /// A position that is not on the board.
+    OutOfBounds(Position),
+    /// A move that can't be made, like one off the board.
+    InvalidMove(Move),
+    /// A team that is not in the game.
+    UnknownTeam(Team),
+    /// A bot name that is not in `bot::NAMES`.
+    UnknownBot(String),
+    /// A cell without units, like a mountain, was asked for units.
+    NoUnits(Cell),
+    /// A board or replay that doesn't make sense, like one with rows of different lengths.
+    BadMap(String),
+    /// The game got into a state the rules should not allow, which is a bug in the engine.
+    BrokenInvariant(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for GeneralsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GeneralsError::OutOfBounds(Position(x, y)) => {
+                write!(f, "({}, {}) is not on the board", x, y)
+            }
+            GeneralsError::InvalidMove((Position(x, y), dir)) => {
+                write!(f, "can't move {:?} from ({}, {})", dir, x, y)
+            }
+            GeneralsError::UnknownTeam(team) => write!(f, "there is no team {}", team),
+            GeneralsError::UnknownBot(ref name) => write!(f, "there is no bot '{}'", name),
+            GeneralsError::NoUnits(cell) => write!(f, "{:?} has no units", cell),
+            GeneralsError::BadMap(ref reason) => write!(f, "bad map: {}", reason),
+            GeneralsError::BrokenInvariant(ref reason) => write!(f, "broken invariant: {}", reason),
+            GeneralsError::Io(ref e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for GeneralsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            GeneralsError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GeneralsError {
+    fn from(e: io::Error) -> Self {
+        GeneralsError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for GeneralsError {
+    fn from(e: serde_json::Error) -> Self {
+        if e.is_io() {
+            GeneralsError::Io(e.into())
+        } else {
+            GeneralsError::BadMap(e.to_string())
+        }
+    }
+}
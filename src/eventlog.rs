@@ -0,0 +1,192 @@
+//! A log of what players do and what happens in games, for looking into desyncs, bot behavior
+//! and balance after the fact. Every line of the log file is a JSON object:
+//!
+//! ```text
+//! {"kind":"command","game":0,"tick":12,"team":1,"command":{"Move":[[4,7],"Up"]}}
+//! {"kind":"event","game":0,"tick":13,"event":{"Captured":{"team":1,"pos":[4,6],"from":null}}}
+//! ```
+//!
+//! Commands are logged with the tick they were given during, and events with the tick they
+//! happened in. Logging is off until `enable` is called, and like `profile` it is per thread.
+//! Only games passed to `attach` are logged, each under its own id, so that the copies bots
+//! search with and the games replays are played back in stay out of the log.
+
+use std::cell::{Cell, RefCell};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde_json;
+
+use frontend::Command;
+use generals::{GameEvent, GameState, Observer, Team};
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Entry<'a> {
+    Command {
+        game: u64,
+        tick: usize,
+        team: Team,
+        command: &'a Command,
+    },
+    Event {
+        game: u64,
+        tick: usize,
+        event: &'a GameEvent,
+    },
+}
+
+/// Any line of the log, read back. Only events are kept, so the command is not read.
+#[derive(Deserialize)]
+struct LoggedEntry {
+    kind: String,
+    game: u64,
+    tick: usize,
+    #[serde(default)]
+    event: Option<GameEvent>,
+}
+
+thread_local! {
+    static LOG: RefCell<Option<BufWriter<File>>> = const { RefCell::new(None) };
+    static NEXT_GAME: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Start logging to a new file at `path`, on this thread.
+pub fn enable<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    LOG.with(|log| *log.borrow_mut() = Some(file));
+    Ok(())
+}
+
+/// Stop logging, and flush what is left.
+pub fn disable() {
+    LOG.with(|log| {
+        if let Some(mut file) = log.borrow_mut().take() {
+            let _ = file.flush();
+        }
+    });
+}
+
+pub fn is_enabled() -> bool {
+    LOG.with(|log| log.borrow().is_some())
+}
+
+/// Write `entries` and flush. Logging stops on the first error, so that a full disk does not
+/// take the game down with it.
+fn write(entries: &[Entry]) {
+    LOG.with(|log| {
+        let mut log = log.borrow_mut();
+        let result = match *log {
+            Some(ref mut file) => {
+                entries.iter()
+                    .try_for_each(|entry| {
+                        serde_json::to_writer(&mut *file, entry)?;
+                        file.write_all(b"\n").map_err(serde_json::Error::io)
+                    })
+                    .map_err(io::Error::from)
+                    .and_then(|_| file.flush())
+            }
+            None => return,
+        };
+        if let Err(e) = result {
+            println!("Stopped logging events: {}", e);
+            *log = None;
+        }
+    });
+}
+
+/// Logs the commands and events of the game it observes, under the id `game`.
+pub struct GameLog {
+    game: u64,
+}
+
+impl Observer for GameLog {
+    fn on_tick(&mut self, game: &GameState, events: &[GameEvent]) {
+        let tick = game.tick_number;
+        let entries = events.iter()
+            .map(|event| Entry::Event { game: self.game, tick, event })
+            .collect::<Vec<_>>();
+        write(&entries);
+    }
+
+    fn on_command(&mut self, game: &GameState, team: Team, command: &Command) {
+        write(&[Entry::Command { game: self.game, tick: game.tick_number, team, command }]);
+    }
+}
+
+/// Log what happens in `game` from now on, under the next free game id, if logging is enabled
+/// on this thread. Returns the id.
+pub fn attach(game: &mut GameState) -> Option<u64> {
+    if !is_enabled() {
+        return None;
+    }
+    let id = NEXT_GAME.with(|next| {
+        let id = next.get();
+        next.set(id + 1);
+        id
+    });
+    game.add_observer(GameLog { game: id });
+    Some(id)
+}
+
+/// Read the events of the log at `path`, with the game and the tick each happened in.
+pub fn read_events<P: AsRef<Path>>(path: P) -> io::Result<Vec<(u64, usize, GameEvent)>> {
+    let mut events = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let entry: LoggedEntry = serde_json::from_str(&line?).map_err(io::Error::from)?;
+        if let (Some(event), "event") = (entry.event, entry.kind.as_str()) {
+            events.push((entry.game, entry.tick, event));
+        }
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use frontend;
+    use generals::{Board, Cell, Direction, Position};
+    use super::*;
+
+    /// A 2-player game on a 3x1 board, where team 0 can take the cell next to its King.
+    fn game() -> GameState {
+        let cells = vec![Cell::King(0, 5), Cell::Open, Cell::King(1, 1)];
+        GameState::new(Board::from_cells(3, cells).unwrap(), 2)
+    }
+
+    #[test]
+    fn only_attached_games_are_logged_each_under_its_own_id() {
+        let path = env::temp_dir().join(format!("generals-eventlog-{}.jsonl", process::id()));
+        enable(&path).unwrap();
+        let (mut first, mut second) = (game(), game());
+        assert_eq!(attach(&mut first), Some(0));
+        assert_eq!(attach(&mut second), Some(1));
+        // Like the games search bots play out, a copy is not logged.
+        let mut copy = first.clone();
+        let ticked = vec![&mut first, &mut copy, &mut second]
+            .into_iter()
+            .map(|game| {
+                let mv = (Position(0, 0), Direction::Right);
+                frontend::apply(game, 0, Command::Move(mv)).unwrap();
+                game.tick().len()
+            })
+            .collect::<Vec<_>>();
+        disable();
+        let events = read_events(&path).unwrap();
+        let commands = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .filter(|line| line.contains(r#""kind":"command""#))
+            .count();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(commands, 2);
+        assert!(ticked[0] > 0);
+        let games = events.iter().map(|&(game, _, _)| game).collect::<Vec<_>>();
+        let expected = vec![0; ticked[0]].into_iter().chain(vec![1; ticked[2]]).collect::<Vec<_>>();
+        assert_eq!(games, expected);
+    }
+}
@@ -0,0 +1,83 @@
+//! `--export <replay> [--every N] [--out PATH] [--delay MS]`
+//!
+//! Render a replay without opening a window, for sharing finished games. If `PATH` ends in
+//! `.gif` the frames are encoded as an animated GIF, otherwise they are written as numbered PNG
+//! images into the directory `PATH`. Only every `N`th tick is drawn.
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+use gif::{self, SetParameter};
+
+use generals::replay::Replay;
+use generals::sim::parse_flag;
+
+use config::Config;
+use tile_color;
+use ui::screenshot;
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let i = args.iter()
+        .position(|a| a == "--export")
+        .ok_or("missing --export")?;
+    let replay_path = args.get(i + 1).ok_or("--export needs a replay file")?;
+    let every = parse_flag(args, "--every", 5usize)?.max(1);
+    let out = parse_flag(args, "--out", "replay.gif".to_string())?;
+    let delay_ms = parse_flag(args, "--delay", 100u16)?;
+
+    let replay = Replay::load(replay_path).map_err(|e| format!("Failed to load replay: {}", e))?;
+    let settings = Config::load().unwrap_or_default().settings;
+    let (theme, palette) = (settings.theme(), settings.palette());
+    let frames = (0..=replay.len()).step_by(every).map(|tick| {
+        let view = replay.state_at(tick).full_view(0);
+        screenshot::render(&view, |tile| tile_color(&theme, &palette, tile), theme.grid)
+    });
+
+    let written = if out.ends_with(".gif") {
+        write_gif(&out, frames, delay_ms)
+    } else {
+        write_pngs(&out, frames)
+    };
+    let count = written.map_err(|e| format!("Failed to export to {}: {}", out, e))?;
+    println!("Wrote {} frames to {}", count, out);
+    Ok(())
+}
+
+/// Write the frames as one looping GIF, and return how many there were.
+fn write_gif<I>(path: &str, frames: I, delay_ms: u16) -> ::std::io::Result<usize>
+where
+    I: Iterator<Item = (Vec<u8>, u32, u32)>,
+{
+    let mut frames = frames.peekable();
+    let (width, height) = match frames.peek() {
+        Some(&(_, w, h)) => (w as u16, h as u16),
+        None => return Ok(0),
+    };
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = gif::Encoder::new(file, width, height, &[])?;
+    encoder.set(gif::Repeat::Infinite)?;
+    let mut count = 0;
+    for (mut pixels, w, h) in frames {
+        let mut frame = gif::Frame::from_rgba(w as u16, h as u16, &mut pixels);
+        // GIF delays are in hundredths of a second.
+        frame.delay = delay_ms / 10;
+        encoder.write_frame(&frame)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Write the frames as `frame-00000.png`, ... into `dir`, and return how many there were.
+fn write_pngs<I>(dir: &str, frames: I) -> ::std::io::Result<usize>
+where
+    I: Iterator<Item = (Vec<u8>, u32, u32)>,
+{
+    fs::create_dir_all(dir)?;
+    let mut count = 0;
+    for (i, (pixels, w, h)) in frames.enumerate() {
+        screenshot::write_png(Path::new(dir).join(format!("frame-{:05}.png", i)), &pixels, w, h)?;
+        count += 1;
+    }
+    Ok(count)
+}
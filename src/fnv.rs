@@ -0,0 +1,65 @@
+//! FNV-1a, a hash whose output is fixed by its definition, unlike the standard hasher's. It is
+//! for hashes that are compared across builds and machines, so everything hashed has to be fed
+//! in as explicit bytes, with integers little-endian.
+
+const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+pub struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(OFFSET_BASIS)
+    }
+}
+
+impl Fnv {
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ u64::from(b)).wrapping_mul(PRIME);
+        }
+    }
+
+    pub fn u8(&mut self, n: u8) {
+        self.bytes(&[n]);
+    }
+
+    pub fn i32(&mut self, n: i32) {
+        self.bytes(&n.to_le_bytes());
+    }
+
+    /// Sizes and counts are hashed as 64 bits, so that the hash is the same on 32-bit
+    /// platforms.
+    pub fn usize(&mut self, n: usize) {
+        self.bytes(&(n as u64).to_le_bytes());
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fnv(bytes: &[u8]) -> u64 {
+        let mut hash = Fnv::default();
+        hash.bytes(bytes);
+        hash.finish()
+    }
+
+    #[test]
+    fn matches_the_published_test_vectors() {
+        assert_eq!(fnv(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn integers_are_hashed_little_endian() {
+        let mut hash = Fnv::default();
+        hash.usize(1);
+        assert_eq!(hash.finish(), fnv(&[1, 0, 0, 0, 0, 0, 0, 0]));
+    }
+}
@@ -0,0 +1,62 @@
+//! The interface between the engine and the programs that show games to people. A frontend only
+//! sees the fogged view of its own team and the events of each tick, and answers with commands,
+//! so the same local game loop can drive the windowed client, a terminal, or nothing at all.
+
+use bot::BotPlayer;
+use error::GeneralsError;
+use generals::{GameEvent, GameState, Move, PlayerView, Team};
+
+/// What a player can ask of the game.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Command {
+    /// Add a move to the end of the queue.
+    Move(Move),
+    ClearMoves,
+    /// Keep only the first moves of the queue.
+    TruncateMoves(usize),
+}
+
+/// Apply `command`, given by `team`, to a game.
+pub fn apply(game: &mut GameState, team: Team, command: Command) -> Result<(), GeneralsError> {
+    game.announce_command(team, &command);
+    match command {
+        Command::Move(mv) => game.queue_move(team, mv)?,
+        Command::ClearMoves => game.player_state_mut(team)?.moves.clear(),
+        Command::TruncateMoves(keep) => game.player_state_mut(team)?.moves.truncate(keep),
+    }
+    Ok(())
+}
+
+pub trait Frontend {
+    /// Whatever the frontend draws with, like a window or a terminal.
+    type Context;
+    type Error;
+
+    /// Show the game as `view` sees it.
+    fn render(&mut self, ctx: &mut Self::Context, view: &PlayerView) -> Result<(), Self::Error>;
+
+    /// Something happened during the last tick.
+    fn event(&mut self, _event: &GameEvent) {}
+
+    /// The commands given since the last call. Frontends that apply commands themselves as
+    /// input arrives, like the windowed client, return none.
+    fn commands(&mut self) -> Vec<Command> {
+        Vec::new()
+    }
+}
+
+/// Run one tick of a local game where `frontend` plays `team` and `bots` play the rest: apply
+/// the commands of the frontend, let the bots act, tick, and tell the frontend what happened.
+/// Commands that can't be applied, like moves off the board, are dropped.
+pub fn step<F: Frontend>(frontend: &mut F, game: &mut GameState, bots: &mut [BotPlayer],
+                         team: Team) {
+    for command in frontend.commands() {
+        let _ = apply(game, team, command);
+    }
+    for bot in bots.iter_mut() {
+        bot.act(game);
+    }
+    for event in game.tick() {
+        frontend.event(&event);
+    }
+}
@@ -1,18 +1,88 @@
 use std::collections::VecDeque;
-use rand::{self, Rng};
+use std::fmt;
+use std::hash::Hash;
+use std::mem;
+#[cfg(feature = "parallel")]
+use std::thread;
+use rand::{self, Rng, SeedableRng, XorShiftRng};
 use rand::distributions::{Weighted, WeightedChoice, IndependentSample};
 
+use error::GeneralsError;
+use fnv::Fnv;
+use frontend::Command;
+use profile::{self, Section};
+
+mod generation;
+mod handicap;
+mod horde;
+mod mode;
+pub mod observer;
+mod packed;
+pub mod path;
+pub mod victory;
+
+pub use self::generation::Generation;
+pub use self::handicap::Handicap;
+pub use self::mode::{GameMode, Hill, DEFAULT_HOLD_TICKS, DEFAULT_TICK_LIMIT};
+pub use self::observer::Observer;
+pub use self::victory::{Verdict, VictoryCondition};
+use self::victory::Conditions;
+use self::observer::Observers;
+pub use self::packed::{CellMut, PackedCell, MAX_TEAMS, MAX_UNITS};
+
+/// Boards with at least this many cells grow on several threads, with the `parallel` feature.
+/// Below it, starting the threads takes longer than the growth itself.
+#[cfg(feature = "parallel")]
+const PARALLEL_GROWTH_CELLS: usize = 256 * 256;
+
+/// Kings and fortresses grow every other tick, and all other land every this many ticks.
+pub const LAND_GROWTH_INTERVAL: usize = 32;
+/// The smallest board `MapSettings::check` lets through.
+pub const MIN_BOARD_SIZE: usize = 4;
+
+#[derive(Clone)]
 pub struct GameState {
     pub board: Board,
     pub tick_number: usize,
     pub num_players: usize,
     pub player_states: Vec<PlayerState>,
     pub dimens: (i32, i32),
+    /// What changed during the last tick.
+    delta: BoardDelta,
+    observers: Observers,
+    mode: GameMode,
+    /// The victory conditions of the mode, and the ones added with `add_victory_condition`.
+    conditions: Conditions,
+    /// The objective of king of the hill games.
+    hill: Option<Hill>,
+    generation: Option<Generation>,
+    /// The cells changed with `edit_cell` since the last tick.
+    edits: Vec<(Position, Cell)>,
+    /// Run `check_invariants` after every tick, and panic if it fails. On by default in debug
+    /// builds.
+    pub check_each_tick: bool,
+    /// Hand all the land of a player whose King is taken to whoever took it. Off by default,
+    /// which leaves their land on the board. Players who forfeit keep their land either way.
+    pub transfer_on_elimination: bool,
+}
+
+/// A saved copy of a game, to go back to with `GameState::restore`.
+#[derive(Clone)]
+pub struct Snapshot(GameState);
+
+impl Snapshot {
+    pub fn tick_number(&self) -> usize {
+        self.0.tick_number
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.0
+    }
 }
 
 pub type Team = usize;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Cell {
     Mountain,
     // TODO: make `Open(usize)`, and have it always be zero?
@@ -22,7 +92,17 @@ pub enum Cell {
     Captured(Team, usize),
 }
 
-#[derive(Debug, Clone, Copy)]
+/// What a cell looks like to a player.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Tile {
+    Visible(Cell),
+    /// An unseen cell.
+    Fog,
+    /// An unseen mountain or fortress: you can tell something is there, but not what.
+    Obstacle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Direction {
     Up,
     Down,
@@ -30,6 +110,8 @@ pub enum Direction {
     Right,
 }
 
+pub const DIRECTIONS: [Direction; 4] = Direction::ALL;
+
 /// A movement, from a position in a direction.
 pub type Move = (Position, Direction);
 
@@ -38,15 +120,143 @@ pub struct PlayerState {
     pub moves: VecDeque<Move>,
     pub dead: bool,
     pub team: Team,
+    pub stats: PlayerStats,
+    /// The handicap the player was given at the start.
+    pub handicap: Handicap,
 }
 
+/// Running totals of a player over the game, kept up to date by `GameState::tick`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PlayerStats {
+    /// The total number of units after the last tick.
+    pub army: usize,
+    /// The number of cells controlled after the last tick.
+    pub land: usize,
+    /// The number of fortresses and Kings taken so far.
+    pub cities_captured: usize,
+    /// The number of moves that were made, not counting the ones dropped from the queue.
+    pub moves_executed: usize,
+}
+
+/// Something noteworthy that happened during a tick.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum GameEvent {
+    /// `team` executed the move `mv`, moving `units` units.
+    Moved { team: Team, mv: Move, units: usize },
+    /// `team` took the cell at `pos` from `from`, or from no one if it was open.
+    Captured {
+        team: Team,
+        pos: Position,
+        from: Option<Team>,
+    },
+    /// `team` took the fortress at `pos` from `from`, or from no one if it was neutral.
+    CityCaptured {
+        team: Team,
+        pos: Position,
+        from: Option<Team>,
+    },
+    /// `team` took the King of `victim` at `pos`, eliminating them.
+    KingCaptured {
+        team: Team,
+        pos: Position,
+        victim: Team,
+    },
+}
 
-#[derive(Clone, Copy)]
+/// Publicly known statistics of a player.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Score {
+    pub team: Team,
+    /// The total number of units.
+    pub army: usize,
+    /// The number of cells controlled.
+    pub land: usize,
+    /// The number of fortresses controlled, including the King.
+    pub cities: usize,
+    pub dead: bool,
+}
+
+/// The game as seen by a single player. Only cells next to cells the player controls are
+/// visible.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerView {
+    pub team: Team,
+    pub tick_number: usize,
+    pub dimens: (i32, i32),
+    pub tiles: Vec<Vec<Tile>>,
+    /// The players own move queue.
+    pub moves: VecDeque<Move>,
+    /// The scores of all players, which are not fogged.
+    pub scores: Vec<Score>,
+    #[serde(default)]
+    pub mode: GameMode,
+    /// The hill of king of the hill games, which is not fogged either.
+    #[serde(default)]
+    pub hill: Option<Hill>,
+}
+
+/// The changes to a `PlayerView` from one tick to the next. Unlike the view itself, it only
+/// holds the tiles that changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewDelta {
+    pub tick_number: usize,
+    pub tiles: Vec<(Position, Tile)>,
+    pub moves: VecDeque<Move>,
+    pub scores: Vec<Score>,
+    #[serde(default)]
+    pub hill: Option<Hill>,
+}
+
+/// What changed on the board during a tick.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BoardDelta {
+    pub tick_number: usize,
+    /// The cells that changed, and what they are now.
+    pub cells: Vec<(Position, Cell)>,
+    /// The players that were eliminated.
+    pub eliminated: Vec<Team>,
+}
+
+impl BoardDelta {
+    pub fn apply(&self, board: &mut Board) -> Result<(), GeneralsError> {
+        for &(Position(x, y), cell) in self.cells.iter() {
+            board.set(x, y, cell)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Position(pub i32, pub i32);
 
-#[derive(Debug)]
+impl Position {
+    /// The Manhattan distance to `other`, which is the number of moves it takes to get there on
+    /// a board without obstacles.
+    pub fn distance(self, other: Position) -> i32 {
+        let Position(dx, dy) = self - other;
+        dx.abs() + dy.abs()
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.0, self.1)
+    }
+}
+
+/// The cells of the board, row by row: the cell at `(x, y)` is `cells[y * width + x]`. They are
+/// stored packed, so cells are handed out by value rather than by reference.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Board {
-    cells: Vec<Vec<Cell>>,
+    width: usize,
+    height: usize,
+    cells: Vec<PackedCell>,
+}
+
+/// A deterministic random number generator, for reproducible maps and bots.
+pub fn seeded_rng(seed: u32) -> XorShiftRng {
+    // XorShift must not be seeded with all zeros.
+    XorShiftRng::from_seed([seed, 0x193a_6754, 0xa8a7_d469, 0x9783_0e05])
 }
 
 impl Cell {
@@ -61,7 +271,60 @@ impl Cell {
         }
     }
 
-    pub fn take_units(&mut self) -> usize {
+    pub fn owner(&self) -> Option<Team> {
+        use Cell::*;
+        match *self {
+            Mountain | Open | Fortress(None, _) => None,
+            Fortress(Some(t), _) |
+            King(t, _) |
+            Captured(t, _) => Some(t),
+        }
+    }
+
+    /// The units in the cell, for cells that can hold units.
+    pub fn units(&self) -> Option<usize> {
+        use Cell::*;
+        match *self {
+            Mountain | Open => None,
+            Fortress(_, n) |
+            King(_, n) |
+            Captured(_, n) => Some(n),
+        }
+    }
+
+    /// Whether units can move onto the cell.
+    pub fn is_passable(&self) -> bool {
+        !matches!(*self, Cell::Mountain)
+    }
+
+    /// Hand the cell over to `team`, keeping its units. Open cells become captured with no
+    /// units, and a King becomes a fortress, as it does when it is taken.
+    pub fn set_owner(&mut self, team: Team) -> Result<(), GeneralsError> {
+        use Cell::*;
+        *self = match *self {
+            Mountain => return Err(GeneralsError::NoUnits(*self)),
+            Open => Captured(team, 0),
+            Fortress(_, n) | King(_, n) => Fortress(Some(team), n),
+            Captured(_, n) => Captured(team, n),
+        };
+        Ok(())
+    }
+
+    pub fn set_units(&mut self, num: usize) -> Result<(), GeneralsError> {
+        use Cell::*;
+        match *self {
+            Fortress(_, ref mut n) |
+            King(_, ref mut n) |
+            Captured(_, ref mut n) => {
+                *n = num;
+                Ok(())
+            }
+            _ => Err(GeneralsError::NoUnits(*self)),
+        }
+    }
+
+    /// Take all units but one out of the cell, and return how many were taken.
+    pub fn take_units(&mut self) -> Result<usize, GeneralsError> {
         use Cell::*;
         match *self {
             Fortress(_, ref mut n) |
@@ -69,34 +332,127 @@ impl Cell {
             Captured(_, ref mut n) => {
                 let num = *n;
                 *n = 1;
-                num - 1
+                Ok(num - 1)
             }
-            _ => panic!("Cell {:?} has no units!", self),
+            _ => Err(GeneralsError::NoUnits(*self)),
         }
     }
 
-    pub fn give_units(&mut self, num: usize) {
+    pub fn give_units(&mut self, num: usize) -> Result<(), GeneralsError> {
         use Cell::*;
         match *self {
             Fortress(_, ref mut n) |
             King(_, ref mut n) |
             Captured(_, ref mut n) => {
                 *n += num;
+                Ok(())
             }
-            _ => panic!("Cell {:?} has no units!", self),
+            _ => Err(GeneralsError::NoUnits(*self)),
         }
     }
 }
 
 
+/// Parameters for generating random maps.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapSettings {
+    /// Side length of the board. `None` scales it with the number of players.
+    pub size: Option<usize>,
+    /// The number of neutral fortresses. `None` scales it with the size of the board.
+    pub fortresses: Option<usize>,
+    /// The number of cells per player when scaling the board.
+    pub cells_per_player: usize,
+    pub min_size: usize,
+    pub max_size: usize,
+    /// The fraction of cells that are fortresses when scaling the fortress count.
+    pub fortress_density: f32,
+}
+
+impl Default for MapSettings {
+    fn default() -> Self {
+        MapSettings {
+            size: None,
+            fortresses: None,
+            cells_per_player: 450,
+            min_size: 16,
+            max_size: 128,
+            fortress_density: 0.025,
+        }
+    }
+}
+
+impl MapSettings {
+    pub fn board_size(&self, num_players: usize) -> usize {
+        self.size.unwrap_or_else(|| {
+            let size = ((num_players * self.cells_per_player) as f32).sqrt().round() as usize;
+            size.max(self.min_size).min(self.max_size)
+        })
+    }
+
+    pub fn num_fortresses(&self, board_size: usize) -> usize {
+        self.fortresses.unwrap_or_else(|| {
+            ((board_size * board_size) as f32 * self.fortress_density).round() as usize
+        })
+    }
+
+    /// Check that the settings make a board for `num_players` no larger than `max_size`
+    /// cells across, with room for every king and fortress.
+    pub fn check(&self, num_players: usize, max_size: usize) -> Result<(), GeneralsError> {
+        let bad = |reason: String| Err(GeneralsError::BadMap(reason));
+        if num_players == 0 {
+            return bad("a game needs at least one player".to_string());
+        }
+        if self.min_size < MIN_BOARD_SIZE || self.min_size > self.max_size {
+            return bad(format!("the board size range {}..{} is empty or below {}",
+                               self.min_size, self.max_size, MIN_BOARD_SIZE));
+        }
+        if self.max_size > max_size {
+            return bad(format!("boards can be at most {} cells across", max_size));
+        }
+        if self.cells_per_player == 0 || self.cells_per_player > max_size * max_size {
+            return bad(format!("{} cells per player is out of range", self.cells_per_player));
+        }
+        if !(self.fortress_density >= 0.0 && self.fortress_density <= 1.0) {
+            return bad(format!("fortress density {} is not between 0 and 1",
+                               self.fortress_density));
+        }
+        let size = self.board_size(num_players);
+        if size < MIN_BOARD_SIZE || size > max_size {
+            return bad(format!("boards must be between {} and {} cells across",
+                               MIN_BOARD_SIZE, max_size));
+        }
+        if num_players + self.num_fortresses(size) > size * size {
+            return bad(format!("{} fortresses don't fit on a {}x{} board",
+                               self.num_fortresses(size), size, size));
+        }
+        Ok(())
+    }
+}
+
 impl Board {
     pub fn empty(n: usize) -> Self {
-        let cells = (0..n).map(|_| vec![Cell::Open; n]).collect::<Vec<_>>();
-        Board { cells }
+        Board {
+            width: n,
+            height: n,
+            cells: vec![PackedCell::pack(Cell::Open); n * n],
+        }
+    }
+
+    /// Generate a random board, sized after the number of players unless `settings` says
+    /// otherwise.
+    pub fn generate<R: Rng>(settings: &MapSettings, num_players: usize, rng: &mut R) -> Self {
+        let size = settings.board_size(num_players);
+        let mut board = Board::empty(size);
+        board.randomize_with(num_players, settings.num_fortresses(size), rng);
+        board
     }
 
     pub fn randomize(&mut self, num_players: usize) {
-        let mut rng = rand::thread_rng();
+        let fortresses = MapSettings::default().num_fortresses(self.width.max(self.height));
+        self.randomize_with(num_players, fortresses, &mut rand::thread_rng());
+    }
+
+    pub fn randomize_with<R: Rng>(&mut self, num_players: usize, fortresses: usize, rng: &mut R) {
         let mut items = [
             Weighted {
                 weight: 100,
@@ -106,49 +462,255 @@ impl Board {
                 weight: 10,
                 item: Cell::Mountain,
             },
-            Weighted {
-                weight: 3,
-                item: Cell::Fortress(None, 0),
-            },
         ];
         let wc = WeightedChoice::new(&mut items);
 
-        for row in self.cells.iter_mut() {
-            for cell in row.iter_mut() {
-                *cell = wc.ind_sample(&mut rng);
-                match *cell {
-                    Cell::Fortress(_, ref mut n) => {
-                        *n = rng.gen_range(40, 50);
-                    }
-                    _ => {}
+        for cell in self.cells.iter_mut() {
+            *cell = PackedCell::pack(wc.ind_sample(rng));
+        }
+        // The row is drawn first, as it always has been, so that seeds keep giving the same maps.
+        let (w, h) = (self.width, self.height);
+        for _ in 0..fortresses {
+            let (y, x) = (rng.gen_range(0, h), rng.gen_range(0, w));
+            self.cells[y * w + x] = PackedCell::pack(Cell::Fortress(None, rng.gen_range(40, 50)));
+        }
+        // Kings get a cell of their own, away from the others so that nobody starts next to
+        // someone else. If there is no room for that after a while, any free cell will do.
+        const KING_ATTEMPTS: usize = 100;
+        let min_distance = ((w * h) as f64 / num_players.max(1) as f64).sqrt() as i32 / 2;
+        let mut kings = Vec::new();
+        for team in 0..num_players {
+            let mut attempts = 0;
+            let (x, y) = loop {
+                let (y, x) = (rng.gen_range(0, h), rng.gen_range(0, w));
+                let pos = Position(x as i32, y as i32);
+                let free = !kings.contains(&pos);
+                let far = kings.iter().all(|&king| pos.distance(king) >= min_distance);
+                attempts += 1;
+                if free && (far || attempts >= KING_ATTEMPTS) || attempts >= 2 * KING_ATTEMPTS {
+                    kings.push(pos);
+                    break (x, y);
                 }
+            };
+            self.cells[y * w + x] = PackedCell::pack(Cell::King(team, 1));
+        }
+    }
+
+    /// A board of `width` with `cells` given row by row. Cells of teams that are not below
+    /// `MAX_TEAMS` are an error.
+    pub fn from_cells(width: usize, cells: Vec<Cell>) -> Result<Self, GeneralsError> {
+        if width == 0 || !cells.len().is_multiple_of(width) {
+            let reason = format!("{} cells don't make rows of {}", cells.len(), width);
+            return Err(GeneralsError::BadMap(reason));
+        }
+        Ok(Board {
+            width,
+            height: cells.len() / width,
+            cells: cells.into_iter().map(PackedCell::try_pack).collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// All cells, row by row.
+    pub fn cells<'a>(&'a self) -> impl Iterator<Item = Cell> + 'a {
+        self.cells.iter().map(|packed| packed.unpack())
+    }
+
+    /// Every cell with its position, row by row.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (Position, Cell)> + 'a {
+        let width = self.width;
+        self.cells()
+            .enumerate()
+            .map(move |(i, cell)| (Position((i % width) as i32, (i / width) as i32), cell))
+    }
+
+    /// Every cell with its position, row by row, to change in place. Cells are stored packed,
+    /// so each one is unpacked, and packed again when its `CellMut` is dropped.
+    pub fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = (Position, CellMut<'a>)> + 'a {
+        let width = self.width;
+        self.cells
+            .iter_mut()
+            .enumerate()
+            .map(move |(i, packed)| {
+                (Position((i % width) as i32, (i / width) as i32), CellMut::new(packed))
+            })
+    }
+
+    /// The cells next to `pos` that are on the board, in the order of `DIRECTIONS`.
+    pub fn neighbors<'a>(&'a self, pos: Position) -> impl Iterator<Item = (Position, Cell)> + 'a {
+        DIRECTIONS.iter().filter_map(move |&dir| {
+            let Position(x, y) = pos + dir;
+            self.try_get(x, y).map(|cell| (Position(x, y), cell))
+        })
+    }
+
+    /// The cells controlled by `team`.
+    pub fn cells_owned_by<'a>(&'a self, team: Team) -> impl Iterator<Item = (Position, Cell)> + 'a {
+        self.iter().filter(move |&(_, cell)| cell.is_controlled_by(team))
+    }
+
+    /// Give every cell of `from` to `to`, and return where they are.
+    fn hand_over(&mut self, from: Team, to: Team) -> Vec<Position> {
+        let mut cells = Vec::new();
+        for (pos, mut cell) in self.iter_mut() {
+            if cell.is_controlled_by(from) {
+                let _ = cell.set_owner(to);
+                cells.push(pos);
             }
         }
-        let n = self.cells.len();
-        for team in 0..num_players {
-            let (x, y) = (rng.gen_range(0, n), rng.gen_range(0, n));
-            self.cells[x][y] = Cell::King(team, 1);
+        cells
+    }
+
+    /// The rows of the board, from the top.
+    pub fn rows<'a>(&'a self) -> impl Iterator<Item = Vec<Cell>> + 'a {
+        self.cells
+            .chunks(self.width.max(1))
+            .map(|row| row.iter().map(|packed| packed.unpack()).collect())
+    }
+
+    /// The `(width, height)` of the board.
+    pub fn dimens(&self) -> (i32, i32) {
+        (self.width as i32, self.height as i32)
+    }
+
+    /// The index of `(x, y)` in `cells`, if it is on the board.
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
         }
+        Some(y as usize * self.width + x as usize)
     }
 
-    pub fn cells(&self) -> &Vec<Vec<Cell>> {
-        &self.cells
+    /// The cell at `(x, y)`, which has to be on the board.
+    fn at(&self, x: i32, y: i32) -> Cell {
+        self.cells[y as usize * self.width + x as usize].unpack()
     }
 
-    pub fn get(&self, x: i32, y: i32) -> &Cell {
-        &self.cells[y as usize][x as usize]
+    /// Replace the cell at `(x, y)`, which has to be on the board.
+    fn put(&mut self, x: i32, y: i32, cell: Cell) {
+        self.cells[y as usize * self.width + x as usize] = PackedCell::pack(cell);
     }
 
-    pub fn try_get(&self, x: i32, y: i32) -> Option<&Cell> {
-        self.cells.get(y as usize).and_then(|r| r.get(x as usize))
+    pub fn get(&self, x: i32, y: i32) -> Result<Cell, GeneralsError> {
+        self.try_get(x, y).ok_or(GeneralsError::OutOfBounds(Position(x, y)))
     }
 
-    pub fn get_mut(&mut self, x: i32, y: i32) -> &mut Cell {
-        &mut self.cells[y as usize][x as usize]
+    pub fn try_get(&self, x: i32, y: i32) -> Option<Cell> {
+        self.index(x, y).and_then(|i| self.cells.get(i)).map(|packed| packed.unpack())
     }
+
+    pub fn set(&mut self, x: i32, y: i32, cell: Cell) -> Result<(), GeneralsError> {
+        match self.index(x, y) {
+            Some(i) if i < self.cells.len() => {
+                self.cells[i] = PackedCell::try_pack(cell)?;
+                Ok(())
+            }
+            _ => Err(GeneralsError::OutOfBounds(Position(x, y))),
+        }
+    }
+
+    /// Add units to every fortress and King that has an owner, and to every captured cell if
+    /// `update_all`: `growth[team]` units to the cells of `team`, or one if it has no entry.
+    /// Returns the positions of the cells that grew, row by row.
+    #[cfg(feature = "parallel")]
+    fn grow(&mut self, update_all: bool, growth: &[usize]) -> Vec<Position> {
+        let width = self.width;
+        if self.cells.len() < PARALLEL_GROWTH_CELLS {
+            return grow_cells(&mut self.cells, 0, width, update_all, growth);
+        }
+        let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let chunk = self.cells.len().div_ceil(threads);
+        thread::scope(|scope| {
+            let handles = self.cells
+                .chunks_mut(chunk)
+                .enumerate()
+                .map(|(i, cells)| {
+                    scope.spawn(move || grow_cells(cells, i * chunk, width, update_all, growth))
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("the growth pass panicked"))
+                .collect()
+        })
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn grow(&mut self, update_all: bool, growth: &[usize]) -> Vec<Position> {
+        let width = self.width;
+        grow_cells(&mut self.cells, 0, width, update_all, growth)
+    }
+
+    /// Check that the board can be played with `num_players`: that its size matches its
+    /// cells, and that every King belongs to one of the players.
+    pub fn check(&self, num_players: usize) -> Result<(), GeneralsError> {
+        if self.cells.is_empty() {
+            return Err(GeneralsError::BadMap("the board is empty".to_string()));
+        }
+        if self.cells.len() != self.width * self.height {
+            let reason = format!("{} cells on a {}x{} board",
+                                 self.cells.len(),
+                                 self.width,
+                                 self.height);
+            return Err(GeneralsError::BadMap(reason));
+        }
+        for cell in self.cells() {
+            match cell.owner() {
+                Some(team) if team >= num_players => return Err(GeneralsError::UnknownTeam(team)),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The growth pass of `Board::grow` over `cells`, which start at `offset` in a board of
+/// `width`. Cells grow independently of each other, so this can be done in pieces.
+fn grow_cells(cells: &mut [PackedCell],
+              offset: usize,
+              width: usize,
+              update_all: bool,
+              growth: &[usize])
+    -> Vec<Position> {
+    let mut grown = Vec::new();
+    for (i, packed) in cells.iter_mut().enumerate() {
+        let grows = match packed.unpack() {
+            Cell::Fortress(Some(_), _) | Cell::King(_, _) => true,
+            Cell::Captured(_, _) => update_all,
+            _ => false,
+        };
+        if grows {
+            let units = packed.owner().and_then(|team| growth.get(team)).cloned().unwrap_or(1);
+            packed.grow(units);
+            let i = offset + i;
+            grown.push(Position((i % width) as i32, (i / width) as i32));
+        }
+    }
+    grown
 }
 
 impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    pub fn opposite(self) -> Direction {
+        use Direction::*;
+        match self {
+            Up => Down,
+            Down => Up,
+            Left => Right,
+            Right => Left,
+        }
+    }
+
+    /// The direction to go in from `a` to get to `b`, if they are next to each other.
+    pub fn between(a: Position, b: Position) -> Option<Direction> {
+        Direction::ALL.iter().cloned().find(|&dir| a + dir == b)
+    }
+
     pub fn to_xy(&self) -> (i32, i32) {
         use Direction::*;
         match *self {
@@ -172,57 +734,562 @@ impl Direction {
     }
 }
 
+impl Clone for PlayerState {
+    fn clone(&self) -> Self {
+        PlayerState {
+            moves: self.moves.clone(),
+            dead: self.dead,
+            team: self.team,
+            stats: self.stats,
+            handicap: self.handicap,
+        }
+    }
+
+    /// Reuses the move queue, for `GameState::restore`.
+    fn clone_from(&mut self, other: &Self) {
+        self.moves.clone_from(&other.moves);
+        self.dead = other.dead;
+        self.team = other.team;
+        self.stats = other.stats;
+        self.handicap = other.handicap;
+    }
+}
+
 impl PlayerState {
     pub fn new(team: Team) -> Self {
         Self {
             moves: VecDeque::new(),
             dead: false,
             team,
+            stats: PlayerStats::default(),
+            handicap: Handicap::default(),
+        }
+    }
+
+    /// The queued moves, in the order they will be made.
+    pub fn queued_moves(&self) -> impl Iterator<Item = &Move> {
+        self.moves.iter()
+    }
+
+    /// The move that will be made on the next tick, if any.
+    pub fn next_move(&self) -> Option<Move> {
+        self.moves.front().cloned()
+    }
+
+    pub fn queue_len(&self) -> usize {
+        self.moves.len()
+    }
+}
+
+impl PlayerView {
+    pub fn is_over(&self) -> bool {
+        self.scores.iter().filter(|s| !s.dead).count() <= 1
+    }
+
+    /// Bring the view up to date with the next tick. Returns `false`, and leaves the view as it
+    /// is, if the delta is not for the next tick or has tiles off the board; a full view is
+    /// needed to catch up then.
+    pub fn apply(&mut self, delta: &ViewDelta) -> bool {
+        if delta.tick_number != self.tick_number + 1 {
+            return false;
         }
+        let (w, h) = self.dimens;
+        if delta.tiles.iter().any(|&(Position(x, y), _)| x < 0 || y < 0 || x >= w || y >= h) {
+            return false;
+        }
+        for &(Position(x, y), tile) in delta.tiles.iter() {
+            self.tiles[y as usize][x as usize] = tile;
+        }
+        self.tick_number = delta.tick_number;
+        self.moves = delta.moves.clone();
+        self.scores = delta.scores.clone();
+        self.hill = delta.hill;
+        true
+    }
+    pub fn get(&self, x: i32, y: i32) -> Tile {
+        self.tiles[y as usize][x as usize]
+    }
+
+    pub fn try_get(&self, x: i32, y: i32) -> Option<Tile> {
+        self.tiles.get(y as usize).and_then(|r| r.get(x as usize)).cloned()
+    }
+
+    /// Where the King of the player is, unless it was taken.
+    pub fn king(&self) -> Option<Position> {
+        let (w, h) = self.dimens;
+        (0..h)
+            .flat_map(|y| (0..w).map(move |x| Position(x, y)))
+            .find(|&Position(x, y)| match self.get(x, y) {
+                Tile::Visible(Cell::King(team, _)) => team == self.team,
+                _ => false,
+            })
     }
 }
 
 impl GameState {
+    pub fn new(board: Board, num_players: usize) -> Self {
+        let dimens = board.dimens();
+        GameState {
+            board,
+            tick_number: 0,
+            num_players,
+            player_states: (0..num_players).map(PlayerState::new).collect(),
+            dimens,
+            delta: BoardDelta::default(),
+            observers: Observers::default(),
+            mode: GameMode::Classic,
+            conditions: Conditions::default(),
+            hill: None,
+            generation: None,
+            edits: Vec::new(),
+            check_each_tick: cfg!(debug_assertions),
+            transfer_on_elimination: false,
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot(self.clone())
+    }
+
+    /// Put the game back the way it was when `snapshot` was taken. This reuses the memory of
+    /// `self`, so restoring the same game over and over, like search bots do, doesn't allocate.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        let GameState {
+            ref board,
+            tick_number,
+            num_players,
+            ref player_states,
+            dimens,
+            ref delta,
+            observers: _,
+            mode,
+            ref conditions,
+            hill,
+            ref generation,
+            ref edits,
+            check_each_tick,
+            transfer_on_elimination,
+        } = snapshot.0;
+        self.board.width = board.width;
+        self.board.height = board.height;
+        self.board.cells.clone_from(&board.cells);
+        self.tick_number = tick_number;
+        self.num_players = num_players;
+        self.player_states.clone_from(player_states);
+        self.dimens = dimens;
+        self.delta.clone_from(delta);
+        self.mode = mode;
+        self.conditions = conditions.clone();
+        self.hill = hill;
+        self.generation.clone_from(generation);
+        self.edits.clone_from(edits);
+        self.check_each_tick = check_each_tick;
+        self.transfer_on_elimination = transfer_on_elimination;
+    }
+
+    /// A best guess of the full game from what a single player can see. Fog is assumed to be
+    /// open land, and obstacles to be mountains.
+    pub fn from_view(view: &PlayerView) -> Self {
+        let cells = view.tiles
+            .iter()
+            .flat_map(|row| row.iter())
+            .map(|tile| match *tile {
+                Tile::Visible(cell) => cell,
+                Tile::Fog => Cell::Open,
+                Tile::Obstacle => Cell::Mountain,
+            })
+            .collect::<Vec<_>>();
+        let (w, h) = view.dimens;
+        let board = Board {
+            width: w as usize,
+            height: h as usize,
+            cells: cells.into_iter().map(PackedCell::pack).collect(),
+        };
+        let mut game = GameState::new(board, view.scores.len());
+        // The Kings of the other players are usually somewhere in the fog.
+        game.check_each_tick = false;
+        game.tick_number = view.tick_number;
+        for score in view.scores.iter() {
+            game.player_states[score.team].dead = score.dead;
+        }
+        game.player_states[view.team].moves = view.moves.clone();
+        game.set_mode(view.mode);
+        game.hill = view.hill;
+        if game.horde().is_some() {
+            game.num_players -= 1;
+        }
+        game
+    }
+
+    /// The game is over when at most one player is left alive.
+    pub fn is_over(&self) -> bool {
+        self.player_states.iter().filter(|p| !p.dead).count() <= 1
+    }
+
+    /// The last player standing, if the game is over.
+    pub fn winner(&self) -> Option<Team> {
+        if !self.is_over() {
+            return None;
+        }
+        self.player_states.iter().find(|p| !p.dead).map(|p| p.team)
+    }
+
+    /// Take `team` out of the game. Their land stays on the board.
+    pub fn forfeit(&mut self, team: Team) {
+        let was_dead = {
+            let player = self.player_mut(team);
+            let was_dead = player.dead;
+            player.dead = true;
+            player.moves.clear();
+            was_dead
+        };
+        if !was_dead {
+            self.notify(|observer, _| observer.on_player_eliminated(team));
+        }
+    }
+
+    /// Tell `observer` about what happens in the game from now on. Observers are not copied
+    /// along with the game.
+    pub fn add_observer<O: Observer + 'static>(&mut self, observer: O) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Tell the observers that `team` gave `command`. Commands reach the game from outside the
+    /// engine, so whatever applies them calls this, like `frontend::apply` and `BotPlayer::act`.
+    pub fn announce_command(&mut self, team: Team, command: &Command) {
+        self.notify(|observer, game| observer.on_command(game, team, command));
+    }
+
+    /// Call `f` with every observer. The observers are taken out of the game while this runs,
+    /// so that they can look at it.
+    fn notify<F: FnMut(&mut dyn Observer, &GameState)>(&mut self, mut f: F) {
+        if self.observers.is_empty() {
+            return;
+        }
+        let mut observers = mem::take(&mut self.observers);
+        for observer in observers.iter_mut() {
+            f(&mut **observer, self);
+        }
+        self.observers = observers;
+    }
+
     pub fn player_mut(&mut self, player: usize) -> &mut PlayerState {
         &mut self.player_states[player]
     }
 
-    pub fn tick(&mut self) {
-        const ALL_UPDATE_INTERVAL: usize = 32;
-        self.tick_number += 1;
-        let update_tick = self.tick_number % 2 == 0;
-        let update_all = self.tick_number % ALL_UPDATE_INTERVAL == 0;
-        for row in self.board.cells.iter_mut() {
-            for cell in row.iter_mut() {
-                match *cell {
-                    Cell::Fortress(Some(_), ref mut n) |
-                    Cell::King(_, ref mut n) => {
-                        if update_tick {
-                            *n += 1;
-                        }
+    pub fn player_state(&self, team: Team) -> Result<&PlayerState, GeneralsError> {
+        self.player_states.get(team).ok_or(GeneralsError::UnknownTeam(team))
+    }
+
+    /// Like `player_mut`, for teams that may not be in the game.
+    pub fn player_state_mut(&mut self, team: Team) -> Result<&mut PlayerState, GeneralsError> {
+        self.player_states.get_mut(team).ok_or(GeneralsError::UnknownTeam(team))
+    }
+
+    /// Add `mv` to the end of the move queue of `team`. Both ends of the move have to be on the
+    /// board, but the cell it starts from may belong to anyone by the time it is made.
+    pub fn queue_move(&mut self, team: Team, mv: Move) -> Result<(), GeneralsError> {
+        let (from, dir) = mv;
+        if !self.in_bounds(from) || !self.in_bounds(from + dir) {
+            return Err(GeneralsError::InvalidMove(mv));
+        }
+        self.player_state_mut(team)?.moves.push_back(mv);
+        Ok(())
+    }
+
+    /// Replace the cell at `pos` with `cell`, outside of the rules, like the sandbox does.
+    /// Edits can leave the game in a state the rules don't allow, like a player without a
+    /// King, so this turns off `check_each_tick`.
+    pub fn edit_cell(&mut self, pos: Position, cell: Cell) -> Result<(), GeneralsError> {
+        if let Some(team) = cell.owner() {
+            self.player_state(team)?;
+        }
+        self.board.set(pos.0, pos.1, cell)?;
+        self.check_each_tick = false;
+        self.edits.push((pos, cell));
+        Ok(())
+    }
+
+    /// The edits made with `edit_cell` since the last tick, in order.
+    pub fn edits(&self) -> &[(Position, Cell)] {
+        &self.edits
+    }
+
+    pub fn in_bounds(&self, Position(x, y): Position) -> bool {
+        let (w, h) = self.dimens;
+        x >= 0 && y >= 0 && x < w && y < h
+    }
+
+    /// A view of the whole board for `team`, without any fog.
+    pub fn full_view(&self, team: Team) -> PlayerView {
+        PlayerView {
+            team,
+            tick_number: self.tick_number,
+            dimens: self.dimens,
+            tiles: self.board
+                .rows()
+                .map(|row| row.into_iter().map(Tile::Visible).collect())
+                .collect(),
+            moves: self.player_states[team].moves.clone(),
+            scores: self.scores(),
+            mode: self.mode,
+            hill: self.hill,
+        }
+    }
+
+    /// A player can see every cell that is next to, or diagonal to, a cell they control.
+    pub fn is_visible_to(&self, team: Team, x: i32, y: i32) -> bool {
+        for dy in -1..2 {
+            for dx in -1..2 {
+                if let Some(cell) = self.board.try_get(x + dx, y + dy) {
+                    if cell.is_controlled_by(team) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// The fogged view of the game for `team`.
+    pub fn player_view(&self, team: Team) -> PlayerView {
+        let _timer = profile::start(Section::Fog);
+        let (w, h) = self.dimens;
+        // Light up around every cell of the team, rather than looking around every cell for one.
+        let mut visible = vec![false; (w * h) as usize];
+        for (Position(x, y), _) in self.board.cells_owned_by(team) {
+            for dy in -1..2 {
+                for dx in -1..2 {
+                    if self.in_bounds(Position(x + dx, y + dy)) {
+                        visible[((y + dy) * w + x + dx) as usize] = true;
                     }
-                    Cell::Captured(_, ref mut n) if update_all => {
-                        if update_tick {
-                            *n += 1;
+                }
+            }
+        }
+        let tiles = self.board
+            .rows()
+            .zip(visible.chunks(w.max(1) as usize))
+            .map(|(row, visible)| {
+                row.into_iter().zip(visible.iter()).map(|(cell, &v)| fogged(cell, v)).collect()
+            })
+            .collect();
+        PlayerView {
+            team,
+            tick_number: self.tick_number,
+            dimens: self.dimens,
+            tiles,
+            moves: self.player_states[team].moves.clone(),
+            scores: self.scores(),
+            mode: self.mode,
+            hill: self.hill,
+        }
+    }
+
+    /// What `team` sees at `(x, y)`.
+    fn tile_for(&self, team: Team, x: i32, y: i32) -> Tile {
+        fogged(self.board.at(x, y), self.is_visible_to(team, x, y))
+    }
+
+    /// What changed on the board during the last tick.
+    pub fn last_delta(&self) -> &BoardDelta {
+        &self.delta
+    }
+
+    /// The changes to the view of `team` during the last tick, or to `full_view` if `team` is
+    /// `None`. Visibility only changes next to cells that changed, so only those are checked.
+    pub fn view_delta(&self, team: Option<Team>) -> ViewDelta {
+        let mut positions = Vec::new();
+        for &(Position(x, y), _) in self.delta.cells.iter() {
+            match team {
+                Some(_) => {
+                    for dy in -1..2 {
+                        for dx in -1..2 {
+                            if self.in_bounds(Position(x + dx, y + dy)) {
+                                positions.push((x + dx, y + dy));
+                            }
                         }
                     }
-                    _ => {}
                 }
+                None => positions.push((x, y)),
+            }
+        }
+        positions.sort();
+        positions.dedup();
+        let tiles = positions
+            .into_iter()
+            .map(|(x, y)| {
+                let tile = match team {
+                    Some(team) => self.tile_for(team, x, y),
+                    None => Tile::Visible(self.board.at(x, y)),
+                };
+                (Position(x, y), tile)
+            })
+            .collect();
+        ViewDelta {
+            tick_number: self.tick_number,
+            tiles,
+            moves: team.map(|t| self.player_states[t].moves.clone()).unwrap_or_default(),
+            scores: self.scores(),
+            hill: self.hill,
+        }
+    }
+
+    /// Check what the rules promise about the game: every cell belongs to a team in the game,
+    /// no cell has reached `MAX_UNITS`, where its count would be capped rather than grow, every
+    /// player still in the game has exactly one King, and with `transfer_on_elimination`,
+    /// players whose King was taken own nothing.
+    pub fn check_invariants(&self) -> Result<(), GeneralsError> {
+        let mut kings = vec![0; self.player_states.len()];
+        let mut land = vec![0; self.player_states.len()];
+        for (pos, cell) in self.board.iter() {
+            if let Some(team) = cell.owner() {
+                if team >= self.player_states.len() {
+                    return Err(GeneralsError::UnknownTeam(team));
+                }
+                land[team] += 1;
+            }
+            if let Cell::King(team, _) = cell {
+                kings[team] += 1;
+            }
+            if cell.units() == Some(MAX_UNITS) {
+                let reason = format!("the units at {} reached the cap of {}", pos, MAX_UNITS);
+                return Err(GeneralsError::BrokenInvariant(reason));
             }
         }
+        for player in self.player_states.iter() {
+            let team = player.team;
+            if !player.dead && kings[team] != 1 {
+                let reason = format!("team {} is alive with {} Kings", team, kings[team]);
+                return Err(GeneralsError::BrokenInvariant(reason));
+            }
+            // Players who forfeit keep their King, so only the ones whose King was taken. The
+            // horde never has one.
+            let taken = player.dead && kings[team] == 0 && Some(team) != self.horde();
+            if taken && self.transfer_on_elimination && land[team] > 0 {
+                let reason = format!("team {} lost its King but still owns {} cells",
+                                     team,
+                                     land[team]);
+                return Err(GeneralsError::BrokenInvariant(reason));
+            }
+        }
+        Ok(())
+    }
+
+    /// A hash of the board and the players, for checking that two games are in sync. It is the
+    /// same on every platform and build, so games can be compared across machines, and pinned.
+    pub fn hash(&self) -> u64 {
+        fn option(hash: &mut Fnv, n: Option<usize>) {
+            hash.u8(n.is_some() as u8);
+            hash.usize(n.unwrap_or(0));
+        }
+        let mut hash = Fnv::default();
+        hash.usize(self.tick_number);
+        hash.usize(self.board.width);
+        hash.usize(self.board.height);
+        for packed in self.board.cells.iter() {
+            hash.bytes(&packed.to_bytes());
+        }
+        for player in self.player_states.iter() {
+            hash.u8(player.dead as u8);
+            hash.usize(player.moves.len());
+            for &(Position(x, y), dir) in player.moves.iter() {
+                hash.i32(x);
+                hash.i32(y);
+                hash.u8(dir as u8);
+            }
+            option(&mut hash, player.handicap.king_units);
+            hash.usize(player.handicap.growth);
+            hash.usize(player.handicap.extra_cities);
+        }
+        hash.u8(self.hill.is_some() as u8);
+        if let Some(ref hill) = self.hill {
+            hash.i32(hill.pos.0);
+            hash.i32(hill.pos.1);
+            option(&mut hash, hill.holder);
+            hash.usize(hill.held);
+            hash.usize(hill.needed);
+        }
+        hash.finish()
+    }
+
+    /// The current scores of all players, indexed by team.
+    pub fn scores(&self) -> Vec<Score> {
+        let mut scores = self.player_states
+            .iter()
+            .map(|p| Score {
+                team: p.team,
+                army: 0,
+                land: 0,
+                cities: 0,
+                dead: p.dead,
+            })
+            .collect::<Vec<_>>();
+        for cell in self.board.cells() {
+            match cell {
+                Cell::Captured(team, n) => {
+                    scores[team].army += n;
+                    scores[team].land += 1;
+                }
+                Cell::Fortress(Some(team), n) | Cell::King(team, n) => {
+                    scores[team].army += n;
+                    scores[team].land += 1;
+                    scores[team].cities += 1;
+                }
+                _ => {}
+            }
+        }
+        scores
+    }
+
+    /// Advance the game by one tick, and return what happened.
+    pub fn tick(&mut self) -> Vec<GameEvent> {
+        let _tick_timer = profile::start(Section::Tick);
+        self.tick_number += 1;
+        self.edits.clear();
+        let update_tick = self.tick_number.is_multiple_of(2);
+        let update_all = self.tick_number.is_multiple_of(LAND_GROWTH_INTERVAL);
+        let growth_timer = profile::start(Section::Growth);
+        let mut changed = if update_tick {
+            let growth = self.player_states.iter().map(|p| p.handicap.growth).collect::<Vec<_>>();
+            self.board.grow(update_all, &growth)
+        } else {
+            Vec::new()
+        };
+        drop(growth_timer);
+        let _moves_timer = profile::start(Section::Moves);
+        let mut events = Vec::new();
+        let mut eliminated = Vec::new();
         for player_state in self.player_states.iter_mut() {
+            if player_state.dead {
+                continue;
+            }
             let team = player_state.team;
             if let Some((from, dir)) = player_state.moves.pop_front() {
                 let Position(x, y) = from;
                 let (dx, dy) = dir.to_xy();
                 let (new_x, new_y) = (x + dx, y + dy);
-                let mut units = self.board.get_mut(x, y).take_units();
+                // Moves off the board or from cells we lost, from stale queues or bad clients,
+                // end the queue like moves without units do.
+                let on_board = self.board.try_get(new_x, new_y).is_some();
+                let mut units = match self.board.get(x, y) {
+                    Ok(mut cell) if on_board && cell.is_controlled_by(team) => {
+                        let units = cell.take_units().unwrap_or(0);
+                        self.board.put(x, y, cell);
+                        units
+                    }
+                    _ => 0,
+                };
                 if units == 0 {
                     player_state.moves.clear();
                     continue;
                 }
+                let moved_units = units;
+                let target = Position(new_x, new_y);
+                changed.push(from);
+                changed.push(target);
 
-                let mut did_capture = false;
+                let mut captured_king = None;
+                let mut event = None;
                 let mut return_units_and_break = false;
                 // Possible scenarios:
                 //  We move units from our cell to another of our cells:
@@ -232,56 +1299,134 @@ impl GameState {
                 //      - If the neutral cell is Fortress, eat from it.
 
                 {
-                    let target_cell = self.board.get_mut(new_x, new_y);
+                    let mut target_value = self.board.at(new_x, new_y);
+                    let target_cell = &mut target_value;
                     if target_cell.is_controlled_by(team) {
-                        target_cell.give_units(units);
+                        // Cells we control always have units.
+                        let _ = target_cell.give_units(units);
                     } else {
-                        match target_cell {
-                            &mut Cell::Mountain => {
+                        match *target_cell {
+                            Cell::Mountain => {
                                 player_state.moves.clear();
                                 return_units_and_break = true;
                             }
-                            cell @ &mut Cell::Open => {
-                                *cell = Cell::Captured(team, units);
-                            }
-                            &mut Cell::Captured(ref mut team, ref mut n) |
-                            &mut Cell::Fortress(Some(ref mut team), ref mut n) => {
-                                if *n >= units {
-                                    *n -= units;
-                                } else {
-                                    *team = player_state.team;
-                                    *n = units - *n;
-                                }
-                            }
-                            &mut Cell::King(_team, ref mut n) => {
+                            Cell::King(king_team, ref mut n) => {
                                 if *n >= units {
                                     *n -= units;
                                 } else {
                                     units -= *n - 1;
-                                    did_capture = true;
+                                    captured_king = Some(king_team);
                                 }
                             }
-                            &mut Cell::Fortress(ref mut team @ None, ref mut n) => {
-                                if *n >= units {
-                                    *n -= units;
+                            _ => {
+                                let defenders = target_cell.units().unwrap_or(0);
+                                if defenders >= units {
+                                    let _ = target_cell.set_units(defenders - units);
                                 } else {
-                                    *team = Some(player_state.team);
-                                    *n = units - *n;
+                                    let from = target_cell.owner();
+                                    event = Some(match *target_cell {
+                                        Cell::Fortress(_, _) => GameEvent::CityCaptured {
+                                            team,
+                                            pos: target,
+                                            from,
+                                        },
+                                        _ => GameEvent::Captured {
+                                            team,
+                                            pos: target,
+                                            from,
+                                        },
+                                    });
+                                    let _ = target_cell.set_owner(team);
+                                    let _ = target_cell.set_units(units - defenders);
                                 }
                             }
                         }
                     }
+                    self.board.put(new_x, new_y, target_value);
                 }
                 if return_units_and_break {
-                    self.board.get_mut(x, y).give_units(units);
+                    let mut cell = self.board.at(x, y);
+                    let _ = cell.give_units(units);
+                    self.board.put(x, y, cell);
                     break;
                 }
-                if did_capture {
-                    *self.board.get_mut(new_x, new_y) =
-                        Cell::Fortress(Some(player_state.team), units);
+                events.push(GameEvent::Moved {
+                    team,
+                    mv: (from, dir),
+                    units: moved_units,
+                });
+                player_state.stats.moves_executed += 1;
+                if let Some(king_team) = captured_king {
+                    self.board.put(new_x, new_y, Cell::Fortress(Some(player_state.team), units));
+                    if self.transfer_on_elimination {
+                        changed.extend(self.board.hand_over(king_team, team));
+                    }
+                    eliminated.push(king_team);
+                    event = Some(GameEvent::KingCaptured {
+                        team,
+                        pos: target,
+                        victim: king_team,
+                    });
+                }
+                match event {
+                    Some(GameEvent::CityCaptured { .. }) |
+                    Some(GameEvent::KingCaptured { .. }) => {
+                        player_state.stats.cities_captured += 1;
+                    }
+                    _ => {}
                 }
+                events.extend(event);
             }
         }
+        self.spread_horde(&mut changed, &mut events, &mut eliminated);
+        self.notify(|observer, _| {
+            for event in events.iter() {
+                match *event {
+                    GameEvent::Captured { team, pos, from } |
+                    GameEvent::CityCaptured { team, pos, from } => {
+                        observer.on_capture(team, pos, from);
+                    }
+                    GameEvent::KingCaptured { team, pos, victim } => {
+                        observer.on_capture(team, pos, Some(victim));
+                    }
+                    GameEvent::Moved { .. } => {}
+                }
+            }
+        });
+        for &team in eliminated.iter() {
+            self.forfeit(team);
+        }
+        eliminated.extend(self.check_victory());
+        for score in self.scores() {
+            let stats = &mut self.player_states[score.team].stats;
+            stats.army = score.army;
+            stats.land = score.land;
+        }
+        changed.sort_by_key(|&Position(x, y)| (y, x));
+        changed.dedup_by_key(|&mut Position(x, y)| (x, y));
+        self.delta = BoardDelta {
+            tick_number: self.tick_number,
+            cells: changed.into_iter().map(|p| (p, self.board.at(p.0, p.1))).collect(),
+            eliminated,
+        };
+        if self.check_each_tick {
+            if let Err(e) = self.check_invariants() {
+                panic!("after tick {}: {}", self.tick_number, e);
+            }
+        }
+        self.notify(|observer, game| observer.on_tick(game, &events));
+        events
+    }
+}
+
+/// What a player sees of `cell`, depending on whether it is `visible` to them.
+fn fogged(cell: Cell, visible: bool) -> Tile {
+    if visible {
+        return Tile::Visible(cell);
+    }
+    match cell {
+        Cell::Mountain | Cell::Fortress(_, _) => Tile::Obstacle,
+        _ => Tile::Fog,
     }
 }
 
@@ -292,3 +1437,182 @@ impl ::std::ops::Add<Direction> for Position {
         Position(self.0 + x, self.1 + y)
     }
 }
+
+/// The offset from `other` to `self`.
+impl ::std::ops::Sub for Position {
+    type Output = Position;
+    fn sub(self, other: Position) -> Self {
+        Position(self.0 - other.0, self.1 - other.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bot::{self, BotPlayer};
+    use super::*;
+
+    /// A 2-player game on a board that is a single row of `cells`.
+    fn game(cells: Vec<Cell>) -> GameState {
+        let width = cells.len();
+        let mut game = GameState::new(Board::from_cells(width, cells).unwrap(), 2);
+        game.check_each_tick = false;
+        game
+    }
+
+    fn broken(game: &GameState) -> String {
+        match game.check_invariants() {
+            Err(GeneralsError::BrokenInvariant(reason)) => reason,
+            other => panic!("expected a broken invariant, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_fair_game_keeps_the_invariants() {
+        let game = game(vec![Cell::King(0, 1), Cell::Open, Cell::King(1, 1)]);
+        game.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn cells_of_teams_outside_the_game_are_reported() {
+        let game = game(vec![Cell::King(0, 1), Cell::Captured(2, 1), Cell::King(1, 1)]);
+        match game.check_invariants() {
+            Err(GeneralsError::UnknownTeam(2)) => {}
+            other => panic!("expected team 2 to be unknown, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn capped_unit_counts_are_reported() {
+        let game = game(vec![Cell::King(0, 1), Cell::Captured(0, MAX_UNITS), Cell::King(1, 1)]);
+        assert!(broken(&game).contains("cap"));
+    }
+
+    #[test]
+    fn living_players_need_exactly_one_king() {
+        let without = game(vec![Cell::King(0, 1), Cell::Open, Cell::Captured(1, 1)]);
+        assert!(broken(&without).contains("team 1 is alive with 0 Kings"));
+        let with_two = game(vec![Cell::King(0, 1), Cell::King(0, 1), Cell::King(1, 1)]);
+        assert!(broken(&with_two).contains("team 0 is alive with 2 Kings"));
+    }
+
+    #[test]
+    fn players_whose_king_was_taken_own_nothing_only_with_transfer() {
+        let mut game = game(vec![Cell::King(0, 1), Cell::Open, Cell::Captured(1, 1)]);
+        game.forfeit(1);
+        game.check_invariants().unwrap();
+        game.transfer_on_elimination = true;
+        assert!(broken(&game).contains("team 1 lost its King but still owns 1 cells"));
+    }
+
+    #[test]
+    fn players_who_forfeit_keep_their_land_with_transfer() {
+        let mut game = game(vec![Cell::King(0, 1), Cell::Captured(1, 1), Cell::King(1, 1)]);
+        game.transfer_on_elimination = true;
+        game.forfeit(1);
+        game.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn taking_a_king_with_transfer_takes_its_land() {
+        for &transfer in [false, true].iter() {
+            let mut game = game(vec![Cell::King(0, 10), Cell::King(1, 1), Cell::Captured(1, 3)]);
+            game.check_each_tick = true;
+            game.transfer_on_elimination = transfer;
+            game.queue_move(0, (Position(0, 0), Direction::Right)).unwrap();
+            game.tick();
+            assert!(game.player_states[1].dead);
+            let owner = if transfer { 0 } else { 1 };
+            assert_eq!(game.board.at(2, 0), Cell::Captured(owner, 3));
+            // Clients only hear about the land that changed hands.
+            let sent = game.last_delta().cells.iter().any(|&(pos, _)| pos == Position(2, 0));
+            assert_eq!(sent, transfer);
+        }
+    }
+
+    #[test]
+    fn views_kept_up_to_date_with_deltas_match_fresh_views() {
+        let board = Board::generate(&MapSettings::default(), 2, &mut seeded_rng(7));
+        let mut game = GameState::new(board, 2);
+        let mut bots = (0..2)
+            .map(|team| BotPlayer::new(team, bot::seeded("greedy", team as u32).unwrap()))
+            .collect::<Vec<_>>();
+        let mut view = game.player_view(0);
+        let mut board = game.board.clone();
+        for _ in 0..60 {
+            for bot in bots.iter_mut() {
+                bot.act(&mut game);
+            }
+            game.tick();
+            assert!(view.apply(&game.view_delta(Some(0))));
+            game.last_delta().apply(&mut board).unwrap();
+            assert_eq!(view.tiles, game.player_view(0).tiles);
+            assert!(board.cells().eq(game.board.cells()));
+        }
+        // A delta that was already applied is not for the next tick.
+        assert!(!view.apply(&game.view_delta(Some(0))));
+    }
+
+    #[test]
+    fn flat_boards_are_indexed_row_by_row() {
+        let cells = (0..6).map(|n| Cell::Captured(0, n)).collect();
+        let mut board = Board::from_cells(3, cells).unwrap();
+        assert_eq!(board.dimens(), (3, 2));
+        assert_eq!(board.get(2, 0).unwrap(), Cell::Captured(0, 2));
+        assert_eq!(board.get(0, 1).unwrap(), Cell::Captured(0, 3));
+        assert!(board.get(3, 0).is_err());
+        assert!(board.get(0, 2).is_err());
+        assert!(board.get(-1, 0).is_err());
+        board.set(1, 1, Cell::Mountain).unwrap();
+        assert!(board.set(3, 1, Cell::Mountain).is_err());
+        let rows = board.rows().collect::<Vec<_>>();
+        assert_eq!(rows[1], vec![Cell::Captured(0, 3), Cell::Mountain, Cell::Captured(0, 5)]);
+        let (pos, _) = board.iter().find(|&(_, cell)| cell == Cell::Mountain).unwrap();
+        assert_eq!(pos, Position(1, 1));
+        assert!(Board::from_cells(4, vec![Cell::Open; 6]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn growing_on_several_threads_matches_growing_on_one() {
+        let width = 701;
+        let cells = (0..PARALLEL_GROWTH_CELLS + width)
+            .map(|i| match i % 6 {
+                0 => Cell::Open,
+                1 => Cell::Mountain,
+                2 => Cell::Fortress(None, i % 50),
+                3 => Cell::Fortress(Some(i % 3), i % 50),
+                4 => Cell::King(i % 3, i),
+                _ => Cell::Captured(i % 3, i % 7),
+            })
+            .collect::<Vec<_>>();
+        let cells = cells[..cells.len() / width * width].to_vec();
+        let mut parallel = Board::from_cells(width, cells).unwrap();
+        assert!(parallel.cells.len() >= PARALLEL_GROWTH_CELLS);
+        let mut serial = parallel.clone();
+        let growth = [1, 3];
+        for &update_all in [false, true].iter() {
+            let grown = parallel.grow(update_all, &growth);
+            let expected = grow_cells(&mut serial.cells, 0, width, update_all, &growth);
+            assert_eq!(grown, expected);
+            assert_eq!(parallel.cells, serial.cells);
+        }
+    }
+
+    #[test]
+    fn horde_games_with_transfer_keep_the_invariants() {
+        let board = Board::generate(&MapSettings::default(), 2, &mut seeded_rng(5));
+        let mut game = GameState::with_mode(board, 2, GameMode::Horde);
+        game.check_each_tick = true;
+        game.transfer_on_elimination = true;
+        let mut bots = (0..2)
+            .map(|team| BotPlayer::new(team, bot::seeded("greedy", team as u32).unwrap()))
+            .collect::<Vec<_>>();
+        while game.tick_number < 400 && !game.is_over() {
+            for bot in bots.iter_mut() {
+                bot.act(&mut game);
+            }
+            game.tick();
+        }
+        game.check_invariants().unwrap();
+    }
+}
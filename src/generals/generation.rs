@@ -0,0 +1,106 @@
+use generals::{seeded_rng, Board, Cell, GameMode, GameState, MapSettings};
+
+/// Everything a generated board was made from, so that the same map can be played again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Generation {
+    pub seed: u32,
+    pub map: MapSettings,
+    /// How many places the spawns were moved along, see `GameState::generate`.
+    pub rotation: usize,
+}
+
+impl Generation {
+    pub fn new(seed: u32, map: MapSettings) -> Self {
+        Generation {
+            seed,
+            map,
+            rotation: 0,
+        }
+    }
+
+    /// The same map, with every player starting where the next team did.
+    pub fn rotated(&self) -> Self {
+        Generation {
+            rotation: self.rotation + 1,
+            ..self.clone()
+        }
+    }
+}
+
+impl GameState {
+    /// A new game of `mode` on a board generated from `generation`. The King of team `t`
+    /// starts where the King of team `t + rotation` would, so that players can try the map
+    /// from each other's spawns.
+    pub fn generate(generation: &Generation, num_players: usize, mode: GameMode) -> Self {
+        let mut board =
+            Board::generate(&generation.map, num_players, &mut seeded_rng(generation.seed));
+        // Without players there are no Kings to move.
+        if let Some(rotation) = generation.rotation.checked_rem(num_players) {
+            let shift = num_players - rotation;
+            for (pos, cell) in board.iter().collect::<Vec<_>>() {
+                if let Cell::King(team, n) = cell {
+                    board.put(pos.0, pos.1, Cell::King((team + shift) % num_players, n));
+                }
+            }
+        }
+        let mut game = GameState::with_mode(board, num_players, mode);
+        game.generation = Some(generation.clone());
+        game
+    }
+
+    /// What the board was generated from, if it was generated with `generate`.
+    pub fn generation(&self) -> Option<&Generation> {
+        self.generation.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use generals::{Position, Team};
+    use super::*;
+
+    fn kings(game: &GameState) -> Vec<(Team, Position)> {
+        let mut kings = game.board
+            .iter()
+            .filter_map(|(pos, cell)| match cell {
+                Cell::King(team, _) => Some((team, pos)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        kings.sort_by_key(|&(team, _)| team);
+        kings
+    }
+
+    #[test]
+    fn the_same_generation_makes_the_same_game() {
+        let generation = Generation::new(42, MapSettings::default());
+        let game = GameState::generate(&generation, 3, GameMode::Classic);
+        let again = GameState::generate(game.generation().unwrap(), 3, GameMode::Classic);
+        assert_eq!(game.hash(), again.hash());
+        assert_eq!(again.generation().unwrap().seed, 42);
+    }
+
+    #[test]
+    fn rotating_moves_every_king_to_the_spawn_of_the_next_team() {
+        let generation = Generation::new(7, MapSettings::default());
+        let game = GameState::generate(&generation, 3, GameMode::Classic);
+        let rotated = GameState::generate(&generation.rotated(), 3, GameMode::Classic);
+        let (spawns, moved) = (kings(&game), kings(&rotated));
+        for t in 0..3 {
+            assert_eq!(moved[t].1, spawns[(t + 1) % 3].1);
+        }
+        let others = |game: &GameState| {
+            game.board.cells().filter(|cell| cell.owner().is_none()).collect::<Vec<_>>()
+        };
+        assert_eq!(others(&game), others(&rotated));
+        let around = (0..3).fold(generation.clone(), |g, _| g.rotated());
+        let back = GameState::generate(&around, 3, GameMode::Classic);
+        assert_eq!(kings(&back), spawns);
+    }
+
+    #[test]
+    fn games_without_players_can_be_rotated() {
+        let generation = Generation::new(1, MapSettings::default()).rotated();
+        GameState::generate(&generation, 0, GameMode::Classic);
+    }
+}
@@ -0,0 +1,147 @@
+use std::str::FromStr;
+
+use error::GeneralsError;
+use generals::{Cell, GameState, Team};
+
+/// How close to the King extra cities are placed.
+const CITY_DISTANCE: i32 = 4;
+
+/// Gives a player a head start, so that players of different strength, or a human and a
+/// hard bot, can have a close game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Handicap {
+    /// The units in the King at the start, instead of one.
+    pub king_units: Option<usize>,
+    /// How many units each cell of the player grows by, instead of one.
+    pub growth: usize,
+    /// Cities the player owns from the start, on open land near their King.
+    pub extra_cities: usize,
+}
+
+impl Default for Handicap {
+    fn default() -> Self {
+        Handicap {
+            king_units: None,
+            growth: 1,
+            extra_cities: 0,
+        }
+    }
+}
+
+/// Parses `king=N,growth=N,cities=N`, where every part is optional.
+impl FromStr for Handicap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut handicap = Handicap::default();
+        for part in s.split(',').filter(|p| !p.is_empty()) {
+            let mut kv = part.splitn(2, '=');
+            let key = kv.next().unwrap_or("");
+            let value = kv.next()
+                .and_then(|v| v.parse::<usize>().ok())
+                .ok_or_else(|| format!("'{}' needs a number, like {}=2", part, key))?;
+            match key {
+                "king" if value > 0 => handicap.king_units = Some(value),
+                "growth" if value > 0 => handicap.growth = value,
+                "cities" => handicap.extra_cities = value,
+                "king" | "growth" => return Err(format!("{} needs to be at least 1", key)),
+                _ => return Err(format!("Unknown handicap '{}', try king, growth or cities", key)),
+            }
+        }
+        Ok(handicap)
+    }
+}
+
+impl GameState {
+    /// Give `team` a handicap. This changes the board, so it is for games that haven't started
+    /// yet. Extra cities are placed on the open cells closest to the King, so there may be
+    /// fewer of them on crowded boards.
+    pub fn set_handicap(&mut self, team: Team, handicap: Handicap) -> Result<(), GeneralsError> {
+        self.player_state_mut(team)?.handicap = handicap;
+        let king = self.board
+            .iter()
+            .find(|&(_, cell)| match cell {
+                Cell::King(t, _) => t == team,
+                _ => false,
+            })
+            .map(|(pos, _)| pos)
+            .ok_or(GeneralsError::UnknownTeam(team))?;
+        if let Some(units) = handicap.king_units {
+            self.board.put(king.0, king.1, Cell::King(team, units));
+        }
+        let mut spots = self.board
+            .iter()
+            .filter(|&(pos, cell)| {
+                let distance = pos.distance(king);
+                cell == Cell::Open && distance > 1 && distance <= CITY_DISTANCE
+            })
+            .map(|(pos, _)| pos)
+            .collect::<Vec<_>>();
+        spots.sort_by_key(|&pos| (pos.distance(king), pos.1, pos.0));
+        for pos in spots.into_iter().take(handicap.extra_cities) {
+            self.board.put(pos.0, pos.1, Cell::Fortress(Some(team), 1));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use generals::{Board, Position};
+    use super::*;
+
+    /// A 2-player game on an open 7x7 board, with team 0's King in the middle and team 1's in
+    /// a corner.
+    fn game() -> GameState {
+        let mut cells = vec![Cell::Open; 49];
+        cells[24] = Cell::King(0, 1);
+        cells[0] = Cell::King(1, 1);
+        GameState::new(Board::from_cells(7, cells).unwrap(), 2)
+    }
+
+    #[test]
+    fn handicaps_are_parsed_part_by_part() {
+        let handicap = "king=20,cities=2".parse::<Handicap>().unwrap();
+        assert_eq!(handicap, Handicap { king_units: Some(20), growth: 1, extra_cities: 2 });
+        assert_eq!("".parse::<Handicap>().unwrap(), Handicap::default());
+        assert!("king=0".parse::<Handicap>().is_err());
+        assert!("growth".parse::<Handicap>().is_err());
+        assert!("speed=2".parse::<Handicap>().is_err());
+    }
+
+    #[test]
+    fn the_king_starts_with_its_garrison() {
+        let mut game = game();
+        game.set_handicap(0, "king=20".parse().unwrap()).unwrap();
+        assert_eq!(game.board.at(3, 3), Cell::King(0, 20));
+        assert_eq!(game.board.at(0, 0), Cell::King(1, 1));
+    }
+
+    #[test]
+    fn extra_cities_are_placed_near_the_king() {
+        let mut game = game();
+        game.set_handicap(0, "cities=3".parse().unwrap()).unwrap();
+        let cities = game.board
+            .iter()
+            .filter(|&(_, cell)| cell == Cell::Fortress(Some(0), 1))
+            .map(|(pos, _)| pos.distance(Position(3, 3)))
+            .collect::<Vec<_>>();
+        assert_eq!(cities, vec![2, 2, 2]);
+    }
+
+    #[test]
+    fn cells_grow_by_the_growth_of_their_team() {
+        let mut game = game();
+        game.set_handicap(0, "growth=3".parse().unwrap()).unwrap();
+        game.tick();
+        game.tick();
+        assert_eq!(game.board.at(3, 3), Cell::King(0, 4));
+        assert_eq!(game.board.at(0, 0), Cell::King(1, 2));
+    }
+
+    #[test]
+    fn teams_without_a_king_cant_get_a_handicap() {
+        let mut game = game();
+        assert!(game.set_handicap(2, Handicap::default()).is_err());
+    }
+}
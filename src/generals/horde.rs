@@ -0,0 +1,182 @@
+use rand::Rng;
+
+use generals::{seeded_rng, Board, Cell, GameEvent, GameMode, GameState, Position, Team};
+
+/// A new group of the horde comes in every this many ticks.
+const SPAWN_INTERVAL: usize = 20;
+/// The horde attacks every this many ticks.
+const ATTACK_INTERVAL: usize = 4;
+/// The units of the first groups. Every group after that has one more unit per
+/// `SPAWN_INTERVAL` ticks of the game, so that the horde gets stronger over time.
+const SPAWN_UNITS: usize = 5;
+
+impl GameState {
+    /// A game for `num_players` players, plus the horde as the team after theirs. The horde is
+    /// out of the game from the start, so that it never counts as a player left standing, has
+    /// no King and no moves, and its land stays on the board as that of players who are out.
+    pub(super) fn with_horde(board: Board, num_players: usize) -> Self {
+        let mut game = GameState::new(board, num_players + 1);
+        game.num_players = num_players;
+        game.player_states[num_players].dead = true;
+        game
+    }
+
+    /// The team of the horde, in horde games.
+    pub fn horde(&self) -> Option<Team> {
+        match self.mode {
+            GameMode::Horde => Some(self.player_states.len() - 1),
+            _ => None,
+        }
+    }
+
+    /// Spawn new groups of the horde on the edges of the board, and attack with the groups
+    /// that are there. Every cell of the horde with units to spare attacks the weakest cell
+    /// next to it that it doesn't own. Where the horde spawns only depends on the tick, so
+    /// games stay deterministic.
+    pub(super) fn spread_horde(&mut self,
+                               changed: &mut Vec<Position>,
+                               events: &mut Vec<GameEvent>,
+                               eliminated: &mut Vec<Team>) {
+        let horde = match self.horde() {
+            Some(horde) => horde,
+            None => return,
+        };
+        if self.tick_number.is_multiple_of(SPAWN_INTERVAL) {
+            let (w, h) = self.dimens;
+            let edges = self.board
+                .iter()
+                .filter(|&(Position(x, y), cell)| {
+                    cell == Cell::Open && (x == 0 || y == 0 || x == w - 1 || y == h - 1)
+                })
+                .map(|(pos, _)| pos)
+                .collect::<Vec<_>>();
+            if !edges.is_empty() {
+                let mut rng = seeded_rng(self.tick_number as u32);
+                let Position(x, y) = edges[rng.gen_range(0, edges.len())];
+                let units = SPAWN_UNITS + self.tick_number / SPAWN_INTERVAL;
+                self.board.put(x, y, Cell::Captured(horde, units));
+                changed.push(Position(x, y));
+            }
+        }
+        if !self.tick_number.is_multiple_of(ATTACK_INTERVAL) {
+            return;
+        }
+        let groups = self.board
+            .cells_owned_by(horde)
+            .filter(|&(_, cell)| cell.units().unwrap_or(0) > 1)
+            .map(|(pos, _)| pos)
+            .collect::<Vec<_>>();
+        for from in groups {
+            let target = self.board
+                .neighbors(from)
+                .filter(|&(_, cell)| cell.is_passable() && cell.owner() != Some(horde))
+                .min_by_key(|&(pos, cell)| (cell.units().unwrap_or(0), pos.1, pos.0))
+                .map(|(pos, _)| pos);
+            let target = match target {
+                Some(target) => target,
+                None => continue,
+            };
+            let mut source = self.board.at(from.0, from.1);
+            // The group may have been eaten by another group of the horde this tick.
+            if source.owner() != Some(horde) {
+                continue;
+            }
+            let units = source.take_units().unwrap_or(0);
+            self.board.put(from.0, from.1, source);
+            let mut cell = self.board.at(target.0, target.1);
+            let defenders = cell.units().unwrap_or(0);
+            if defenders >= units {
+                let _ = cell.set_units(defenders - units);
+            } else {
+                let event = match cell {
+                    Cell::King(victim, _) => {
+                        eliminated.push(victim);
+                        GameEvent::KingCaptured {
+                            team: horde,
+                            pos: target,
+                            victim,
+                        }
+                    }
+                    Cell::Fortress(from, _) => GameEvent::CityCaptured {
+                        team: horde,
+                        pos: target,
+                        from,
+                    },
+                    _ => GameEvent::Captured {
+                        team: horde,
+                        pos: target,
+                        from: cell.owner(),
+                    },
+                };
+                let _ = cell.set_owner(horde);
+                let _ = cell.set_units(units - defenders);
+                if let GameEvent::KingCaptured { victim, .. } = event {
+                    if self.transfer_on_elimination {
+                        changed.extend(self.board.hand_over(victim, horde));
+                    }
+                }
+                events.push(event);
+            }
+            self.board.put(target.0, target.1, cell);
+            changed.push(from);
+            changed.push(target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2-player horde game on a 5x5 board with the Kings away from the edges.
+    fn game() -> GameState {
+        let mut cells = vec![Cell::Open; 25];
+        cells[6] = Cell::King(0, 1);
+        cells[18] = Cell::King(1, 1);
+        GameState::with_mode(Board::from_cells(5, cells).unwrap(), 2, GameMode::Horde)
+    }
+
+    #[test]
+    fn the_horde_is_an_extra_team_that_is_never_standing() {
+        let game = game();
+        assert_eq!(game.horde(), Some(2));
+        assert_eq!(game.player_states.len(), 3);
+        assert!(game.player_states[2].dead);
+        assert!(!game.is_over());
+    }
+
+    #[test]
+    fn the_horde_spawns_on_the_edge() {
+        let mut game = game();
+        for _ in 0..SPAWN_INTERVAL {
+            assert_eq!(game.board.cells_owned_by(2).count(), 0);
+            game.tick();
+        }
+        // The group attacks right away, since it spawns on a tick the horde attacks on.
+        let spawned = game.board.cells_owned_by(2).collect::<Vec<_>>();
+        assert_eq!(spawned.len(), 2);
+        let on_edge = |&(Position(x, y), _): &(Position, Cell)| {
+            x == 0 || y == 0 || x == 4 || y == 4
+        };
+        assert!(spawned.iter().any(on_edge));
+        let units = spawned.iter().map(|&(_, cell)| cell.units().unwrap()).sum::<usize>();
+        assert_eq!(units, SPAWN_UNITS + 1);
+    }
+
+    #[test]
+    fn the_horde_takes_kings_and_the_last_one_standing_wins() {
+        let mut game = game();
+        // Walled in, so that the King is the only cell to attack.
+        game.edit_cell(Position(2, 4), Cell::Mountain).unwrap();
+        game.edit_cell(Position(4, 4), Cell::Mountain).unwrap();
+        game.edit_cell(Position(3, 4), Cell::Captured(2, 10)).unwrap();
+        let mut events = Vec::new();
+        for _ in 0..ATTACK_INTERVAL {
+            events.extend(game.tick());
+        }
+        assert!(events.iter().any(|event| {
+            matches!(*event, GameEvent::KingCaptured { team: 2, pos: Position(3, 3), victim: 1 })
+        }));
+        assert_eq!(game.winner(), Some(0));
+    }
+}
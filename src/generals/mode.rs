@@ -0,0 +1,282 @@
+use std::mem;
+
+use generals::{Board, Cell, GameState, Position, Team};
+use generals::victory::{HoldObjective, TurnLimit, Verdict, VictoryCondition};
+
+/// How long the hill has to be held to win, unless the game says otherwise.
+pub const DEFAULT_HOLD_TICKS: usize = 100;
+/// How long blitz games last, unless the game says otherwise.
+pub const DEFAULT_TICK_LIMIT: usize = 500;
+
+/// The rules that decide who wins, on top of taking Kings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GameMode {
+    /// The last player with a King wins.
+    #[default]
+    Classic,
+    /// Holding the cell in the middle of the board for `hold_ticks` ticks in a row also wins.
+    KingOfTheHill { hold_ticks: usize },
+    /// The game ends after `ticks` ticks, and the player with the most land wins. Ties go to
+    /// the larger army.
+    Blitz { ticks: usize },
+    /// A horde that belongs to no player comes in from the edges of the board and attacks
+    /// everyone. See `GameState::horde`.
+    Horde,
+}
+
+impl GameMode {
+    pub fn parse(name: &str) -> Option<GameMode> {
+        match name {
+            "classic" => Some(GameMode::Classic),
+            "koth" | "king-of-the-hill" => {
+                Some(GameMode::KingOfTheHill { hold_ticks: DEFAULT_HOLD_TICKS })
+            }
+            "blitz" => Some(GameMode::Blitz { ticks: DEFAULT_TICK_LIMIT }),
+            "horde" | "zombie" => Some(GameMode::Horde),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            GameMode::Classic => "classic",
+            GameMode::KingOfTheHill { .. } => "king of the hill",
+            GameMode::Blitz { .. } => "blitz",
+            GameMode::Horde => "horde",
+        }
+    }
+
+    /// The rules that end games of this mode, besides taking Kings.
+    pub fn conditions(&self) -> Vec<Box<dyn VictoryCondition>> {
+        match *self {
+            GameMode::Classic | GameMode::Horde => Vec::new(),
+            GameMode::KingOfTheHill { .. } => vec![Box::new(HoldObjective)],
+            GameMode::Blitz { ticks } => vec![Box::new(TurnLimit { ticks })],
+        }
+    }
+
+    /// The number of teams in a game of this mode for `num_players`, which includes the horde
+    /// in horde games.
+    pub fn teams(&self, num_players: usize) -> usize {
+        match *self {
+            GameMode::Horde => num_players + 1,
+            _ => num_players,
+        }
+    }
+
+    /// The tick the game ends at, if it has a limit.
+    pub fn tick_limit(&self) -> Option<usize> {
+        match *self {
+            GameMode::Blitz { ticks } => Some(ticks),
+            _ => None,
+        }
+    }
+}
+
+/// The objective cell of a king of the hill game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Hill {
+    pub pos: Position,
+    /// Who has held the hill since the last tick it changed hands, if anyone.
+    pub holder: Option<Team>,
+    /// How many ticks in a row `holder` has held it.
+    pub held: usize,
+    /// How many ticks it has to be held to win.
+    pub needed: usize,
+}
+
+impl Hill {
+    /// The hill in the middle of a board of size `dimens`.
+    pub fn new((w, h): (i32, i32), needed: usize) -> Self {
+        Hill {
+            pos: Position(w / 2, h / 2),
+            holder: None,
+            held: 0,
+            needed,
+        }
+    }
+
+    /// How many more ticks `holder` has to hold on to win.
+    pub fn remaining(&self) -> usize {
+        self.needed.saturating_sub(self.held)
+    }
+}
+
+impl GameState {
+    /// A new game played by the rules of `mode`. The hill of king of the hill games is never
+    /// a mountain, so that it can be taken. Horde games get an extra team for the horde.
+    pub fn with_mode(mut board: Board, num_players: usize, mode: GameMode) -> Self {
+        let mut hill = None;
+        if let GameMode::KingOfTheHill { hold_ticks } = mode {
+            let h = Hill::new(board.dimens(), hold_ticks);
+            let Position(x, y) = h.pos;
+            if board.at(x, y) == Cell::Mountain {
+                board.put(x, y, Cell::Open);
+            }
+            hill = Some(h);
+        }
+        let mut game = match mode {
+            GameMode::Horde => GameState::with_horde(board, num_players),
+            _ => GameState::new(board, num_players),
+        };
+        game.set_mode(mode);
+        game.hill = hill;
+        game
+    }
+
+    /// Play by the rules of `mode`. Only for new games, which don't have any conditions yet.
+    pub(super) fn set_mode(&mut self, mode: GameMode) {
+        self.mode = mode;
+        for condition in mode.conditions() {
+            self.conditions.push(condition);
+        }
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    pub fn hill(&self) -> Option<Hill> {
+        self.hill
+    }
+
+    /// Add a rule that can end the game. It is checked after the ones of the mode, and any
+    /// that were added before it.
+    pub fn add_victory_condition<V: VictoryCondition + 'static>(&mut self, condition: V) {
+        self.conditions.push(Box::new(condition));
+    }
+
+    /// End the game if one of its victory conditions says it is over, after taking Kings has
+    /// been dealt with. Returns the players that lost because of it.
+    pub(super) fn check_victory(&mut self) -> Vec<Team> {
+        self.update_hill();
+        if self.is_over() {
+            return Vec::new();
+        }
+        // The conditions are taken out of the game while they run, so that they can look at it.
+        let mut conditions = mem::take(&mut self.conditions);
+        let verdict = conditions.check(self);
+        self.conditions = conditions;
+        match verdict {
+            Some(Verdict::Winner(team)) => self.end_with(Some(team)),
+            Some(Verdict::Draw) => self.end_with(None),
+            None => Vec::new(),
+        }
+    }
+
+    /// Take everyone but `winner` out of the game, and return who that was.
+    fn end_with(&mut self, winner: Option<Team>) -> Vec<Team> {
+        let losers = self.player_states
+            .iter()
+            .filter(|p| !p.dead && Some(p.team) != winner)
+            .map(|p| p.team)
+            .collect::<Vec<_>>();
+        for &team in losers.iter() {
+            self.forfeit(team);
+        }
+        losers
+    }
+
+    /// Count another tick for whoever holds the hill.
+    fn update_hill(&mut self) {
+        let mut hill = match self.hill {
+            Some(hill) => hill,
+            None => return,
+        };
+        let Position(x, y) = hill.pos;
+        let owner = self.board
+            .at(x, y)
+            .owner()
+            .filter(|&team| !self.player_states[team].dead);
+        if owner.is_some() && owner == hill.holder {
+            hill.held += 1;
+        } else {
+            hill.holder = owner;
+            hill.held = if owner.is_some() { 1 } else { 0 };
+        }
+        self.hill = Some(hill);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use generals::{Direction, Move};
+    use super::*;
+
+    /// A 3x3 game of `mode` with a mountain in the middle, team 0's King in the top left
+    /// corner and team 1's in the bottom right one.
+    fn game(mode: GameMode) -> GameState {
+        let mut cells = vec![Cell::Open; 9];
+        cells[0] = Cell::King(0, 10);
+        cells[4] = Cell::Mountain;
+        cells[8] = Cell::King(1, 1);
+        GameState::with_mode(Board::from_cells(3, cells).unwrap(), 2, mode)
+    }
+
+    /// The moves that take team 0 from its King to the hill.
+    const TO_HILL: [Move; 2] = [
+        (Position(0, 0), Direction::Down),
+        (Position(0, 1), Direction::Right),
+    ];
+
+    #[test]
+    fn holding_the_hill_long_enough_wins() {
+        let mut game = game(GameMode::KingOfTheHill { hold_ticks: 3 });
+        assert_eq!(game.board.at(1, 1), Cell::Open);
+        for &mv in TO_HILL.iter() {
+            game.queue_move(0, mv).unwrap();
+        }
+        for _ in 0..4 {
+            assert!(!game.is_over());
+            game.tick();
+        }
+        assert_eq!(game.hill().unwrap().holder, Some(0));
+        assert_eq!(game.winner(), Some(0));
+    }
+
+    #[test]
+    fn the_hold_starts_over_when_the_hill_changes_hands() {
+        let mut game = game(GameMode::KingOfTheHill { hold_ticks: 3 });
+        for &mv in TO_HILL.iter() {
+            game.queue_move(0, mv).unwrap();
+        }
+        game.tick();
+        game.tick();
+        game.tick();
+        assert_eq!(game.hill().unwrap().held, 2);
+        game.edit_cell(Position(1, 1), Cell::Captured(1, 1)).unwrap();
+        game.tick();
+        let hill = game.hill().unwrap();
+        assert_eq!((hill.holder, hill.held, hill.remaining()), (Some(1), 1, 2));
+        assert!(!game.is_over());
+    }
+
+    #[test]
+    fn blitz_goes_to_the_most_land_at_the_limit() {
+        let mut game = game(GameMode::Blitz { ticks: 3 });
+        game.queue_move(0, TO_HILL[0]).unwrap();
+        game.tick();
+        game.tick();
+        assert!(!game.is_over());
+        game.tick();
+        assert_eq!(game.winner(), Some(0));
+    }
+
+    #[test]
+    fn blitz_ties_on_land_go_to_the_larger_army() {
+        let mut game = game(GameMode::Blitz { ticks: 2 });
+        game.tick();
+        game.tick();
+        assert_eq!(game.winner(), Some(0));
+    }
+
+    #[test]
+    fn blitz_ties_on_land_and_army_are_a_draw() {
+        let mut game = game(GameMode::Blitz { ticks: 2 });
+        game.edit_cell(Position(0, 0), Cell::King(0, 1)).unwrap();
+        game.tick();
+        game.tick();
+        assert!(game.is_over());
+        assert_eq!(game.winner(), None);
+    }
+}
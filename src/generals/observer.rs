@@ -0,0 +1,66 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use frontend::Command;
+use generals::{GameEvent, GameState, Position, Team};
+
+/// Something that wants to know what happens in a game, like sound, notifications or stats.
+/// Register it with `GameState::add_observer`; all methods do nothing by default.
+pub trait Observer {
+    /// Called at the end of every tick, with everything that happened during it.
+    fn on_tick(&mut self, _game: &GameState, _events: &[GameEvent]) {}
+
+    /// `team` took the cell at `pos` from `from`, or from no one if it was neutral. This
+    /// includes fortresses and Kings.
+    fn on_capture(&mut self, _team: Team, _pos: Position, _from: Option<Team>) {}
+
+    /// `team` is out of the game, either because its King was taken or because it forfeit.
+    fn on_player_eliminated(&mut self, _team: Team) {}
+
+    /// `team` gave `command` during the current tick, see `GameState::announce_command`.
+    fn on_command(&mut self, _game: &GameState, _team: Team, _command: &Command) {}
+}
+
+/// Lets an observer be shared with the code that reads what it collected.
+impl<O: Observer> Observer for Rc<RefCell<O>> {
+    fn on_tick(&mut self, game: &GameState, events: &[GameEvent]) {
+        self.borrow_mut().on_tick(game, events);
+    }
+
+    fn on_capture(&mut self, team: Team, pos: Position, from: Option<Team>) {
+        self.borrow_mut().on_capture(team, pos, from);
+    }
+
+    fn on_player_eliminated(&mut self, team: Team) {
+        self.borrow_mut().on_player_eliminated(team);
+    }
+
+    fn on_command(&mut self, game: &GameState, team: Team, command: &Command) {
+        self.borrow_mut().on_command(game, team, command);
+    }
+}
+
+/// The observers of a game. Copies of a game, like the ones search bots play out, start
+/// without any, so observers only hear about the game they were added to.
+#[derive(Default)]
+pub struct Observers(Vec<Box<dyn Observer>>);
+
+impl Clone for Observers {
+    fn clone(&self) -> Self {
+        Observers::default()
+    }
+}
+
+impl Observers {
+    pub fn push(&mut self, observer: Box<dyn Observer>) {
+        self.0.push(observer);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Box<dyn Observer>> {
+        self.0.iter_mut()
+    }
+}
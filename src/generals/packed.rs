@@ -0,0 +1,189 @@
+use std::convert::TryFrom;
+use std::ops::{Deref, DerefMut};
+
+use error::GeneralsError;
+use generals::{Cell, Team};
+
+const MOUNTAIN: u8 = 0;
+const OPEN: u8 = 1;
+const FORTRESS: u8 = 2;
+const KING: u8 = 3;
+const CAPTURED: u8 = 4;
+
+/// The owner byte of cells without an owner.
+const NO_OWNER: u8 = u8::MAX;
+
+/// The most units a packed cell holds. Larger counts are capped to it.
+pub const MAX_UNITS: usize = u32::MAX as usize;
+/// Packed cells can only belong to teams below this, since the owner byte has to fit them and
+/// `NO_OWNER`.
+pub const MAX_TEAMS: usize = NO_OWNER as usize;
+
+/// A `Cell` in 8 bytes rather than 24, which is how boards store their cells, so that large
+/// boards stay in cache and are cheap to copy. Teams have to be below `MAX_TEAMS`, and unit
+/// counts in 32 bits; larger counts are capped. It is serialized as the `Cell` it holds, and
+/// cells of teams that don't fit are an error when read back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Cell", into = "Cell")]
+pub struct PackedCell {
+    kind: u8,
+    owner: u8,
+    units: u32,
+}
+
+impl PackedCell {
+    /// Pack `cell`, which the engine made. Panics if its team is not below `MAX_TEAMS`; cells
+    /// from outside, like the ones of a map file, go through `try_pack`.
+    pub fn pack(cell: Cell) -> Self {
+        fn owner(team: Option<Team>) -> u8 {
+            match team {
+                Some(team) => {
+                    assert!(team < MAX_TEAMS, "team {} doesn't fit in a packed cell", team);
+                    team as u8
+                }
+                None => NO_OWNER,
+            }
+        }
+        let units = |n: usize| n.min(MAX_UNITS) as u32;
+        let (kind, owner, units) = match cell {
+            Cell::Mountain => (MOUNTAIN, NO_OWNER, 0),
+            Cell::Open => (OPEN, NO_OWNER, 0),
+            Cell::Fortress(team, n) => (FORTRESS, owner(team), units(n)),
+            Cell::King(team, n) => (KING, owner(Some(team)), units(n)),
+            Cell::Captured(team, n) => (CAPTURED, owner(Some(team)), units(n)),
+        };
+        PackedCell { kind, owner, units }
+    }
+
+    /// Pack `cell`, or fail if its team is not below `MAX_TEAMS`.
+    pub fn try_pack(cell: Cell) -> Result<Self, GeneralsError> {
+        match cell.owner() {
+            Some(team) if team >= MAX_TEAMS => {
+                let reason = format!("team {} is past the limit of {} teams", team, MAX_TEAMS);
+                Err(GeneralsError::BadMap(reason))
+            }
+            _ => Ok(PackedCell::pack(cell)),
+        }
+    }
+
+    pub fn unpack(self) -> Cell {
+        let owner = self.owner();
+        let n = self.units as usize;
+        match self.kind {
+            OPEN => Cell::Open,
+            FORTRESS => Cell::Fortress(owner, n),
+            KING => Cell::King(self.owner as Team, n),
+            CAPTURED => Cell::Captured(self.owner as Team, n),
+            _ => Cell::Mountain,
+        }
+    }
+
+    /// The cell as bytes that are the same on every platform: the kind, the owner, and the
+    /// units little-endian.
+    pub fn to_bytes(self) -> [u8; 6] {
+        let units = self.units.to_le_bytes();
+        [self.kind, self.owner, units[0], units[1], units[2], units[3]]
+    }
+
+    pub fn owner(self) -> Option<Team> {
+        if self.owner == NO_OWNER { None } else { Some(self.owner as Team) }
+    }
+
+    /// Add `n` units, if the cell holds units.
+    pub fn grow(&mut self, n: usize) {
+        if self.kind != MOUNTAIN && self.kind != OPEN {
+            self.units = self.units.saturating_add(n.min(MAX_UNITS) as u32);
+        }
+    }
+}
+
+impl TryFrom<Cell> for PackedCell {
+    type Error = GeneralsError;
+
+    fn try_from(cell: Cell) -> Result<Self, GeneralsError> {
+        PackedCell::try_pack(cell)
+    }
+}
+
+impl From<PackedCell> for Cell {
+    fn from(packed: PackedCell) -> Self {
+        packed.unpack()
+    }
+}
+
+/// A cell of a board, unpacked so that it can be changed in place. The change is packed back
+/// into the board when this is dropped.
+pub struct CellMut<'a> {
+    packed: &'a mut PackedCell,
+    cell: Cell,
+}
+
+impl<'a> CellMut<'a> {
+    pub fn new(packed: &'a mut PackedCell) -> Self {
+        let cell = packed.unpack();
+        CellMut { packed, cell }
+    }
+}
+
+impl<'a> Deref for CellMut<'a> {
+    type Target = Cell;
+
+    fn deref(&self) -> &Cell {
+        &self.cell
+    }
+}
+
+impl<'a> DerefMut for CellMut<'a> {
+    fn deref_mut(&mut self) -> &mut Cell {
+        &mut self.cell
+    }
+}
+
+impl<'a> Drop for CellMut<'a> {
+    fn drop(&mut self) {
+        *self.packed = PackedCell::pack(self.cell);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use generals::Board;
+    use super::*;
+
+    #[test]
+    fn every_kind_of_cell_survives_packing() {
+        let cells = [
+            Cell::Mountain,
+            Cell::Open,
+            Cell::Fortress(None, 40),
+            Cell::Fortress(Some(3), 12),
+            Cell::King(0, 1),
+            Cell::Captured(MAX_TEAMS - 1, 0),
+            Cell::Captured(7, MAX_UNITS),
+        ];
+        for &cell in cells.iter() {
+            assert_eq!(PackedCell::pack(cell).unpack(), cell);
+        }
+    }
+
+    #[test]
+    fn units_past_the_cap_are_capped() {
+        let mut packed = PackedCell::pack(Cell::King(1, MAX_UNITS + 1));
+        assert_eq!(packed.unpack(), Cell::King(1, MAX_UNITS));
+        packed.grow(1);
+        assert_eq!(packed.unpack(), Cell::King(1, MAX_UNITS));
+    }
+
+    #[test]
+    fn teams_that_dont_fit_are_an_error() {
+        let cell = Cell::Captured(MAX_TEAMS, 1);
+        assert!(PackedCell::try_pack(cell).is_err());
+        assert!(Board::from_cells(1, vec![cell]).is_err());
+        let json = serde_json::to_string(&cell).unwrap();
+        assert!(serde_json::from_str::<PackedCell>(&json).is_err());
+        let fits = serde_json::to_string(&Cell::Captured(MAX_TEAMS - 1, 1)).unwrap();
+        assert!(serde_json::from_str::<PackedCell>(&fits).is_ok());
+    }
+}
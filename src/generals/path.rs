@@ -0,0 +1,235 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use generals::{Board, Cell, Move, PlayerView, Position, Team, Tile, DIRECTIONS};
+
+fn in_bounds((w, h): (i32, i32), Position(x, y): Position) -> bool {
+    x >= 0 && y >= 0 && x < w && y < h
+}
+
+fn index((w, _): (i32, i32), Position(x, y): Position) -> usize {
+    (y * w + x) as usize
+}
+
+/// Walk the `came_from` links backwards from `to`, and return the moves in walking order.
+fn reconstruct(dimens: (i32, i32), came_from: &[Option<Move>], to: Position) -> Vec<Move> {
+    let mut moves = Vec::new();
+    let mut i = index(dimens, to);
+    while let Some(m) = came_from[i] {
+        moves.push(m);
+        i = index(dimens, m.0);
+    }
+    moves.reverse();
+    moves
+}
+
+/// Breadth first search from `from` to `to`, only walking on cells where `passable` is true.
+pub fn bfs_by<F>(dimens: (i32, i32), from: Position, to: Position, passable: F) -> Option<Vec<Move>>
+where
+    F: Fn(Position) -> bool,
+{
+    if !in_bounds(dimens, from) || !in_bounds(dimens, to) {
+        return None;
+    }
+    let mut seen = vec![false; (dimens.0 * dimens.1) as usize];
+    let mut came_from: Vec<Option<Move>> = vec![None; seen.len()];
+    let mut queue = VecDeque::new();
+    seen[index(dimens, from)] = true;
+    queue.push_back(from);
+    while let Some(pos) = queue.pop_front() {
+        if index(dimens, pos) == index(dimens, to) {
+            return Some(reconstruct(dimens, &came_from, to));
+        }
+        for &dir in DIRECTIONS.iter() {
+            let next = pos + dir;
+            if !in_bounds(dimens, next) || seen[index(dimens, next)] || !passable(next) {
+                continue;
+            }
+            seen[index(dimens, next)] = true;
+            came_from[index(dimens, next)] = Some((pos, dir));
+            queue.push_back(next);
+        }
+    }
+    None
+}
+
+/// A* search from `from` to `to`. `cost` gives the cost of stepping onto a cell, which must be
+/// at least 1, or `None` if it can't be walked on.
+pub fn astar_by<F>(dimens: (i32, i32), from: Position, to: Position, cost: F) -> Option<Vec<Move>>
+where
+    F: Fn(Position) -> Option<u32>,
+{
+    if !in_bounds(dimens, from) || !in_bounds(dimens, to) {
+        return None;
+    }
+    let heuristic = |pos: Position| pos.distance(to) as u32;
+    let mut best = vec![u32::MAX; (dimens.0 * dimens.1) as usize];
+    let mut came_from: Vec<Option<Move>> = vec![None; best.len()];
+    let mut open = BinaryHeap::new();
+    best[index(dimens, from)] = 0;
+    open.push((Reverse(heuristic(from)), 0, index(dimens, from)));
+    while let Some((_, g, i)) = open.pop() {
+        // A cheaper way to this cell was found after this entry was pushed.
+        if g > best[i] {
+            continue;
+        }
+        let pos = Position(i as i32 % dimens.0, i as i32 / dimens.0);
+        if i == index(dimens, to) {
+            return Some(reconstruct(dimens, &came_from, to));
+        }
+        for &dir in DIRECTIONS.iter() {
+            let next = pos + dir;
+            if !in_bounds(dimens, next) {
+                continue;
+            }
+            let step = match cost(next) {
+                Some(c) => c,
+                None => continue,
+            };
+            let j = index(dimens, next);
+            let g = best[i] + step;
+            if g < best[j] {
+                best[j] = g;
+                came_from[j] = Some((pos, dir));
+                open.push((Reverse(g + heuristic(next)), g, j));
+            }
+        }
+    }
+    None
+}
+
+/// The shortest path on the board, going around mountains.
+pub fn bfs(board: &Board, from: Position, to: Position) -> Option<Vec<Move>> {
+    bfs_by(board.dimens(), from, to, |Position(x, y)| match board.get(x, y) {
+        Ok(Cell::Mountain) | Err(_) => false,
+        Ok(_) => true,
+    })
+}
+
+/// Like `bfs`, but only using what is visible in `view`. Fog is assumed to be open, and
+/// obstacles to be mountains.
+pub fn bfs_in_view(view: &PlayerView, from: Position, to: Position) -> Option<Vec<Move>> {
+    bfs_by(view.dimens, from, to, |Position(x, y)| {
+        !matches!(view.get(x, y), Tile::Obstacle | Tile::Visible(Cell::Mountain))
+    })
+}
+
+/// The cheapest path for `team` on the board, going around mountains. Stepping on cells that
+/// `team` does not control, other than open cells, costs an extra `enemy_cost`.
+pub fn astar(board: &Board, team: Team, from: Position, to: Position, enemy_cost: u32)
+    -> Option<Vec<Move>> {
+    astar_by(board.dimens(), from, to, |Position(x, y)| match board.get(x, y) {
+        Ok(Cell::Mountain) | Err(_) => None,
+        Ok(Cell::Open) => Some(1),
+        Ok(cell) if cell.is_controlled_by(team) => Some(1),
+        Ok(_) => Some(1 + enemy_cost),
+    })
+}
+
+/// Like `astar`, but only using what is visible in `view`. Fog is assumed to be open, and
+/// obstacles to be mountains.
+pub fn astar_in_view(view: &PlayerView, from: Position, to: Position, enemy_cost: u32)
+    -> Option<Vec<Move>> {
+    astar_by(view.dimens, from, to, |Position(x, y)| match view.get(x, y) {
+        Tile::Obstacle | Tile::Visible(Cell::Mountain) => None,
+        Tile::Fog | Tile::Visible(Cell::Open) => Some(1),
+        Tile::Visible(ref cell) if cell.is_controlled_by(view.team) => Some(1),
+        Tile::Visible(_) => Some(1 + enemy_cost),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board from rows of `.` for open cells, `M` for mountains, `O` for cells of team 0 and
+    /// `E` for cells of team 1.
+    fn board(rows: &[&str]) -> Board {
+        let cells = rows.iter()
+            .flat_map(|row| row.chars())
+            .map(|c| match c {
+                'M' => Cell::Mountain,
+                'O' => Cell::Captured(0, 1),
+                'E' => Cell::Captured(1, 1),
+                _ => Cell::Open,
+            })
+            .collect();
+        Board::from_cells(rows[0].len(), cells).unwrap()
+    }
+
+    /// Where walking `path` from `from` ends up, checking that every step stays on the board
+    /// and off mountains.
+    fn walk(board: &Board, from: Position, path: &[Move]) -> Position {
+        let mut pos = from;
+        for &(at, dir) in path {
+            assert_eq!(at, pos);
+            pos = pos + dir;
+            match board.get(pos.0, pos.1) {
+                Ok(Cell::Mountain) => panic!("walked onto the mountain at {}", pos),
+                Ok(_) => {}
+                Err(_) => panic!("walked off the board at {}", pos),
+            }
+        }
+        pos
+    }
+
+    #[test]
+    fn bfs_goes_around_mountains() {
+        let board = board(&[".M...", ".M.M.", "...M."]);
+        let (from, to) = (Position(0, 0), Position(4, 0));
+        let path = bfs(&board, from, to).unwrap();
+        assert_eq!(walk(&board, from, &path), to);
+        assert_eq!(path.len(), 8);
+    }
+
+    #[test]
+    fn bfs_to_itself_is_empty() {
+        let board = board(&["..."]);
+        assert_eq!(bfs(&board, Position(1, 0), Position(1, 0)), Some(Vec::new()));
+    }
+
+    #[test]
+    fn bfs_finds_no_path_to_unreachable_cells() {
+        let board = board(&["..M..", "..M..", "..M.."]);
+        assert_eq!(bfs(&board, Position(0, 0), Position(4, 2)), None);
+        assert_eq!(bfs(&board, Position(0, 0), Position(5, 0)), None);
+        assert_eq!(bfs_by((3, 3), Position(0, 0), Position(2, 2), |_| false), None);
+    }
+
+    #[test]
+    fn astar_matches_bfs_without_enemies() {
+        let board = board(&[".M...", ".M.M.", "...M."]);
+        let (from, to) = (Position(0, 0), Position(4, 0));
+        let path = astar(&board, 0, from, to, 5).unwrap();
+        assert_eq!(walk(&board, from, &path), to);
+        assert_eq!(path.len(), bfs(&board, from, to).unwrap().len());
+    }
+
+    #[test]
+    fn astar_finds_no_path_to_unreachable_cells() {
+        let board = board(&["..M..", "..M..", "..M.."]);
+        assert_eq!(astar(&board, 0, Position(0, 0), Position(4, 2), 0), None);
+        assert_eq!(astar_by((3, 3), Position(0, 0), Position(2, 2), |_| None), None);
+    }
+
+    #[test]
+    fn astar_avoids_enemy_cells_when_they_cost_more() {
+        let board = board(&["O.E..", "O.E..", "O...."]);
+        let (from, to) = (Position(0, 0), Position(4, 0));
+        let around = astar(&board, 0, from, to, 10).unwrap();
+        assert_eq!(walk(&board, from, &around), to);
+        assert_eq!(around.len(), 8);
+        let through = astar(&board, 0, from, to, 0).unwrap();
+        assert_eq!(walk(&board, from, &through), to);
+        assert_eq!(through.len(), 4);
+    }
+
+    #[test]
+    fn astar_walks_through_enemies_when_going_around_costs_more() {
+        let board = board(&["O.E..", "O.E..", "O.E.."]);
+        let (from, to) = (Position(0, 0), Position(4, 0));
+        let path = astar(&board, 0, from, to, 10).unwrap();
+        assert_eq!(walk(&board, from, &path), to);
+        assert_eq!(path.len(), 4);
+    }
+}
@@ -0,0 +1,212 @@
+use generals::{Cell, GameState, Team};
+
+/// How a game was decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Verdict {
+    Winner(Team),
+    /// Everyone still in the game is out.
+    Draw,
+}
+
+/// A rule that can end the game, checked after every tick once Kings that were taken have
+/// been dealt with. Add one with `GameState::add_victory_condition`; the first condition that
+/// decides the game ends it, by taking everyone but the winner out.
+///
+/// Game modes are made of these too, see `GameMode::conditions`.
+pub trait VictoryCondition {
+    /// Whether the game is decided after the tick that was just played.
+    fn check(&mut self, game: &GameState) -> Option<Verdict>;
+
+    /// A copy of the condition, for copies of the game.
+    fn box_clone(&self) -> Box<dyn VictoryCondition>;
+}
+
+/// The last player with a King wins, and the game is a draw if no one has one. Every game is
+/// played by this rule; it is here for composing with rules of your own.
+#[derive(Debug, Clone, Copy)]
+pub struct LastKingStanding;
+
+impl VictoryCondition for LastKingStanding {
+    fn check(&mut self, game: &GameState) -> Option<Verdict> {
+        if !game.is_over() {
+            return None;
+        }
+        Some(game.winner().map(Verdict::Winner).unwrap_or(Verdict::Draw))
+    }
+
+    fn box_clone(&self) -> Box<dyn VictoryCondition> {
+        Box::new(*self)
+    }
+}
+
+/// A player wins by owning at least `share` of the cells that are not mountains.
+#[derive(Debug, Clone, Copy)]
+pub struct LandThreshold {
+    pub share: f64,
+}
+
+impl VictoryCondition for LandThreshold {
+    fn check(&mut self, game: &GameState) -> Option<Verdict> {
+        let land = game.board.cells().filter(|&cell| cell != Cell::Mountain).count();
+        let needed = self.share * land as f64;
+        game.scores()
+            .iter()
+            .find(|s| !s.dead && s.land as f64 >= needed)
+            .map(|s| Verdict::Winner(s.team))
+    }
+
+    fn box_clone(&self) -> Box<dyn VictoryCondition> {
+        Box::new(*self)
+    }
+}
+
+/// The player who has held the hill of a king of the hill game long enough wins.
+#[derive(Debug, Clone, Copy)]
+pub struct HoldObjective;
+
+impl VictoryCondition for HoldObjective {
+    fn check(&mut self, game: &GameState) -> Option<Verdict> {
+        let hill = game.hill()?;
+        match hill.holder {
+            Some(team) if hill.held >= hill.needed => Some(Verdict::Winner(team)),
+            _ => None,
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn VictoryCondition> {
+        Box::new(*self)
+    }
+}
+
+/// The game ends after `ticks` ticks, and the player with the most land wins. Ties go to the
+/// larger army, and a tie on both is a draw.
+#[derive(Debug, Clone, Copy)]
+pub struct TurnLimit {
+    pub ticks: usize,
+}
+
+impl VictoryCondition for TurnLimit {
+    fn check(&mut self, game: &GameState) -> Option<Verdict> {
+        if game.tick_number < self.ticks {
+            return None;
+        }
+        let best = game.scores()
+            .into_iter()
+            .filter(|s| !s.dead)
+            .map(|s| ((s.land, s.army), s.team))
+            .collect::<Vec<_>>();
+        let top = best.iter().map(|&(key, _)| key).max();
+        let mut leaders = best.iter().filter(|&&(key, _)| Some(key) == top);
+        match (leaders.next(), leaders.next()) {
+            (Some(&(_, team)), None) => Some(Verdict::Winner(team)),
+            _ => Some(Verdict::Draw),
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn VictoryCondition> {
+        Box::new(*self)
+    }
+}
+
+/// The victory conditions of a game. Unlike observers, they are copied along with the game,
+/// since copies have to play by the same rules.
+#[derive(Default)]
+pub struct Conditions(Vec<Box<dyn VictoryCondition>>);
+
+impl Clone for Conditions {
+    fn clone(&self) -> Self {
+        Conditions(self.0.iter().map(|c| c.box_clone()).collect())
+    }
+}
+
+impl Conditions {
+    pub fn push(&mut self, condition: Box<dyn VictoryCondition>) {
+        self.0.push(condition);
+    }
+
+    /// The verdict of the first condition that decides the game.
+    pub fn check(&mut self, game: &GameState) -> Option<Verdict> {
+        self.0.iter_mut().filter_map(|c| c.check(game)).next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use generals::{Board, Direction, GameMode, Position};
+    use super::*;
+
+    /// A 2-player game on a 4x1 board, with the Kings at the ends.
+    fn game() -> GameState {
+        let cells = vec![Cell::King(0, 5), Cell::Open, Cell::Open, Cell::King(1, 1)];
+        GameState::new(Board::from_cells(4, cells).unwrap(), 2)
+    }
+
+    /// Decides every game the same way, as soon as it is checked.
+    #[derive(Clone, Copy)]
+    struct Always(Verdict);
+
+    impl VictoryCondition for Always {
+        fn check(&mut self, _game: &GameState) -> Option<Verdict> {
+            Some(self.0)
+        }
+
+        fn box_clone(&self) -> Box<dyn VictoryCondition> {
+            Box::new(*self)
+        }
+    }
+
+    #[test]
+    fn owning_enough_land_wins() {
+        let mut game = game();
+        game.add_victory_condition(LandThreshold { share: 0.5 });
+        game.tick();
+        assert!(!game.is_over());
+        game.queue_move(0, (Position(0, 0), Direction::Right)).unwrap();
+        game.tick();
+        assert_eq!(game.winner(), Some(0));
+    }
+
+    #[test]
+    fn the_first_condition_that_decides_ends_the_game() {
+        let mut game = game();
+        game.add_victory_condition(Always(Verdict::Winner(1)));
+        game.add_victory_condition(Always(Verdict::Winner(0)));
+        game.tick();
+        assert_eq!(game.winner(), Some(1));
+        assert!(game.player_states[0].dead);
+    }
+
+    #[test]
+    fn conditions_of_the_mode_come_first() {
+        let mut game = GameState::with_mode(game().board, 2, GameMode::Blitz { ticks: 1 });
+        game.add_victory_condition(Always(Verdict::Winner(1)));
+        game.tick();
+        assert_eq!(game.winner(), Some(0));
+    }
+
+    #[test]
+    fn a_draw_takes_everyone_out() {
+        let mut game = game();
+        game.add_victory_condition(Always(Verdict::Draw));
+        game.tick();
+        assert!(game.player_states.iter().all(|p| p.dead));
+        assert_eq!(game.winner(), None);
+    }
+
+    #[test]
+    fn copies_of_the_game_keep_the_conditions() {
+        let mut game = game();
+        game.add_victory_condition(Always(Verdict::Winner(1)));
+        let mut copy = game.clone();
+        copy.tick();
+        assert_eq!(copy.winner(), Some(1));
+    }
+
+    #[test]
+    fn the_last_king_standing_wins() {
+        let mut game = game();
+        assert_eq!(LastKingStanding.check(&game), None);
+        game.forfeit(1);
+        assert_eq!(LastKingStanding.check(&game), Some(Verdict::Winner(0)));
+    }
+}
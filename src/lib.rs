@@ -0,0 +1,36 @@
+//! The generals game engine: the board, the rules, bots, and replays.
+//!
+//! This crate has no graphics dependencies, so it can be used by headless servers and tools.
+//! The windowed client lives in `main.rs`, behind the `client` feature.
+
+extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+#[cfg(feature = "net")]
+extern crate tungstenite;
+
+pub mod achievements;
+pub mod analysis;
+pub mod api;
+pub mod bot;
+pub mod campaign;
+pub mod daily;
+pub mod error;
+pub mod eventlog;
+mod fnv;
+pub mod frontend;
+mod generals;
+pub mod matches;
+pub mod net;
+pub mod profile;
+pub mod rating;
+pub mod replay;
+pub mod scenario;
+pub mod sim;
+pub mod stats;
+pub mod tournament;
+
+pub use error::GeneralsError;
+pub use generals::*;
@@ -1,180 +1,1287 @@
+extern crate dirs;
+extern crate generals;
+extern crate gif;
 extern crate ggez;
+extern crate image;
 extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
+
+mod config;
+mod export;
+mod music;
+mod selftest;
+mod sound;
+mod ui;
 
-mod generals;
 use generals::*;
+use generals::achievements::{Achievement, Tracker, Unlocked};
+use generals::bot::BotPlayer;
+use generals::campaign::{self, Progress};
+use generals::daily::{self, Challenge, DailyRecords, DailyResult};
+use generals::matches::{self, MatchOutcome, MatchSummary};
+use generals::eventlog;
+use generals::frontend::{self, Command, Frontend};
+use generals::net::{Client, ClientMessage, Server, ServerConfig, ServerMessage};
+use generals::replay::{self, Playback, Replay};
+use generals::scenario::Scenario;
+use generals::sim::{parse_flag, parse_handicaps, parse_mode};
+use generals::stats::History;
+
+use config::{Config, GameConfig};
+use music::{Mood, Music};
+use sound::{Sound, Sounds};
+use ui::animation::Animations;
+use ui::camera::Camera;
+use ui::cell_text::CellText;
+use ui::chat::ChatBox;
+use ui::game_over::Outcome;
+use ui::gamepad::{Gamepad, Input};
+use ui::keymap::{Action, Keymap};
+use ui::kill_feed::KillFeed;
+use ui::lobby::LobbyScreen;
+use ui::menu::{Menu, MenuChoice};
+use ui::minimap::Minimap;
+use ui::palette::Palette;
+use ui::sandbox::Sandbox;
+use ui::settings::{Settings, SettingsScreen};
+use ui::sprites::{Sprite, Sprites};
+use ui::theme::Theme;
+use ui::threats::Threats;
+use ui::toasts::Toasts;
+use ui::tutorial::Tutorial;
 
-use std::time::Duration;
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use ggez::conf;
-use ggez::event::{self, MouseButton, Keycode, Mod};
-use ggez::{GameResult, Context};
-use ggez::graphics::{self, Color, DrawMode, Rect, Point, Drawable};
+use ggez::event::{self, Axis, Button, MouseButton, MouseState, Keycode, Mod};
+use ggez::{Context, GameError, GameResult};
+use ggez::graphics::{self, Color, DrawMode, Point, Rect, Drawable};
 
-const CELL_SIZE: f32 = 48.0;
+/// How often to try to get back to the server after losing the connection.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+/// SDL's left and right control modifier bits, which ggez does not re-export.
+const CTRL_BITS: u16 = 0x0040 | 0x0080;
 
-pub fn red() -> Color {
-    Color::new(1.0, 0.1, 0.1, 1.0)
-}
-pub fn blue() -> Color {
-    Color::new(0.1, 0.1, 1.0, 1.0)
+fn tile_color(theme: &Theme, palette: &Palette, tile: &Tile) -> Color {
+    match *tile {
+        Tile::Visible(ref cell) => cell_color(theme, palette, cell),
+        Tile::Fog | Tile::Obstacle => theme.fog,
+    }
 }
-pub fn black() -> Color {
-    Color::new(0.0, 0.0, 0.0, 1.0)
+
+fn cell_color(theme: &Theme, palette: &Palette, cell: &Cell) -> Color {
+    use Cell::*;
+    match *cell {
+        Mountain => theme.mountain,
+        Open => theme.open,
+        Fortress(None, _) => theme.neutral_city,
+
+        Captured(team, _) |
+        King(team, _) |
+        Fortress(Some(team), _) => palette.team(team),
+    }
 }
-pub fn black_overlay() -> Color {
-    Color::new(0.0, 0.0, 0.0, 0.3)
+
+/// Draw a queue of moves as arrows from cell to cell, numbered in the order they will be
+/// executed. The cell the queue ends in is circled.
+fn draw_moves<'a, I>(ctx: &mut Context, font: &graphics::Font, camera: &Camera, moves: I,
+                     color: Color)
+    -> GameResult<()>
+where
+    I: Iterator<Item = &'a Move>,
+{
+    let half = camera.cell_size() / 2.0;
+    let mut end = None;
+    for (i, &(from_pos, dir)) in moves.enumerate() {
+        end = Some(from_pos + dir);
+        if !camera.is_visible(from_pos) && !camera.is_visible(from_pos + dir) {
+            continue;
+        }
+        let Position(x, y) = from_pos;
+        let (dx, dy) = dir.to_xy();
+        let (dx, dy) = (dx as f32, dy as f32);
+        let from = camera.cell_center(x, y);
+        let to = camera.cell_center(x + dx as i32, y + dy as i32);
+        // Start and stop short of the centers, so that the unit counts stay readable.
+        let start = Point::new(from.x + dx * half * 0.4, from.y + dy * half * 0.4);
+        let tip = Point::new(to.x - dx * half * 0.4, to.y - dy * half * 0.4);
+        let (base_x, base_y) = (tip.x - dx * half * 0.3, tip.y - dy * half * 0.3);
+        let wing = half * 0.2;
+        graphics::set_color(ctx, color)?;
+        graphics::line(ctx, &[start, Point::new(base_x, base_y)])?;
+        graphics::polygon(ctx,
+                          DrawMode::Fill,
+                          &[tip,
+                            Point::new(base_x - dy * wing, base_y + dx * wing),
+                            Point::new(base_x + dy * wing, base_y - dx * wing)])?;
+
+        let number = graphics::Text::new(ctx, &format!("{}", i + 1), font)?;
+        let at = Point::new((from.x + to.x) / 2.0 - dy * half * 0.25,
+                            (from.y + to.y) / 2.0 + dx * half * 0.25);
+        number.draw(ctx, at, 0.0)?;
+    }
+    if let Some(Position(x, y)) = end {
+        graphics::set_color(ctx, color)?;
+        graphics::circle(ctx, DrawMode::Line, camera.cell_center(x, y), half * 0.8, 0.5)?;
+    }
+    Ok(())
 }
-pub fn red_overlay() -> Color {
-    Color::new(1.0, 0.0, 0.0, 0.5)
+
+/// How local games are set up, as given on the command line.
+#[derive(Debug, Clone)]
+struct GameSetup {
+    map: MapSettings,
+    num_players: usize,
+    /// The bots playing the other teams, in order. Teams past the end get the default bot.
+    bots: Vec<String>,
+    /// The seed of the first game, or `None` for a random one.
+    seed: Option<u32>,
+    /// The time between ticks, instead of the one in the settings.
+    tick_ms: Option<u64>,
+    mode: GameMode,
+    handicaps: Vec<(Team, Handicap)>,
+    /// Whether the board can be edited while the game runs.
+    sandbox: bool,
+    /// The puzzle that every game is, instead of a generated map.
+    scenario: Option<Scenario>,
 }
 
-fn team_color(team: Team) -> Color {
-    match team {
-        0 => red(),
-        1 => blue(),
-        _ => panic!("Missing team color for team {}", team),
+impl Default for GameSetup {
+    fn default() -> Self {
+        GameSetup {
+            map: MapSettings::default(),
+            num_players: 2,
+            bots: Vec::new(),
+            seed: None,
+            tick_ms: None,
+            mode: GameMode::Classic,
+            handicaps: Vec::new(),
+            sandbox: false,
+            scenario: None,
+        }
     }
 }
 
-fn cell_color(cell: &Cell) -> Color {
-    use generals::Cell::*;
-    match *cell {
-        Mountain => Color::new(0.2, 0.2, 0.2, 1.0),
-        Open => Color::new(1.0, 1.0, 1.0, 1.0),
-        Fortress(None, _) => Color::new(0.4, 0.4, 0.4, 1.0),
+impl GameSetup {
+    /// `[--size N] [--players N] [--bots a,b,..] [--seed N] [--tick-ms N] [--mode M]
+    /// [--hold-ticks N] [--tick-limit N] [--handicap TEAM:SPEC].. [--sandbox] [--scenario S]`,
+    /// where the flags that are missing come from `config`.
+    fn from_args(args: &[String], config: &GameConfig) -> Result<Self, String> {
+        let has = |flag: &str| args.iter().any(|a| a == flag);
+        let num_players = parse_flag(args, "--players", config.players)?;
+        if num_players < 2 || num_players > net::MAX_PLAYERS {
+            return Err(format!("--players needs to be between 2 and {}", net::MAX_PLAYERS));
+        }
+        let bots = parse_flag(args, "--bots", config.bots.join(","))?
+            .split(',')
+            .filter(|n| !n.is_empty())
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>();
+        if bots.len() >= num_players {
+            return Err(format!("{} bots is too many for {} players", bots.len(), num_players));
+        }
+        if let Some(name) = bots.iter().find(|n| !bot::NAMES.contains(&n.as_str())) {
+            return Err(format!("Unknown bot '{}', try one of {:?}", name, bot::NAMES));
+        }
+        let size = parse_flag(args, "--size", config.size.unwrap_or(0))?;
+        let mode = parse_mode(args)?;
+        let handicaps = parse_handicaps(args, num_players)?;
+        let scenario = if has("--scenario") {
+            Some(Scenario::find(&parse_flag(args, "--scenario", String::new())?)?)
+        } else {
+            None
+        };
+        Ok(GameSetup {
+            map: MapSettings {
+                size: if size == 0 { None } else { Some(size) },
+                ..MapSettings::default()
+            },
+            num_players,
+            bots,
+            seed: if has("--seed") { Some(parse_flag(args, "--seed", 0)?) } else { None },
+            tick_ms: if has("--tick-ms") { Some(parse_flag(args, "--tick-ms", 0)?) } else { None },
+            mode,
+            handicaps,
+            sandbox: has("--sandbox"),
+            scenario,
+        })
+    }
 
-        Captured(team, _) |
-        King(team, _) |
-        Fortress(Some(team), _) => team_color(team),
+    /// A new game generated from `seed`, with bots for all teams but `team`. Scenarios are
+    /// always played as team 0, on their own map.
+    fn start(&self, seed: u32, team: Team) -> (GameState, Vec<BotPlayer>) {
+        if let Some(ref scenario) = self.scenario {
+            return scenario.start();
+        }
+        self.start_from(&Generation::new(seed, self.map.clone()), team)
+    }
+
+    /// The names of the bots we play against, in the order of their teams.
+    fn opponents(&self) -> Vec<String> {
+        if let Some(ref scenario) = self.scenario {
+            return (1..scenario.num_players)
+                .map(|t| scenario.bots.get(t - 1).cloned().unwrap_or_else(|| "idle".to_string()))
+                .collect();
+        }
+        (1..self.num_players)
+            .map(|i| self.bots.get(i - 1).cloned().unwrap_or_else(|| bot::DEFAULT_BOT.to_string()))
+            .collect()
+    }
+
+    /// Like `start`, on the board of an earlier game.
+    fn start_from(&self, generation: &Generation, team: Team) -> (GameState, Vec<BotPlayer>) {
+        let mut game = GameState::generate(generation, self.num_players, self.mode);
+        for &(t, handicap) in self.handicaps.iter() {
+            // Teams past the number of players, after it was changed in the menu, have none.
+            let _ = game.set_handicap(t, handicap);
+        }
+        let bots = (0..self.num_players)
+            .filter(|&t| t != team)
+            .enumerate()
+            .map(|(i, t)| {
+                let name = self.bots.get(i).map(|n| n.as_str()).unwrap_or(bot::DEFAULT_BOT);
+                BotPlayer::new(t, bot::by_name(name).unwrap())
+            })
+            .collect();
+        (game, bots)
     }
 }
 
-fn direction_from_keycode(keycode: Keycode) -> Direction {
-    match keycode {
-        Keycode::Up | Keycode::W => Direction::Up,
-        Keycode::Down | Keycode::S => Direction::Down,
-        Keycode::Left | Keycode::A => Direction::Left,
-        Keycode::Right | Keycode::D => Direction::Right,
-        _ => panic!("Not a valid direction: {:?}", keycode),
+/// Spectators can switch between the whole board (F) and the view of a single team (1-8), or
+/// stop watching (Escape).
+fn spectator_key_down(client: &mut Client, lobby: net::LobbyId, keycode: Keycode) {
+    let msg = match keycode {
+        Keycode::Escape => ClientMessage::LeaveLobby,
+        Keycode::F => ClientMessage::Spectate { lobby, team: None },
+        _ => {
+            let digits = [Keycode::Num1, Keycode::Num2, Keycode::Num3, Keycode::Num4,
+                          Keycode::Num5, Keycode::Num6, Keycode::Num7, Keycode::Num8];
+            match digits.iter().position(|&k| k == keycode) {
+                Some(team) => ClientMessage::Spectate { lobby, team: Some(team) },
+                None => return,
+            }
+        }
+    };
+    if let Err(e) = client.send(&msg) {
+        println!("Failed to send: {}", e);
     }
 }
 
 struct MainState {
     font: graphics::Font,
+    sprites: Sprites,
+    cell_text: CellText,
+    sounds: Sounds,
+    music: Music,
     game: GameState,
     time: Duration,
     last_tick: Duration,
-    tick_interval: Duration,
+    config: Config,
+    /// The settings of `config`, which are saved to it along with `keymap`.
+    settings: Settings,
+    /// The colors picked in the settings.
+    theme: Theme,
+    palette: Palette,
+    /// `Some` while the settings page is open, which pauses local games.
+    settings_screen: Option<SettingsScreen>,
+    keymap: Keymap,
+    gamepad: Gamepad,
+    /// Whether the move keys queue moves, or only move the focus.
+    queueing: bool,
+    /// Whether the live graphs of army and land are shown.
+    show_graph: bool,
+    /// Where an enemy last took one of our cells.
+    last_alert: Option<Position>,
+    /// Whether the local game is paused, in which case it only ticks when stepped.
+    paused: bool,
+    fullscreen: bool,
+    /// Show the debug overlay.
+    debug: bool,
+    /// Whether the left mouse button went down on one of our cells and is still held, in which
+    /// case moving the mouse queues moves.
+    dragging: bool,
+    /// Whether a shift key is held, which makes left clicks queue paths.
+    shift_held: bool,
+    /// Set when `fullscreen` changes, until the next `update` applies it. Key handlers don't
+    /// get the `Context` needed to do it right away.
+    fullscreen_changed: bool,
 
     team: usize,
-    focus: Option<Position>
+    focus: Option<Position>,
+    camera: Camera,
+    minimap: Minimap,
+    animations: Animations,
+    threats: Threats,
+    toasts: Toasts,
+    kill_feed: KillFeed,
+    /// Bots controlling every team but ours.
+    bots: Vec<BotPlayer>,
+    /// The setup of the local game, for rematches.
+    setup: GameSetup,
+    /// The seed the map of the local game was generated from.
+    seed: Option<u32>,
+
+    /// Print the `profile` report every second.
+    print_profile: bool,
+
+    replay: Replay,
+    /// The scores of every tick of the local game.
+    history: History,
+    /// `Some` when the game is over, and we are stepping through the replay.
+    review: Option<Review>,
+    /// `Some` from when the local game ends until the summary is dismissed.
+    outcome: Option<Outcome>,
+    /// `Some` while the menu is shown instead of the board.
+    menu: Option<Menu>,
+    /// `Some` in sandbox games, where the board can be edited.
+    sandbox: Option<Sandbox>,
+    campaign: Progress,
+    /// The mission of the campaign being played, if any.
+    mission: Option<usize>,
+    daily: DailyRecords,
+    achievements: Unlocked,
+    /// Follows the local game for achievements. `None` when it can't earn any, like in replays
+    /// or after taking over another team.
+    tracker: Option<Tracker>,
+    /// The daily challenge being played, if any.
+    challenge: Option<Challenge>,
+    /// The hints of the tutorial, while it is being played.
+    tutorial: Option<Tutorial>,
+    /// `Some` when the game is played on a server, in which case `game` is unused.
+    remote: Option<Remote>,
+}
+
+/// A game running on a server.
+struct Remote {
+    client: Client,
+    /// The latest view of the game we got from the server, or `None` while in the lobby.
+    view: Option<PlayerView>,
+    lobby: LobbyScreen,
+    chat: ChatBox,
+    /// The lobby whose game we are watching, if we are a spectator.
+    spectating: Option<net::LobbyId>,
+    /// When we last tried to reconnect after losing the connection.
+    last_reconnect: Option<Instant>,
+    /// When the game we are in started, or when we joined it.
+    started: Option<Instant>,
+    /// The players of the game we are in, from the server's last heartbeat.
+    players: Vec<net::PlayerInfo>,
+}
+
+/// Post-game review state.
+struct Review {
+    /// The tick of the replay currently shown.
+    tick: usize,
+    /// Show the move queues of all players, not just our own.
+    show_queues: bool,
+    playback: Playback,
 }
 
 impl MainState {
-    fn new(_ctx: &mut Context) -> GameResult<MainState> {
-        let num_players = 2;
-        let mut board = Board::empty(32);
-        board.randomize(num_players);
+    fn new(ctx: &mut Context, setup: &GameSetup, config: Config) -> GameResult<MainState> {
+        let team = 0;
+        let seed = setup.seed.unwrap_or_else(rand::random);
+        let (game, bots) = setup.start(seed, team);
+        let mut settings = config.settings.clone();
+        if let Some(ms) = setup.tick_ms {
+            settings.tick_ms = ms;
+        }
+        let keymap = Keymap::from_bindings(&config.keys);
+        let mut camera = Camera::new();
+        camera.resize(ctx.conf.window_width as f32, ctx.conf.window_height as f32);
+        camera.fit(game.dimens);
         Ok(MainState {
-            font: graphics::Font::default_font().unwrap(),
+            font: graphics::Font::default_font()?,
+            sprites: Sprites::load(ctx),
+            cell_text: CellText::new(),
+            sounds: Sounds::load(ctx, settings.volume),
+            music: Music::load(ctx, settings.music_volume),
             time: Duration::new(0, 0),
             last_tick: Duration::new(0, 0),
-            tick_interval: Duration::new(0, 500_000_000),
-            team: 0,
+            theme: settings.theme(),
+            palette: settings.palette(),
+            settings,
+            config,
+            settings_screen: None,
+            keymap,
+            gamepad: Gamepad::new(),
+            queueing: true,
+            show_graph: false,
+            last_alert: None,
+            paused: false,
+            fullscreen: false,
+            debug: false,
+            dragging: false,
+            shift_held: false,
+            fullscreen_changed: false,
+            team,
             focus: None,
-            game: GameState {
-                board,
-                tick_number: 0,
-                num_players: 2,
-                player_states: (0..num_players).map(PlayerState::new).collect(),
-                dimens: (32, 32),
+            camera,
+            minimap: Minimap::new(),
+            animations: Animations::new(),
+            threats: Threats::new(),
+            toasts: Toasts::new(),
+            kill_feed: KillFeed::new(),
+            bots,
+            setup: setup.clone(),
+            seed: Some(seed),
+            print_profile: false,
+            replay: Replay::new(&game),
+            history: History::new(),
+            review: None,
+            outcome: None,
+            menu: None,
+            sandbox: if setup.sandbox { Some(Sandbox::new()) } else { None },
+            campaign: Progress::load(config::campaign_path()).unwrap_or_else(|e| {
+                println!("Failed to load the campaign progress: {}", e);
+                Progress::default()
+            }),
+            mission: None,
+            daily: DailyRecords::load(config::daily_path()).unwrap_or_else(|e| {
+                println!("Failed to load the daily challenge results: {}", e);
+                DailyRecords::default()
+            }),
+            challenge: None,
+            tutorial: None,
+            achievements: Unlocked::load(config::achievements_path()).unwrap_or_else(|e| {
+                println!("Failed to load the achievements: {}", e);
+                Unlocked::default()
+            }),
+            tracker: if setup.sandbox { None } else { Some(Tracker::new(&game, team)) },
+            remote: None,
+            game,
+        })
+    }
+
+    /// Play a game hosted on a server.
+    fn from_client(ctx: &mut Context, client: Client, config: Config) -> GameResult<MainState> {
+        let mut state = MainState::new(ctx, &GameSetup::default(), config)?;
+        state.bots.clear();
+        state.seed = None;
+        state.remote = Some(Remote {
+            client,
+            view: None,
+            lobby: LobbyScreen::new(),
+            chat: ChatBox::new(),
+            spectating: None,
+            last_reconnect: None,
+            started: None,
+            players: Vec::new(),
+        });
+        Ok(state)
+    }
+
+    /// Open a recorded game for review, starting at the first tick.
+    fn from_replay(ctx: &mut Context, replay: Replay, config: Config) -> GameResult<MainState> {
+        let mut state = MainState::new(ctx, &GameSetup::default(), config)?;
+        state.open_replay(replay);
+        Ok(state)
+    }
+
+    /// What the debug overlay shows.
+    fn debug_lines(&self, ctx: &Context, view: &PlayerView) -> Vec<String> {
+        let mut lines = vec![format!("FPS: {:.0}", ggez::timer::get_fps(ctx))];
+        if self.remote.is_none() {
+            let ms = self.settings.tick_ms;
+            lines.push(format!("Tick interval: {} ms ({:.1} ticks/s)", ms, 1000.0 / ms as f64));
+        }
+        match self.camera.mouse() {
+            Some((x, y)) => {
+                let Position(cx, cy) = self.camera.cell_at(x, y);
+                // Local games show the real cell, even if it is in the fog.
+                let what = match self.remote {
+                    Some(_) => view.try_get(cx, cy).map(|tile| format!("{:?}", tile)),
+                    None => self.game.board.try_get(cx, cy).map(|cell| format!("{:?}", cell)),
+                };
+                lines.push(format!("Cursor: ({}, {}) {}",
+                                   cx,
+                                   cy,
+                                   what.unwrap_or_else(|| "off the board".to_string())));
+            }
+            None => lines.push("Cursor: -".to_string()),
+        }
+        match self.remote {
+            Some(_) => lines.push(format!("Queue: {} moves", view.moves.len())),
+            None => {
+                for player in self.game.player_states.iter() {
+                    lines.push(format!("Team {} queue: {} moves",
+                                       player.team,
+                                       player.queue_len()));
+                }
+            }
+        }
+        lines.push(match self.seed {
+            Some(seed) => format!("Seed: {}", seed),
+            None => "Seed: unknown".to_string(),
+        });
+        for (section, time) in profile::last_second() {
+            lines.push(format!("Time in {}: {:.2} ms/s", section.name(), profile::millis(time)));
+        }
+        lines
+    }
+
+    /// The cells of `team` that we can see.
+    fn visible_land(&self, team: Team) -> Vec<Position> {
+        let (w, h) = self.game.dimens;
+        (0..h)
+            .flat_map(|y| (0..w).map(move |x| Position(x, y)))
+            .filter(|&Position(x, y)| {
+                self.game.board.try_get(x, y).and_then(|cell| cell.owner()) == Some(team) &&
+                self.game.is_visible_to(self.team, x, y)
+            })
+            .collect()
+    }
+
+    /// Let the bots act, and run one tick of the local game.
+    fn tick(&mut self) {
+        for bot in self.bots.iter_mut() {
+            bot.act(&mut self.game);
+        }
+        self.replay.record(&self.game);
+        let events = self.game.tick();
+        self.history.record(&self.game, &events);
+        for event in events.iter() {
+            self.event(event);
+        }
+        let tick = self.game.tick_number;
+        let earned = match self.tracker {
+            Some(ref mut tracker) => events.iter().filter_map(|e| tracker.event(e, tick)).collect(),
+            None => Vec::new(),
+        };
+        for achievement in earned {
+            self.unlock(achievement);
+        }
+    }
+
+    /// Look at the local game from the next team's point of view. We take over that team, and
+    /// the bot that was playing it takes over ours.
+    fn switch_perspective(&mut self) {
+        let old = self.team;
+        self.team = (old + 1) % self.game.num_players;
+        for bot in self.bots.iter_mut().filter(|bot| bot.team == self.team) {
+            bot.team = old;
+        }
+        self.focus = None;
+        self.animations.clear();
+        self.threats.clear();
+        self.tracker = None;
+        self.toasts.push(format!("Viewing the game as team {}", self.team + 1), self.team);
+    }
+
+    /// Start a new local game with the same settings as the last one, on a new map.
+    fn rematch(&mut self) {
+        self.challenge = None;
+        let seed = rand::random();
+        let (game, bots) = self.setup.start(seed, self.team);
+        self.seed = Some(seed);
+        self.restart(game, bots);
+    }
+
+    /// Play the map of the last game again, with the same settings. With `swap_spawns`,
+    /// everyone starts where the next team did last time. Games without a generated map, like
+    /// replays, get a new one.
+    fn replay_map(&mut self, swap_spawns: bool) {
+        let generation = match self.game.generation() {
+            Some(generation) if swap_spawns => {
+                // With other spawns it is no longer the daily challenge.
+                self.challenge = None;
+                generation.rotated()
+            }
+            Some(generation) => generation.clone(),
+            None => return self.rematch(),
+        };
+        let (game, bots) = self.setup.start_from(&generation, self.team);
+        self.seed = Some(generation.seed);
+        self.restart(game, bots);
+    }
+
+    /// Play the daily `challenge`, as team 0 against its bot and with its map. Later games keep
+    /// those settings.
+    fn start_daily(&mut self, challenge: Challenge) {
+        self.setup = GameSetup {
+            map: challenge.generation.map.clone(),
+            bots: vec![challenge.bot.to_string()],
+            tick_ms: self.setup.tick_ms,
+            ..GameSetup::default()
+        };
+        self.mission = None;
+        self.team = 0;
+        let (game, bots) = self.setup.start_from(&challenge.generation, self.team);
+        self.seed = Some(challenge.generation.seed);
+        self.restart(game, bots);
+        self.challenge = Some(challenge);
+    }
+
+    /// Play the tutorial scenario, with the hints that teach the game.
+    fn start_tutorial(&mut self) {
+        let scenario = match Scenario::find("tutorial") {
+            Ok(scenario) => scenario,
+            Err(e) => {
+                println!("Failed to load the tutorial: {}", e);
+                return;
+            }
+        };
+        self.setup.num_players = scenario.num_players;
+        self.setup.scenario = Some(scenario);
+        self.mission = None;
+        self.team = 0;
+        self.rematch();
+        // Nothing is earned in a game played by the book.
+        self.tracker = None;
+        self.tutorial = Some(Tutorial::new());
+    }
+
+    /// Stop showing the hints, and don't start with the tutorial anymore.
+    fn finish_tutorial(&mut self) {
+        self.tutorial = None;
+        if !self.config.tutorial_done {
+            self.config.tutorial_done = true;
+            self.save_config();
+        }
+    }
+
+    /// Add the local game that `winner` just won to the history of finished games. Replays are
+    /// not games we played, so they are left out.
+    fn record_match(&mut self, winner: Option<Team>) {
+        if self.bots.is_empty() {
+            return;
+        }
+        let outcome = match winner {
+            Some(team) if team == self.team => MatchOutcome::Won,
+            Some(_) => MatchOutcome::Lost,
+            None => MatchOutcome::Draw,
+        };
+        let seed = self.game.generation().map(|g| g.seed);
+        let summary = MatchSummary::new(self.game.mode(),
+                                        self.setup.opponents(),
+                                        outcome,
+                                        self.game.tick_number,
+                                        seed);
+        if let Err(e) = matches::append(config::matches_path(), &summary) {
+            println!("Failed to save the game to the history: {}", e);
+        }
+    }
+
+    /// Unlock `achievement`, and tell about it if it is new.
+    fn unlock(&mut self, achievement: Achievement) {
+        if !self.achievements.unlock(achievement) {
+            return;
+        }
+        let text = format!("Achievement unlocked: {}", achievement.title());
+        self.toasts.push(text, self.team);
+        if let Err(e) = self.achievements.save(config::achievements_path()) {
+            println!("Failed to save the achievements: {}", e);
+        }
+    }
+
+    /// The menu, with the campaign progress and today's best daily challenge result.
+    fn menu(&self, can_review: bool) -> Menu {
+        let best = self.daily.best(daily::today());
+        Menu::new(can_review, self.setup.num_players, self.campaign, best)
+    }
+
+    /// Switch to the new local `game`, played by `bots` and us.
+    fn restart(&mut self, mut game: GameState, bots: Vec<BotPlayer>) {
+        eventlog::attach(&mut game);
+        self.replay = Replay::new(&game);
+        self.history = History::new();
+        self.tracker = if self.sandbox.is_some() {
+            None
+        } else {
+            Some(Tracker::new(&game, self.team))
+        };
+        self.game = game;
+        self.bots = bots;
+        self.time = Duration::new(0, 0);
+        self.last_tick = Duration::new(0, 0);
+        self.focus = None;
+        self.minimap = Minimap::new();
+        self.animations.clear();
+        self.threats.clear();
+        self.toasts.clear();
+        self.kill_feed.clear();
+        self.last_alert = None;
+        self.camera.fit(self.game.dimens);
+        self.review = None;
+        self.outcome = None;
+        self.menu = None;
+        self.paused = false;
+        self.tutorial = None;
+    }
+
+    /// Stop whatever we were doing, and review `replay` from the first tick.
+    fn open_replay(&mut self, replay: Replay) {
+        self.remote = None;
+        self.bots.clear();
+        self.seed = None;
+        self.history = History::new();
+        self.tracker = None;
+        self.tutorial = None;
+        self.focus = None;
+        self.outcome = None;
+        self.menu = None;
+        self.animations.clear();
+        self.threats.clear();
+        self.toasts.clear();
+        self.kill_feed.clear();
+        self.last_alert = None;
+        let mut playback = Playback::new(&replay);
+        self.game = playback.state_at(&replay, 0);
+        self.camera.fit(self.game.dimens);
+        self.replay = replay;
+        self.review = Some(Review {
+            tick: 0,
+            show_queues: true,
+            playback,
+        });
+    }
+
+    /// Whether we are connected to a server, but not in a game.
+    fn in_lobby(&self) -> bool {
+        self.remote.as_ref().map(|r| r.view.is_none()).unwrap_or(false)
+    }
+
+    /// The game as seen by our team, or `None` while in the lobby.
+    fn view(&self) -> Option<PlayerView> {
+        match self.remote {
+            Some(ref remote) => remote.view.clone(),
+            // The review shows the game as it really went.
+            None if self.review.is_some() => Some(self.game.full_view(self.team)),
+            None => Some(self.game.player_view(self.team)),
+        }
+    }
+
+    /// Save the board as we see it, all of it, as an image.
+    fn screenshot(&self) {
+        let view = match self.view() {
+            Some(view) => view,
+            None => return,
+        };
+        let (theme, palette) = (&self.theme, &self.palette);
+        match ui::screenshot::save(&view, |tile| tile_color(theme, palette, tile), theme.grid) {
+            Ok(path) => println!("Saved the board to {}", path.display()),
+            Err(e) => println!("Failed to save the board: {}", e),
+        }
+    }
+
+    /// The size of the board, or `None` while in the lobby.
+    fn dimens(&self) -> Option<(i32, i32)> {
+        match self.remote {
+            Some(ref remote) => remote.view.as_ref().map(|view| view.dimens),
+            None => Some(self.game.dimens),
+        }
+    }
+
+    /// Queue a move from the focused cell in `dir`, and move the focus along with it. Returns
+    /// `false` if there is no focus, or the move would leave the board.
+    fn queue_step(&mut self, dir: Direction) -> bool {
+        let (w, h) = match self.dimens() {
+            Some(dimens) => dimens,
+            None => return false,
+        };
+        let pos = match self.focus {
+            Some(pos) => pos,
+            None => return false,
+        };
+        let Position(x, y) = pos + dir;
+        if x < 0 || x >= w || y < 0 || y >= h {
+            return false;
+        }
+        self.push_move((pos, dir));
+        self.focus = Some(Position(x, y));
+        true
+    }
+
+    /// Queue moves from the focused cell to `target` while dragging the mouse. The mouse can
+    /// skip over cells when moved quickly, so this walks there one cell at a time.
+    fn drag_to(&mut self, target: Position) {
+        while let Some(Position(x, y)) = self.focus {
+            let dir = if x < target.0 {
+                Direction::Right
+            } else if x > target.0 {
+                Direction::Left
+            } else if y < target.1 {
+                Direction::Down
+            } else if y > target.1 {
+                Direction::Up
+            } else {
+                return;
+            };
+            if !self.queue_step(dir) {
+                return;
+            }
+        }
+    }
+
+    /// What moving from the focus to the neighbouring cell `target` would do: the units that
+    /// would arrive, since one unit is always left behind, and what the fight there would leave.
+    fn move_preview(&self, view: &PlayerView, target: Position) -> Option<String> {
+        let focus = self.focus?;
+        if focus.distance(target) != 1 {
+            return None;
+        }
+        let units = match view.try_get(focus.0, focus.1) {
+            Some(Tile::Visible(cell)) if cell.is_controlled_by(view.team) => cell.units()?,
+            _ => return None,
+        };
+        let moving = units.saturating_sub(1);
+        Some(match view.try_get(target.0, target.1) {
+            Some(Tile::Visible(ref cell)) if !cell.is_passable() => "blocked".to_string(),
+            Some(Tile::Visible(ref cell)) if cell.is_controlled_by(view.team) => {
+                format!("+{}", moving)
+            }
+            Some(Tile::Visible(Cell::Open)) => format!("{}", moving),
+            Some(Tile::Visible(cell)) => {
+                let n = cell.units().unwrap_or(0);
+                if moving > n {
+                    format!("{} vs {}: take it", moving, n)
+                } else {
+                    format!("{} vs {}", moving, n)
+                }
+            }
+            _ => format!("{}", moving),
+        })
+    }
+
+    /// Queue the shortest path from the focused cell to `target` that goes around the
+    /// mountains we know of, and move the focus there.
+    fn queue_path(&mut self, target: Position) {
+        let (view, from) = match (self.view(), self.focus) {
+            (Some(view), Some(from)) => (view, from),
+            _ => return,
+        };
+        if let Some(moves) = path::bfs_in_view(&view, from, target) {
+            for mv in moves {
+                self.push_move(mv);
+            }
+            self.focus = Some(target);
+        }
+    }
+
+    /// Give `command` to the local game.
+    fn apply_local(&mut self, command: Command) {
+        if let Err(e) = frontend::apply(&mut self.game, self.team, command) {
+            println!("Can't do that: {}", e);
+        }
+    }
+
+    fn push_move(&mut self, mv: Move) {
+        match self.remote {
+            Some(ref mut remote) => {
+                // Show the move right away, instead of waiting for the next update.
+                if let Some(ref mut view) = remote.view {
+                    view.moves.push_back(mv);
+                }
+                if let Err(e) = remote.client.send(&ClientMessage::Move(mv)) {
+                    println!("Failed to send move: {}", e);
+                }
+            }
+            None => self.apply_local(Command::Move(mv)),
+        }
+    }
+
+    fn clear_moves(&mut self) {
+        match self.remote {
+            Some(ref mut remote) => {
+                if let Some(ref mut view) = remote.view {
+                    view.moves.clear();
+                }
+                if let Err(e) = remote.client.send(&ClientMessage::ClearMoves) {
+                    println!("Failed to clear moves: {}", e);
+                }
+            }
+            None => self.apply_local(Command::ClearMoves),
+        }
+    }
+
+    /// Do what a key or button bound to `action` does during a game.
+    fn perform(&mut self, action: Action) {
+        let local = self.remote.is_none();
+        match action {
+            Action::ClearMoves => self.clear_moves(),
+            Action::Undo => self.undo_move(),
+            Action::Pause if local => self.paused = !self.paused,
+            Action::Step if local && self.paused => self.tick(),
+            Action::Mute => {
+                let muted = self.sounds.toggle_mute();
+                let text = format!("Sound {}", if muted { "off" } else { "on" });
+                self.toasts.push(text, self.team);
+            }
+            Action::Faster if local => self.change_speed(-1),
+            Action::Slower if local => self.change_speed(1),
+            Action::ZoomIn => self.camera.wheel(1),
+            Action::ZoomOut => self.camera.wheel(-1),
+            Action::FocusKing => {
+                if let Some(king) = self.king() {
+                    self.set_focus(king);
+                }
+            }
+            Action::CenterKing => {
+                if let Some(king) = self.king() {
+                    self.camera.center_on(king);
+                }
+            }
+            Action::JumpToAlert => {
+                if let Some(pos) = self.last_alert {
+                    self.camera.center_on(pos);
+                }
+            }
+            Action::ToggleGraph => self.show_graph = !self.show_graph,
+            Action::ToggleQueueing => {
+                self.queueing = !self.queueing;
+                let text = format!("Arrows {}",
+                                   if self.queueing { "queue moves" } else { "move the focus" });
+                self.toasts.push(text, self.team);
+            }
+            _ => {
+                if let Some(dir) = action.direction() {
+                    self.queue_step(dir);
+                }
+            }
+        }
+    }
+
+    /// Handle controller input, unless a menu is open or we are only watching.
+    fn gamepad_input(&mut self, input: Input) {
+        let watching = self.review.is_some() ||
+                       self.remote.as_ref().map(|r| r.spectating.is_some()).unwrap_or(false);
+        if self.menu.is_some() || self.settings_screen.is_some() || self.outcome.is_some() ||
+           self.in_lobby() || watching {
+            return;
+        }
+        match input {
+            Input::Cursor(dir) => self.move_focus(dir),
+            Input::Queue(dir) => {
+                self.queue_step(dir);
+            }
+            Input::Action(action) => self.perform(action),
+        }
+    }
+
+    /// Where our King is, if we still have one.
+    fn king(&self) -> Option<Position> {
+        self.view()?.king()
+    }
+
+    /// Focus the cell at `pos`, and bring it into view.
+    fn set_focus(&mut self, pos: Position) {
+        self.focus = Some(pos);
+        if !self.camera.is_visible(pos) {
+            self.camera.center_on(pos);
+        }
+    }
+
+    /// Move the focus one cell without moving any units. Without a focus, start at our King.
+    fn move_focus(&mut self, dir: Direction) {
+        let focus = match self.focus {
+            Some(pos) => pos + dir,
+            None => match self.king() {
+                Some(pos) => pos,
+                None => return,
             },
+        };
+        let Position(x, y) = focus;
+        let (w, h) = self.dimens().unwrap_or((0, 0));
+        if x >= 0 && x < w && y >= 0 && y < h {
+            self.set_focus(focus);
+        }
+    }
+
+    /// Drop the last move in our queue, and focus the cell it would have moved from.
+    fn undo_move(&mut self) {
+        let last = self.view()
+            .and_then(|view| view.moves.back().map(|&mv| (view.moves.len(), mv)));
+        if let Some((len, (from, _))) = last {
+            self.truncate_moves(len - 1);
+            self.focus = Some(from);
+        }
+    }
+
+    /// Change the tick interval of local games by `step` steps, and remember it.
+    fn change_speed(&mut self, step: i64) {
+        self.settings.step_tick(step);
+        let text = format!("Tick speed: {} ms", self.settings.tick_ms);
+        self.toasts.push(text, self.team);
+        self.save_config();
+    }
+
+    /// Write the settings and key bindings to the config file.
+    fn save_config(&mut self) {
+        self.config.settings = self.settings.clone();
+        self.config.keys = self.keymap.bindings();
+        if let Err(e) = self.config.save() {
+            println!("Failed to save the config: {}", e);
+        }
+    }
+
+    /// Drop every move in our queue after the first `keep`.
+    fn truncate_moves(&mut self, keep: usize) {
+        match self.remote {
+            Some(ref mut remote) => {
+                let kept = match remote.view {
+                    Some(ref mut view) => {
+                        view.moves.truncate(keep);
+                        view.moves.clone()
+                    }
+                    None => return,
+                };
+                let result = if remote.client.has(net::CAP_TRUNCATE) {
+                    remote.client.send(&ClientMessage::TruncateMoves(keep))
+                } else {
+                    // Older servers can only clear the queue, so send back what we keep.
+                    remote.client.send(&ClientMessage::ClearMoves).and_then(|_| {
+                        kept.into_iter()
+                            .map(|mv| remote.client.send(&ClientMessage::Move(mv)))
+                            .collect()
+                    })
+                };
+                if let Err(e) = result {
+                    println!("Failed to change moves: {}", e);
+                }
+            }
+            None => self.apply_local(Command::TruncateMoves(keep)),
+        }
+    }
+
+    /// The index of the move in our queue whose arrow is at the window position `(x, y)`.
+    fn queued_move_at(&self, view: &PlayerView, x: f32, y: f32) -> Option<usize> {
+        let reach = self.camera.cell_size() * 0.15;
+        view.moves.iter().position(|&(from, dir)| {
+            let a = self.camera.cell_center(from.0, from.1);
+            let to = from + dir;
+            let b = self.camera.cell_center(to.0, to.1);
+            // The distance from (x, y) to the line segment from a to b.
+            let (dx, dy) = (b.x - a.x, b.y - a.y);
+            let t = (((x - a.x) * dx + (y - a.y) * dy) / (dx * dx + dy * dy)).max(0.0).min(1.0);
+            let (px, py) = (a.x + t * dx, a.y + t * dy);
+            ((x - px).powi(2) + (y - py).powi(2)).sqrt() <= reach
         })
     }
 
-    fn dimens(&self) -> (i32, i32) {
-        self.game.dimens
+    fn update_remote(&mut self) {
+        let mut downloaded = None;
+        if let Some(ref mut remote) = self.remote {
+            if !remote.client.is_connected() {
+                let due = remote.last_reconnect
+                    .map(|t| t.elapsed() >= RECONNECT_INTERVAL)
+                    .unwrap_or(true);
+                if due {
+                    remote.last_reconnect = Some(Instant::now());
+                    match remote.client.reconnect() {
+                        Ok(()) => println!("Reconnected"),
+                        Err(e) => println!("Lost the connection, retrying: {}", e),
+                    }
+                }
+            }
+            for msg in remote.client.poll() {
+                remote.lobby.handle(&msg);
+                match msg {
+                    ServerMessage::Welcome { team, .. } => {
+                        self.team = team;
+                        self.focus = None;
+                    }
+                    ServerMessage::State(view) => {
+                        if remote.view.is_none() {
+                            self.camera.fit(view.dimens);
+                            remote.started = Some(Instant::now());
+                            self.history = History::new();
+                        }
+                        remote.view = Some(view);
+                    }
+                    ServerMessage::Delta(ref delta) => {
+                        let applied = match remote.view {
+                            Some(ref mut view) => view.apply(delta),
+                            None => true,
+                        };
+                        if !applied {
+                            if let Err(e) = remote.client.send(&ClientMessage::Resync) {
+                                println!("Failed to ask for the game state: {}", e);
+                            }
+                        } else if let Some(ref view) = remote.view {
+                            // Every delta is a tick. The views only have scores, no events.
+                            self.history.push(view.scores.clone(), &[]);
+                        }
+                    }
+                    ServerMessage::GameOver { winner } => {
+                        if remote.spectating.is_none() {
+                            self.sounds.play(if winner == Some(self.team) {
+                                Sound::Victory
+                            } else {
+                                Sound::Defeat
+                            });
+                        }
+                        match winner {
+                            Some(team) => println!("Game over, team {} won", team),
+                            None => println!("Game over"),
+                        }
+                        remote.view = None;
+                        remote.spectating = None;
+                        remote.players.clear();
+                    }
+                    ServerMessage::LeftLobby => {
+                        remote.view = None;
+                        remote.spectating = None;
+                        remote.players.clear();
+                    }
+                    ServerMessage::Spectating { lobby, team, .. } => {
+                        remote.spectating = Some(lobby);
+                        self.team = team.unwrap_or(0);
+                        self.focus = None;
+                    }
+                    ServerMessage::Chat { ref from, channel, ref text } => {
+                        remote.chat.push(from, channel, text)
+                    }
+                    ServerMessage::ReplayData { ref file, ref replay } => {
+                        if let Err(e) = replay.check() {
+                            println!("Can't open {}: {}", file, e);
+                            continue;
+                        }
+                        match replay.save_timestamped() {
+                            Ok(path) => println!("Saved {} to {}", file, path.display()),
+                            Err(e) => println!("Failed to save {}: {}", file, e),
+                        }
+                        downloaded = Some(replay.clone());
+                    }
+                    ServerMessage::Ping(number) => {
+                        if let Err(e) = remote.client.send(&ClientMessage::Pong(number)) {
+                            println!("Failed to answer a ping: {}", e);
+                        }
+                    }
+                    ServerMessage::Players(players) => remote.players = players,
+                    _ => {}
+                }
+            }
+        }
+        if let Some(replay) = downloaded {
+            self.open_replay(replay);
+        }
     }
-}
 
-impl event::EventHandler for MainState {
-    fn update(&mut self, _ctx: &mut Context, _dt: Duration) -> GameResult<()> {
-        self.time += _dt;
-        while self.time - self.last_tick > self.tick_interval {
-            self.last_tick += self.tick_interval;
-            self.game.tick();
+    fn start_review(&mut self) {
+        self.replay.winner = self.game.winner();
+        match self.replay.save_timestamped() {
+            Ok(path) => println!("Saved replay to {}", path.display()),
+            Err(e) => println!("Failed to save replay: {}", e),
         }
+        self.focus = None;
+        self.review = Some(Review {
+            tick: self.replay.len(),
+            show_queues: false,
+            playback: Playback::new(&self.replay),
+        });
+    }
 
-        Ok(())
+    /// Move the review `delta` ticks forwards or backwards.
+    fn step_review(&mut self, delta: isize) {
+        if let Some(ref mut review) = self.review {
+            let tick = (review.tick as isize + delta).max(0) as usize;
+            review.tick = tick.min(self.replay.len());
+            self.game = review.playback.state_at(&self.replay, review.tick);
+        }
     }
+}
 
-    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        let board_size = self.game.dimens.0;
+impl Frontend for MainState {
+    type Context = Context;
+    type Error = GameError;
+
+    /// Draw the board and everything on top of it.
+    fn render(&mut self, ctx: &mut Context, view: &PlayerView) -> GameResult<()> {
+        let (w, h) = view.dimens;
+        graphics::set_background_color(ctx, self.theme.grid);
         graphics::clear(ctx);
-        for (y, row) in self.game.board.cells().iter().enumerate() {
-            for (x, cell) in row.iter().enumerate() {
-                let (x, y) = (
-                    x as f32 * (CELL_SIZE + 1.0) + CELL_SIZE / 2.0,
-                    y as f32 * (CELL_SIZE + 1.0) + CELL_SIZE / 2.0,
-                );
-                let rect = Rect {
-                    x,
-                    y,
-                    w: CELL_SIZE,
-                    h: CELL_SIZE,
+        let ((x0, x1), (y0, y1)) = self.camera.visible_cells(view.dimens);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let tile = &view.tiles[y as usize][x as usize];
+                let rect = self.camera.cell_rect(x, y);
+                graphics::set_color(ctx, tile_color(&self.theme, &self.palette, tile))?;
+                graphics::rectangle(ctx, DrawMode::Fill, rect)?;
+                if let Tile::Visible(cell) = *tile {
+                    match cell.owner() {
+                        Some(team) if self.settings.patterns => {
+                            ui::palette::draw_pattern(ctx, ui::palette::pattern(team), rect)?;
+                        }
+                        _ => {}
+                    }
+                }
+                let has_sprite = match Sprite::for_tile(tile) {
+                    Some(sprite) => self.sprites.draw(ctx, sprite, rect)?,
+                    None => false,
                 };
-                graphics::set_color(ctx, cell_color(&cell)).unwrap();
-                graphics::rectangle(ctx, DrawMode::Fill, rect).unwrap();
-                match *cell {
-                    Cell::Fortress(_, n) |
-                    Cell::King(_, n) |
-                    Cell::Captured(_, n) => {
-                        let t = graphics::Text::new(ctx, &format!("{}", n), &self.font).unwrap();
-                        graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 1.0)).unwrap();
-                        t.draw(ctx, Point::new(x, y), 0.0).unwrap();
+                if let Tile::Visible(Cell::King(..)) = *tile {
+                    if !has_sprite {
+                        ui::marker::draw_crown(ctx, rect, Color::new(1.0, 0.85, 0.2, 1.0))?;
+                    }
+                }
+                match *tile {
+                    Tile::Obstacle if !has_sprite => {
+                        // We can't tell mountains from fortresses in the fog.
+                        let radius = self.camera.cell_size() * 0.3;
+                        graphics::set_color(ctx, self.theme.obstacle)?;
+                        graphics::circle(ctx,
+                                         DrawMode::Fill,
+                                         Point::new(rect.x, rect.y),
+                                         radius,
+                                         0.5)?;
+                    }
+                    Tile::Visible(Cell::Fortress(_, n)) |
+                    Tile::Visible(Cell::King(_, n)) |
+                    Tile::Visible(Cell::Captured(_, n)) => {
+                        let background = tile_color(&self.theme, &self.palette, tile);
+                        self.cell_text.draw(ctx, &self.font, &n.to_string(), rect, background)?;
                     }
                     _ => {}
                 }
             }
         }
 
-        let player_state = &self.game.player_states[self.team];
-        // Draw queued line
-        graphics::set_color(ctx, black()).unwrap();
-        for &(from_pos, dir) in player_state.moves.iter() {
-            let Position(x, y) = from_pos;
-            let (dx, dy) = dir.to_xy();
-            let points = [
-                Point {
-                    x: x as f32 * (CELL_SIZE + 1.0) + CELL_SIZE / 2.0,
-                    y: y as f32 * (CELL_SIZE + 1.0) + CELL_SIZE / 2.0,
-                },
-                Point {
-                    x: (x + dx) as f32 * (CELL_SIZE + 1.0) + CELL_SIZE / 2.0,
-                    y: (y + dy) as f32 * (CELL_SIZE + 1.0) + CELL_SIZE / 2.0,
-                },
-            ];
-            graphics::line(ctx, &points).unwrap();
+        let theme = &self.theme;
+        let palette = &self.palette;
+        ui::borders::draw(ctx, &self.camera, view, |t| palette.team(t))?;
+        if let Some(hill) = view.hill {
+            // The hill is ringed in the color of whoever holds it.
+            let Position(x, y) = hill.pos;
+            let color = hill.holder.map(|t| palette.team(t)).unwrap_or(theme.text);
+            let radius = self.camera.cell_size() * 0.45;
+            graphics::set_color(ctx, color)?;
+            graphics::circle(ctx, DrawMode::Line, self.camera.cell_center(x, y), radius, 0.5)?;
+        }
+        if self.review.is_none() {
+            self.threats.update(view);
+            self.threats.draw(ctx, &self.camera)?;
+        }
+        self.animations.draw(ctx, &self.font, &self.camera, |t| palette.team(t))?;
+        if let Some(king) = view.king() {
+            ui::marker::draw_arrow_to(ctx, &self.camera, king, palette.team(view.team))?;
+        }
+
+        // Draw the move queues
+        let show_all_queues = self.review.as_ref().map(|r| r.show_queues).unwrap_or(false);
+        if show_all_queues {
+            for player_state in self.game.player_states.iter() {
+                draw_moves(ctx,
+                           &self.font,
+                           &self.camera,
+                           player_state.queued_moves(),
+                           palette.team(player_state.team))?;
+            }
+        } else {
+            draw_moves(ctx, &self.font, &self.camera, view.moves.iter(), self.theme.moves)?;
         }
 
         // Draw focus shade stuff
         if let Some(Position(x, y)) = self.focus {
-            let (fx, fy) = (
-                x as f32 * (CELL_SIZE + 1.0) + CELL_SIZE / 2.0,
-                y as f32 * (CELL_SIZE + 1.0) + CELL_SIZE / 2.0,
-            );
-            let rect = Rect {
-                x: fx,
-                y: fy,
-                w: CELL_SIZE,
-                h: CELL_SIZE,
-            };
-            graphics::set_color(ctx, red_overlay()).unwrap();
-            graphics::rectangle(ctx, DrawMode::Fill, rect).unwrap();
+            let rect = self.camera.cell_rect(x, y);
+            graphics::set_color(ctx, self.theme.focus)?;
+            graphics::rectangle(ctx, DrawMode::Fill, rect)?;
 
-            let w = board_size as i32;
-            let h = board_size as i32;
             for d in &[
                 Direction::Up,
                 Direction::Left,
@@ -183,69 +1290,698 @@ impl event::EventHandler for MainState {
             ]
             {
                 if let Some((x, y)) = d.from((x, y), w, h) {
-                    let (fx, fy) = (
-                        x as f32 * (CELL_SIZE + 1.0) + CELL_SIZE / 2.0,
-                        y as f32 * (CELL_SIZE + 1.0) + CELL_SIZE / 2.0,
-                    );
-                    let rect = Rect {
-                        x: fx,
-                        y: fy,
-                        w: CELL_SIZE,
-                        h: CELL_SIZE,
-                    };
-                    graphics::set_color(ctx, black_overlay()).unwrap();
-                    graphics::rectangle(ctx, DrawMode::Fill, rect).unwrap();
+                    let rect = self.camera.cell_rect(x, y);
+                    graphics::set_color(ctx, self.theme.overlay)?;
+                    graphics::rectangle(ctx, DrawMode::Fill, rect)?;
                 }
             }
         }
 
+        // Highlight the cell under the mouse, and if it is next to the focus, show how many units
+        // a move there would bring.
+        if let Some((mx, my)) = self.camera.mouse() {
+            let Position(x, y) = self.camera.cell_at(mx, my);
+            if x >= 0 && x < w && y >= 0 && y < h {
+                let rect = self.camera.cell_rect(x, y);
+                graphics::set_color(ctx, Color::new(1.0, 1.0, 1.0, 0.8))?;
+                graphics::rectangle(ctx, DrawMode::Line, rect)?;
+                if let Some(text) = self.move_preview(view, Position(x, y)) {
+                    let label =
+                        Rect::new(rect.x, rect.y - rect.h * 0.7, rect.w * 1.5, rect.h * 0.4);
+                    graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.75))?;
+                    graphics::rectangle(ctx, DrawMode::Fill, label)?;
+                    let black = Color::new(0.0, 0.0, 0.0, 1.0);
+                    self.cell_text.draw(ctx, &self.font, &text, label, black)?;
+                }
+            }
+        }
+
+        ui::scoreboard::draw(ctx, &self.font, &view.scores, view.team, |t| palette.team(t))?;
+        self.minimap.update(ctx, view, |tile| tile_color(theme, palette, tile))?;
+        self.minimap.draw(ctx, &self.camera)?;
+
+        if let Some(ref remote) = self.remote {
+            remote.chat.draw(ctx, &self.font)?;
+        }
+        let elapsed = match self.remote {
+            Some(ref remote) => remote.started.map(|t| t.elapsed()).unwrap_or_default(),
+            None if self.review.is_some() => {
+                self.settings.tick_interval() * view.tick_number as u32
+            }
+            None => self.time,
+        };
+        ui::hud::draw(ctx, &self.font, view.tick_number, elapsed)?;
+        let local = self.remote.is_none();
+        let mut top = ui::hud::BOTTOM;
+        if let Some(ref hill) = view.hill {
+            ui::hud::draw_hill(ctx, &self.font, hill, |t| palette.team(t))?;
+            top += ui::hud::HEIGHT;
+        } else if let Some(limit) = view.mode.tick_limit() {
+            ui::hud::draw_countdown(ctx, &self.font, limit.saturating_sub(view.tick_number))?;
+            top += ui::hud::HEIGHT;
+        } else if let Some(scenario) = self.setup.scenario.as_ref().filter(|_| local) {
+            let objective = scenario.objective.describe(view.tick_number);
+            ui::hud::draw_objective(ctx, &self.font, &objective)?;
+            top += ui::hud::HEIGHT;
+        }
+        self.toasts.draw(ctx, &self.font, |t| palette.team(t))?;
+        if let Some(tutorial) = self.tutorial.as_mut().filter(|_| local) {
+            tutorial.update(view, self.focus);
+            tutorial.draw(ctx, &self.font, &self.camera, view)?;
+        }
+        let mut feed_top = ui::scoreboard::bottom(view.scores.len()) + 10.0;
+        if let Some(remote) = self.remote.as_ref().filter(|r| !r.players.is_empty()) {
+            let players = &remote.players;
+            ui::player_list::draw(ctx, &self.font, players, view.team, feed_top, |t| {
+                palette.team(t)
+            })?;
+            feed_top += ui::player_list::height(players.len()) + 10.0;
+        }
+        self.kill_feed.draw(ctx, &self.font, view.team, feed_top, |t| palette.team(t))?;
+        if self.paused && self.remote.is_none() && self.review.is_none() {
+            let y = top + 10.0;
+            graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.7))?;
+            graphics::rectangle(ctx, DrawMode::Fill, Rect::new(220.0, y + 12.0, 420.0, 40.0))?;
+            ui::draw_text(ctx,
+                          &self.font,
+                          "Paused - N: step one tick   P: resume",
+                          20.0,
+                          y,
+                          Color::new(1.0, 1.0, 1.0, 1.0))?;
+            top += 50.0;
+        }
+        if self.show_graph && self.review.is_none() {
+            // Opponents are only shown while some of their land is in sight.
+            let mut teams = vec![view.team];
+            for row in view.tiles.iter() {
+                for tile in row.iter() {
+                    if let Tile::Visible(ref cell) = *tile {
+                        match cell.owner() {
+                            Some(team) if !teams.contains(&team) => teams.push(team),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            teams.sort();
+            ui::graph::draw_live(ctx, &self.font, &self.history, &teams, |t| palette.team(t))?;
+        }
+        if let Some(ref sandbox) = self.sandbox {
+            if self.remote.is_none() && self.review.is_none() {
+                sandbox.draw(ctx, &self.font, top + 10.0)?;
+            }
+        }
+        if self.debug {
+            let lines = self.debug_lines(ctx, view);
+            ui::debug::draw(ctx, &self.font, &lines)?;
+        }
+        if let Some(ref outcome) = self.outcome {
+            let palette = &self.palette;
+            ui::game_over::draw(ctx, &self.font, outcome, self.team, |t| palette.team(t))?;
+        }
+        if let Some(ref screen) = self.settings_screen {
+            screen.draw(ctx, &self.font, &self.settings, &self.keymap)?;
+        }
+
+        Ok(())
+    }
+
+    fn event(&mut self, event: &GameEvent) {
+        self.sounds.event(event, self.team);
+        let (game, us) = (&self.game, self.team);
+        self.toasts.event(event, us, |x, y| game.is_visible_to(us, x, y));
+        self.kill_feed.event(event, us);
+        match *event {
+            GameEvent::Moved { team, mv, units } => {
+                let (Position(x, y), dir) = mv;
+                let Position(tx, ty) = mv.0 + dir;
+                if self.game.is_visible_to(self.team, x, y) ||
+                   self.game.is_visible_to(self.team, tx, ty) {
+                    let duration = self.settings.tick_interval();
+                    self.animations.push_move(team, mv, units, duration);
+                }
+            }
+            GameEvent::Captured { pos, from: Some(from), .. } |
+            GameEvent::CityCaptured { pos, from: Some(from), .. } if from == self.team => {
+                self.last_alert = Some(pos);
+            }
+            GameEvent::KingCaptured { team, pos, victim } => {
+                let land = self.visible_land(victim);
+                self.animations.push_king_capture(team, pos, land);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl event::EventHandler for MainState {
+    fn update(&mut self, ctx: &mut Context, dt: Duration) -> GameResult<()> {
+        if self.fullscreen_changed {
+            self.fullscreen_changed = false;
+            graphics::set_fullscreen(ctx, self.fullscreen)?;
+            let (width, height) = graphics::get_size(ctx);
+            self.resize_event(ctx, width, height);
+        }
+        let (w, h) = (ctx.conf.window_width as f32, ctx.conf.window_height as f32);
+        self.camera.update(dt, w, h);
+        if let Some(input) = self.gamepad.update(dt) {
+            self.gamepad_input(input);
+        }
+        let mood = if self.menu.is_some() || self.in_lobby() {
+            Mood::Menu
+        } else {
+            Mood::Game
+        };
+        self.music.update(dt, mood);
+        if self.remote.is_some() {
+            self.update_remote();
+            return Ok(());
+        }
+        if self.review.is_some() || self.menu.is_some() || self.settings_screen.is_some() {
+            return Ok(());
+        }
+        if self.game.is_over() {
+            let winner = self.game.winner();
+            self.sounds.play(if winner == Some(self.team) {
+                Sound::Victory
+            } else {
+                Sound::Defeat
+            });
+            if let Some(mission) = self.mission {
+                if let Some(unlocked) = self.campaign.record(mission, winner, self.team) {
+                    if unlocked {
+                        println!("Mission {} won, the next one is unlocked", mission + 1);
+                    }
+                    if let Err(e) = self.campaign.save(config::campaign_path()) {
+                        println!("Failed to save the campaign progress: {}", e);
+                    }
+                }
+            }
+            if let Some(ref challenge) = self.challenge {
+                let result = DailyResult {
+                    won: winner == Some(self.team),
+                    ticks: self.game.tick_number,
+                    land: self.game.scores()[self.team].land,
+                };
+                if self.daily.record(challenge.day, result) {
+                    println!("New best in the daily challenge: {}", result.describe());
+                    if let Err(e) = self.daily.save(config::daily_path()) {
+                        println!("Failed to save the daily challenge results: {}", e);
+                    }
+                }
+            }
+            if self.tutorial.is_some() {
+                self.finish_tutorial();
+            }
+            self.record_match(winner);
+            let earned = match self.tracker.take() {
+                Some(tracker) => tracker.finish(&self.game, &self.setup.opponents()),
+                None => Vec::new(),
+            };
+            for achievement in earned {
+                self.unlock(achievement);
+            }
+            self.outcome = Some(Outcome {
+                winner,
+                scores: self.game.scores(),
+                ticks: self.game.tick_number,
+                history: self.history.clone(),
+            });
+            self.start_review();
+            return Ok(());
+        }
+        if self.paused || self.tutorial.as_ref().map(|t| t.holds_game()).unwrap_or(false) {
+            return Ok(());
+        }
+        let previous_second = self.time.as_secs();
+        self.time += dt;
+        if self.print_profile && self.time.as_secs() != previous_second {
+            println!("{}", profile::report());
+        }
+        let tick_interval = self.settings.tick_interval();
+        while self.time - self.last_tick > tick_interval {
+            self.last_tick += tick_interval;
+            self.tick();
+        }
+
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let _timer = profile::start(profile::Section::Render);
+        if let Some(color) = self.config.color {
+            self.palette.set_own(self.team, color);
+        }
+        if let Some(ref menu) = self.menu {
+            menu.draw(ctx, &self.font, &self.theme)?;
+            graphics::present(ctx);
+            return Ok(());
+        }
+        let view = match self.view() {
+            Some(view) => view,
+            None => {
+                if let Some(ref remote) = self.remote {
+                    remote.lobby.draw(ctx, &self.font, &self.theme)?;
+                }
+                graphics::present(ctx);
+                return Ok(());
+            }
+        };
+        self.render(ctx, &view)?;
         graphics::present(ctx);
         Ok(())
     }
 
     fn mouse_button_down_event(&mut self, button: MouseButton, x: i32, y: i32) {
+        if self.menu.is_some() || self.outcome.is_some() || self.settings_screen.is_some() {
+            return;
+        }
+        if button == MouseButton::Middle {
+            self.camera.grab(true);
+            return;
+        }
+        if button == MouseButton::Left && self.view().is_some() {
+            if let Some(pos) = self.minimap.cell_at(x, y) {
+                self.camera.center_on(pos);
+                return;
+            }
+        }
+        let spectating = self.remote.as_ref().map(|r| r.spectating.is_some()).unwrap_or(false);
+        if self.review.is_some() || spectating {
+            return;
+        }
+        if let Some(ref mut sandbox) = self.sandbox {
+            if sandbox.editing && self.remote.is_none() {
+                let pos = self.camera.cell_at(x as f32, y as f32);
+                match button {
+                    MouseButton::Left => {
+                        if let Err(e) = self.game.edit_cell(pos, sandbox.cell()) {
+                            println!("Can't edit {}: {}", pos, e);
+                        }
+                    }
+                    MouseButton::Right => {
+                        if let Ok(cell) = self.game.board.get(pos.0, pos.1) {
+                            sandbox.pick(cell);
+                        }
+                    }
+                    _ => {}
+                }
+                return;
+            }
+        }
+        let path_click = button == MouseButton::Right ||
+                         (button == MouseButton::Left && self.shift_held);
+        if path_click && self.focus.is_some() {
+            self.queue_path(self.camera.cell_at(x as f32, y as f32));
+            return;
+        }
         if button != MouseButton::Left {
             return;
         }
-        let ix = x / (CELL_SIZE + 1.0) as i32;
-        let iy = y / (CELL_SIZE + 1.0) as i32;
-        if let Some(cell) = self.game.board.try_get(ix, iy) {
+        let clicked_move = self.view().and_then(|view| {
+            self.queued_move_at(&view, x as f32, y as f32).map(|i| (i, view.moves[i].0))
+        });
+        if let Some((i, from)) = clicked_move {
+            self.truncate_moves(i);
+            self.focus = Some(from);
+            return;
+        }
+        let Position(ix, iy) = self.camera.cell_at(x as f32, y as f32);
+        let tile = self.view().and_then(|view| view.try_get(ix, iy));
+        if let Some(Tile::Visible(cell)) = tile {
             if cell.is_controlled_by(self.team) {
                 self.focus = Some(Position(ix, iy));
+                self.dragging = true;
             }
         }
     }
 
-    fn key_down_event(&mut self, keycode: Keycode, _keymod: Mod, _repeat: bool) {
-        match keycode {
-            Keycode::Q => {
-                self.game.player_mut(self.team).moves.clear();
+    fn mouse_button_up_event(&mut self, button: MouseButton, _x: i32, _y: i32) {
+        match button {
+            MouseButton::Left => self.dragging = false,
+            MouseButton::Middle => self.camera.grab(false),
+            _ => {}
+        }
+    }
+
+    fn key_down_event(&mut self, keycode: Keycode, keymod: Mod, _repeat: bool) {
+        if keycode == Keycode::LShift || keycode == Keycode::RShift {
+            self.shift_held = true;
+        }
+        if keycode == Keycode::F3 {
+            self.debug = !self.debug;
+            return;
+        }
+        if keycode == Keycode::F12 {
+            self.screenshot();
+            return;
+        }
+        if keycode == Keycode::F1 && self.tutorial.is_some() {
+            self.finish_tutorial();
+            return;
+        }
+        if keycode == Keycode::F11 {
+            self.fullscreen = !self.fullscreen;
+            self.fullscreen_changed = true;
+            return;
+        }
+        if let Some(choice) = self.menu.as_mut().map(|menu| menu.key_down(keycode)) {
+            match choice {
+                Some(MenuChoice::NewGame(players)) => {
+                    self.setup.num_players = players;
+                    self.setup.scenario = None;
+                    self.mission = None;
+                    self.team = self.team.min(players - 1);
+                    self.rematch();
+                }
+                Some(MenuChoice::Campaign(mission)) => {
+                    let scenario = campaign::MISSIONS[mission].scenario();
+                    self.setup.num_players = scenario.num_players;
+                    self.setup.scenario = Some(scenario);
+                    self.mission = Some(mission);
+                    self.team = 0;
+                    self.rematch();
+                }
+                Some(MenuChoice::Daily) => self.start_daily(Challenge::today()),
+                Some(MenuChoice::Tutorial) => self.start_tutorial(),
+                Some(MenuChoice::History) => {
+                    let games = matches::load(config::matches_path()).unwrap_or_else(|e| {
+                        println!("Failed to load the earlier games: {}", e);
+                        Vec::new()
+                    });
+                    let name = self.config.name.clone();
+                    if let Some(ref mut menu) = self.menu {
+                        menu.show_history(games, name);
+                    }
+                }
+                Some(MenuChoice::Achievements) => {
+                    let unlocked = self.achievements.clone();
+                    if let Some(ref mut menu) = self.menu {
+                        menu.show_achievements(unlocked);
+                    }
+                }
+                Some(MenuChoice::Replays) => {
+                    let replays = replay::saved_replays().unwrap_or_else(|e| {
+                        println!("Failed to list the replays: {}", e);
+                        Vec::new()
+                    });
+                    if let Some(ref mut menu) = self.menu {
+                        menu.show_replays(replays);
+                    }
+                }
+                Some(MenuChoice::WatchReplay(path)) => {
+                    match Replay::load(&path) {
+                        Ok(replay) => self.open_replay(replay),
+                        Err(e) => println!("Failed to load {}: {}", path.display(), e),
+                    }
+                }
+                Some(MenuChoice::Review) => self.menu = None,
+                None => {}
+            }
+            return;
+        }
+        if let Some(mut screen) = self.settings_screen.take() {
+            if screen.key_down(keycode, &mut self.settings, &mut self.keymap) {
+                self.settings_screen = Some(screen);
+            } else {
+                self.save_config();
             }
-            Keycode::Up | Keycode::Down | Keycode::Left | Keycode::Right | Keycode::W |
-            Keycode::A | Keycode::S | Keycode::D => {
-                let dir = direction_from_keycode(keycode);
-                let (w, h) = self.dimens();
-                if let Some(ref mut pos) = self.focus {
-                    let Position(x, y) = *pos;
-                    let (dx, dy) = dir.to_xy();
-                    let nx = x + dx;
-                    let ny = y + dy;
-                    if nx >= 0 && nx < w && ny >= 0 && ny < h {
-                        self.game.player_mut(self.team).moves.push_back((*pos, dir));
-                        *pos = Position(nx, ny);
+            self.theme = self.settings.theme();
+            self.palette = self.settings.palette();
+            self.sounds.set_volume(self.settings.volume);
+            self.music.set_volume(self.settings.music_volume);
+            return;
+        }
+        if self.outcome.is_some() {
+            match keycode {
+                Keycode::R => self.rematch(),
+                Keycode::S => self.replay_map(false),
+                Keycode::X => self.replay_map(true),
+                Keycode::M => {
+                    self.outcome = None;
+                    let can_review = self.review.is_some();
+                    self.menu = Some(self.menu(can_review));
+                }
+                Keycode::Return | Keycode::Escape => self.outcome = None,
+                _ => {}
+            }
+            return;
+        }
+        let in_lobby = self.in_lobby();
+        if let Some(ref mut remote) = self.remote {
+            if in_lobby {
+                remote.lobby.key_down(keycode, &mut remote.client);
+                return;
+            }
+            if remote.chat.key_down(keycode, keymod, &mut remote.client) {
+                return;
+            }
+        }
+        let action = self.keymap.action(keycode);
+        if let Some(dir) = action.and_then(|a| a.pan()) {
+            self.camera.hold(dir, true);
+            return;
+        }
+        if let Some(zoom) = action.filter(|&a| a == Action::ZoomIn || a == Action::ZoomOut) {
+            self.perform(zoom);
+            return;
+        }
+        if let Some(ref mut remote) = self.remote {
+            if let Some(lobby) = remote.spectating {
+                spectator_key_down(&mut remote.client, lobby, keycode);
+                return;
+            }
+        }
+        if let Some(ref mut sandbox) = self.sandbox {
+            if self.remote.is_none() && self.review.is_none() &&
+               sandbox.key_down(keycode, self.game.num_players) {
+                return;
+            }
+        }
+        if keycode == Keycode::Escape {
+            self.settings_screen = Some(SettingsScreen::new());
+            return;
+        }
+        if let Some(ref mut tutorial) = self.tutorial {
+            if self.review.is_none() && tutorial.key_down(keycode) {
+                return;
+            }
+        }
+        if self.review.is_some() {
+            match keycode {
+                Keycode::O => {
+                    if let Some(ref mut review) = self.review {
+                        review.show_queues = !review.show_queues;
                     }
                 }
+                Keycode::Comma | Keycode::Left => self.step_review(-1),
+                Keycode::Period | Keycode::Right => self.step_review(1),
+                Keycode::M => {
+                    self.menu = Some(self.menu(true))
+                }
+                Keycode::V => self.switch_perspective(),
+                Keycode::H => self.toasts.toggle_history(),
+                _ => {}
+            }
+            return;
+        }
+        let ctrl = keymod.bits() & CTRL_BITS != 0;
+        match action {
+            Some(action) if ctrl || !self.queueing => {
+                match action.direction() {
+                    Some(dir) => self.move_focus(dir),
+                    None => self.perform(action),
+                }
+            }
+            Some(action) => self.perform(action),
+            None => {
+                match keycode {
+                    Keycode::V if self.remote.is_none() => self.switch_perspective(),
+                    Keycode::H => self.toasts.toggle_history(),
+                    _ => {}
+                }
             }
-            _ => {}
         }
     }
+
+    fn controller_button_down_event(&mut self, button: Button, _instance_id: i32) {
+        if let Some(input) = self.gamepad.button_down(button) {
+            self.gamepad_input(input);
+        }
+    }
+
+    fn controller_axis_event(&mut self, axis: Axis, value: i16, _instance_id: i32) {
+        if let Some(input) = self.gamepad.axis(axis, value) {
+            self.gamepad_input(input);
+        }
+    }
+    fn resize_event(&mut self, ctx: &mut Context, width: u32, height: u32) {
+        ctx.conf.window_width = width;
+        ctx.conf.window_height = height;
+        let (w, h) = (width as f32, height as f32);
+        if let Err(e) = graphics::set_screen_coordinates(ctx, 0.0, w, 0.0, h) {
+            println!("Failed to resize: {}", e);
+        }
+        self.camera.resize(w, h);
+    }
+
+    fn key_up_event(&mut self, keycode: Keycode, _keymod: Mod, _repeat: bool) {
+        if let Some(dir) = self.keymap.action(keycode).and_then(|a| a.pan()) {
+            self.camera.hold(dir, false);
+        }
+        if keycode == Keycode::LShift || keycode == Keycode::RShift {
+            self.shift_held = false;
+        }
+    }
+
+    fn mouse_motion_event(&mut self, _state: MouseState, x: i32, y: i32, _xrel: i32, _yrel: i32) {
+        self.camera.mouse_moved(x, y);
+        if self.dragging {
+            self.drag_to(self.camera.cell_at(x as f32, y as f32));
+        }
+    }
+
+    fn mouse_wheel_event(&mut self, _x: i32, y: i32) {
+        self.camera.wheel(y);
+    }
 }
 
-pub fn main() {
+fn connect<A: std::net::ToSocketAddrs>(addr: A, name: Option<String>, key: &str)
+    -> Result<Client, String> {
+    let mut client = Client::connect(addr).map_err(|e| format!("Failed to connect: {}", e))?;
+    if let Some(name) = name {
+        if let Err(e) = client.set_name(&name) {
+            println!("Failed to set name: {}", e);
+        }
+    }
+    if let Err(e) = client.identify(key) {
+        println!("Failed to identify: {}", e);
+    }
+    Ok(client)
+}
+
+/// Open the window, and play or watch what the flags say.
+fn run_client(args: &[String]) -> Result<(), String> {
+    let mut config = Config::load().unwrap_or_else(|e| {
+        println!("Failed to load the config from {}: {}", config::path().display(), e);
+        Config::default()
+    });
+    let key = match config.key.clone() {
+        Some(key) => key,
+        None => {
+            let key = format!("{:016x}{:016x}", rand::random::<u64>(), rand::random::<u64>());
+            config.key = Some(key.clone());
+            if let Err(e) = config.save() {
+                println!("Failed to save the config: {}", e);
+            }
+            key
+        }
+    };
+    let setup = GameSetup::from_args(args, &config.game)?;
     let mut c = conf::Conf::new();
-    c.window_height = 1600;
-    c.window_width = 1600;
-    let ctx = &mut Context::load_from_conf("GeNeRaLs", "martin", c).unwrap();
-    let state = &mut MainState::new(ctx).unwrap();
-    event::run(ctx, state).unwrap();
+    c.window_width = parse_flag(args, "--width", config.window.width)?;
+    c.window_height = parse_flag(args, "--height", config.window.height)?;
+    c.resizable = true;
+    let ctx = &mut Context::load_from_conf("GeNeRaLs", "martin", c).map_err(|e| e.to_string())?;
+    let flag_value = |flag: &str| {
+        args.iter()
+            .position(|a| a == flag)
+            .map(|i| args.get(i + 1).filter(|v| !v.starts_with("--")).cloned())
+    };
+    let name = flag_value("--name").and_then(|n| n).or_else(|| config.name.clone());
+    // The very first game, started without any flags, is the tutorial.
+    let tutorial = args.iter().any(|a| a == "--tutorial") ||
+                   (args.len() == 1 && !config.tutorial_done);
+    let state = if let Some(path) = flag_value("--replay") {
+        let path = path.ok_or("--replay needs a file")?;
+        let replay = Replay::load(path).map_err(|e| format!("Failed to load replay: {}", e))?;
+        MainState::from_replay(ctx, replay, config)
+    } else if let Some(addr) = flag_value("--server") {
+        // Host a game, and play in it ourselves.
+        let addr = addr.unwrap_or_else(|| format!("0.0.0.0:{}", net::DEFAULT_PORT));
+        let listener = TcpListener::bind(&addr)
+            .map_err(|e| format!("Failed to start server: {}", e))?;
+        let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+        thread::spawn(move || {
+            if let Err(e) = Server::new(listener, ServerConfig::default()).run() {
+                println!("Server stopped: {}", e);
+            }
+        });
+        println!("Hosting on {}", addr);
+        let client = connect(("127.0.0.1", port), name, &key)?;
+        MainState::from_client(ctx, client, config)
+    } else if let Some(addr) = flag_value("--connect") {
+        let addr = addr.ok_or("--connect needs host:port")?;
+        let client = connect(addr.as_str(), name, &key)?;
+        MainState::from_client(ctx, client, config)
+    } else {
+        MainState::new(ctx, &setup, config).map(|mut state| {
+            if tutorial {
+                state.start_tutorial();
+            }
+            state
+        })
+    };
+    let state = &mut state.map_err(|e| e.to_string())?;
+    state.print_profile = args.iter().any(|a| a == "--profile");
+    event::run(ctx, state).map_err(|e| e.to_string())
+}
+
+pub fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    // Logged from every mode below, since they all play their games on this thread.
+    let log = parse_flag(&args, "--log-events", String::new()).unwrap_or_default();
+    if !log.is_empty() {
+        if let Err(e) = eventlog::enable(&log) {
+            eprintln!("Failed to create {}: {}", log, e);
+            std::process::exit(1);
+        }
+    }
+    if let Err(e) = sim::configure_search(&args) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    if args.iter().any(|a| a == "--self-test") {
+        std::process::exit(if selftest::run() { 0 } else { 1 });
+    }
+    if args.iter().any(|a| a == "--simulate") {
+        if let Err(e) = sim::run(&args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.iter().any(|a| a == "--headless") {
+        if let Err(e) = sim::run_headless(&args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.iter().any(|a| a == "--export-data") {
+        if let Err(e) = analysis::run(&args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.iter().any(|a| a == "--export") {
+        if let Err(e) = export::run(&args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.iter().any(|a| a == "--tournament") {
+        if let Err(e) = tournament::run(&args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Err(e) = run_client(&args) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
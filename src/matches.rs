@@ -0,0 +1,95 @@
+//! The history of finished local games. Each game is appended to the file as one line of JSON,
+//! so that older entries never have to be rewritten.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json;
+
+use generals::GameMode;
+
+/// How a game ended for us.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchOutcome {
+    Won,
+    Lost,
+    /// Nobody won, like a blitz game that ended even.
+    Draw,
+}
+
+impl MatchOutcome {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            MatchOutcome::Won => "won",
+            MatchOutcome::Lost => "lost",
+            MatchOutcome::Draw => "draw",
+        }
+    }
+}
+
+/// The summary of a finished game.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchSummary {
+    /// When the game ended, in seconds since the Unix epoch.
+    pub finished: u64,
+    pub mode: GameMode,
+    /// The bots we played against, in the order of their teams.
+    pub opponents: Vec<String>,
+    pub outcome: MatchOutcome,
+    pub ticks: usize,
+    /// The seed the map was generated from. Scenarios have none.
+    pub seed: Option<u32>,
+}
+
+impl MatchSummary {
+    /// A summary of a game that just ended.
+    pub fn new(mode: GameMode,
+               opponents: Vec<String>,
+               outcome: MatchOutcome,
+               ticks: usize,
+               seed: Option<u32>)
+        -> Self {
+        let finished = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        MatchSummary {
+            finished,
+            mode,
+            opponents,
+            outcome,
+            ticks,
+            seed,
+        }
+    }
+}
+
+/// Add `summary` to the end of the history at `path`.
+pub fn append<P: AsRef<Path>>(path: P, summary: &MatchSummary) -> io::Result<()> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let line = serde_json::to_string(summary).map_err(io::Error::from)?;
+    writeln!(file, "{}", line)
+}
+
+/// Every game in the history at `path`, oldest first. No file means no games were played yet.
+/// Lines that can't be read, like one cut short by a crash, are skipped.
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Vec<MatchSummary>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut matches = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Ok(summary) = serde_json::from_str(&line?) {
+            matches.push(summary);
+        }
+    }
+    Ok(matches)
+}
@@ -0,0 +1,129 @@
+//! Background music. Tracks are loaded from the `music` folder of the ggez resources directory,
+//! named `menu-1.ogg`, `menu-2.ogg`, ... for the menus and `game-1.ogg`, ... for games. Each
+//! list is played in order and loops, and switching between them crossfades.
+
+use std::time::Duration;
+
+use ggez::Context;
+use ggez::audio::Source;
+
+const DIR: &str = "/music";
+const CROSSFADE: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mood {
+    Menu,
+    Game,
+}
+
+/// The names the tracks of the playlists start with.
+pub const PLAYLISTS: [&str; 2] = ["menu", "game"];
+
+/// Where track `n` of the playlist `prefix` is in the resources directory, counting from 1.
+pub fn track_path(prefix: &str, n: usize) -> String {
+    format!("{}/{}-{}.ogg", DIR, prefix, n)
+}
+
+fn load_playlist(ctx: &mut Context, prefix: &str) -> Vec<Source> {
+    (1..)
+        .map(|n| Source::new(ctx, track_path(prefix, n)))
+        .take_while(|source| source.is_ok())
+        .map(|source| source.unwrap())
+        .collect()
+}
+
+pub struct Music {
+    menu: Vec<Source>,
+    game: Vec<Source>,
+    mood: Mood,
+    /// The track that is playing, by mood and index.
+    current: Option<(Mood, usize)>,
+    /// The track being faded out.
+    previous: Option<(Mood, usize)>,
+    /// How far into the crossfade we are, from 0 to 1.
+    fade: f32,
+    volume: f32,
+}
+
+impl Music {
+    pub fn load(ctx: &mut Context, volume: f32) -> Self {
+        let menu = load_playlist(ctx, "menu");
+        let game = load_playlist(ctx, "game");
+        if menu.is_empty() && game.is_empty() {
+            println!("No music found in {}", DIR);
+        }
+        Music {
+            menu,
+            game,
+            mood: Mood::Menu,
+            current: None,
+            previous: None,
+            fade: 1.0,
+            volume,
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+    }
+
+    fn track(&mut self, (mood, i): (Mood, usize)) -> &mut Source {
+        match mood {
+            Mood::Menu => &mut self.menu[i],
+            Mood::Game => &mut self.game[i],
+        }
+    }
+
+    fn playlist_len(&self, mood: Mood) -> usize {
+        match mood {
+            Mood::Menu => self.menu.len(),
+            Mood::Game => self.game.len(),
+        }
+    }
+
+    /// Start track `i` of `mood`, fading out the one that is playing.
+    fn start(&mut self, mood: Mood, i: usize) {
+        if let Some(previous) = self.previous.take() {
+            self.track(previous).stop();
+        }
+        self.previous = self.current.take();
+        self.fade = if self.previous.is_some() { 0.0 } else { 1.0 };
+        if i < self.playlist_len(mood) {
+            self.current = Some((mood, i));
+            let volume = self.volume * self.fade;
+            let track = self.track((mood, i));
+            track.set_volume(volume);
+            if let Err(e) = track.play() {
+                println!("Failed to play music: {}", e);
+            }
+        }
+    }
+
+    /// Play music for `mood`, moving on to the next track when one ends.
+    pub fn update(&mut self, dt: Duration, mood: Mood) {
+        if mood != self.mood || (self.current.is_none() && self.playlist_len(mood) > 0) {
+            self.mood = mood;
+            self.start(mood, 0);
+        } else if let Some((mood, i)) = self.current {
+            if !self.track((mood, i)).playing() {
+                let next = (i + 1) % self.playlist_len(mood);
+                self.start(mood, next);
+            }
+        }
+
+        let seconds = dt.as_secs() as f32 + dt.subsec_nanos() as f32 * 1e-9;
+        self.fade = (self.fade + seconds / CROSSFADE).min(1.0);
+        let (fade, volume) = (self.fade, self.volume);
+        if let Some(current) = self.current {
+            self.track(current).set_volume(volume * fade);
+        }
+        if let Some(previous) = self.previous {
+            if fade >= 1.0 {
+                self.track(previous).stop();
+                self.previous = None;
+            } else {
+                self.track(previous).set_volume(volume * (1.0 - fade));
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::io;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use generals::Team;
+use super::{split, ClientMessage, LobbyId, MessageWriter, ServerMessage, CAPABILITIES,
+            CAP_IDENTITY, PROTOCOL_VERSION};
+
+/// How long to wait for the server to answer our `Hello`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What we need to take our team back after losing the connection.
+struct Seat {
+    lobby: LobbyId,
+    team: Team,
+    token: String,
+}
+
+/// A connection to a game server.
+pub struct Client {
+    addr: SocketAddr,
+    writer: MessageWriter,
+    rx: mpsc::Receiver<ServerMessage>,
+    connected: bool,
+    /// The optional features both we and the server support.
+    capabilities: Vec<String>,
+    /// `Some` while we are playing a game.
+    seat: Option<Seat>,
+    /// The name we asked for, which is sent again after reconnecting.
+    name: Option<String>,
+    /// The key we identified with, which is sent again after reconnecting.
+    key: Option<String>,
+}
+
+struct Connection {
+    writer: MessageWriter,
+    rx: mpsc::Receiver<ServerMessage>,
+    capabilities: Vec<String>,
+}
+
+/// Connect, and agree with the server on which protocol to speak.
+fn open(addr: SocketAddr) -> io::Result<Connection> {
+    let (mut writer, mut reader) = split(TcpStream::connect(addr)?)?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        while let Ok(msg) = reader.recv() {
+            if tx.send(msg).is_err() {
+                break;
+            }
+        }
+    });
+    writer.send(&ClientMessage::Hello {
+        version: PROTOCOL_VERSION,
+        capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+    })?;
+    let capabilities = match rx.recv_timeout(HANDSHAKE_TIMEOUT) {
+        Ok(ServerMessage::Hello { capabilities, .. }) => capabilities,
+        Ok(ServerMessage::Incompatible { reason, .. }) => {
+            return Err(io::Error::other(reason));
+        }
+        Ok(msg) => {
+            let error = format!("Expected Hello from the server, got {:?}", msg);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+        }
+        Err(_) => {
+            let error = "The server did not answer our Hello";
+            return Err(io::Error::new(io::ErrorKind::TimedOut, error));
+        }
+    };
+    Ok(Connection {
+        writer,
+        rx,
+        capabilities,
+    })
+}
+
+impl Client {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Client> {
+        let addr = addr.to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address"))?;
+        let connection = open(addr)?;
+        Ok(Client {
+            addr,
+            writer: connection.writer,
+            rx: connection.rx,
+            connected: true,
+            capabilities: connection.capabilities,
+            seat: None,
+            name: None,
+            key: None,
+        })
+    }
+
+    pub fn send(&mut self, msg: &ClientMessage) -> io::Result<()> {
+        let result = self.writer.send(msg);
+        if result.is_err() {
+            self.connected = false;
+        }
+        result
+    }
+
+    /// Set the name others see us as, and the one our rating is kept under.
+    pub fn set_name(&mut self, name: &str) -> io::Result<()> {
+        self.name = Some(name.to_string());
+        self.send(&ClientMessage::SetName(name.to_string()))
+    }
+
+    /// Prove who we are, so that ranked games are rated under our name. Does nothing if the
+    /// server has no ranked games to rate.
+    pub fn identify(&mut self, key: &str) -> io::Result<()> {
+        self.key = Some(key.to_string());
+        if !self.has(CAP_IDENTITY) {
+            return Ok(());
+        }
+        self.send(&ClientMessage::Identify(key.to_string()))
+    }
+
+    /// All messages that arrived since the last call.
+    pub fn poll(&mut self) -> Vec<ServerMessage> {
+        let mut messages = Vec::new();
+        loop {
+            match self.rx.try_recv() {
+                Ok(msg) => {
+                    match msg {
+                        ServerMessage::Welcome { lobby, team, ref token, .. } => {
+                            self.seat = Some(Seat {
+                                lobby,
+                                team,
+                                token: token.clone(),
+                            });
+                        }
+                        ServerMessage::GameOver { .. } |
+                        ServerMessage::LeftLobby => self.seat = None,
+                        _ => {}
+                    }
+                    messages.push(msg);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.connected = false;
+                    break;
+                }
+            }
+        }
+        messages
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Whether both we and the server support `capability`, one of `net::CAPABILITIES`.
+    pub fn has(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+
+    /// Open a new connection to the server, and take back our team if we were in a game. The
+    /// server answers with `Welcome` and the full state of the game.
+    pub fn reconnect(&mut self) -> io::Result<()> {
+        let connection = open(self.addr)?;
+        self.writer = connection.writer;
+        self.rx = connection.rx;
+        self.capabilities = connection.capabilities;
+        self.connected = true;
+        if let Some(name) = self.name.clone() {
+            self.send(&ClientMessage::SetName(name))?;
+        }
+        if let Some(key) = self.key.clone() {
+            if self.has(CAP_IDENTITY) {
+                self.send(&ClientMessage::Identify(key))?;
+            }
+        }
+        let rejoin = self.seat.as_ref().map(|seat| {
+            ClientMessage::Rejoin {
+                lobby: seat.lobby,
+                team: seat.team,
+                token: seat.token.clone(),
+            }
+        });
+        if let Some(rejoin) = rejoin {
+            self.send(&rejoin)?;
+        }
+        Ok(())
+    }
+}
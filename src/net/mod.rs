@@ -0,0 +1,228 @@
+//! Networked multiplayer: an authoritative server that owns the `GameState`, and clients that
+//! send moves and get fogged views back.
+//!
+//! Messages are JSON. Over raw TCP they are separated by newlines, and over WebSockets they are
+//! sent as one text message each. The server accepts both on the same port.
+//!
+//! The first thing a client sends is `ClientMessage::Hello`, and the server answers with
+//! `ServerMessage::Hello` if it speaks the same `PROTOCOL_VERSION`, or `Incompatible` if it does
+//! not. Both sides list the optional features they support, and a feature is only used if both
+//! sides support it. The `Hello` and `Incompatible` messages must never change, so that
+//! mismatched builds can always tell each other so.
+
+use generals::*;
+use rating::PlayerRating;
+use replay::{Replay, ReplayInfo};
+
+#[cfg(feature = "net")]
+mod client;
+#[cfg(feature = "net")]
+mod server;
+#[cfg(feature = "net")]
+mod transport;
+#[cfg(feature = "net")]
+pub use self::client::Client;
+#[cfg(feature = "net")]
+pub use self::server::{Server, ServerConfig, MAX_PLAYERS};
+#[cfg(feature = "net")]
+pub use self::transport::{accept, split, split_websocket, MessageReader, MessageWriter};
+
+pub const DEFAULT_PORT: u16 = 4567;
+
+/// Bump this on any change to the messages that older builds would not understand.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// The client understands `ServerMessage::Delta`. Without it, `State` is sent every tick.
+pub const CAP_DELTAS: &str = "deltas";
+/// The client wants `ServerMessage::Chat`.
+pub const CAP_CHAT: &str = "chat";
+/// The client can show a game it is not playing in.
+pub const CAP_SPECTATE: &str = "spectate";
+/// The server understands `ClientMessage::TruncateMoves`.
+pub const CAP_TRUNCATE: &str = "truncate";
+/// The server understands `ClientMessage::SetBots`.
+pub const CAP_BOTS: &str = "bots";
+/// The client answers `ServerMessage::Ping`, and wants `ServerMessage::Players` during games.
+pub const CAP_PLAYERS: &str = "players";
+/// The server understands `ClientMessage::Identify`. Ranked games need it.
+pub const CAP_IDENTITY: &str = "identity";
+/// Every optional feature this build supports. Capabilities are strings, so that builds can
+/// list ones the other side has never heard of.
+pub const CAPABILITIES: [&str; 7] =
+    [CAP_DELTAS, CAP_CHAT, CAP_SPECTATE, CAP_TRUNCATE, CAP_BOTS, CAP_PLAYERS, CAP_IDENTITY];
+
+pub type ClientId = usize;
+pub type LobbyId = usize;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LobbyMember {
+    pub name: String,
+    pub ready: bool,
+}
+
+/// A game that is being set up, or is running.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LobbyInfo {
+    pub id: LobbyId,
+    pub name: String,
+    pub num_players: usize,
+    pub members: Vec<LobbyMember>,
+    /// The bots taking the seats the members don't, by name.
+    #[serde(default)]
+    pub bots: Vec<String>,
+    pub spectators: usize,
+    pub started: bool,
+    /// Whether the game counts towards the players' ratings.
+    pub ranked: bool,
+    #[serde(default)]
+    pub mode: GameMode,
+}
+
+/// Whether a player in a running game is still in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Presence {
+    Alive,
+    Dead,
+    /// Lost the connection, and has not rejoined yet.
+    Disconnected,
+}
+
+/// A player in a running game, as listed by `ServerMessage::Players`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerInfo {
+    pub team: Team,
+    pub name: String,
+    pub bot: bool,
+    pub presence: Presence,
+    /// How long the last ping took to get answered, in milliseconds. `None` for bots, and for
+    /// players who have not answered one yet.
+    pub ping_ms: Option<u32>,
+}
+
+/// Who a chat message goes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChatChannel {
+    /// Everyone in the lobby.
+    All,
+    /// Only players on the same team.
+    Team,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// The first message from a client.
+    Hello {
+        version: u32,
+        capabilities: Vec<String>,
+    },
+    SetName(String),
+    ListLobbies,
+    /// Create a new lobby and join it.
+    CreateLobby {
+        name: String,
+        num_players: usize,
+        map: MapSettings,
+        /// Only 1v1 games can be ranked.
+        ranked: bool,
+        #[serde(default)]
+        mode: GameMode,
+    },
+    JoinLobby(LobbyId),
+    LeaveLobby,
+    /// The game starts when the lobby is full, and everyone is ready.
+    Ready(bool),
+    /// Fill seats of our lobby with these bots, by their names in `bot::NAMES`. Only sent if
+    /// the server has `CAP_BOTS`.
+    SetBots(Vec<String>),
+    /// Append a move to our queue.
+    Move(Move),
+    /// Clear our move queue.
+    ClearMoves,
+    /// Keep only the first moves of our queue. Only sent if the server has `CAP_TRUNCATE`.
+    TruncateMoves(usize),
+    /// Watch a running game, either the whole board, or as `team` sees it. Send again to
+    /// switch.
+    Spectate {
+        lobby: LobbyId,
+        team: Option<Team>,
+    },
+    /// Ask for the whole game, after missing an update.
+    Resync,
+    /// Ask for the highest rated players.
+    Leaderboard,
+    /// Ask for the replays of games played on the server.
+    ListReplays,
+    /// Ask for a replay, by its `ReplayInfo::file`.
+    DownloadReplay(String),
+    /// Say something to the others in our lobby.
+    Chat { channel: ChatChannel, text: String },
+    /// Take back control of `team` after losing the connection, using the token from `Welcome`.
+    Rejoin {
+        lobby: LobbyId,
+        team: Team,
+        token: String,
+    },
+    /// The answer to `ServerMessage::Ping`, with its number.
+    Pong(u64),
+    /// A secret only we know, which proves that our name on the ladder is ours. The first
+    /// ranked game under a name ties it to the key. Only sent if the server has `CAP_IDENTITY`.
+    Identify(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ServerMessage {
+    /// The answer to `ClientMessage::Hello`, with the capabilities both sides support.
+    Hello {
+        version: u32,
+        capabilities: Vec<String>,
+    },
+    /// The client speaks a protocol version the server does not. The server hangs up after this.
+    Incompatible { version: u32, reason: String },
+    /// All lobbies on the server. Sent on request, and whenever they change while we are not in
+    /// one.
+    Lobbies(Vec<LobbyInfo>),
+    /// The lobby we are in changed.
+    Lobby(LobbyInfo),
+    /// We are no longer in a lobby.
+    LeftLobby,
+    /// The last request could not be done.
+    Error(String),
+    /// The game is starting, and you are playing `team`. Also sent after rejoining. `token`
+    /// lets us rejoin the game if we lose the connection.
+    Welcome {
+        lobby: LobbyId,
+        team: Team,
+        num_players: usize,
+        token: String,
+    },
+    /// We are watching the game in `lobby`. `State` shows the whole board if `team` is `None`.
+    Spectating {
+        lobby: LobbyId,
+        team: Option<Team>,
+        num_players: usize,
+    },
+    /// The game as seen by you. Sent at the start, after rejoining, on request, and every now
+    /// and then during the game.
+    State(PlayerView),
+    /// What changed in the game as seen by you during the last tick. Sent after every tick
+    /// that does not send `State`.
+    Delta(ViewDelta),
+    GameOver { winner: Option<Team> },
+    /// The highest rated players on the server, best first.
+    Leaderboard(Vec<(String, PlayerRating)>),
+    /// The replays of games played on the server, most recent first.
+    Replays(Vec<ReplayInfo>),
+    /// A replay we asked for.
+    ReplayData { file: String, replay: Replay },
+    /// Someone in our lobby said something.
+    Chat {
+        from: String,
+        channel: ChatChannel,
+        text: String,
+    },
+    /// Answer with `ClientMessage::Pong` and the same number. Sent every second, to measure
+    /// the round trip.
+    Ping(u64),
+    /// Everyone playing the game we are in or watching. Sent every second during the game.
+    Players(Vec<PlayerInfo>),
+}
@@ -0,0 +1,1276 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, BufRead};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand;
+
+use bot::{self, BotPlayer};
+use eventlog;
+use frontend::{self, Command};
+use profile;
+use generals::*;
+use rating::Ladder;
+use replay::{Archive, Replay};
+use super::{accept, ChatChannel, ClientId, ClientMessage, LobbyId, LobbyInfo, LobbyMember,
+            MessageWriter, PlayerInfo, Presence, ServerMessage, CAPABILITIES, CAP_CHAT,
+            CAP_DELTAS, CAP_PLAYERS, CAP_SPECTATE, PROTOCOL_VERSION};
+
+/// The most players a single game can have.
+pub const MAX_PLAYERS: usize = 8;
+/// Every this many ticks, everyone gets the whole game instead of what changed, in case they
+/// missed something.
+const FULL_STATE_INTERVAL: usize = 20;
+/// How many players `Leaderboard` lists.
+const LEADERBOARD_SIZE: usize = 20;
+/// The largest board a lobby can ask for, in cells across, so that one lobby can't tie up the
+/// server generating and simulating a huge map.
+const MAX_BOARD_SIZE: usize = 128;
+/// Longer chat messages are cut off.
+const MAX_CHAT_LENGTH: usize = 200;
+/// How many chat messages a client can send in a burst...
+const CHAT_BURST: f32 = 5.0;
+/// ...and how many per second after that.
+const CHAT_RATE: f32 = 1.0;
+/// How often clients get pinged, and players in games the list of players.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub tick_interval: Duration,
+    /// How long a player who lost their connection can take to rejoin before they forfeit.
+    pub reconnect_grace: Duration,
+    /// Where to archive the replays of finished games. `None` does not record them.
+    pub replay_dir: Option<PathBuf>,
+    /// Where to keep the ratings of players. `None` keeps them in memory only.
+    pub ratings_path: Option<PathBuf>,
+    /// Read admin commands from stdin. See `Server::console`.
+    pub console: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            tick_interval: Duration::from_millis(500),
+            reconnect_grace: Duration::from_secs(60),
+            replay_dir: None,
+            ratings_path: None,
+            console: false,
+        }
+    }
+}
+
+/// What the connection threads tell the server.
+enum Event {
+    Connected(ClientId, MessageWriter),
+    Message(ClientId, ClientMessage),
+    Disconnected(ClientId),
+    /// A line typed into the admin console.
+    Console(String),
+}
+
+/// A connected client.
+struct Session {
+    name: String,
+    /// `None` if sending to the client failed.
+    writer: Option<MessageWriter>,
+    /// The capabilities both we and the client support, or `None` until it has said hello.
+    capabilities: Option<Vec<String>>,
+    lobby: Option<LobbyId>,
+    ready: bool,
+    /// How many chat messages the client may send right now.
+    chat_allowance: f32,
+    last_chat: Instant,
+    /// The number of the last `Ping`, and when it was sent.
+    ping_sent: Option<(u64, Instant)>,
+    /// How long the client took to answer its last `Ping`.
+    ping: Option<Duration>,
+    /// The key from `ClientMessage::Identify`, without which the client can't play ranked.
+    key: Option<String>,
+}
+
+/// A game in progress.
+struct Match {
+    state: GameState,
+    /// The client playing each team, if they are still here.
+    teams: Vec<Option<ClientId>>,
+    /// The secret each team rejoins with.
+    tokens: Vec<String>,
+    /// For teams whose client lost the connection, when they forfeit unless they rejoin.
+    dropped: Vec<Option<Instant>>,
+    replay: Replay,
+    /// The names of the players when the game started, for rating them.
+    names: Vec<String>,
+    /// The bots playing the teams after those of the members.
+    bots: Vec<BotPlayer>,
+    next_tick: Instant,
+}
+
+impl Match {
+    fn team_of(&self, id: ClientId) -> Option<Team> {
+        self.teams.iter().position(|&t| t == Some(id))
+    }
+}
+
+struct Lobby {
+    name: String,
+    num_players: usize,
+    map: MapSettings,
+    ranked: bool,
+    mode: GameMode,
+    members: Vec<ClientId>,
+    /// The bots that take the seats after the members, by name.
+    bots: Vec<String>,
+    /// Clients watching the game, and the team whose view they get. `None` is the whole board.
+    spectators: Vec<(ClientId, Option<Team>)>,
+    game: Option<Match>,
+}
+
+/// Hosts lobbies and the games started from them. All state is owned by the thread calling
+/// `run`; every connection gets a thread of its own that forwards its messages.
+pub struct Server {
+    listener: TcpListener,
+    config: ServerConfig,
+    sessions: HashMap<ClientId, Session>,
+    lobbies: BTreeMap<LobbyId, Lobby>,
+    next_lobby_id: LobbyId,
+    ladder: Ladder,
+    archive: Option<Archive>,
+    next_heartbeat: Instant,
+    next_ping: u64,
+}
+
+fn send(sessions: &mut HashMap<ClientId, Session>, id: ClientId, msg: &ServerMessage) {
+    if let Some(session) = sessions.get_mut(&id) {
+        let failed = match session.writer {
+            Some(ref mut w) => w.send(msg).is_err(),
+            None => false,
+        };
+        if failed {
+            session.writer = None;
+        }
+    }
+}
+
+/// Why `members` can't play a ranked game against each other, if they can't. Ratings are kept
+/// by name, so every name has to be different, and belong to whoever plays under it.
+fn check_identities(sessions: &HashMap<ClientId, Session>, ladder: &Ladder, members: &[ClientId])
+    -> Result<(), String> {
+    let mut names = HashSet::new();
+    for session in members.iter().filter_map(|id| sessions.get(id)) {
+        match session.key {
+            None => {
+                return Err(format!("{} can't play ranked games with their client",
+                                   session.name));
+            }
+            Some(ref key) if !ladder.may_play_as(&session.name, key) => {
+                return Err(format!("The name {} belongs to someone else on the ladder",
+                                   session.name));
+            }
+            Some(_) => {}
+        }
+        if !names.insert(session.name.as_str()) {
+            return Err(format!("Only one player can be called {} in a ranked game",
+                               session.name));
+        }
+    }
+    Ok(())
+}
+
+/// The view of `team`, or the whole board if `team` is `None`; either all of it, or just what
+/// changed during the last tick.
+fn view_message(state: &GameState, team: Option<Team>, full: bool) -> ServerMessage {
+    if !full {
+        return ServerMessage::Delta(state.view_delta(team));
+    }
+    let view = match team {
+        Some(team) => state.player_view(team),
+        None => {
+            let mut view = state.full_view(0);
+            view.moves.clear();
+            view
+        }
+    };
+    ServerMessage::State(view)
+}
+
+fn console_loop(tx: mpsc::Sender<Event>) {
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        match line {
+            Ok(line) => {
+                if tx.send(Event::Console(line)).is_err() {
+                    return;
+                }
+            }
+            Err(_) => return,
+        }
+    }
+}
+
+fn accept_loop(listener: TcpListener, tx: mpsc::Sender<Event>) {
+    for (id, stream) in listener.incoming().enumerate() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        let tx = tx.clone();
+        // The WebSocket handshake can block, so do it on the connections own thread.
+        thread::spawn(move || {
+            let (writer, mut reader) = match accept(stream) {
+                Ok(halves) => halves,
+                Err(e) => {
+                    println!("Failed to accept connection: {}", e);
+                    return;
+                }
+            };
+            if tx.send(Event::Connected(id, writer)).is_err() {
+                return;
+            }
+            while let Ok(msg) = reader.recv() {
+                if tx.send(Event::Message(id, msg)).is_err() {
+                    return;
+                }
+            }
+            let _ = tx.send(Event::Disconnected(id));
+        });
+    }
+}
+
+impl Server {
+    pub fn new(listener: TcpListener, config: ServerConfig) -> Self {
+        Server {
+            listener,
+            config,
+            sessions: HashMap::new(),
+            lobbies: BTreeMap::new(),
+            next_lobby_id: 0,
+            ladder: Ladder::default(),
+            archive: None,
+            next_heartbeat: Instant::now(),
+            next_ping: 0,
+        }
+    }
+
+    /// Serve clients forever.
+    pub fn run(mut self) -> io::Result<()> {
+        if let Some(ref path) = self.config.ratings_path {
+            self.ladder = Ladder::load(path)?;
+        }
+        if let Some(ref dir) = self.config.replay_dir {
+            self.archive = Some(Archive::open(dir)?);
+        }
+        let (tx, rx) = mpsc::channel();
+        let listener = self.listener.try_clone()?;
+        if self.config.console {
+            let tx = tx.clone();
+            thread::spawn(move || console_loop(tx));
+        }
+        thread::spawn(move || accept_loop(listener, tx));
+        loop {
+            match rx.recv_timeout(self.time_until_next_tick()) {
+                Ok(Event::Console(line)) => {
+                    if !self.console(&line) {
+                        return Ok(());
+                    }
+                }
+                Ok(event) => self.handle(event),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+            self.tick_games();
+            self.heartbeat();
+        }
+    }
+
+    fn time_until_next_tick(&self) -> Duration {
+        let now = Instant::now();
+        self.lobbies
+            .values()
+            .filter_map(|l| l.game.as_ref())
+            .map(|m| if m.next_tick > now { m.next_tick - now } else { Duration::new(0, 0) })
+            .chain(Some(if self.next_heartbeat > now {
+                self.next_heartbeat - now
+            } else {
+                Duration::new(0, 0)
+            }))
+            .min()
+            .unwrap_or(HEARTBEAT_INTERVAL)
+    }
+
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Connected(id, writer) => {
+                println!("Client {} connected", id);
+                self.sessions.insert(id, Session {
+                    name: format!("Player {}", id),
+                    writer: Some(writer),
+                    capabilities: None,
+                    lobby: None,
+                    ready: false,
+                    chat_allowance: CHAT_BURST,
+                    last_chat: Instant::now(),
+                    ping_sent: None,
+                    ping: None,
+                    key: None,
+                });
+            }
+            Event::Disconnected(id) => {
+                println!("Client {} disconnected", id);
+                if !self.drop_player(id) {
+                    self.leave_lobby(id);
+                }
+                self.sessions.remove(&id);
+            }
+            Event::Message(id, msg) => self.handle_message(id, msg),
+            Event::Console(line) => {
+                self.console(&line);
+            }
+        }
+    }
+
+    /// Run an admin command. Returns `false` if the server should stop.
+    fn console(&mut self, line: &str) -> bool {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let arg = words.next().and_then(|w| w.parse::<usize>().ok());
+        match (command, arg) {
+            ("", _) => {}
+            ("help", _) => {
+                println!("status         players and games on the server");
+                println!("lobbies        list all lobbies");
+                println!("players        list all connected clients");
+                println!("profile        time spent ticking games in the last second");
+                println!("kick <client>  disconnect a client");
+                println!("close <lobby>  end a lobby, and any game in it");
+                println!("say <text>     send a chat message to everyone");
+                println!("quit           stop the server");
+            }
+            ("status", _) => {
+                let games = self.lobbies.values().filter(|l| l.game.is_some()).count();
+                println!("{} clients, {} lobbies, {} games running",
+                         self.sessions.len(),
+                         self.lobbies.len(),
+                         games);
+            }
+            ("lobbies", _) => {
+                for info in self.lobby_list() {
+                    let status = if info.started { "playing" } else { "waiting" };
+                    println!("{}: {} ({}/{}, {}, {} watching)",
+                             info.id,
+                             info.name,
+                             info.members.len(),
+                             info.num_players,
+                             status,
+                             info.spectators);
+                }
+            }
+            ("players", _) => {
+                let mut ids = self.sessions.keys().cloned().collect::<Vec<_>>();
+                ids.sort();
+                for id in ids {
+                    let session = &self.sessions[&id];
+                    match session.lobby {
+                        Some(lobby) => println!("{}: {} (lobby {})", id, session.name, lobby),
+                        None => println!("{}: {}", id, session.name),
+                    }
+                }
+            }
+            ("profile", _) => println!("{}", profile::report()),
+            ("kick", Some(id)) => {
+                match self.sessions.get_mut(&id).and_then(|s| s.writer.as_mut()) {
+                    // The connection thread notices, and reports the disconnect.
+                    Some(writer) => {
+                        let _ = writer.close();
+                    }
+                    None => println!("No such client: {}", id),
+                }
+            }
+            ("close", Some(lobby_id)) => {
+                let lobby = match self.lobbies.remove(&lobby_id) {
+                    Some(lobby) => lobby,
+                    None => {
+                        println!("No such lobby: {}", lobby_id);
+                        return true;
+                    }
+                };
+                let ids = lobby.members.iter().cloned().chain(lobby.spectators.iter().map(|s| s.0));
+                for id in ids {
+                    if let Some(session) = self.sessions.get_mut(&id) {
+                        session.lobby = None;
+                        session.ready = false;
+                    }
+                    let error = "The lobby was closed by the server".to_string();
+                    send(&mut self.sessions, id, &ServerMessage::Error(error));
+                    send(&mut self.sessions, id, &ServerMessage::LeftLobby);
+                }
+                self.broadcast_lobby(lobby_id);
+            }
+            ("say", _) => {
+                let text = line.trim_start()["say".len()..].trim().to_string();
+                let msg = ServerMessage::Chat {
+                    from: "Server".to_string(),
+                    channel: ChatChannel::All,
+                    text,
+                };
+                let ids = self.sessions.keys().cloned().collect::<Vec<_>>();
+                for id in ids {
+                    if self.has(id, CAP_CHAT) {
+                        send(&mut self.sessions, id, &msg);
+                    }
+                }
+            }
+            ("quit", _) => return false,
+            _ => println!("Unknown command '{}', try 'help'", line.trim()),
+        }
+        true
+    }
+
+    fn has(&self, id: ClientId, capability: &str) -> bool {
+        self.sessions
+            .get(&id)
+            .and_then(|s| s.capabilities.as_ref())
+            .map(|c| c.iter().any(|c| c == capability))
+            .unwrap_or(false)
+    }
+
+    fn hello(&mut self, id: ClientId, version: u32, capabilities: Vec<String>) {
+        if version != PROTOCOL_VERSION {
+            println!("Client {} speaks protocol version {}, we speak {}",
+                     id,
+                     version,
+                     PROTOCOL_VERSION);
+            let msg = ServerMessage::Incompatible {
+                version: PROTOCOL_VERSION,
+                reason: format!("The server speaks protocol version {}, but you speak {}. \
+                                 Use a build of the same version as the server.",
+                                PROTOCOL_VERSION,
+                                version),
+            };
+            send(&mut self.sessions, id, &msg);
+            // Nothing else it says will make sense to us, so hang up.
+            if let Some(mut session) = self.sessions.remove(&id) {
+                if let Some(mut writer) = session.writer.take() {
+                    let _ = writer.close();
+                }
+            }
+            return;
+        }
+        let shared = capabilities
+            .into_iter()
+            .filter(|c| CAPABILITIES.contains(&c.as_str()))
+            .collect::<Vec<_>>();
+        if let Some(session) = self.sessions.get_mut(&id) {
+            session.capabilities = Some(shared.clone());
+        }
+        let msg = ServerMessage::Hello {
+            version: PROTOCOL_VERSION,
+            capabilities: shared,
+        };
+        send(&mut self.sessions, id, &msg);
+        let lobbies = ServerMessage::Lobbies(self.lobby_list());
+        send(&mut self.sessions, id, &lobbies);
+    }
+
+    fn handle_message(&mut self, id: ClientId, msg: ClientMessage) {
+        let greeted = self.sessions.get(&id).map(|s| s.capabilities.is_some()).unwrap_or(false);
+        if !greeted {
+            match msg {
+                ClientMessage::Hello { version, capabilities } => {
+                    self.hello(id, version, capabilities)
+                }
+                _ => {
+                    let error = "Send Hello before anything else".to_string();
+                    send(&mut self.sessions, id, &ServerMessage::Error(error));
+                }
+            }
+            return;
+        }
+        match msg {
+            ClientMessage::Hello { .. } => {}
+            ClientMessage::SetName(name) => {
+                if let Some(session) = self.sessions.get_mut(&id) {
+                    session.name = name;
+                }
+                let lobby = self.sessions.get(&id).and_then(|s| s.lobby);
+                if let Some(lobby) = lobby {
+                    self.broadcast_lobby(lobby);
+                }
+            }
+            ClientMessage::ListLobbies => {
+                let lobbies = ServerMessage::Lobbies(self.lobby_list());
+                send(&mut self.sessions, id, &lobbies);
+            }
+            ClientMessage::CreateLobby { name, num_players, map, ranked, mode } => {
+                if !(2..=MAX_PLAYERS).contains(&num_players) {
+                    let error = format!("A game needs between 2 and {} players", MAX_PLAYERS);
+                    send(&mut self.sessions, id, &ServerMessage::Error(error));
+                    return;
+                }
+                if ranked && num_players != 2 {
+                    let error = "Only 1v1 games can be ranked".to_string();
+                    send(&mut self.sessions, id, &ServerMessage::Error(error));
+                    return;
+                }
+                if let Err(e) = map.check(num_players, MAX_BOARD_SIZE) {
+                    send(&mut self.sessions, id, &ServerMessage::Error(e.to_string()));
+                    return;
+                }
+                let lobby_id = self.next_lobby_id;
+                self.next_lobby_id += 1;
+                self.lobbies.insert(lobby_id, Lobby {
+                    name,
+                    num_players,
+                    map,
+                    ranked,
+                    mode,
+                    members: Vec::new(),
+                    bots: Vec::new(),
+                    spectators: Vec::new(),
+                    game: None,
+                });
+                self.join_lobby(id, lobby_id);
+            }
+            ClientMessage::JoinLobby(lobby_id) => self.join_lobby(id, lobby_id),
+            ClientMessage::LeaveLobby => self.leave_lobby(id),
+            ClientMessage::Ready(ready) => {
+                let lobby = match self.sessions.get_mut(&id) {
+                    Some(session) => {
+                        session.ready = ready;
+                        session.lobby
+                    }
+                    None => None,
+                };
+                if let Some(lobby) = lobby {
+                    self.broadcast_lobby(lobby);
+                    self.maybe_start(lobby);
+                }
+            }
+            ClientMessage::SetBots(bots) => self.set_bots(id, bots),
+            ClientMessage::Move(_) |
+            ClientMessage::ClearMoves |
+            ClientMessage::TruncateMoves(_) => self.apply_move(id, msg),
+            ClientMessage::Spectate { lobby, team } => self.spectate(id, lobby, team),
+            ClientMessage::Resync => self.resync(id),
+            ClientMessage::ListReplays => {
+                let replays = self.archive.as_ref().map(|a| a.list()).unwrap_or_default();
+                send(&mut self.sessions, id, &ServerMessage::Replays(replays));
+            }
+            ClientMessage::DownloadReplay(file) => {
+                let msg = match self.archive.as_ref().map(|a| a.load(&file)) {
+                    Some(Ok(replay)) => ServerMessage::ReplayData { file, replay },
+                    Some(Err(e)) => ServerMessage::Error(format!("Can not load {}: {}", file, e)),
+                    None => ServerMessage::Error("This server keeps no replays".to_string()),
+                };
+                send(&mut self.sessions, id, &msg);
+            }
+            ClientMessage::Leaderboard => {
+                let msg = ServerMessage::Leaderboard(self.ladder.top(LEADERBOARD_SIZE));
+                send(&mut self.sessions, id, &msg);
+            }
+            ClientMessage::Chat { channel, text } => self.chat(id, channel, text),
+            ClientMessage::Rejoin { lobby, team, token } => self.rejoin(id, lobby, team, &token),
+            ClientMessage::Identify(key) => {
+                if let Some(session) = self.sessions.get_mut(&id) {
+                    session.key = Some(key);
+                }
+            }
+            ClientMessage::Pong(number) => {
+                if let Some(session) = self.sessions.get_mut(&id) {
+                    match session.ping_sent {
+                        Some((sent, at)) if sent == number => {
+                            session.ping = Some(at.elapsed());
+                            session.ping_sent = None;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Relay a chat message, unless the client is flooding.
+    fn chat(&mut self, id: ClientId, channel: ChatChannel, text: String) {
+        let (from, lobby_id, flooding) = match self.sessions.get_mut(&id) {
+            Some(session) => {
+                let now = Instant::now();
+                let elapsed = now - session.last_chat;
+                let seconds = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 * 1e-9;
+                session.chat_allowance = (session.chat_allowance + seconds * CHAT_RATE)
+                    .min(CHAT_BURST);
+                session.last_chat = now;
+                let flooding = session.chat_allowance < 1.0;
+                if !flooding {
+                    session.chat_allowance -= 1.0;
+                }
+                (session.name.clone(), session.lobby, flooding)
+            }
+            None => return,
+        };
+        if flooding {
+            let error = "You are sending messages too fast".to_string();
+            send(&mut self.sessions, id, &ServerMessage::Error(error));
+            return;
+        }
+        let lobby = match lobby_id.and_then(|l| self.lobbies.get(&l)) {
+            Some(lobby) => lobby,
+            None => {
+                let error = "Join a lobby to chat".to_string();
+                send(&mut self.sessions, id, &ServerMessage::Error(error));
+                return;
+            }
+        };
+        let recipients = match (channel, lobby.game.as_ref()) {
+            (ChatChannel::Team, Some(game)) => {
+                let team = game.team_of(id);
+                lobby.members.iter().cloned().filter(|&m| game.team_of(m) == team).collect()
+            }
+            (ChatChannel::Team, None) => vec![id],
+            (ChatChannel::All, _) => {
+                lobby.members.iter().cloned().chain(lobby.spectators.iter().map(|s| s.0)).collect()
+            }
+        };
+        let text = text.chars().take(MAX_CHAT_LENGTH).collect();
+        let msg = ServerMessage::Chat { from, channel, text };
+        for member in recipients {
+            if self.has(member, CAP_CHAT) {
+                send(&mut self.sessions, member, &msg);
+            }
+        }
+    }
+
+    fn lobby_info(&self, id: LobbyId, lobby: &Lobby) -> LobbyInfo {
+        LobbyInfo {
+            id,
+            name: lobby.name.clone(),
+            num_players: lobby.num_players,
+            members: lobby.members
+                .iter()
+                .filter_map(|m| self.sessions.get(m))
+                .map(|s| LobbyMember {
+                    name: s.name.clone(),
+                    ready: s.ready,
+                })
+                .collect(),
+            bots: lobby.bots.clone(),
+            spectators: lobby.spectators.len(),
+            started: lobby.game.is_some(),
+            ranked: lobby.ranked,
+            mode: lobby.mode,
+        }
+    }
+
+    fn lobby_list(&self) -> Vec<LobbyInfo> {
+        self.lobbies.iter().map(|(&id, l)| self.lobby_info(id, l)).collect()
+    }
+
+    /// Tell everyone in the lobby about it, and everyone not in a lobby about all lobbies.
+    fn broadcast_lobby(&mut self, lobby_id: LobbyId) {
+        if let Some(info) = self.lobbies.get(&lobby_id).map(|l| self.lobby_info(lobby_id, l)) {
+            let members = self.lobbies[&lobby_id].members.clone();
+            for id in members {
+                send(&mut self.sessions, id, &ServerMessage::Lobby(info.clone()));
+            }
+        }
+        let lobbies = ServerMessage::Lobbies(self.lobby_list());
+        let idle = self.sessions
+            .iter()
+            .filter(|&(_, s)| s.lobby.is_none() && s.capabilities.is_some())
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        for id in idle {
+            send(&mut self.sessions, id, &lobbies);
+        }
+    }
+
+    fn join_lobby(&mut self, id: ClientId, lobby_id: LobbyId) {
+        self.leave_lobby(id);
+        let error = match self.lobbies.get_mut(&lobby_id) {
+            None => Some("No such lobby"),
+            Some(ref lobby) if lobby.game.is_some() => Some("The game has already started"),
+            Some(ref lobby) if lobby.members.len() + lobby.bots.len() >= lobby.num_players => {
+                Some("The lobby is full")
+            }
+            Some(lobby) => {
+                lobby.members.push(id);
+                None
+            }
+        };
+        if let Some(error) = error {
+            send(&mut self.sessions, id, &ServerMessage::Error(error.to_string()));
+            return;
+        }
+        if let Some(session) = self.sessions.get_mut(&id) {
+            session.lobby = Some(lobby_id);
+            session.ready = false;
+        }
+        self.broadcast_lobby(lobby_id);
+    }
+
+    fn leave_lobby(&mut self, id: ClientId) {
+        let lobby_id = match self.sessions.get_mut(&id) {
+            Some(session) => {
+                session.ready = false;
+                session.lobby.take()
+            }
+            None => None,
+        };
+        let lobby_id = match lobby_id {
+            Some(lobby_id) => lobby_id,
+            None => return,
+        };
+        let empty = match self.lobbies.get_mut(&lobby_id) {
+            Some(lobby) => {
+                lobby.members.retain(|&m| m != id);
+                lobby.spectators.retain(|s| s.0 != id);
+                // Leaving a running game on purpose is giving up.
+                if let Some(ref mut game) = lobby.game {
+                    if let Some(team) = game.team_of(id) {
+                        game.teams[team] = None;
+                        game.state.forfeit(team);
+                    }
+                }
+                let rejoining = lobby.game
+                    .as_ref()
+                    .map(|g| g.dropped.iter().any(Option::is_some))
+                    .unwrap_or(false);
+                lobby.members.is_empty() && !rejoining
+            }
+            None => return,
+        };
+        if empty {
+            let spectators = self.lobbies.remove(&lobby_id).map(|l| l.spectators).unwrap_or_default();
+            for (spectator, _) in spectators {
+                if let Some(session) = self.sessions.get_mut(&spectator) {
+                    session.lobby = None;
+                }
+                send(&mut self.sessions, spectator, &ServerMessage::LeftLobby);
+            }
+        }
+        send(&mut self.sessions, id, &ServerMessage::LeftLobby);
+        self.broadcast_lobby(lobby_id);
+        self.finish_if_over(lobby_id);
+    }
+
+    /// Start watching a running game, or switch whose view we watch.
+    fn spectate(&mut self, id: ClientId, lobby_id: LobbyId, team: Option<Team>) {
+        if !self.has(id, CAP_SPECTATE) {
+            let error = "Your client can not spectate".to_string();
+            send(&mut self.sessions, id, &ServerMessage::Error(error));
+            return;
+        }
+        let watching = self.sessions.get(&id).and_then(|s| s.lobby) == Some(lobby_id);
+        if !watching {
+            self.leave_lobby(id);
+        }
+        let error = match self.lobbies.get_mut(&lobby_id) {
+            None => Some("No such lobby"),
+            Some(ref lobby) if watching && lobby.members.contains(&id) => {
+                Some("Players can not spectate their own game")
+            }
+            Some(lobby) => {
+                match lobby.game {
+                    None => Some("The game has not started yet"),
+                    Some(ref game) if team.map(|t| t >= game.teams.len()).unwrap_or(false) => {
+                        Some("No such team")
+                    }
+                    Some(_) => {
+                        lobby.spectators.retain(|s| s.0 != id);
+                        lobby.spectators.push((id, team));
+                        None
+                    }
+                }
+            }
+        };
+        if let Some(error) = error {
+            send(&mut self.sessions, id, &ServerMessage::Error(error.to_string()));
+            return;
+        }
+        if let Some(session) = self.sessions.get_mut(&id) {
+            session.lobby = Some(lobby_id);
+            session.ready = false;
+        }
+        let num_players = self.lobbies[&lobby_id].num_players;
+        let msg = ServerMessage::Spectating {
+            lobby: lobby_id,
+            team,
+            num_players,
+        };
+        send(&mut self.sessions, id, &msg);
+        self.resync(id);
+        if !watching {
+            self.broadcast_lobby(lobby_id);
+        }
+    }
+
+    /// Keep the team of a client that lost its connection in a running game, so that they can
+    /// rejoin. Returns `false` if the client was not playing.
+    fn drop_player(&mut self, id: ClientId) -> bool {
+        let grace = self.config.reconnect_grace;
+        let lobby_id = match self.sessions.get_mut(&id).and_then(|s| s.lobby.take()) {
+            Some(lobby_id) => lobby_id,
+            None => return false,
+        };
+        let dropped = match self.lobbies.get_mut(&lobby_id) {
+            Some(lobby) => {
+                lobby.members.retain(|&m| m != id);
+                match lobby.game {
+                    Some(ref mut game) => {
+                        match game.team_of(id) {
+                            Some(team) => {
+                                game.teams[team] = None;
+                                game.dropped[team] = Some(Instant::now() + grace);
+                                true
+                            }
+                            None => false,
+                        }
+                    }
+                    None => false,
+                }
+            }
+            None => false,
+        };
+        if dropped {
+            self.broadcast_lobby(lobby_id);
+        } else if let Some(session) = self.sessions.get_mut(&id) {
+            // Let `leave_lobby` clean up.
+            session.lobby = Some(lobby_id);
+        }
+        dropped
+    }
+
+    /// Give `team` back to a client that lost its connection.
+    fn rejoin(&mut self, id: ClientId, lobby_id: LobbyId, team: Team, token: &str) {
+        self.leave_lobby(id);
+        let previous = {
+            let game = match self.lobbies.get_mut(&lobby_id).and_then(|l| l.game.as_mut()) {
+                Some(game) => game,
+                None => {
+                    let error = "The game is over".to_string();
+                    send(&mut self.sessions, id, &ServerMessage::Error(error));
+                    return;
+                }
+            };
+            if game.tokens.get(team).map(|t| t != token).unwrap_or(true) ||
+                game.state.player_states[team].dead
+            {
+                let error = "Can not rejoin that team".to_string();
+                send(&mut self.sessions, id, &ServerMessage::Error(error));
+                return;
+            }
+            game.dropped[team] = None;
+            // The old connection may not have timed out yet.
+            game.teams[team].take()
+        };
+        if let Some(previous) = previous {
+            if let Some(session) = self.sessions.get_mut(&previous) {
+                session.lobby = None;
+            }
+        }
+        if let Some(lobby) = self.lobbies.get_mut(&lobby_id) {
+            lobby.members.retain(|&m| Some(m) != previous);
+            lobby.members.push(id);
+            if let Some(ref mut game) = lobby.game {
+                game.teams[team] = Some(id);
+            }
+        }
+        if let Some(session) = self.sessions.get_mut(&id) {
+            session.lobby = Some(lobby_id);
+            session.ready = true;
+        }
+        println!("Client {} rejoined as team {}", id, team);
+        self.welcome(lobby_id, team);
+        self.broadcast_lobby(lobby_id);
+    }
+
+    /// Tell the player of `team` that they are in the game, and what it looks like.
+    fn welcome(&mut self, lobby_id: LobbyId, team: Team) {
+        let (id, welcome, view) = match self.lobbies.get(&lobby_id).and_then(|l| l.game.as_ref()) {
+            Some(game) => {
+                let id = match game.teams[team] {
+                    Some(id) => id,
+                    None => return,
+                };
+                let welcome = ServerMessage::Welcome {
+                    lobby: lobby_id,
+                    team,
+                    num_players: game.teams.len(),
+                    token: game.tokens[team].clone(),
+                };
+                (id, welcome, game.state.player_view(team))
+            }
+            None => return,
+        };
+        send(&mut self.sessions, id, &welcome);
+        send(&mut self.sessions, id, &ServerMessage::State(view));
+    }
+
+    /// Replace the bots of the lobby of `id`, which has to have room for them.
+    fn set_bots(&mut self, id: ClientId, bots: Vec<String>) {
+        let lobby_id = match self.sessions.get(&id).and_then(|s| s.lobby) {
+            Some(lobby_id) => lobby_id,
+            None => return,
+        };
+        let error = match self.lobbies.get_mut(&lobby_id) {
+            None => return,
+            Some(ref lobby) if lobby.game.is_some() => Some("The game has already started".into()),
+            Some(ref lobby) if lobby.ranked && !bots.is_empty() => {
+                Some("Bots can't play ranked games".into())
+            }
+            Some(ref lobby) if lobby.members.len() + bots.len() > lobby.num_players => {
+                Some("There is no room for that many bots".into())
+            }
+            Some(_) if bots.iter().any(|name| !bot::NAMES.contains(&name.as_str())) => {
+                Some(format!("The bots are {:?}", bot::NAMES))
+            }
+            Some(lobby) => {
+                lobby.bots = bots;
+                None
+            }
+        };
+        if let Some(error) = error {
+            send(&mut self.sessions, id, &ServerMessage::Error(error));
+            return;
+        }
+        self.broadcast_lobby(lobby_id);
+        self.maybe_start(lobby_id);
+    }
+
+    /// Start the game if the lobby is full and everyone is ready.
+    fn maybe_start(&mut self, lobby_id: LobbyId) {
+        let tick_interval = self.config.tick_interval;
+        let refused = {
+            let sessions = &self.sessions;
+            let lobby = match self.lobbies.get(&lobby_id) {
+                Some(lobby) => lobby,
+                None => return,
+            };
+            let all_ready = lobby.members
+                .iter()
+                .all(|m| sessions.get(m).map(|s| s.ready).unwrap_or(false));
+            let humans = lobby.members.len();
+            let full = humans + lobby.bots.len() >= lobby.num_players;
+            if lobby.game.is_some() || humans == 0 || !full || !all_ready {
+                return;
+            }
+            if lobby.ranked {
+                check_identities(sessions, &self.ladder, &lobby.members).err()
+            } else {
+                None
+            }
+        };
+        if let Some(error) = refused {
+            // Nobody is ready anymore, so the game doesn't start until the names are sorted out.
+            let members = self.lobbies[&lobby_id].members.clone();
+            for id in members {
+                if let Some(session) = self.sessions.get_mut(&id) {
+                    session.ready = false;
+                }
+                send(&mut self.sessions, id, &ServerMessage::Error(error.clone()));
+            }
+            self.broadcast_lobby(lobby_id);
+            return;
+        }
+        let num_players = {
+            let sessions = &self.sessions;
+            let name_of = |id: &ClientId| sessions.get(id).map(|s| s.name.clone()).unwrap_or_default();
+            let lobby = match self.lobbies.get_mut(&lobby_id) {
+                Some(lobby) => lobby,
+                None => return,
+            };
+            let humans = lobby.members.len();
+            let board = Board::generate(&lobby.map, lobby.num_players, &mut rand::thread_rng());
+            let mut state = GameState::with_mode(board, lobby.num_players, lobby.mode);
+            eventlog::attach(&mut state);
+            let bots = lobby.bots
+                .iter()
+                .enumerate()
+                .filter_map(|(i, name)| bot::by_name(name).map(|b| BotPlayer::new(humans + i, b)))
+                .collect();
+            lobby.game = Some(Match {
+                replay: Replay::new(&state),
+                state,
+                teams: lobby.members
+                    .iter()
+                    .cloned()
+                    .map(Some)
+                    .chain(lobby.bots.iter().map(|_| None))
+                    .collect(),
+                tokens: (0..lobby.num_players)
+                    .map(|_| format!("{:016x}", rand::random::<u64>()))
+                    .collect(),
+                dropped: vec![None; lobby.num_players],
+                names: lobby.members
+                    .iter()
+                    .map(name_of)
+                    .chain(lobby.bots.iter().map(|name| format!("{} (bot)", name)))
+                    .collect(),
+                bots,
+                next_tick: Instant::now() + tick_interval,
+            });
+            lobby.num_players
+        };
+        let lobby = &self.lobbies[&lobby_id];
+        if lobby.ranked {
+            let sessions = &self.sessions;
+            let ladder = &mut self.ladder;
+            for session in lobby.members.iter().filter_map(|id| sessions.get(id)) {
+                if let Some(ref key) = session.key {
+                    ladder.claim(&session.name, key);
+                }
+            }
+        }
+        for team in 0..num_players {
+            self.welcome(lobby_id, team);
+        }
+        self.broadcast_lobby(lobby_id);
+    }
+
+    fn apply_move(&mut self, id: ClientId, msg: ClientMessage) {
+        let lobby_id = match self.sessions.get(&id).and_then(|s| s.lobby) {
+            Some(lobby_id) => lobby_id,
+            None => return,
+        };
+        let game = match self.lobbies.get_mut(&lobby_id).and_then(|l| l.game.as_mut()) {
+            Some(game) => game,
+            None => return,
+        };
+        let team = match game.team_of(id) {
+            Some(team) => team,
+            None => return,
+        };
+        let command = match msg {
+            ClientMessage::Move(mv) => Command::Move(mv),
+            ClientMessage::ClearMoves => Command::ClearMoves,
+            ClientMessage::TruncateMoves(keep) => Command::TruncateMoves(keep),
+            _ => return,
+        };
+        if let Err(e) = frontend::apply(&mut game.state, team, command) {
+            send(&mut self.sessions, id, &ServerMessage::Error(e.to_string()));
+        }
+    }
+
+    /// Send everyone in the game what happened during the last tick, or the whole game if
+    /// `full` is set.
+    fn send_views(&mut self, lobby_id: LobbyId, full: bool) {
+        let lobby = match self.lobbies.get(&lobby_id) {
+            Some(lobby) => lobby,
+            None => return,
+        };
+        let game = match lobby.game {
+            Some(ref game) => game,
+            None => return,
+        };
+        let players = game.teams
+            .iter()
+            .enumerate()
+            .filter_map(|(team, &id)| id.map(|id| (id, Some(team))));
+        let messages = players.chain(lobby.spectators.iter().cloned())
+            .map(|(id, team)| {
+                let full = full || !self.has(id, CAP_DELTAS);
+                (id, view_message(&game.state, team, full))
+            })
+            .collect::<Vec<_>>();
+        for (id, msg) in messages {
+            send(&mut self.sessions, id, &msg);
+        }
+    }
+
+    /// Send the whole game to a single player or spectator, so they can catch up.
+    fn resync(&mut self, id: ClientId) {
+        let msg = {
+            let lobby = match self.sessions.get(&id).and_then(|s| s.lobby) {
+                Some(lobby_id) => {
+                    match self.lobbies.get(&lobby_id) {
+                        Some(lobby) => lobby,
+                        None => return,
+                    }
+                }
+                None => return,
+            };
+            let game = match lobby.game {
+                Some(ref game) => game,
+                None => return,
+            };
+            let team = match game.team_of(id) {
+                Some(team) => Some(team),
+                None => {
+                    match lobby.spectators.iter().find(|s| s.0 == id) {
+                        Some(&(_, team)) => team,
+                        None => return,
+                    }
+                }
+            };
+            view_message(&game.state, team, true)
+        };
+        send(&mut self.sessions, id, &msg);
+    }
+
+    fn tick_games(&mut self) {
+        let now = Instant::now();
+        let interval = self.config.tick_interval;
+        let mut ticked = Vec::new();
+        for (&id, lobby) in self.lobbies.iter_mut() {
+            if let Some(ref mut game) = lobby.game {
+                for team in 0..game.dropped.len() {
+                    if game.dropped[team].map(|t| t <= now).unwrap_or(false) {
+                        println!("Team {} did not rejoin in time", team);
+                        game.dropped[team] = None;
+                        game.state.forfeit(team);
+                    }
+                }
+                if game.next_tick <= now {
+                    game.next_tick += interval;
+                    for bot in game.bots.iter_mut() {
+                        bot.act(&mut game.state);
+                    }
+                    game.replay.record(&game.state);
+                    game.state.tick();
+                    ticked.push((id, game.state.tick_number));
+                }
+            }
+        }
+        for (lobby_id, tick_number) in ticked {
+            self.send_views(lobby_id, tick_number % FULL_STATE_INTERVAL == 0);
+            self.finish_if_over(lobby_id);
+        }
+    }
+
+    /// Once a second, ping every client, and tell everyone in a game how its players are doing.
+    fn heartbeat(&mut self) {
+        let now = Instant::now();
+        if self.next_heartbeat > now {
+            return;
+        }
+        self.next_heartbeat = now + HEARTBEAT_INTERVAL;
+        let number = self.next_ping;
+        self.next_ping += 1;
+        let ids = self.sessions.keys().cloned().collect::<Vec<_>>();
+        for id in ids {
+            if self.has(id, CAP_PLAYERS) {
+                if let Some(session) = self.sessions.get_mut(&id) {
+                    session.ping_sent = Some((number, now));
+                }
+                send(&mut self.sessions, id, &ServerMessage::Ping(number));
+            }
+        }
+        let lobby_ids = self.lobbies.keys().cloned().collect::<Vec<_>>();
+        for lobby_id in lobby_ids {
+            let (players, audience) = {
+                let lobby = &self.lobbies[&lobby_id];
+                let game = match lobby.game {
+                    Some(ref game) => game,
+                    None => continue,
+                };
+                let players = self.player_list(game);
+                let audience = game.teams
+                    .iter()
+                    .filter_map(|&id| id)
+                    .chain(lobby.spectators.iter().map(|s| s.0))
+                    .filter(|&id| self.has(id, CAP_PLAYERS))
+                    .collect::<Vec<_>>();
+                (players, audience)
+            };
+            let msg = ServerMessage::Players(players);
+            for id in audience {
+                send(&mut self.sessions, id, &msg);
+            }
+        }
+    }
+
+    fn player_list(&self, game: &Match) -> Vec<PlayerInfo> {
+        let humans = game.teams.len() - game.bots.len();
+        (0..game.teams.len())
+            .map(|team| {
+                let presence = if game.state.player_states[team].dead {
+                    Presence::Dead
+                } else if game.dropped[team].is_some() {
+                    Presence::Disconnected
+                } else {
+                    Presence::Alive
+                };
+                let ping_ms = game.teams[team]
+                    .and_then(|id| self.sessions.get(&id))
+                    .and_then(|s| s.ping)
+                    .map(|p| p.as_secs() as u32 * 1000 + p.subsec_millis());
+                PlayerInfo {
+                    team,
+                    name: game.names[team].clone(),
+                    bot: team >= humans,
+                    presence,
+                    ping_ms,
+                }
+            })
+            .collect()
+    }
+
+    /// Update the ratings after a ranked 1v1 game.
+    fn rate(&mut self, names: &[String], winner: Option<Team>) {
+        if names.len() != 2 {
+            return;
+        }
+        let score = match winner {
+            Some(0) => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        };
+        self.ladder.record(&names[0], &names[1], score);
+        println!("{} is now rated {:.0}, {} {:.0}",
+                 names[0],
+                 self.ladder.get(&names[0]).rating,
+                 names[1],
+                 self.ladder.get(&names[1]).rating);
+        if let Some(ref path) = self.config.ratings_path {
+            if let Err(e) = self.ladder.save(path) {
+                println!("Failed to save ratings: {}", e);
+            }
+        }
+    }
+
+    /// Send everyone home when the game is over.
+    fn finish_if_over(&mut self, lobby_id: LobbyId) {
+        let winner = match self.lobbies.get(&lobby_id).and_then(|l| l.game.as_ref()) {
+            Some(game) if game.state.is_over() => game.state.winner(),
+            _ => return,
+        };
+        let mut lobby = match self.lobbies.remove(&lobby_id) {
+            Some(lobby) => lobby,
+            None => return,
+        };
+        if let (Some(archive), Some(game)) = (self.archive.as_mut(), lobby.game.as_mut()) {
+            game.replay.winner = winner;
+            match archive.add(&game.replay, &lobby.name, game.names.clone(), winner) {
+                Ok(info) => println!("Archived replay of '{}' as {}", lobby.name, info.file),
+                Err(e) => println!("Failed to archive replay of '{}': {}", lobby.name, e),
+            }
+        }
+        if let Some(ref game) = lobby.game {
+            if lobby.ranked {
+                self.rate(&game.names, winner);
+            }
+        }
+        let members = lobby.members
+            .into_iter()
+            .chain(lobby.spectators.into_iter().map(|s| s.0))
+            .collect::<Vec<_>>();
+        for id in members {
+            send(&mut self.sessions, id, &ServerMessage::GameOver { winner });
+            if let Some(session) = self.sessions.get_mut(&id) {
+                session.lobby = None;
+                session.ready = false;
+            }
+        }
+        let lobbies = ServerMessage::Lobbies(self.lobby_list());
+        let ids = self.sessions
+            .iter()
+            .filter(|&(_, s)| s.capabilities.is_some())
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        for id in ids {
+            send(&mut self.sessions, id, &lobbies);
+        }
+    }
+}
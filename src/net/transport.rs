@@ -0,0 +1,171 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+use tungstenite::{self, Message, WebSocket};
+
+/// Sends whole messages over some transport.
+trait Outgoing: Send {
+    fn send_text(&mut self, text: &str) -> io::Result<()>;
+    /// Hang up, which also ends the other half.
+    fn close(&mut self) -> io::Result<()>;
+}
+
+/// Receives whole messages from some transport. Returns `None` when the connection is closed.
+trait Incoming: Send {
+    fn recv_text(&mut self) -> io::Result<Option<String>>;
+}
+
+/// Messages over raw TCP are separated by newlines.
+impl Outgoing for TcpStream {
+    fn send_text(&mut self, text: &str) -> io::Result<()> {
+        let mut line = text.as_bytes().to_vec();
+        line.push(b'\n');
+        self.write_all(&line)
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.shutdown(Shutdown::Both)
+    }
+}
+
+/// The longest line `recv_text` reads, so that the other side can't make us buffer without
+/// end. Replays are the longest messages by far.
+const MAX_LINE_BYTES: u64 = 16 * 1024 * 1024;
+
+impl Incoming for BufReader<TcpStream> {
+    fn recv_text(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        let read = self.by_ref().take(MAX_LINE_BYTES).read_line(&mut line)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if !line.ends_with('\n') && read as u64 == MAX_LINE_BYTES {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "message too long"));
+        }
+        Ok(Some(line))
+    }
+}
+
+fn ws_error(e: tungstenite::Error) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+/// How long the reading half of a WebSocket waits for data before letting the writing half
+/// have the socket.
+const WS_POLL: Duration = Duration::from_millis(20);
+/// How often `accept` looks again while the first bytes of a connection trickle in.
+const PEEK_INTERVAL: Duration = Duration::from_millis(5);
+
+/// One WebSocket message per game message. Both halves share one socket behind a lock, since
+/// reading writes too: tungstenite answers pings and close frames as it reads them. Reads time
+/// out every `WS_POLL`, so the reader never holds the lock for long.
+#[derive(Clone)]
+struct WsHalf(Arc<Mutex<WebSocket<TcpStream>>>);
+
+impl Outgoing for WsHalf {
+    fn send_text(&mut self, text: &str) -> io::Result<()> {
+        let mut ws = self.0.lock().unwrap();
+        ws.write_message(Message::Text(text.to_string())).map_err(ws_error)
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap().get_mut().shutdown(Shutdown::Both)
+    }
+}
+
+impl Incoming for WsHalf {
+    fn recv_text(&mut self) -> io::Result<Option<String>> {
+        loop {
+            let read = self.0.lock().unwrap().read_message();
+            match read {
+                Ok(Message::Text(text)) => return Ok(Some(text)),
+                Ok(Message::Binary(bytes)) => {
+                    return String::from_utf8(bytes)
+                        .map(Some)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+                }
+                Ok(Message::Close(_)) |
+                Err(tungstenite::Error::ConnectionClosed) => return Ok(None),
+                Err(tungstenite::Error::Io(ref e)) if e.kind() == io::ErrorKind::WouldBlock ||
+                                                      e.kind() == io::ErrorKind::TimedOut => {
+                    // Nothing arrived: give the writer a chance at the lock.
+                    thread::yield_now();
+                }
+                Ok(_) => {}
+                Err(e) => return Err(ws_error(e)),
+            }
+        }
+    }
+}
+
+/// The sending half of a connection.
+pub struct MessageWriter {
+    out: Box<dyn Outgoing>,
+}
+
+/// The receiving half of a connection.
+pub struct MessageReader {
+    inc: Box<dyn Incoming>,
+}
+
+impl MessageWriter {
+    pub fn send<T: Serialize>(&mut self, msg: &T) -> io::Result<()> {
+        let text = serde_json::to_string(msg).map_err(io::Error::from)?;
+        self.out.send_text(&text)
+    }
+
+    pub fn close(&mut self) -> io::Result<()> {
+        self.out.close()
+    }
+}
+
+impl MessageReader {
+    /// Block until the next message arrives.
+    pub fn recv<T: DeserializeOwned>(&mut self) -> io::Result<T> {
+        match self.inc.recv_text()? {
+            Some(text) => serde_json::from_str(&text).map_err(io::Error::from),
+            None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed")),
+        }
+    }
+}
+
+/// Split a raw TCP stream into halves that can be used from different threads.
+pub fn split(stream: TcpStream) -> io::Result<(MessageWriter, MessageReader)> {
+    let reader = BufReader::new(stream.try_clone()?);
+    Ok((MessageWriter { out: Box::new(stream) }, MessageReader { inc: Box::new(reader) }))
+}
+
+/// Do the server side of the WebSocket handshake, and split the socket into halves.
+pub fn split_websocket(stream: TcpStream) -> io::Result<(MessageWriter, MessageReader)> {
+    let ws = tungstenite::accept(stream).map_err(|e| {
+        io::Error::new(io::ErrorKind::ConnectionAborted, e.to_string())
+    })?;
+    ws.get_ref().set_read_timeout(Some(WS_POLL))?;
+    let ws = WsHalf(Arc::new(Mutex::new(ws)));
+    Ok((MessageWriter { out: Box::new(ws.clone()) }, MessageReader { inc: Box::new(ws) }))
+}
+
+/// Accept a new connection on the server, which can be either raw TCP or a WebSocket. WebSocket
+/// connections start with an HTTP request, so we can tell them apart by peeking.
+pub fn accept(stream: TcpStream) -> io::Result<(MessageWriter, MessageReader)> {
+    const WEBSOCKET_START: &[u8] = b"GET ";
+    let mut start = [0; 4];
+    // The first bytes can arrive in pieces. Wait for all four, unless they already tell that
+    // this is not a WebSocket, or the connection ends.
+    let mut n = stream.peek(&mut start)?;
+    while n > 0 && n < start.len() && start[..n] == WEBSOCKET_START[..n] {
+        thread::sleep(PEEK_INTERVAL);
+        n = stream.peek(&mut start)?;
+    }
+    if start[..n] == *WEBSOCKET_START {
+        split_websocket(stream)
+    } else {
+        split(stream)
+    }
+}
@@ -0,0 +1,114 @@
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+/// The parts of the game we keep timings for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    /// The whole of `GameState::tick`.
+    Tick,
+    /// Unit growth, part of `Tick`.
+    Growth,
+    /// Move resolution, part of `Tick`.
+    Moves,
+    /// Computing fogged player views.
+    Fog,
+    /// Bots choosing their moves, including their fog.
+    Bots,
+    Render,
+}
+
+pub const SECTIONS: [Section; 6] = [
+    Section::Tick,
+    Section::Growth,
+    Section::Moves,
+    Section::Fog,
+    Section::Bots,
+    Section::Render,
+];
+
+impl Section {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Section::Tick => "tick",
+            Section::Growth => "growth",
+            Section::Moves => "moves",
+            Section::Fog => "fog",
+            Section::Bots => "bots",
+            Section::Render => "render",
+        }
+    }
+}
+
+/// Accumulates time spent per section, in windows of one second.
+struct Profiler {
+    window_start: Instant,
+    current: [Duration; 6],
+    last: [Duration; 6],
+}
+
+impl Profiler {
+    fn new() -> Self {
+        Profiler {
+            window_start: Instant::now(),
+            current: [Duration::new(0, 0); 6],
+            last: [Duration::new(0, 0); 6],
+        }
+    }
+
+    fn add(&mut self, section: Section, now: Instant, time: Duration) {
+        if now.duration_since(self.window_start) >= Duration::from_secs(1) {
+            self.last = self.current;
+            self.current = [Duration::new(0, 0); 6];
+            self.window_start = now;
+        }
+        self.current[section as usize] += time;
+    }
+}
+
+thread_local! {
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::new());
+}
+
+/// Times a section until it is dropped.
+pub struct Timer {
+    section: Section,
+    /// `None` where there is no clock, like in browsers, where nothing is timed.
+    start: Option<Instant>,
+}
+
+/// Start timing `section`. The time is recorded when the returned `Timer` goes out of scope.
+pub fn start(section: Section) -> Timer {
+    let start = if cfg!(target_arch = "wasm32") { None } else { Some(Instant::now()) };
+    Timer { section, start }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            let now = Instant::now();
+            let time = now.duration_since(start);
+            PROFILER.with(|p| p.borrow_mut().add(self.section, now, time));
+        }
+    }
+}
+
+/// The time spent in each section during the last full second, on this thread.
+pub fn last_second() -> Vec<(Section, Duration)> {
+    PROFILER.with(|p| {
+        let p = p.borrow();
+        SECTIONS.iter().map(|&s| (s, p.last[s as usize])).collect()
+    })
+}
+
+pub fn millis(d: Duration) -> f64 {
+    d.as_secs() as f64 * 1000.0 + d.subsec_nanos() as f64 / 1_000_000.0
+}
+
+/// A one line summary of `last_second`, in milliseconds per second.
+pub fn report() -> String {
+    last_second()
+        .into_iter()
+        .map(|(s, d)| format!("{} {:.2}ms", s.name(), millis(d)))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use serde_json;
+
+use fnv::Fnv;
+
+/// The rating new players start out with.
+pub const INITIAL_RATING: f64 = 1000.0;
+/// How much a single game can move a rating.
+pub const K_FACTOR: f64 = 32.0;
+
+/// The expected score of a player rated `a` against one rated `b`, between 0 and 1.
+pub fn expected(a: f64, b: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((b - a) / 400.0))
+}
+
+/// The new ratings of two players after a game, where `score_a` is 1 if `a` won, 0 if `b` won,
+/// and 0.5 for a draw.
+pub fn update(a: f64, b: f64, score_a: f64) -> (f64, f64) {
+    let delta = K_FACTOR * (score_a - expected(a, b));
+    (a + delta, b - delta)
+}
+
+/// The rating and record of a single player on a `Ladder`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PlayerRating {
+    pub rating: f64,
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+}
+
+impl Default for PlayerRating {
+    fn default() -> Self {
+        PlayerRating {
+            rating: INITIAL_RATING,
+            wins: 0,
+            losses: 0,
+            draws: 0,
+        }
+    }
+}
+
+impl PlayerRating {
+    fn add(&mut self, score: f64) {
+        if score == 1.0 {
+            self.wins += 1;
+        } else if score == 0.0 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+}
+
+/// A hash of a player's key, so that the ladder file doesn't give the keys away.
+fn key_hash(key: &str) -> u64 {
+    let mut hash = Fnv::default();
+    hash.bytes(key.as_bytes());
+    hash.finish()
+}
+
+/// Ratings of players by name, kept across games.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Ladder {
+    players: BTreeMap<String, PlayerRating>,
+    /// The hash of the key each name is tied to, from the first ranked game under it.
+    #[serde(default)]
+    owners: BTreeMap<String, u64>,
+}
+
+impl Ladder {
+    /// Load a ladder from `path`. A missing file is an empty ladder.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match File::open(path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file)).map_err(io::Error::from),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Ladder::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(file, self).map_err(io::Error::from)
+    }
+
+    pub fn get(&self, name: &str) -> PlayerRating {
+        self.players.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Whether the player with `key` may play ranked games as `name`: it is theirs, or nobody's
+    /// yet.
+    pub fn may_play_as(&self, name: &str, key: &str) -> bool {
+        self.owners.get(name).map(|&hash| hash == key_hash(key)).unwrap_or(true)
+    }
+
+    /// Tie `name` to `key`, unless it already belongs to someone.
+    pub fn claim(&mut self, name: &str, key: &str) {
+        self.owners.entry(name.to_string()).or_insert_with(|| key_hash(key));
+    }
+
+    /// Record a game between `a` and `b`, scored like `update`.
+    pub fn record(&mut self, a: &str, b: &str, score_a: f64) {
+        let (mut ra, mut rb) = (self.get(a), self.get(b));
+        let (new_a, new_b) = update(ra.rating, rb.rating, score_a);
+        ra.rating = new_a;
+        rb.rating = new_b;
+        ra.add(score_a);
+        rb.add(1.0 - score_a);
+        self.players.insert(a.to_string(), ra);
+        self.players.insert(b.to_string(), rb);
+    }
+
+    /// The `n` highest rated players, best first.
+    pub fn top(&self, n: usize) -> Vec<(String, PlayerRating)> {
+        let mut players = self.players
+            .iter()
+            .map(|(name, &rating)| (name.clone(), rating))
+            .collect::<Vec<_>>();
+        players.sort_by(|a, b| b.1.rating.total_cmp(&a.1.rating));
+        players.truncate(n);
+        players
+    }
+}
@@ -0,0 +1,378 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::de::{Deserializer, IgnoredAny, SeqAccess, Visitor};
+use serde_json;
+
+use error::GeneralsError;
+use generals::*;
+
+const REPLAY_DIR: &str = "replays";
+/// How many ticks apart `Playback` keeps snapshots.
+const KEYFRAME_INTERVAL: usize = 32;
+
+/// The move queues of every player right before a tick.
+///
+/// The first move in each queue is the one executed that tick; the rest were still pending.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Frame {
+    pub tick_number: usize,
+    pub queues: Vec<Vec<Move>>,
+    /// The teams that are out of the game, including those who forfeit between ticks.
+    #[serde(default)]
+    pub dead: Vec<Team>,
+    /// The cells that were edited since the last tick, in sandbox games.
+    #[serde(default)]
+    pub edits: Vec<(Position, Cell)>,
+}
+
+/// A recorded game: the starting board, and the queue snapshots of every tick.
+///
+/// Since `GameState::tick` is deterministic, restoring the queues of each frame and ticking
+/// reproduces the game exactly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub num_players: usize,
+    pub board: Board,
+    pub frames: Vec<Frame>,
+    #[serde(default)]
+    pub mode: GameMode,
+    /// The handicaps of the players, by team. Their changes to the board are already in
+    /// `board`.
+    #[serde(default)]
+    pub handicaps: Vec<Handicap>,
+    /// Who won, once the game is over. `None` for draws, and in replays from before winners
+    /// were recorded.
+    #[serde(default)]
+    pub winner: Option<Team>,
+}
+
+impl Replay {
+    pub fn new(game: &GameState) -> Self {
+        Replay {
+            num_players: game.num_players,
+            board: game.board.clone(),
+            frames: Vec::new(),
+            mode: game.mode(),
+            handicaps: game.player_states.iter().map(|p| p.handicap).collect(),
+            winner: None,
+        }
+    }
+
+    /// Record the queues of all players. Call this right before `GameState::tick`.
+    pub fn record(&mut self, game: &GameState) {
+        self.frames.push(Frame::new(game));
+    }
+
+    /// The number of recorded ticks.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Reconstruct the game as it was right before tick number `n` was executed, with the move
+    /// queues of that tick restored. For `n == self.len()` this is the final state of the game.
+    pub fn state_at(&self, n: usize) -> GameState {
+        Playback::new(self).state_at(self, n)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(file, self).map_err(io::Error::from)
+    }
+
+    /// Load a replay, and check that it can be played back.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GeneralsError> {
+        let file = BufReader::new(File::open(path)?);
+        let replay: Replay = serde_json::from_reader(file)?;
+        replay.check()?;
+        Ok(replay)
+    }
+
+    /// Check that the board is sound and that every frame is about the teams of the game.
+    /// Moves in the queues are not checked, since the game skips the ones it can't make.
+    pub fn check(&self) -> Result<(), GeneralsError> {
+        let teams = self.mode.teams(self.num_players);
+        self.board.check(teams)?;
+        for frame in self.frames.iter() {
+            if frame.queues.len() > teams {
+                let reason = format!("tick {} has more queues than players", frame.tick_number);
+                return Err(GeneralsError::BadMap(reason));
+            }
+            if let Some(&team) = frame.dead.iter().find(|&&team| team >= teams) {
+                return Err(GeneralsError::UnknownTeam(team));
+            }
+        }
+        Ok(())
+    }
+
+    /// Save the replay into the replay directory, named by the current time.
+    pub fn save_timestamped(&self) -> io::Result<PathBuf> {
+        self.save_in(REPLAY_DIR)
+    }
+
+    /// Save the replay into `dir`, named by the current time.
+    pub fn save_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<PathBuf> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        // Several games can end in the same second on a server.
+        let mut path = dir.join(format!("replay-{}.json", secs));
+        let mut n = 1;
+        while path.exists() {
+            path = dir.join(format!("replay-{}-{}.json", secs, n));
+            n += 1;
+        }
+        self.save(&path)?;
+        Ok(path)
+    }
+}
+
+impl Frame {
+    /// The queues of all players of `game`, right before its next tick.
+    pub fn new(game: &GameState) -> Self {
+        Frame {
+            tick_number: game.tick_number,
+            queues: game.player_states
+                .iter()
+                .map(|p| p.moves.iter().cloned().collect())
+                .collect(),
+            dead: game.player_states.iter().filter(|p| p.dead).map(|p| p.team).collect(),
+            edits: game.edits().to_vec(),
+        }
+    }
+
+    /// Give the players of `game` the queues of this frame, forfeit the teams that are out, and
+    /// make the edits of the frame.
+    pub fn restore(&self, game: &mut GameState) {
+        for &(pos, cell) in self.edits.iter() {
+            // The edits were made on the same game, so they fit.
+            let _ = game.edit_cell(pos, cell);
+        }
+        for &team in self.dead.iter() {
+            game.forfeit(team);
+        }
+        for (player, queue) in game.player_states.iter_mut().zip(self.queues.iter()) {
+            player.moves = queue.iter().cloned().collect();
+        }
+    }
+}
+
+/// Seeks back and forth in a replay. A snapshot of the game is kept every `KEYFRAME_INTERVAL`
+/// ticks as they are passed, so that going back only plays the ticks since the last snapshot
+/// again, and not the game from the start.
+pub struct Playback {
+    /// `keyframes[i]` is the game after `i * KEYFRAME_INTERVAL` ticks.
+    keyframes: Vec<Snapshot>,
+}
+
+impl Playback {
+    pub fn new(replay: &Replay) -> Self {
+        let mut start =
+            GameState::with_mode(replay.board.clone(), replay.num_players, replay.mode);
+        for (player, &handicap) in start.player_states.iter_mut().zip(replay.handicaps.iter()) {
+            player.handicap = handicap;
+        }
+        Playback { keyframes: vec![start.snapshot()] }
+    }
+
+    /// Like `Replay::state_at`. Frames are only ever added to a replay, so the same `Playback`
+    /// can be used while it is being recorded.
+    pub fn state_at(&mut self, replay: &Replay, n: usize) -> GameState {
+        let n = n.min(replay.len());
+        let k = (n / KEYFRAME_INTERVAL).min(self.keyframes.len() - 1);
+        let mut game = self.keyframes[k].state().clone();
+        for i in k * KEYFRAME_INTERVAL..n {
+            replay.frames[i].restore(&mut game);
+            game.tick();
+            let ticks = i + 1;
+            if ticks == self.keyframes.len() * KEYFRAME_INTERVAL {
+                self.keyframes.push(game.snapshot());
+            }
+        }
+        if let Some(frame) = replay.frames.get(n) {
+            frame.restore(&mut game);
+        }
+        game
+    }
+}
+
+/// A replay file in the replay directory, as listed by `saved_replays`.
+#[derive(Debug, Clone)]
+pub struct SavedReplay {
+    pub path: PathBuf,
+    /// When the file was last written, in seconds since the Unix epoch.
+    pub saved_at: u64,
+    pub num_players: usize,
+    pub dimens: (i32, i32),
+    pub mode: GameMode,
+    pub winner: Option<Team>,
+    pub ticks: usize,
+}
+
+/// What `saved_replays` reads of a replay file. The cells and frames are skipped over rather
+/// than kept, so that listing many long replays stays cheap.
+#[derive(Deserialize)]
+struct Header {
+    num_players: usize,
+    board: BoardSize,
+    #[serde(deserialize_with = "count")]
+    frames: usize,
+    #[serde(default)]
+    mode: GameMode,
+    #[serde(default)]
+    winner: Option<Team>,
+}
+
+impl Header {
+    fn load(path: &Path) -> Result<Self, GeneralsError> {
+        let file = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(file)?)
+    }
+}
+
+#[derive(Deserialize)]
+struct BoardSize {
+    width: usize,
+    height: usize,
+}
+
+/// The length of a list, without keeping its elements.
+fn count<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    struct Count;
+
+    impl<'de> Visitor<'de> for Count {
+        type Value = usize;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a list")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<usize, A::Error> {
+            let mut n = 0;
+            while seq.next_element::<IgnoredAny>()?.is_some() {
+                n += 1;
+            }
+            Ok(n)
+        }
+    }
+
+    deserializer.deserialize_seq(Count)
+}
+
+/// The replays saved with `Replay::save_timestamped`, most recent first. Files that are not
+/// replays are skipped.
+pub fn saved_replays() -> io::Result<Vec<SavedReplay>> {
+    let entries = match fs::read_dir(REPLAY_DIR) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut replays = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().map(|e| e != "json").unwrap_or(true) {
+            continue;
+        }
+        let header = match Header::load(&path) {
+            Ok(header) => header,
+            Err(_) => continue,
+        };
+        let saved_at = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        replays.push(SavedReplay {
+            path,
+            saved_at,
+            num_players: header.num_players,
+            dimens: (header.board.width as i32, header.board.height as i32),
+            mode: header.mode,
+            winner: header.winner,
+            ticks: header.frames,
+        });
+    }
+    replays.sort_by_key(|r| u64::MAX - r.saved_at);
+    Ok(replays)
+}
+
+/// What an `Archive` knows about a replay without loading it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayInfo {
+    /// The file name of the replay in the archive.
+    pub file: String,
+    /// The name of the lobby the game was played in.
+    pub title: String,
+    pub players: Vec<String>,
+    pub winner: Option<Team>,
+    pub ticks: usize,
+    /// Seconds since the Unix epoch.
+    pub finished_at: u64,
+}
+
+/// A directory of replays, with an index of them.
+pub struct Archive {
+    dir: PathBuf,
+    entries: Vec<ReplayInfo>,
+}
+
+impl Archive {
+    const INDEX: &'static str = "index.json";
+
+    /// Open the archive in `dir`, which is created if it does not exist.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let entries = match File::open(dir.join(Archive::INDEX)) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file)).map_err(io::Error::from)?,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Archive { dir, entries })
+    }
+
+    /// Save `replay` into the archive, and add it to the index.
+    pub fn add(&mut self, replay: &Replay, title: &str, players: Vec<String>,
+               winner: Option<Team>) -> io::Result<ReplayInfo> {
+        let path = replay.save_in(&self.dir)?;
+        let info = ReplayInfo {
+            file: path.file_name().unwrap().to_string_lossy().into_owned(),
+            title: title.to_string(),
+            players,
+            winner,
+            ticks: replay.len(),
+            finished_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        };
+        self.entries.push(info.clone());
+        let index = BufWriter::new(File::create(self.dir.join(Archive::INDEX))?);
+        serde_json::to_writer(index, &self.entries).map_err(io::Error::from)?;
+        Ok(info)
+    }
+
+    /// All replays, most recent first.
+    pub fn list(&self) -> Vec<ReplayInfo> {
+        self.entries.iter().rev().cloned().collect()
+    }
+
+    /// Load a replay by its file name. Only files in the index can be loaded.
+    pub fn load(&self, file: &str) -> Result<Replay, GeneralsError> {
+        if !self.entries.iter().any(|e| e.file == file) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no such replay").into());
+        }
+        Replay::load(self.dir.join(file))
+    }
+}
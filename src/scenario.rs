@@ -0,0 +1,263 @@
+//! Puzzles: a hand made position, and an objective to reach from it.
+//!
+//! Scenarios are text files. Lines starting with `#` are comments, and the map comes last:
+//!
+//! ```text
+//! title First strike
+//! description The enemy King is close, and barely defended.
+//! objective capture-king 40
+//! bot idle
+//! map
+//! K0:10 .  .    M
+//! .     M  C1:2 K1:4
+//! ```
+//!
+//! Cells are `.` for open land, `M` for mountains, `F:n` for neutral fortresses, and `Ft:n`,
+//! `Kt:n` and `Ct:n` for fortresses, Kings and captured cells of team `t` with `n` units. The
+//! player is always team 0, and the other teams are played by the `bot` lines in order, or by
+//! idle bots.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use bot::{self, BotPlayer};
+use generals::*;
+
+/// The scenarios that come with the game, by name.
+pub const BUNDLED: &[(&str, &str)] = &[
+    ("first-strike", include_str!("../scenarios/first-strike.txt")),
+    ("hold-the-pass", include_str!("../scenarios/hold-the-pass.txt")),
+    ("land-grab", include_str!("../scenarios/land-grab.txt")),
+    ("last-stand", include_str!("../scenarios/last-stand.txt")),
+    ("tutorial", include_str!("../scenarios/tutorial.txt")),
+];
+
+/// What the player has to do, and how long they have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    /// Take the King of any other team within `ticks` ticks.
+    CaptureKing { ticks: usize },
+    /// Still be in the game after `ticks` ticks.
+    Survive { ticks: usize },
+    /// Own the cell at `pos` within `ticks` ticks.
+    Capture { pos: Position, ticks: usize },
+    /// Own `cells` cells within `ticks` ticks.
+    Land { cells: usize, ticks: usize },
+}
+
+impl Objective {
+    fn parse(words: &[&str]) -> Option<Objective> {
+        let n = |i: usize| words.get(i).and_then(|w| w.parse::<usize>().ok());
+        match words.first().cloned() {
+            Some("capture-king") => Some(Objective::CaptureKing { ticks: n(1)? }),
+            Some("survive") => Some(Objective::Survive { ticks: n(1)? }),
+            Some("capture") => {
+                let pos = Position(n(1)? as i32, n(2)? as i32);
+                Some(Objective::Capture { pos, ticks: n(3)? })
+            }
+            Some("land") => Some(Objective::Land { cells: n(1)?, ticks: n(2)? }),
+            _ => None,
+        }
+    }
+
+    fn ticks(&self) -> usize {
+        match *self {
+            Objective::CaptureKing { ticks } |
+            Objective::Survive { ticks } |
+            Objective::Capture { ticks, .. } |
+            Objective::Land { ticks, .. } => ticks,
+        }
+    }
+
+    /// What to do, and how long is left of it at `tick`.
+    pub fn describe(&self, tick: usize) -> String {
+        let left = self.ticks().saturating_sub(tick);
+        match *self {
+            Objective::CaptureKing { .. } => format!("Take an enemy King: {} ticks left", left),
+            Objective::Survive { .. } => format!("Survive for {} more ticks", left),
+            Objective::Capture { pos, .. } => format!("Take {}: {} ticks left", pos, left),
+            Objective::Land { cells, .. } => format!("Own {} cells: {} ticks left", cells, left),
+        }
+    }
+
+    /// Whether team 0 reached the objective, failed it, or neither yet.
+    fn verdict(&self, game: &GameState) -> Option<bool> {
+        const PLAYER: Team = 0;
+        if game.player_states[PLAYER].dead {
+            return Some(false);
+        }
+        let reached = match *self {
+            Objective::CaptureKing { .. } => {
+                game.player_states.iter().any(|p| p.team != PLAYER && p.dead)
+            }
+            Objective::Survive { ticks } => game.tick_number >= ticks,
+            Objective::Capture { pos, .. } => {
+                game.board.try_get(pos.0, pos.1).and_then(|c| c.owner()) == Some(PLAYER)
+            }
+            Objective::Land { cells, .. } => game.scores()[PLAYER].land >= cells,
+        };
+        if reached {
+            Some(true)
+        } else if game.tick_number >= self.ticks() {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+/// Ends the game when the objective is reached, with the player as the winner, or failed, with
+/// the first other team still in the game as the winner.
+impl VictoryCondition for Objective {
+    fn check(&mut self, game: &GameState) -> Option<Verdict> {
+        match self.verdict(game)? {
+            true => Some(Verdict::Winner(0)),
+            false => {
+                let rival = game.player_states.iter().find(|p| p.team != 0 && !p.dead);
+                Some(rival.map(|p| Verdict::Winner(p.team)).unwrap_or(Verdict::Draw))
+            }
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn VictoryCondition> {
+        Box::new(*self)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub title: String,
+    pub description: String,
+    pub objective: Objective,
+    /// The bots playing teams 1 and up, in order.
+    pub bots: Vec<String>,
+    pub board: Board,
+    pub num_players: usize,
+}
+
+fn parse_cell(word: &str) -> Option<Cell> {
+    if word == "." {
+        return Some(Cell::Open);
+    }
+    if word == "M" {
+        return Some(Cell::Mountain);
+    }
+    let mut parts = word.get(1..)?.splitn(2, ':');
+    let team = parts.next()?;
+    let units = parts.next()?.parse::<usize>().ok()?;
+    let team = if team.is_empty() { None } else { Some(team.parse::<Team>().ok()?) };
+    match (word.get(..1)?, team) {
+        ("F", team) => Some(Cell::Fortress(team, units)),
+        ("K", Some(team)) => Some(Cell::King(team, units)),
+        ("C", Some(team)) => Some(Cell::Captured(team, units)),
+        _ => None,
+    }
+}
+
+impl Scenario {
+    /// A bundled scenario by its name, or else a scenario file at `name`.
+    pub fn find(name: &str) -> Result<Self, String> {
+        if let Some(&(_, text)) = BUNDLED.iter().find(|&&(n, _)| n == name) {
+            return Scenario::parse(text).map_err(|e| format!("{}: {}", name, e));
+        }
+        Scenario::load(name).map_err(|e| {
+            let names = BUNDLED.iter().map(|&(n, _)| n).collect::<Vec<_>>();
+            format!("{}, and there is no bundled scenario by that name in {:?}", e, names)
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Scenario::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut title = String::new();
+        let mut description = String::new();
+        let mut objective = None;
+        let mut bots = Vec::new();
+        let mut lines = text.lines().enumerate();
+        for (i, line) in &mut lines {
+            let line = line.split('#').next().unwrap().trim();
+            let (key, rest) = match line.find(' ') {
+                Some(at) => (&line[..at], line[at..].trim()),
+                None => (line, ""),
+            };
+            match key {
+                "" => {}
+                "title" => title = rest.to_string(),
+                "description" => description = rest.to_string(),
+                "objective" => {
+                    let words = rest.split_whitespace().collect::<Vec<_>>();
+                    objective = Some(Objective::parse(&words).ok_or_else(|| {
+                        format!("line {}: expected 'objective capture-king T', 'survive T', \
+                                 'capture X Y T' or 'land N T'",
+                                i + 1)
+                    })?);
+                }
+                "bot" if bot::NAMES.contains(&rest) => bots.push(rest.to_string()),
+                "bot" => return Err(format!("line {}: unknown bot '{}'", i + 1, rest)),
+                "map" => break,
+                _ => return Err(format!("line {}: unknown key '{}'", i + 1, key)),
+            }
+        }
+        let objective = objective.ok_or("the scenario has no objective")?;
+
+        let mut width = 0;
+        let mut cells = Vec::new();
+        for (i, line) in lines {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let row = line.split_whitespace()
+                .map(|w| parse_cell(w).ok_or_else(|| format!("line {}: bad cell '{}'", i + 1, w)))
+                .collect::<Result<Vec<_>, _>>()?;
+            if width != 0 && row.len() != width {
+                return Err(format!("line {}: rows need {} cells, not {}", i + 1, width, row.len()));
+            }
+            width = row.len();
+            cells.extend(row);
+        }
+        let kings = cells.iter()
+            .filter_map(|cell| match *cell {
+                Cell::King(team, _) => Some(team),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let num_players = kings.len();
+        if num_players < 2 || (0..num_players).any(|team| !kings.contains(&team)) {
+            return Err("the map needs one King for each of teams 0, 1 and so on, and at least \
+                        two of them"
+                .to_string());
+        }
+        let board = Board::from_cells(width, cells).map_err(|e| e.to_string())?;
+        board.check(num_players).map_err(|e| e.to_string())?;
+        Ok(Scenario {
+            title,
+            description,
+            objective,
+            bots,
+            board,
+            num_players,
+        })
+    }
+
+    /// A new game of the scenario, with bots for the other teams.
+    pub fn start(&self) -> (GameState, Vec<BotPlayer>) {
+        let mut game = GameState::new(self.board.clone(), self.num_players);
+        game.add_victory_condition(self.objective);
+        let bots = (1..self.num_players)
+            .map(|team| {
+                let name = self.bots.get(team - 1).map(|n| n.as_str()).unwrap_or("idle");
+                BotPlayer::new(team, bot::seeded(name, team as u32).unwrap())
+            })
+            .collect();
+        (game, bots)
+    }
+}
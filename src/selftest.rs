@@ -0,0 +1,152 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use ggez::graphics::Font;
+use image;
+
+use generals::*;
+use generals::bot::{self, BotPlayer};
+use generals::replay::Replay;
+use generals::sim;
+
+use music;
+use sound;
+use ui::sprites;
+
+const SEED: u32 = 1337;
+const BOARD_SIZE: usize = 32;
+const TICKS: usize = 300;
+const BOTS: [&str; 2] = ["greedy", "random"];
+/// The `GameState::hash` of the seeded game after `TICKS` ticks. Builds for other platforms,
+/// or with other compiler and dependency versions, have to end up with the same one.
+const EXPECTED_HASH: u64 = 0x1478_e530_d577_581e;
+
+/// The starting state and the bots of a short seeded game.
+fn seeded_game() -> (GameState, Vec<BotPlayer>) {
+    let settings = MapSettings {
+        size: Some(BOARD_SIZE),
+        ..MapSettings::default()
+    };
+    let board = Board::generate(&settings, BOTS.len(), &mut seeded_rng(SEED));
+    let bots = BOTS.iter()
+        .enumerate()
+        .map(|(team, name)| BotPlayer::new(team, bot::seeded(name, SEED + team as u32).unwrap()))
+        .collect::<Vec<_>>();
+    (GameState::new(board, BOTS.len()), bots)
+}
+
+/// Play a short seeded game between bots.
+fn play_seeded() -> (Replay, GameState) {
+    let (mut game, mut bots) = seeded_game();
+    let mut replay = Replay::new(&game);
+    sim::play(&mut game, &mut bots, TICKS, Some(&mut replay));
+    (replay, game)
+}
+
+/// The same seed should give the same game as on the build the hash was pinned on, and
+/// saving and playing back its replay should end up in the same state.
+fn check_determinism() -> Result<(), String> {
+    let (replay, game) = play_seeded();
+    let (_, again) = play_seeded();
+    if game.hash() != again.hash() {
+        return Err(format!("two seeded games ended with hashes {:x} and {:x}",
+                           game.hash(),
+                           again.hash()));
+    }
+    if game.hash() != EXPECTED_HASH {
+        return Err(format!("the seeded game ended with hash {:016x}, but it should be {:016x}",
+                           game.hash(),
+                           EXPECTED_HASH));
+    }
+
+    let path = env::temp_dir().join("generals-self-test.json");
+    replay.save(&path).map_err(|e| format!("saving replay: {}", e))?;
+    let loaded = Replay::load(&path).map_err(|e| format!("loading replay: {}", e));
+    let _ = fs::remove_file(&path);
+    let replayed = loaded?.state_at(replay.len());
+    if replayed.hash() != game.hash() {
+        return Err(format!("the game ended with hash {:x}, but its replay with {:x}",
+                           game.hash(),
+                           replayed.hash()));
+    }
+    Ok(())
+}
+
+/// A copy of the game that only gets the move queues should stay in sync with it.
+fn check_lockstep() -> Result<(), String> {
+    let (mut game, mut bots) = seeded_game();
+    sim::play_lockstep(&mut game, &mut bots, TICKS)
+        .map(|_| ())
+        .map_err(|d| format!("out of sync after tick {} in {:?}", d.tick, d.cells))
+}
+
+/// The resources directory ggez loads from: `resources` in the crate when run by cargo, or
+/// next to the executable.
+fn resources_dir() -> Option<PathBuf> {
+    let manifest = env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from);
+    let exe = env::current_exe().ok().and_then(|e| e.parent().map(|p| p.to_path_buf()));
+    manifest.into_iter().chain(exe).map(|dir| dir.join("resources")).find(|dir| dir.is_dir())
+}
+
+/// Every sprite, sound and music track in the resources directory has to load. Missing ones
+/// are fine, since the game does without them.
+fn check_assets() -> Result<(), String> {
+    Font::default_font().map_err(|e| format!("loading the default font: {:?}", e))?;
+    let dir = match resources_dir() {
+        Some(dir) => dir,
+        None => {
+            println!("assets: no resources directory, only the built in font was loaded");
+            return Ok(());
+        }
+    };
+    let images = sprites::ALL.iter().map(|s| s.path());
+    let sounds = sound::ALL
+        .iter()
+        .map(|s| s.path())
+        .chain(music::PLAYLISTS.iter().flat_map(|prefix| {
+            let dir = dir.clone();
+            (1..)
+                .map(move |n| music::track_path(prefix, n))
+                .take_while(move |path| dir.join(&path[1..]).is_file())
+        }));
+    let files = images.map(|p| (p, true)).chain(sounds.map(|p| (p, false)));
+    let mut loaded = 0;
+    for (path, is_image) in files {
+        // Paths in the resources directory start with a slash.
+        let file = dir.join(&path[1..]);
+        if !file.is_file() {
+            continue;
+        }
+        let bytes = fs::read(&file).map_err(|e| format!("reading {}: {}", file.display(), e))?;
+        if is_image {
+            image::load_from_memory(&bytes)
+                .map_err(|e| format!("loading {}: {}", file.display(), e))?;
+        } else if !bytes.starts_with(b"OggS") {
+            return Err(format!("{} is not an Ogg file", file.display()));
+        }
+        loaded += 1;
+    }
+    println!("assets: loaded {} files from {}", loaded, dir.display());
+    Ok(())
+}
+
+/// Run all checks, printing the results. Returns `true` if everything passed.
+pub fn run() -> bool {
+    let checks: [(&str, fn() -> Result<(), String>); 3] = [
+        ("determinism", check_determinism),
+        ("lockstep", check_lockstep),
+        ("assets", check_assets),
+    ];
+    let mut ok = true;
+    for &(name, check) in checks.iter() {
+        match check() {
+            Ok(()) => println!("{}: ok", name),
+            Err(e) => {
+                println!("{}: FAILED: {}", name, e);
+                ok = false;
+            }
+        }
+    }
+    ok
+}
@@ -0,0 +1,52 @@
+//! A dedicated game server without graphics, for hosting games on a machine without a display.
+//!
+//! ```text
+//! generals-server [--bind addr] [--tick-ms N] [--grace-secs N] [--replays dir]
+//!                 [--ratings file] [--log-events file] [--no-console] [--search-ms N]
+//! ```
+
+extern crate generals;
+
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use generals::eventlog;
+use generals::net::{self, Server, ServerConfig};
+use generals::sim::{self, parse_flag};
+
+fn run(args: &[String]) -> Result<(), String> {
+    let defaults = ServerConfig::default();
+    let addr = parse_flag(args, "--bind", format!("0.0.0.0:{}", net::DEFAULT_PORT))?;
+    let tick_ms = parse_flag(args, "--tick-ms", 500u64)?;
+    let grace_secs = parse_flag(args, "--grace-secs", defaults.reconnect_grace.as_secs())?;
+    let replays = parse_flag(args, "--replays", "replays".to_string())?;
+    let ratings = parse_flag(args, "--ratings", "ratings.json".to_string())?;
+    let console = !args.iter().any(|a| a == "--no-console");
+    sim::configure_search(args)?;
+    let log = parse_flag(args, "--log-events", String::new())?;
+    if !log.is_empty() {
+        eventlog::enable(&log).map_err(|e| format!("Failed to create {}: {}", log, e))?;
+    }
+    let config = ServerConfig {
+        tick_interval: Duration::from_millis(tick_ms),
+        reconnect_grace: Duration::from_secs(grace_secs),
+        replay_dir: Some(PathBuf::from(replays)),
+        ratings_path: Some(PathBuf::from(ratings)),
+        console,
+    };
+    let listener = TcpListener::bind(&addr).map_err(|e| format!("Failed to bind {}: {}", addr, e))?;
+    println!("Listening on {}", addr);
+    if console {
+        println!("Type 'help' for admin commands");
+    }
+    Server::new(listener, config).run().map_err(|e| format!("Server stopped: {}", e))
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    if let Err(e) = run(&args) {
+        println!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,351 @@
+use serde_json;
+
+use bot::{self, Bot, BotPlayer, Script};
+use eventlog;
+use generals::*;
+use generals::victory::LandThreshold;
+use replay::{Frame, Replay};
+
+/// Games still running after this many ticks are called a draw.
+pub const DEFAULT_MAX_TICKS: usize = 5000;
+
+/// The outcome of a single game.
+#[derive(Debug, Clone, Copy)]
+pub struct MatchResult {
+    /// `None` if the game hit the tick limit.
+    pub winner: Option<Team>,
+    pub ticks: usize,
+}
+
+/// Play `game` with `bots` until it is over, or `max_ticks` is reached. If `replay` is given,
+/// every tick is recorded to it.
+pub fn play(game: &mut GameState, bots: &mut [BotPlayer], max_ticks: usize,
+            mut replay: Option<&mut Replay>) -> MatchResult {
+    while game.tick_number < max_ticks && !game.is_over() {
+        for bot in bots.iter_mut() {
+            bot.act(game);
+        }
+        if let Some(ref mut replay) = replay {
+            replay.record(game);
+        }
+        game.tick();
+    }
+    MatchResult {
+        winner: game.winner(),
+        ticks: game.tick_number,
+    }
+}
+
+/// The first tick after which two games that should be in sync were not.
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub tick: usize,
+    /// The hash of the game the bots played, and of the copy that was only given their moves.
+    pub hashes: (u64, u64),
+    /// The cells that differ. Empty if only the players do.
+    pub cells: Vec<Position>,
+}
+
+/// Like `play`, but with a second copy of the game that is only given the move queues of the
+/// first, sent through JSON like a replay or a network message would be. The games are
+/// compared after every tick, and the first difference is returned as an error.
+pub fn play_lockstep(game: &mut GameState, bots: &mut [BotPlayer], max_ticks: usize)
+    -> Result<MatchResult, Divergence> {
+    let mut copy = game.clone();
+    while game.tick_number < max_ticks && !game.is_over() {
+        for bot in bots.iter_mut() {
+            bot.act(game);
+        }
+        let sent = serde_json::to_string(&Frame::new(game)).expect("frames can be serialized");
+        let received: Frame = serde_json::from_str(&sent).expect("frames can be read back");
+        received.restore(&mut copy);
+        game.tick();
+        copy.tick();
+        let hashes = (game.hash(), copy.hash());
+        if hashes.0 != hashes.1 {
+            let cells = game.board
+                .iter()
+                .zip(copy.board.cells())
+                .filter(|&((_, a), b)| a != b)
+                .map(|((pos, _), _)| pos)
+                .collect();
+            return Err(Divergence {
+                tick: game.tick_number,
+                hashes,
+                cells,
+            });
+        }
+    }
+    Ok(MatchResult {
+        winner: game.winner(),
+        ticks: game.tick_number,
+    })
+}
+
+/// Play a seeded game of `mode` between the named bots, where bot `i` plays team `i`.
+pub fn run_match(names: &[&str],
+                 settings: &MapSettings,
+                 mode: GameMode,
+                 seed: u32,
+                 max_ticks: usize)
+    -> Result<MatchResult, String> {
+    let board = Board::generate(settings, names.len(), &mut seeded_rng(seed));
+    let mut game = GameState::with_mode(board, names.len(), mode);
+    eventlog::attach(&mut game);
+    let mut bots = Vec::new();
+    for (team, name) in names.iter().enumerate() {
+        let bot = bot::seeded(name, seed.wrapping_add(team as u32))
+            .ok_or_else(|| format!("Unknown bot '{}', try one of {:?}", name, bot::NAMES))?;
+        bots.push(BotPlayer::new(team, bot));
+    }
+    Ok(play(&mut game, &mut bots, max_ticks, None))
+}
+
+/// The value following `flag` in `args`, or `default` if the flag is not there.
+pub fn parse_flag<T>(args: &[String], flag: &str, default: T) -> Result<T, String>
+where
+    T: ::std::str::FromStr,
+{
+    match args.iter().position(|a| a == flag) {
+        Some(i) => {
+            args.get(i + 1)
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| format!("{} needs a valid value", flag))
+        }
+        None => Ok(default),
+    }
+}
+
+/// Set the budgets of the search bot from `--search-ms N`, how long unseeded ones think per
+/// move, and `--search-rollouts N`, how many rollouts seeded ones play per move.
+pub fn configure_search(args: &[String]) -> Result<(), String> {
+    bot::set_search_time(parse_flag(args, "--search-ms", bot::DEFAULT_SEARCH_MS)?);
+    bot::set_search_rollouts(parse_flag(args, "--search-rollouts", bot::DEFAULT_SEARCH_ROLLOUTS)?);
+    Ok(())
+}
+
+/// The game mode from `--mode`, and `--hold-ticks` or `--tick-limit` for the modes that use
+/// them. Without `--mode` the game is classic.
+pub fn parse_mode(args: &[String]) -> Result<GameMode, String> {
+    let name = parse_flag(args, "--mode", "classic".to_string())?;
+    match GameMode::parse(&name) {
+        Some(GameMode::KingOfTheHill { .. }) => {
+            let hold_ticks = parse_flag(args, "--hold-ticks", DEFAULT_HOLD_TICKS)?;
+            if hold_ticks == 0 {
+                return Err("--hold-ticks needs to be at least 1".to_string());
+            }
+            Ok(GameMode::KingOfTheHill { hold_ticks })
+        }
+        Some(GameMode::Blitz { .. }) => {
+            let ticks = parse_flag(args, "--tick-limit", DEFAULT_TICK_LIMIT)?;
+            if ticks == 0 {
+                return Err("--tick-limit needs to be at least 1".to_string());
+            }
+            Ok(GameMode::Blitz { ticks })
+        }
+        Some(mode) => Ok(mode),
+        None => Err(format!("Unknown mode '{}', try classic, koth, blitz or horde", name)),
+    }
+}
+
+/// Every `--handicap TEAM:SPEC` in `args`, where `SPEC` is parsed by `Handicap::from_str`,
+/// for a game of `num_players`.
+pub fn parse_handicaps(args: &[String], num_players: usize)
+    -> Result<Vec<(Team, Handicap)>, String> {
+    let mut handicaps = Vec::new();
+    for (i, _) in args.iter().enumerate().filter(|&(_, a)| a == "--handicap") {
+        let arg = args.get(i + 1).ok_or("--handicap needs TEAM:SPEC")?;
+        let mut parts = arg.splitn(2, ':');
+        let team = parts.next().and_then(|t| t.parse::<usize>().ok());
+        match (team, parts.next()) {
+            (Some(team), Some(spec)) if team < num_players => {
+                handicaps.push((team, spec.parse::<Handicap>()?));
+            }
+            _ => {
+                return Err(format!("--handicap needs TEAM:SPEC with a valid team, not {}", arg))
+            }
+        }
+    }
+    Ok(handicaps)
+}
+
+/// `--simulate <bot> <bot> [--games N] [--size N] [--seed N] [--max-ticks N] [--mode M]`
+///
+/// Play games between two bots without graphics, and print how they did. The bots switch teams
+/// every other game, since team 0 gets to move first.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let i = args.iter()
+        .position(|a| a == "--simulate")
+        .ok_or("missing --simulate")?;
+    let names = match (args.get(i + 1), args.get(i + 2)) {
+        (Some(a), Some(b)) => [a.as_str(), b.as_str()],
+        _ => return Err("--simulate needs two bot names".to_string()),
+    };
+    let games = parse_flag(args, "--games", 10)?;
+    let seed = parse_flag(args, "--seed", 0u32)?;
+    let max_ticks = parse_flag(args, "--max-ticks", DEFAULT_MAX_TICKS)?;
+    let mode = parse_mode(args)?;
+    let settings = MapSettings {
+        size: parse_flag(args, "--size", 0usize).map(|s| if s == 0 { None } else { Some(s) })?,
+        ..MapSettings::default()
+    };
+
+    let mut wins = [0; 2];
+    let mut draws = 0;
+    let mut total_ticks = 0;
+    for game in 0..games {
+        let swap = game % 2 == 1;
+        let order = if swap { [names[1], names[0]] } else { names };
+        let seed = seed.wrapping_add(game as u32);
+        let result = run_match(&order, &settings, mode, seed, max_ticks)?;
+        total_ticks += result.ticks;
+        match result.winner {
+            Some(team) => wins[if swap { 1 - team } else { team }] += 1,
+            None => draws += 1,
+        }
+    }
+
+    println!("{} vs {}, {} games of {}", names[0], names[1], games, mode.name());
+    for (name, wins) in names.iter().zip(wins.iter()) {
+        println!("{}: {} wins ({:.1}%)",
+                 name,
+                 wins,
+                 100.0 * *wins as f64 / games.max(1) as f64);
+    }
+    println!("draws: {}", draws);
+    println!("average game length: {:.1} ticks",
+             total_ticks as f64 / games.max(1) as f64);
+    Ok(())
+}
+
+/// `--headless [--players N] [--bots a,b,..] [--script TEAM:FILE].. [--size N] [--seed N]
+/// [--ticks N] [--lockstep] [--mode M] [--hold-ticks N] [--tick-limit N] [--land-share F]
+/// [--handicap TEAM:SPEC].. [--transfer-land]`
+///
+/// Play one game as fast as possible and print how it ended, for checking balance and rule
+/// changes. Team `i` is played by the `i`th bot in `--bots`, or the default bot if the list is
+/// short, unless a `--script` gives its moves. The final hash of the game is printed too, so
+/// that two runs can be compared.
+///
+/// With `--lockstep`, the game is played with `play_lockstep`, and the mode fails on the first
+/// tick where the copy of the game goes out of sync. With `--land-share`, the first player to
+/// own that share of the land wins, on top of the rules of the mode. With `--transfer-land`,
+/// taking a King takes all the land of its player too.
+pub fn run_headless(args: &[String]) -> Result<(), String> {
+    let num_players = parse_flag(args, "--players", 2usize)?;
+    let bots = parse_flag(args, "--bots", String::new())?;
+    let seed = parse_flag(args, "--seed", 0u32)?;
+    let max_ticks = parse_flag(args, "--ticks", DEFAULT_MAX_TICKS)?;
+    let mode = parse_mode(args)?;
+    let settings = MapSettings {
+        size: parse_flag(args, "--size", 0usize).map(|s| if s == 0 { None } else { Some(s) })?,
+        ..MapSettings::default()
+    };
+    if num_players < 2 {
+        return Err("--players needs to be at least 2".to_string());
+    }
+
+    let mut names = bots.split(',')
+        .filter(|n| !n.is_empty())
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>();
+    if names.len() > num_players {
+        return Err(format!("{} bots for {} players", names.len(), num_players));
+    }
+    names.resize(num_players, bot::DEFAULT_BOT.to_string());
+    let mut players = names.into_iter()
+        .enumerate()
+        .map(|(team, name)| {
+            let bot = bot::seeded(&name, seed.wrapping_add(team as u32))
+                .ok_or_else(|| format!("Unknown bot '{}', try one of {:?}", name, bot::NAMES))?;
+            Ok((name, bot))
+        })
+        .collect::<Result<Vec<(String, Box<dyn Bot>)>, String>>()?;
+    for (i, _) in args.iter().enumerate().filter(|&(_, a)| a == "--script") {
+        let arg = args.get(i + 1).ok_or("--script needs TEAM:FILE")?;
+        let mut parts = arg.splitn(2, ':');
+        let team = parts.next().and_then(|t| t.parse::<usize>().ok());
+        match (team, parts.next()) {
+            (Some(team), Some(path)) if team < players.len() => {
+                players[team] = (path.to_string(), Box::new(Script::load(path)?));
+            }
+            _ => return Err(format!("--script needs TEAM:FILE with a valid team, not {}", arg)),
+        }
+    }
+
+    let board = Board::generate(&settings, players.len(), &mut seeded_rng(seed));
+    let (w, h) = board.dimens();
+    let mut game = GameState::with_mode(board, players.len(), mode);
+    game.transfer_on_elimination = args.iter().any(|a| a == "--transfer-land");
+    eventlog::attach(&mut game);
+    if args.iter().any(|a| a == "--land-share") {
+        let share = parse_flag(args, "--land-share", 1.0f64)?;
+        if share <= 0.0 || share > 1.0 {
+            return Err("--land-share needs to be above 0 and at most 1".to_string());
+        }
+        game.add_victory_condition(LandThreshold { share });
+    }
+    for (team, handicap) in parse_handicaps(args, players.len())? {
+        game.set_handicap(team, handicap).map_err(|e| e.to_string())?;
+    }
+    let (names, bots): (Vec<_>, Vec<_>) = players.into_iter().unzip();
+    let mut bots = bots.into_iter()
+        .enumerate()
+        .map(|(team, bot)| BotPlayer::new(team, bot))
+        .collect::<Vec<_>>();
+    let result = if args.iter().any(|a| a == "--lockstep") {
+        play_lockstep(&mut game, &mut bots, max_ticks).map_err(|d| {
+            format!("out of sync after tick {}: hashes {:016x} and {:016x}, {} cells differ {:?}",
+                    d.tick,
+                    d.hashes.0,
+                    d.hashes.1,
+                    d.cells.len(),
+                    d.cells)
+        })?
+    } else {
+        play(&mut game, &mut bots, max_ticks, None)
+    };
+
+    println!("seed {}, {} players, {}x{}, {}", seed, names.len(), w, h, mode.name());
+    match result.winner {
+        Some(team) => {
+            println!("winner: team {} ({}) after {} ticks", team, names[team], result.ticks)
+        }
+        None => println!("no winner after {} ticks", result.ticks),
+    }
+    for (score, name) in game.scores().iter().zip(names.iter()) {
+        println!("team {} ({}): army {}, land {}, cities {}{}",
+                 score.team,
+                 name,
+                 score.army,
+                 score.land,
+                 score.cities,
+                 if score.dead { ", dead" } else { "" });
+    }
+    println!("hash: {:016x}", game.hash());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A seeded 2-player game between greedy bots.
+    fn setup() -> (GameState, Vec<BotPlayer>) {
+        let board = Board::generate(&MapSettings::default(), 2, &mut seeded_rng(3));
+        let bots = (0..2)
+            .map(|team| BotPlayer::new(team, bot::seeded("greedy", team as u32).unwrap()))
+            .collect();
+        (GameState::new(board, 2), bots)
+    }
+
+    #[test]
+    fn a_copy_given_only_the_queues_stays_in_sync() {
+        let (mut game, mut bots) = setup();
+        let lockstep = play_lockstep(&mut game, &mut bots, 300).unwrap();
+        let (mut plain, mut bots) = setup();
+        let result = play(&mut plain, &mut bots, 300, None);
+        assert_eq!((lockstep.winner, lockstep.ticks), (result.winner, result.ticks));
+        assert_eq!(game.hash(), plain.hash());
+    }
+}
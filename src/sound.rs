@@ -0,0 +1,120 @@
+//! Sound effects for what happens in the game. They are loaded from the `sounds` folder of the
+//! ggez resources directory; any that are missing are silent.
+
+use std::time::{Duration, Instant};
+
+use ggez::Context;
+use ggez::audio::Source;
+
+use generals::{GameEvent, Team};
+
+const DIR: &str = "/sounds";
+/// The alarm plays at most this often, so that a long attack doesn't drown out everything else.
+const ALARM_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy)]
+pub enum Sound {
+    /// One of our moves was executed.
+    Move,
+    CityCaptured,
+    KingCaptured,
+    Victory,
+    Defeat,
+    /// An enemy took one of our cells.
+    UnderAttack,
+}
+
+pub const ALL: [Sound; 6] = [Sound::Move,
+                         Sound::CityCaptured,
+                         Sound::KingCaptured,
+                         Sound::Victory,
+                         Sound::Defeat,
+                         Sound::UnderAttack];
+
+impl Sound {
+    fn file_name(&self) -> &'static str {
+        match *self {
+            Sound::Move => "move.ogg",
+            Sound::CityCaptured => "city.ogg",
+            Sound::KingCaptured => "king.ogg",
+            Sound::Victory => "victory.ogg",
+            Sound::Defeat => "defeat.ogg",
+            Sound::UnderAttack => "alarm.ogg",
+        }
+    }
+
+    /// Where the sound is in the resources directory.
+    pub fn path(&self) -> String {
+        format!("{}/{}", DIR, self.file_name())
+    }
+}
+
+pub struct Sounds {
+    /// Indexed by `Sound`.
+    sources: Vec<Option<Source>>,
+    /// From 0 to 1.
+    volume: f32,
+    muted: bool,
+    last_alarm: Option<Instant>,
+}
+
+impl Sounds {
+    /// Load every sound that exists.
+    pub fn load(ctx: &mut Context, volume: f32) -> Self {
+        let sources = ALL.iter()
+            .map(|sound| Source::new(ctx, sound.path()).ok())
+            .collect::<Vec<_>>();
+        let missing = sources.iter().filter(|s| s.is_none()).count();
+        if missing > 0 {
+            println!("{} of {} sounds are missing", missing, sources.len());
+        }
+        Sounds {
+            sources,
+            volume,
+            muted: false,
+            last_alarm: None,
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+    }
+
+    /// Mute or unmute, and return whether we are now muted.
+    pub fn toggle_mute(&mut self) -> bool {
+        self.muted = !self.muted;
+        self.muted
+    }
+
+    pub fn play(&mut self, sound: Sound) {
+        if self.muted || self.volume <= 0.0 {
+            return;
+        }
+        if let Some(ref mut source) = self.sources[sound as usize] {
+            source.set_volume(self.volume);
+            if let Err(e) = source.play() {
+                println!("Failed to play {:?}: {}", sound, e);
+            }
+        }
+    }
+
+    /// Play whatever `event` sounds like to team `us`.
+    pub fn event(&mut self, event: &GameEvent, us: Team) {
+        match *event {
+            GameEvent::Moved { team, .. } if team == us => self.play(Sound::Move),
+            GameEvent::CityCaptured { team, .. } if team == us => self.play(Sound::CityCaptured),
+            GameEvent::KingCaptured { team, victim, .. } if team == us || victim == us => {
+                self.play(Sound::KingCaptured)
+            }
+            GameEvent::Captured { from: Some(from), .. } |
+            GameEvent::CityCaptured { from: Some(from), .. } if from == us => {
+                let due = self.last_alarm.map(|t| t.elapsed() >= ALARM_INTERVAL).unwrap_or(true);
+                if due {
+                    self.last_alarm = Some(Instant::now());
+                    self.play(Sound::UnderAttack);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Statistics of a game over time: the scores of every tick, and running totals of what each
+//! team did. The client shows them as graphs when a game ends.
+
+use generals::{GameEvent, GameState, Score, Team};
+
+/// What a team did over the whole game.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Totals {
+    /// Fortresses taken from neutrals or other players. Kings don't count.
+    pub cities_taken: usize,
+    /// Kings taken.
+    pub kills: usize,
+    /// Moves that were carried out.
+    pub moves: usize,
+}
+
+/// The scores of every tick of a game, sampled after each tick.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct History {
+    /// The scores after each tick, indexed by team.
+    samples: Vec<Vec<Score>>,
+    /// Indexed by team.
+    totals: Vec<Totals>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History::default()
+    }
+
+    /// Sample the scores of `game`, right after a tick that produced `events`.
+    pub fn record(&mut self, game: &GameState, events: &[GameEvent]) {
+        self.push(game.scores(), events);
+    }
+
+    /// Add the scores of the next tick, and count what happened in `events`. Games that are
+    /// only seen through player views have scores but no events.
+    pub fn push(&mut self, scores: Vec<Score>, events: &[GameEvent]) {
+        if self.totals.len() < scores.len() {
+            self.totals.resize(scores.len(), Totals::default());
+        }
+        for event in events {
+            match *event {
+                GameEvent::Moved { team, .. } => self.totals[team].moves += 1,
+                GameEvent::CityCaptured { team, .. } => self.totals[team].cities_taken += 1,
+                GameEvent::KingCaptured { team, .. } => self.totals[team].kills += 1,
+                GameEvent::Captured { .. } => {}
+            }
+        }
+        self.samples.push(scores);
+    }
+
+    /// The number of ticks sampled.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The scores of every tick, oldest first.
+    pub fn samples(&self) -> &[Vec<Score>] {
+        &self.samples
+    }
+
+    /// The army of `team` after every tick.
+    pub fn army(&self, team: Team) -> Vec<usize> {
+        self.series(team, self.len(), |s| s.army)
+    }
+
+    /// The land of `team` after every tick.
+    pub fn land(&self, team: Team) -> Vec<usize> {
+        self.series(team, self.len(), |s| s.land)
+    }
+
+    /// The army of `team` in the last `ticks` ticks.
+    pub fn recent_army(&self, team: Team, ticks: usize) -> Vec<usize> {
+        self.series(team, ticks, |s| s.army)
+    }
+
+    /// The land of `team` in the last `ticks` ticks.
+    pub fn recent_land(&self, team: Team, ticks: usize) -> Vec<usize> {
+        self.series(team, ticks, |s| s.land)
+    }
+
+    fn series<F: Fn(&Score) -> usize>(&self, team: Team, ticks: usize, stat: F) -> Vec<usize> {
+        self.samples[self.len().saturating_sub(ticks)..]
+            .iter()
+            .map(|scores| scores.get(team).map(&stat).unwrap_or(0))
+            .collect()
+    }
+
+    pub fn totals(&self, team: Team) -> Totals {
+        self.totals.get(team).cloned().unwrap_or_default()
+    }
+}
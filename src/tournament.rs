@@ -0,0 +1,153 @@
+use std::fs::File;
+use std::io::Write;
+
+use bot;
+use generals::MapSettings;
+use rating;
+use sim::{self, parse_flag, parse_mode};
+
+const DEFAULT_REPORT: &str = "tournament.txt";
+
+/// How one bot did against another.
+#[derive(Debug, Clone, Copy, Default)]
+struct Record {
+    wins: usize,
+    losses: usize,
+    draws: usize,
+}
+
+impl Record {
+    fn add(&mut self, score: f64) {
+        if score == 1.0 {
+            self.wins += 1;
+        } else if score == 0.0 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+}
+
+struct Standing {
+    name: &'static str,
+    rating: f64,
+    record: Record,
+}
+
+/// `--tournament [--maps N] [--size N] [--seed N] [--max-ticks N] [--mode M] [--out FILE]`
+///
+/// Play every registered bot against every other bot on a pool of seeded maps, twice per map so
+/// that both get to play either team. Ratings are updated after every game, and the results are
+/// written to a report.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let maps = parse_flag(args, "--maps", 5)?;
+    let seed = parse_flag(args, "--seed", 0u32)?;
+    let max_ticks = parse_flag(args, "--max-ticks", sim::DEFAULT_MAX_TICKS)?;
+    let mode = parse_mode(args)?;
+    let out = parse_flag(args, "--out", DEFAULT_REPORT.to_string())?;
+    let settings = MapSettings {
+        size: parse_flag(args, "--size", 0usize).map(|s| if s == 0 { None } else { Some(s) })?,
+        ..MapSettings::default()
+    };
+
+    let mut standings = bot::NAMES
+        .iter()
+        .map(|&name| Standing {
+            name,
+            rating: rating::INITIAL_RATING,
+            record: Record::default(),
+        })
+        .collect::<Vec<_>>();
+    let n = standings.len();
+    // `head_to_head[a][b]` is how `a` did against `b`.
+    let mut head_to_head = vec![vec![Record::default(); n]; n];
+
+    for a in 0..n {
+        for b in a + 1..n {
+            for map in 0..maps {
+                for &(first, second) in [(a, b), (b, a)].iter() {
+                    let names = [standings[first].name, standings[second].name];
+                    let result = sim::run_match(&names,
+                                                &settings,
+                                                mode,
+                                                seed.wrapping_add(map as u32),
+                                                max_ticks)?;
+                    let score = match result.winner {
+                        Some(0) => 1.0,
+                        Some(_) => 0.0,
+                        None => 0.5,
+                    };
+                    let (ra, rb) = rating::update(standings[first].rating,
+                                                  standings[second].rating,
+                                                  score);
+                    standings[first].rating = ra;
+                    standings[second].rating = rb;
+                    for &(me, other, my_score) in
+                        [(first, second, score), (second, first, 1.0 - score)].iter() {
+                        standings[me].record.add(my_score);
+                        head_to_head[me][other].add(my_score);
+                    }
+                    println!("{} vs {} on map {}: {}",
+                             names[0],
+                             names[1],
+                             map,
+                             match result.winner {
+                                 Some(team) => format!("{} won in {} ticks",
+                                                       names[team],
+                                                       result.ticks),
+                                 None => "draw".to_string(),
+                             });
+                }
+            }
+        }
+    }
+
+    let report = format_report(&standings, &head_to_head);
+    print!("{}", report);
+    File::create(&out)
+        .and_then(|mut f| f.write_all(report.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", out, e))?;
+    println!("Report written to {}", out);
+    Ok(())
+}
+
+fn format_report(standings: &[Standing], head_to_head: &[Vec<Record>]) -> String {
+    let mut order = (0..standings.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        standings[b].rating.partial_cmp(&standings[a].rating).unwrap()
+    });
+
+    let mut report = String::new();
+    report.push_str("Standings\n");
+    report.push_str(&format!("{:<10} {:>7} {:>5} {:>5} {:>5}\n", "bot", "rating", "W", "L", "D"));
+    for &i in order.iter() {
+        let s = &standings[i];
+        report.push_str(&format!("{:<10} {:>7.0} {:>5} {:>5} {:>5}\n",
+                                 s.name,
+                                 s.rating,
+                                 s.record.wins,
+                                 s.record.losses,
+                                 s.record.draws));
+    }
+
+    report.push_str("\nHead to head (wins-losses-draws of row against column)\n");
+    report.push_str(&format!("{:<10}", ""));
+    for &j in order.iter() {
+        report.push_str(&format!(" {:>10}", standings[j].name));
+    }
+    report.push('\n');
+    for &i in order.iter() {
+        report.push_str(&format!("{:<10}", standings[i].name));
+        for &j in order.iter() {
+            let r = head_to_head[i][j];
+            let cell = if i == j {
+                "-".to_string()
+            } else {
+                format!("{}-{}-{}", r.wins, r.losses, r.draws)
+            };
+            report.push_str(&format!(" {:>10}", cell));
+        }
+        report.push('\n');
+    }
+    report
+}
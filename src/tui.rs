@@ -0,0 +1,305 @@
+//! A text-mode client that plays a local game against a bot in the terminal, for playing over
+//! SSH or looking at the engine without a GPU.
+//!
+//! ```text
+//! generals-tui [--bot name] [--size N] [--seed N] [--tick-ms N] [--log-events file]
+//!              [--search-rollouts N]
+//! ```
+//!
+//! `h`, `j`, `k` and `l` move the cursor, and with shift they move the units under it. `u` takes
+//! back the last move, `c` clears the queue, `g` jumps to the King, and `q` quits.
+
+extern crate generals;
+extern crate rand;
+extern crate termion;
+
+use std::io::{self, Stdout, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use termion::color::{self, Rgb};
+use termion::event::Key;
+use termion::input::{Keys, TermRead};
+use termion::raw::{IntoRawMode, RawTerminal};
+use termion::{clear, cursor, style, AsyncReader};
+
+use generals::*;
+use generals::bot::{self, BotPlayer};
+use generals::eventlog;
+use generals::frontend::{self, Command, Frontend};
+use generals::sim::{self, parse_flag};
+
+/// How often to look for key presses between ticks.
+const POLL: Duration = Duration::from_millis(20);
+
+const TEAM_COLORS: [(u8, u8, u8); 8] = [(255, 25, 25),
+                                        (25, 25, 255),
+                                        (25, 170, 25),
+                                        (150, 25, 200),
+                                        (255, 140, 0),
+                                        (0, 150, 150),
+                                        (140, 75, 25),
+                                        (255, 100, 180)];
+
+fn team_color(team: Team) -> Rgb {
+    let (r, g, b) = TEAM_COLORS[team % TEAM_COLORS.len()];
+    Rgb(r, g, b)
+}
+
+/// Unit counts squeezed into the three columns of a cell.
+fn count(n: usize) -> String {
+    match n {
+        0..=999 => format!("{:>3}", n),
+        1000..=9999 => format!("{:>2}k", n / 1000),
+        _ => "+++".to_string(),
+    }
+}
+
+/// The background color and text of a cell.
+fn cell_text(tile: &Tile) -> (Rgb, String) {
+    match *tile {
+        Tile::Fog => (Rgb(40, 40, 40), "   ".to_string()),
+        Tile::Obstacle => (Rgb(40, 40, 40), " \u{2592} ".to_string()),
+        Tile::Visible(cell) => {
+            match cell {
+                Cell::Mountain => (Rgb(60, 60, 60), "\u{25b2}\u{25b2}\u{25b2}".to_string()),
+                Cell::Open => (Rgb(220, 220, 220), " \u{00b7} ".to_string()),
+                Cell::Fortress(None, n) => (Rgb(110, 110, 110), count(n)),
+                Cell::Fortress(Some(t), n) | Cell::Captured(t, n) => (team_color(t), count(n)),
+                Cell::King(t, n) => (team_color(t), count(n)),
+            }
+        }
+    }
+}
+
+struct Tui {
+    keys: Keys<AsyncReader>,
+    commands: Vec<Command>,
+    cursor: Option<Position>,
+    /// Our moves as of the last render, for taking back the last one.
+    queued: usize,
+    /// The last thing that happened that is worth a line on screen.
+    news: Option<String>,
+    team: Team,
+    quit: bool,
+}
+
+impl Tui {
+    /// Handle the keys pressed since the last call. Returns `true` if any were.
+    fn read_keys(&mut self, view: &PlayerView) -> bool {
+        let mut any = false;
+        while let Some(Ok(key)) = self.keys.next() {
+            any = true;
+            let (dir, queue) = match key {
+                Key::Char('h') => (Direction::Left, false),
+                Key::Char('j') => (Direction::Down, false),
+                Key::Char('k') => (Direction::Up, false),
+                Key::Char('l') => (Direction::Right, false),
+                Key::Char('H') => (Direction::Left, true),
+                Key::Char('J') => (Direction::Down, true),
+                Key::Char('K') => (Direction::Up, true),
+                Key::Char('L') => (Direction::Right, true),
+                Key::Char('u') => {
+                    if self.queued > 0 {
+                        self.queued -= 1;
+                        self.commands.push(Command::TruncateMoves(self.queued));
+                    }
+                    continue;
+                }
+                Key::Char('c') => {
+                    self.queued = 0;
+                    self.commands.push(Command::ClearMoves);
+                    continue;
+                }
+                Key::Char('g') => {
+                    self.cursor = king(view);
+                    continue;
+                }
+                Key::Char('q') | Key::Ctrl('c') => {
+                    self.quit = true;
+                    continue;
+                }
+                _ => continue,
+            };
+            let from = match self.cursor.or_else(|| king(view)) {
+                Some(pos) => pos,
+                None => continue,
+            };
+            let Position(x, y) = from + dir;
+            if view.try_get(x, y).is_none() {
+                continue;
+            }
+            if queue {
+                self.queued += 1;
+                self.commands.push(Command::Move((from, dir)));
+            }
+            self.cursor = Some(Position(x, y));
+        }
+        any
+    }
+}
+
+fn king(view: &PlayerView) -> Option<Position> {
+    let (w, h) = view.dimens;
+    (0..h)
+        .flat_map(|y| (0..w).map(move |x| Position(x, y)))
+        .find(|&Position(x, y)| match view.try_get(x, y) {
+            Some(Tile::Visible(Cell::King(team, _))) => team == view.team,
+            _ => false,
+        })
+}
+
+fn team_name(team: Team, us: Team) -> String {
+    if team == us {
+        "You".to_string()
+    } else {
+        format!("Player {}", team + 1)
+    }
+}
+
+impl Frontend for Tui {
+    type Context = RawTerminal<Stdout>;
+    type Error = io::Error;
+
+    fn render(&mut self, out: &mut RawTerminal<Stdout>, view: &PlayerView) -> io::Result<()> {
+        self.queued = view.moves.len();
+        write!(out, "{}{}", cursor::Goto(1, 1), clear::All)?;
+        let (w, h) = view.dimens;
+        for y in 0..h {
+            write!(out, "{}", cursor::Goto(1, y as u16 + 1))?;
+            for x in 0..w {
+                let tile = view.try_get(x, y).unwrap_or(Tile::Fog);
+                let (background, text) = cell_text(&tile);
+                let is_king = match tile {
+                    Tile::Visible(Cell::King(..)) => true,
+                    _ => false,
+                };
+                if self.cursor == Some(Position(x, y)) {
+                    write!(out, "{}", style::Invert)?;
+                }
+                if is_king {
+                    write!(out, "{}", style::Bold)?;
+                }
+                write!(out,
+                       "{}{}{}{}",
+                       color::Bg(background),
+                       color::Fg(Rgb(0, 0, 0)),
+                       text,
+                       style::Reset)?;
+            }
+        }
+
+        let mut line = h as u16 + 2;
+        if let Some(score) = view.scores.iter().find(|s| s.team == view.team) {
+            write!(out,
+                   "{}Turn {}   Army {}   Land {}   Cities {}   Queued {}",
+                   cursor::Goto(1, line),
+                   view.tick_number,
+                   score.army,
+                   score.land,
+                   score.cities,
+                   view.moves.len())?;
+            line += 1;
+        }
+        if let Some(ref news) = self.news {
+            write!(out, "{}{}", cursor::Goto(1, line), news)?;
+            line += 1;
+        }
+        write!(out,
+               "{}hjkl: cursor   HJKL: move   u: undo   c: clear   g: King   q: quit",
+               cursor::Goto(1, line))?;
+        out.flush()
+    }
+
+    fn event(&mut self, event: &GameEvent) {
+        let us = self.team;
+        self.news = match *event {
+            GameEvent::CityCaptured { team, from: Some(from), .. } if from == us => {
+                Some(format!("{} took one of your cities", team_name(team, us)))
+            }
+            GameEvent::CityCaptured { team, .. } if team == us => {
+                Some("You captured a city".to_string())
+            }
+            GameEvent::KingCaptured { team, victim, .. } => {
+                let (victim, team) = (team_name(victim, us), team_name(team, us));
+                Some(format!("{} was eliminated by {}", victim, team))
+            }
+            _ => return,
+        };
+    }
+
+    fn commands(&mut self) -> Vec<Command> {
+        self.commands.drain(..).collect()
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let bot_name = parse_flag(args, "--bot", bot::DEFAULT_BOT.to_string())?;
+    let size = parse_flag(args, "--size", 0usize)?;
+    let seed = parse_flag(args, "--seed", rand::random::<u32>())?;
+    let tick = Duration::from_millis(parse_flag(args, "--tick-ms", 500u64)?);
+    let log = parse_flag(args, "--log-events", String::new())?;
+    if !log.is_empty() {
+        eventlog::enable(&log).map_err(|e| format!("Failed to create {}: {}", log, e))?;
+    }
+    sim::configure_search(args)?;
+
+    let settings = MapSettings {
+        size: if size == 0 { None } else { Some(size) },
+        ..MapSettings::default()
+    };
+    let team = 0;
+    let board = Board::generate(&settings, 2, &mut seeded_rng(seed));
+    let mut game = GameState::new(board, 2);
+    eventlog::attach(&mut game);
+    let bot = bot::seeded(&bot_name, seed)
+        .ok_or_else(|| format!("Unknown bot '{}', try one of {:?}", bot_name, bot::NAMES))?;
+    let mut bots = vec![BotPlayer::new(1, bot)];
+
+    let mut out = io::stdout().into_raw_mode().map_err(|e| e.to_string())?;
+    let mut tui = Tui {
+        keys: termion::async_stdin().keys(),
+        commands: Vec::new(),
+        cursor: None,
+        queued: 0,
+        news: None,
+        team,
+        quit: false,
+    };
+    write!(out, "{}", cursor::Hide).map_err(|e| e.to_string())?;
+    let mut next_tick = Instant::now() + tick;
+    let result = loop {
+        let view = game.player_view(team);
+        if let Err(e) = tui.render(&mut out, &view) {
+            break Err(e.to_string());
+        }
+        if tui.quit || game.is_over() {
+            break Ok(game.winner());
+        }
+        // Redraw right away when a key moves the cursor, but only tick on time.
+        while !tui.read_keys(&view) && Instant::now() < next_tick {
+            thread::sleep(POLL);
+        }
+        if Instant::now() >= next_tick {
+            next_tick += tick;
+            frontend::step(&mut tui, &mut game, &mut bots, team);
+        }
+    };
+    write!(out, "{}{}\r\n", style::Reset, cursor::Show).map_err(|e| e.to_string())?;
+    drop(out);
+    match result? {
+        Some(winner) => {
+            println!("{} won after {} turns", team_name(winner, team), game.tick_number)
+        }
+        None => println!("Quit after {} turns", game.tick_number),
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    if let Err(e) = run(&args) {
+        println!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,58 @@
+//! The gallery of achievements, opened from the menu.
+
+use ggez::{Context, GameResult};
+use ggez::event::Keycode;
+use ggez::graphics::{self, Color, Font};
+
+use generals::achievements::{Unlocked, ALL};
+
+use super::draw_text;
+use super::theme::Theme;
+
+const LINE_HEIGHT: f32 = 28.0;
+const MARGIN: f32 = 40.0;
+
+pub struct Gallery {
+    unlocked: Unlocked,
+}
+
+impl Gallery {
+    pub fn new(unlocked: Unlocked) -> Self {
+        Gallery { unlocked }
+    }
+
+    /// Returns `false` when the gallery is closed.
+    pub fn key_down(&mut self, keycode: Keycode) -> bool {
+        match keycode {
+            Keycode::Escape | Keycode::Return => false,
+            _ => true,
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut Context, font: &Font, theme: &Theme) -> GameResult<()> {
+        graphics::set_background_color(ctx, theme.background);
+        graphics::clear(ctx);
+        let gray = Color::new(0.6, 0.6, 0.6, 1.0);
+        let gold = Color::new(1.0, 0.8, 0.2, 1.0);
+        let count = ALL.iter().filter(|&&a| self.unlocked.has(a)).count();
+        let mut y = MARGIN;
+        let title = format!("Achievements: {} of {}", count, ALL.len());
+        draw_text(ctx, font, &title, MARGIN, y, theme.text)?;
+        y += LINE_HEIGHT * 2.0;
+        for &achievement in ALL.iter() {
+            let (marker, color) = if self.unlocked.has(achievement) {
+                ("*", gold)
+            } else {
+                (" ", gray)
+            };
+            let text = format!("{} {}: {}",
+                               marker,
+                               achievement.title(),
+                               achievement.description());
+            draw_text(ctx, font, &text, MARGIN, y, color)?;
+            y += LINE_HEIGHT;
+        }
+        y += LINE_HEIGHT;
+        draw_text(ctx, font, "Escape: back", MARGIN, y, gray)
+    }
+}
@@ -0,0 +1,172 @@
+//! Armies sliding from cell to cell when moves are executed, and the bursts when a King falls.
+
+use std::time::{Duration, Instant};
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawMode, Drawable, Font, Point, Text};
+use rand;
+
+use generals::{Move, Position, Team};
+
+use super::camera::Camera;
+
+struct MoveAnimation {
+    team: Team,
+    from: Position,
+    to: Position,
+    units: usize,
+    start: Instant,
+    duration: Duration,
+}
+
+/// How long the flash and particles on a captured King last.
+const BURST: f32 = 0.8;
+const PARTICLES: usize = 24;
+/// How long the wave takes to get one cell further from the King.
+const SWEEP_STEP: f32 = 0.04;
+/// How long each cell of the wave stays lit.
+const SWEEP_CELL: f32 = 0.5;
+
+struct Particle {
+    /// Direction and speed, in cells per second.
+    velocity: (f32, f32),
+    size: f32,
+}
+
+/// A King being captured: a flash and particles on its cell, and a wave in the color of the
+/// capturer washing over the land of the victim.
+struct KingAnimation {
+    team: Team,
+    pos: Position,
+    particles: Vec<Particle>,
+    /// The land of the victim, and how long after the capture the wave reaches each cell.
+    land: Vec<(Position, f32)>,
+    start: Instant,
+}
+
+impl KingAnimation {
+    fn duration(&self) -> f32 {
+        let last = self.land.iter().map(|&(_, delay)| delay).fold(0.0, f32::max);
+        BURST.max(last + SWEEP_CELL)
+    }
+}
+
+pub struct Animations {
+    moves: Vec<MoveAnimation>,
+    kings: Vec<KingAnimation>,
+}
+
+fn seconds(d: Duration) -> f32 {
+    d.as_secs() as f32 + d.subsec_nanos() as f32 * 1e-9
+}
+
+impl Animations {
+    pub fn new() -> Self {
+        Animations {
+            moves: Vec::new(),
+            kings: Vec::new(),
+        }
+    }
+
+    /// Show `units` units of `team` moving along `mv`, arriving after `duration`.
+    pub fn push_move(&mut self, team: Team, mv: Move, units: usize, duration: Duration) {
+        let (from, dir) = mv;
+        self.moves.push(MoveAnimation {
+            team,
+            from,
+            to: from + dir,
+            units,
+            start: Instant::now(),
+            duration,
+        });
+    }
+
+    /// Show `team` capturing the King at `pos`, and the wave spreading over `land`.
+    pub fn push_king_capture(&mut self, team: Team, pos: Position, land: Vec<Position>) {
+        let particles = (0..PARTICLES)
+            .map(|i| {
+                let angle = (i as f32 + rand::random::<f32>()) / PARTICLES as f32 * 6.2832;
+                let speed = 1.0 + rand::random::<f32>() * 2.0;
+                Particle {
+                    velocity: (angle.cos() * speed, angle.sin() * speed),
+                    size: 0.05 + rand::random::<f32>() * 0.08,
+                }
+            })
+            .collect();
+        let land = land.into_iter()
+            .map(|p| {
+                let (dx, dy) = ((p.0 - pos.0) as f32, (p.1 - pos.1) as f32);
+                (p, (dx * dx + dy * dy).sqrt() * SWEEP_STEP)
+            })
+            .collect();
+        self.kings.push(KingAnimation {
+            team,
+            pos,
+            particles,
+            land,
+            start: Instant::now(),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.moves.clear();
+        self.kings.clear();
+    }
+
+    /// Draw every army on its way, and forget those that have arrived.
+    pub fn draw<F>(&mut self, ctx: &mut Context, font: &Font, camera: &Camera, team_color: F)
+        -> GameResult<()>
+    where
+        F: Fn(Team) -> Color,
+    {
+        self.moves.retain(|m| m.start.elapsed() < m.duration);
+        let radius = camera.cell_size() * 0.3;
+        for m in self.moves.iter() {
+            if !camera.is_visible(m.from) && !camera.is_visible(m.to) {
+                continue;
+            }
+            let t = (seconds(m.start.elapsed()) / seconds(m.duration)).min(1.0);
+            let a = camera.cell_center(m.from.0, m.from.1);
+            let b = camera.cell_center(m.to.0, m.to.1);
+            let at = Point::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t);
+            graphics::set_color(ctx, team_color(m.team))?;
+            graphics::circle(ctx, DrawMode::Fill, at, radius, 0.5)?;
+            graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 1.0))?;
+            graphics::circle(ctx, DrawMode::Line, at, radius, 0.5)?;
+            let text = Text::new(ctx, &format!("{}", m.units), font)?;
+            text.draw(ctx, at, 0.0)?;
+        }
+
+        self.kings.retain(|k| seconds(k.start.elapsed()) < k.duration());
+        let size = camera.cell_size();
+        for k in self.kings.iter() {
+            let t = seconds(k.start.elapsed());
+            let mut color = team_color(k.team);
+            for &(pos, delay) in k.land.iter() {
+                let lit = (t - delay) / SWEEP_CELL;
+                if lit < 0.0 || lit >= 1.0 || !camera.is_visible(pos) {
+                    continue;
+                }
+                color.a = 0.7 * (1.0 - lit);
+                graphics::set_color(ctx, color)?;
+                graphics::rectangle(ctx, DrawMode::Fill, camera.cell_rect(pos.0, pos.1))?;
+            }
+
+            let burst = t / BURST;
+            if burst >= 1.0 || !camera.is_visible(k.pos) {
+                continue;
+            }
+            let center = camera.cell_center(k.pos.0, k.pos.1);
+            graphics::set_color(ctx, Color::new(1.0, 1.0, 1.0, 0.8 * (1.0 - burst)))?;
+            graphics::circle(ctx, DrawMode::Fill, center, size * (0.5 + burst * 1.5), 0.5)?;
+            color.a = 1.0 - burst;
+            graphics::set_color(ctx, color)?;
+            for p in k.particles.iter() {
+                let at = Point::new(center.x + p.velocity.0 * t * size,
+                                    center.y + p.velocity.1 * t * size);
+                graphics::circle(ctx, DrawMode::Fill, at, p.size * size, 0.5)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+//! Outlines around each region of a team's territory, so that the shape of the land can be read
+//! at a glance on big boards.
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawMode, Rect};
+
+use generals::{Direction, PlayerView, Position, Team, Tile};
+
+use super::camera::Camera;
+
+/// The width of the outline, relative to the size of a cell.
+const WIDTH: f32 = 0.12;
+
+fn owner(view: &PlayerView, x: i32, y: i32) -> Option<Team> {
+    match view.try_get(x, y) {
+        Some(Tile::Visible(cell)) => cell.owner(),
+        _ => None,
+    }
+}
+
+fn darken(color: Color) -> Color {
+    Color::new(color.r * 0.6, color.g * 0.6, color.b * 0.6, 1.0)
+}
+
+/// Draw the outline of every region on the edges of the cells at its border.
+pub fn draw<F>(ctx: &mut Context, camera: &Camera, view: &PlayerView, team_color: F)
+    -> GameResult<()>
+where
+    F: Fn(Team) -> Color,
+{
+    let size = camera.cell_size();
+    let width = (size * WIDTH).max(1.0);
+    let ((x0, x1), (y0, y1)) = camera.visible_cells(view.dimens);
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let team = match owner(view, x, y) {
+                Some(team) => team,
+                None => continue,
+            };
+            let rect = camera.cell_rect(x, y);
+            graphics::set_color(ctx, darken(team_color(team)))?;
+            for &dir in Direction::ALL.iter() {
+                let Position(nx, ny) = Position(x, y) + dir;
+                if owner(view, nx, ny) == Some(team) {
+                    continue;
+                }
+                // `rect` is centered on the cell.
+                let inset = (size - width) / 2.0;
+                let edge = match dir {
+                    Direction::Up => Rect::new(rect.x, rect.y - inset, size, width),
+                    Direction::Down => Rect::new(rect.x, rect.y + inset, size, width),
+                    Direction::Left => Rect::new(rect.x - inset, rect.y, width, size),
+                    Direction::Right => Rect::new(rect.x + inset, rect.y, width, size),
+                };
+                graphics::rectangle(ctx, DrawMode::Fill, edge)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,220 @@
+//! The mapping between board cells and window pixels.
+
+use std::time::Duration;
+
+use ggez::graphics::{Point, Rect};
+
+use generals::{Direction, Position};
+
+/// The size of a cell at zoom 1.
+pub const CELL_SIZE: f32 = 48.0;
+/// The space between cells at zoom 1.
+const GAP: f32 = 1.0;
+const MIN_ZOOM: f32 = 0.2;
+const MAX_ZOOM: f32 = 4.0;
+/// How much one step of the mouse wheel zooms.
+const ZOOM_STEP: f32 = 1.15;
+/// Pixels per second.
+const PAN_SPEED: f32 = 900.0;
+/// The camera pans when the mouse is this close to the edge of the window.
+const EDGE_SCROLL_MARGIN: f32 = 12.0;
+
+pub struct Camera {
+    /// The board position at the top left corner of the window, in pixels at zoom 1.
+    x: f32,
+    y: f32,
+    zoom: f32,
+    /// Pan directions held down: up, left, down, right.
+    held: [bool; 4],
+    /// The last known mouse position, for edge scrolling and zooming.
+    mouse: Option<(f32, f32)>,
+    /// Whether the board is being dragged around with the middle mouse button.
+    grabbed: bool,
+    /// The size of the window as of the last `update`.
+    window: (f32, f32),
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Camera {
+            x: 0.0,
+            y: 0.0,
+            zoom: 1.0,
+            held: [false; 4],
+            mouse: None,
+            grabbed: false,
+            window: (0.0, 0.0),
+        }
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// The size of a cell on screen.
+    pub fn cell_size(&self) -> f32 {
+        CELL_SIZE * self.zoom
+    }
+
+    fn stride(&self) -> f32 {
+        (CELL_SIZE + GAP) * self.zoom
+    }
+
+    /// The window position of the center of the cell at `(x, y)`.
+    pub fn cell_center(&self, x: i32, y: i32) -> Point {
+        let half = self.cell_size() / 2.0;
+        Point::new(x as f32 * self.stride() - self.x * self.zoom + half,
+                   y as f32 * self.stride() - self.y * self.zoom + half)
+    }
+
+    /// The rectangle covered by the cell at `(x, y)`. Like everything drawn in ggez, it is
+    /// positioned by its center.
+    pub fn cell_rect(&self, x: i32, y: i32) -> Rect {
+        let center = self.cell_center(x, y);
+        Rect::new(center.x, center.y, self.cell_size(), self.cell_size())
+    }
+
+    /// The cell under the window position `(x, y)`. It may be outside of the board.
+    pub fn cell_at(&self, x: f32, y: f32) -> Position {
+        let stride = CELL_SIZE + GAP;
+        let bx = x / self.zoom + self.x;
+        let by = y / self.zoom + self.y;
+        Position((bx / stride).floor() as i32, (by / stride).floor() as i32)
+    }
+
+    /// The cells of a board of size `dimens` that are at least partly inside the window, as
+    /// the ranges `(x0..x1, y0..y1)`.
+    pub fn visible_cells(&self, dimens: (i32, i32)) -> ((i32, i32), (i32, i32)) {
+        let Position(x0, y0) = self.cell_at(0.0, 0.0);
+        let Position(x1, y1) = self.cell_at(self.window.0, self.window.1);
+        let clamp = |v: i32, max: i32| v.max(0).min(max);
+        ((clamp(x0, dimens.0), clamp(x1 + 1, dimens.0)),
+         (clamp(y0, dimens.1), clamp(y1 + 1, dimens.1)))
+    }
+
+    /// Whether the cell at `pos` is at least partly inside the window.
+    pub fn is_visible(&self, pos: Position) -> bool {
+        let Position(x0, y0) = self.cell_at(0.0, 0.0);
+        let Position(x1, y1) = self.cell_at(self.window.0, self.window.1);
+        pos.0 >= x0 && pos.0 <= x1 && pos.1 >= y0 && pos.1 <= y1
+    }
+
+    /// Move the view by `(dx, dy)` window pixels.
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        self.x += dx / self.zoom;
+        self.y += dy / self.zoom;
+    }
+
+    /// Zoom in (`steps > 0`) or out, keeping the board under the window position `(x, y)` in
+    /// place.
+    pub fn zoom_at(&mut self, steps: i32, x: f32, y: f32) {
+        let (bx, by) = (x / self.zoom + self.x, y / self.zoom + self.y);
+        self.zoom = (self.zoom * ZOOM_STEP.powi(steps)).max(MIN_ZOOM).min(MAX_ZOOM);
+        self.x = bx - x / self.zoom;
+        self.y = by - y / self.zoom;
+    }
+
+    /// Zoom for the mouse wheel, around the mouse.
+    pub fn wheel(&mut self, steps: i32) {
+        let (x, y) = self.mouse.unwrap_or((self.window.0 / 2.0, self.window.1 / 2.0));
+        self.zoom_at(steps, x, y);
+    }
+
+    /// Put the cell at `pos` in the middle of the window.
+    pub fn center_on(&mut self, pos: Position) {
+        let stride = CELL_SIZE + GAP;
+        let (w, h) = self.window;
+        self.x = (pos.0 as f32 + 0.5) * stride - w / 2.0 / self.zoom;
+        self.y = (pos.1 as f32 + 0.5) * stride - h / 2.0 / self.zoom;
+    }
+
+    /// Zoom so that a board of size `dimens` fills the window, and put it in the middle.
+    pub fn fit(&mut self, dimens: (i32, i32)) {
+        let stride = CELL_SIZE + GAP;
+        let (w, h) = (dimens.0 as f32 * stride, dimens.1 as f32 * stride);
+        let (window_w, window_h) = self.window;
+        self.zoom = (window_w / w).min(window_h / h).max(MIN_ZOOM).min(MAX_ZOOM);
+        self.x = w / 2.0 - window_w / 2.0 / self.zoom;
+        self.y = h / 2.0 - window_h / 2.0 / self.zoom;
+    }
+
+    /// The window has been resized to `(w, h)`. Scale the view along with it, keeping the same
+    /// part of the board in the middle.
+    pub fn resize(&mut self, w: f32, h: f32) {
+        let (old_w, old_h) = self.window;
+        let center_x = self.x + old_w / 2.0 / self.zoom;
+        let center_y = self.y + old_h / 2.0 / self.zoom;
+        if old_w > 0.0 && old_h > 0.0 {
+            let scale = (w / old_w).min(h / old_h);
+            self.zoom = (self.zoom * scale).max(MIN_ZOOM).min(MAX_ZOOM);
+        }
+        self.window = (w, h);
+        self.x = center_x - w / 2.0 / self.zoom;
+        self.y = center_y - h / 2.0 / self.zoom;
+    }
+
+    /// Start or stop panning in `dir`, while its key is held down.
+    pub fn hold(&mut self, dir: Direction, down: bool) {
+        let i = match dir {
+            Direction::Up => 0,
+            Direction::Left => 1,
+            Direction::Down => 2,
+            Direction::Right => 3,
+        };
+        self.held[i] = down;
+    }
+
+    /// The last known mouse position.
+    pub fn mouse(&self) -> Option<(f32, f32)> {
+        self.mouse
+    }
+
+    pub fn mouse_moved(&mut self, x: i32, y: i32) {
+        let (x, y) = (x as f32, y as f32);
+        if let (true, Some((old_x, old_y))) = (self.grabbed, self.mouse) {
+            // Keep the board under the mouse.
+            self.pan(old_x - x, old_y - y);
+        }
+        self.mouse = Some((x, y));
+    }
+
+    /// Start or stop dragging the board along with the mouse.
+    pub fn grab(&mut self, grabbed: bool) {
+        self.grabbed = grabbed;
+    }
+
+    /// Pan for the keys held down, and if the mouse is at the edge of a window of size
+    /// `(w, h)`.
+    pub fn update(&mut self, dt: Duration, w: f32, h: f32) {
+        self.window = (w, h);
+        let (mut dx, mut dy) = (0.0f32, 0.0f32);
+        if self.held[0] {
+            dy -= 1.0;
+        }
+        if self.held[1] {
+            dx -= 1.0;
+        }
+        if self.held[2] {
+            dy += 1.0;
+        }
+        if self.held[3] {
+            dx += 1.0;
+        }
+        // Edge scrolling would fight with dragging the board.
+        if let Some((mx, my)) = self.mouse.filter(|_| !self.grabbed) {
+            if mx < EDGE_SCROLL_MARGIN {
+                dx -= 1.0;
+            } else if mx > w - EDGE_SCROLL_MARGIN {
+                dx += 1.0;
+            }
+            if my < EDGE_SCROLL_MARGIN {
+                dy -= 1.0;
+            } else if my > h - EDGE_SCROLL_MARGIN {
+                dy += 1.0;
+            }
+        }
+        let seconds = dt.as_secs() as f32 + dt.subsec_nanos() as f32 * 1e-9;
+        let distance = PAN_SPEED * seconds;
+        self.pan(dx.max(-1.0).min(1.0) * distance, dy.max(-1.0).min(1.0) * distance);
+    }
+}
@@ -0,0 +1,90 @@
+//! Unit counts drawn in the middle of cells, sized to fit them at any zoom.
+
+use std::collections::HashMap;
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawParam, Drawable, Font, Point, Rect, Text};
+
+/// The font used for counts, in the ggez resources directory. Without it, the default font is
+/// scaled instead, which is blurrier.
+const FONT_PATH: &str = "/fonts/counts.ttf";
+/// The size the default font is rendered at.
+const DEFAULT_FONT_SIZE: f32 = 16.0;
+/// Font sizes are rounded to multiples of this, so zooming doesn't load a font per pixel size.
+const SIZE_STEP: u32 = 2;
+const MIN_SIZE: u32 = 6;
+const MAX_SIZE: u32 = 64;
+/// How much of the cell height the text takes up.
+const TEXT_HEIGHT: f32 = 0.45;
+
+/// Whether dark text is easier to read than light text on `background`.
+fn is_light(background: Color) -> bool {
+    0.299 * background.r + 0.587 * background.g + 0.114 * background.b > 0.5
+}
+
+pub struct CellText {
+    /// Loaded fonts by size. `None` if there is no font file.
+    fonts: Option<HashMap<u32, Font>>,
+}
+
+impl CellText {
+    pub fn new() -> Self {
+        CellText { fonts: Some(HashMap::new()) }
+    }
+
+    /// The font of `size`, loading it if needed.
+    fn font(&mut self, ctx: &mut Context, size: u32) -> Option<&Font> {
+        let missing = match self.fonts {
+            Some(ref fonts) => !fonts.contains_key(&size),
+            None => return None,
+        };
+        if missing {
+            match Font::new(ctx, FONT_PATH, size) {
+                Ok(font) => {
+                    self.fonts.as_mut().unwrap().insert(size, font);
+                }
+                Err(e) => {
+                    println!("Failed to load {}, using the default font: {}", FONT_PATH, e);
+                    self.fonts = None;
+                    return None;
+                }
+            }
+        }
+        self.fonts.as_ref().and_then(|fonts| fonts.get(&size))
+    }
+
+    /// Draw `text` in the middle of `rect`, which is positioned by its center, with an outline
+    /// that stands out from `background`.
+    pub fn draw(&mut self, ctx: &mut Context, fallback: &Font, text: &str, rect: Rect,
+                background: Color)
+        -> GameResult<()> {
+        let wanted = rect.h * TEXT_HEIGHT;
+        let size = ((wanted as u32 / SIZE_STEP) * SIZE_STEP).max(MIN_SIZE).min(MAX_SIZE);
+        let (t, scale) = match self.font(ctx, size) {
+            Some(font) => (Text::new(ctx, text, font)?, 1.0),
+            None => (Text::new(ctx, text, fallback)?, wanted / DEFAULT_FONT_SIZE),
+        };
+        // Shrink long numbers to fit the width of the cell.
+        let scale = scale.min(rect.w * 0.9 / t.width() as f32);
+
+        let (fill, outline) = if is_light(background) {
+            (Color::new(0.0, 0.0, 0.0, 1.0), Color::new(1.0, 1.0, 1.0, 0.8))
+        } else {
+            (Color::new(1.0, 1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0, 0.8))
+        };
+        let draw_at = |ctx: &mut Context, dx: f32, dy: f32| {
+            t.draw_ex(ctx,
+                      DrawParam {
+                          dest: Point::new(rect.x + dx, rect.y + dy),
+                          scale: Point::new(scale, scale),
+                          ..Default::default()
+                      })
+        };
+        graphics::set_color(ctx, outline)?;
+        for &(dx, dy) in &[(-1.0, 0.0), (1.0, 0.0), (0.0, -1.0), (0.0, 1.0)] {
+            draw_at(ctx, dx, dy)?;
+        }
+        graphics::set_color(ctx, fill)?;
+        draw_at(ctx, 0.0, 0.0)
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use ggez::{Context, GameResult};
+use ggez::event::{Keycode, Mod};
+use ggez::graphics::{self, Color, DrawMode, Font, Rect};
+
+use generals::net::{ChatChannel, Client, ClientMessage};
+
+use super::draw_text;
+
+const LINE_HEIGHT: f32 = 24.0;
+const MARGIN: f32 = 20.0;
+const WIDTH: f32 = 700.0;
+/// How many messages to show.
+const HISTORY: usize = 8;
+/// Messages fade away after this long, unless the chat is open.
+const SHOW_FOR: Duration = Duration::from_secs(10);
+/// SDL's left and right shift modifier bits, which ggez does not re-export.
+const SHIFT_BITS: u16 = 0x0001 | 0x0002;
+
+/// The text typed by `keycode`, for the printable ASCII keys.
+fn key_char(keycode: Keycode, keymod: Mod) -> Option<char> {
+    let code = keycode as i32;
+    if code < 32 || code >= 127 {
+        return None;
+    }
+    let c = code as u8 as char;
+    let shift = keymod.bits() & SHIFT_BITS != 0;
+    Some(if shift { c.to_ascii_uppercase() } else { c })
+}
+
+/// Chat messages, and the line we are typing. Opened and sent with Enter.
+pub struct ChatBox {
+    /// `Some` while we are typing.
+    input: Option<String>,
+    channel: ChatChannel,
+    messages: VecDeque<(String, Instant)>,
+}
+
+impl ChatBox {
+    pub fn new() -> Self {
+        ChatBox {
+            input: None,
+            channel: ChatChannel::All,
+            messages: VecDeque::new(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.input.is_some()
+    }
+
+    pub fn push(&mut self, from: &str, channel: ChatChannel, text: &str) {
+        let prefix = match channel {
+            ChatChannel::All => "",
+            ChatChannel::Team => "[team] ",
+        };
+        self.messages.push_back((format!("{}{}: {}", prefix, from, text), Instant::now()));
+        if self.messages.len() > HISTORY {
+            self.messages.pop_front();
+        }
+    }
+
+    /// Handle a key press. Returns `true` if the chat used it.
+    pub fn key_down(&mut self, keycode: Keycode, keymod: Mod, client: &mut Client) -> bool {
+        let input = match self.input {
+            Some(ref mut input) => input,
+            None => {
+                if keycode == Keycode::Return {
+                    self.input = Some(String::new());
+                    return true;
+                }
+                return false;
+            }
+        };
+        match keycode {
+            Keycode::Return => {
+                if !input.is_empty() {
+                    let msg = ClientMessage::Chat {
+                        channel: self.channel,
+                        text: input.clone(),
+                    };
+                    if let Err(e) = client.send(&msg) {
+                        println!("Failed to send chat message: {}", e);
+                    }
+                }
+                self.input = None;
+            }
+            Keycode::Escape => self.input = None,
+            Keycode::Backspace => {
+                input.pop();
+            }
+            Keycode::Tab => {
+                self.channel = match self.channel {
+                    ChatChannel::All => ChatChannel::Team,
+                    ChatChannel::Team => ChatChannel::All,
+                };
+            }
+            _ => input.extend(key_char(keycode, keymod)),
+        }
+        true
+    }
+
+    /// Draw the chat in the bottom left corner of the window.
+    pub fn draw(&self, ctx: &mut Context, font: &Font) -> GameResult<()> {
+        let messages = self.messages
+            .iter()
+            .filter(|&&(_, at)| self.is_open() || at.elapsed() < SHOW_FOR)
+            .map(|&(ref text, _)| text.as_str())
+            .collect::<Vec<_>>();
+        let lines = messages.len() + if self.is_open() { 1 } else { 0 };
+        if lines == 0 {
+            return Ok(());
+        }
+        let bottom = ctx.conf.window_height as f32 - MARGIN;
+        let top = bottom - lines as f32 * LINE_HEIGHT;
+        graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.6))?;
+        let height = bottom - top;
+        graphics::rectangle(ctx,
+                            DrawMode::Fill,
+                            Rect::new(MARGIN + WIDTH / 2.0, top + height / 2.0, WIDTH, height))?;
+        let white = Color::new(1.0, 1.0, 1.0, 1.0);
+        let mut y = top;
+        for text in messages {
+            draw_text(ctx, font, text, MARGIN + 4.0, y, white)?;
+            y += LINE_HEIGHT;
+        }
+        if let Some(ref input) = self.input {
+            let channel = match self.channel {
+                ChatChannel::All => "all",
+                ChatChannel::Team => "team",
+            };
+            let line = format!("({}) > {}_", channel, input);
+            draw_text(ctx, font, &line, MARGIN + 4.0, y, Color::new(1.0, 1.0, 0.6, 1.0))?;
+        }
+        Ok(())
+    }
+}
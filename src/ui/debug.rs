@@ -0,0 +1,25 @@
+//! The developer overlay toggled with F3.
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawMode, Font, Rect};
+
+use super::{draw_text, hud};
+
+const MARGIN: f32 = 20.0;
+const LINE_HEIGHT: f32 = 22.0;
+const WIDTH: f32 = 520.0;
+
+/// Draw `lines` in a panel below the HUD.
+pub fn draw(ctx: &mut Context, font: &Font, lines: &[String]) -> GameResult<()> {
+    let top = hud::BOTTOM + 60.0;
+    let height = LINE_HEIGHT * lines.len() as f32 + 12.0;
+    graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.75))?;
+    graphics::rectangle(ctx,
+                        DrawMode::Fill,
+                        Rect::new(MARGIN + WIDTH / 2.0, top + height / 2.0, WIDTH, height))?;
+    let color = Color::new(0.6, 1.0, 0.6, 1.0);
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(ctx, font, line, MARGIN + 8.0, top + 6.0 + i as f32 * LINE_HEIGHT, color)?;
+    }
+    Ok(())
+}
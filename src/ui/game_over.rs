@@ -0,0 +1,95 @@
+//! The summary shown on top of the board when a game ends.
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawMode, Font, Rect};
+
+use generals::{Score, Team};
+use generals::stats::History;
+
+use super::{draw_text, graph};
+
+const LINE_HEIGHT: f32 = 28.0;
+const WIDTH: f32 = 560.0;
+const GRAPH_HEIGHT: f32 = 180.0;
+const GRAPH_GAP: f32 = 20.0;
+
+/// How a game ended.
+pub struct Outcome {
+    pub winner: Option<Team>,
+    pub scores: Vec<Score>,
+    pub ticks: usize,
+    /// The scores of every tick of the game.
+    pub history: History,
+}
+
+/// Dim the board, and show who won along with the final scores, what everyone did, and graphs
+/// of army and land over the game.
+pub fn draw<F>(ctx: &mut Context, font: &Font, outcome: &Outcome, us: Team, team_color: F)
+    -> GameResult<()>
+where
+    F: Fn(Team) -> Color,
+{
+    let (w, h) = (ctx.conf.window_width as f32, ctx.conf.window_height as f32);
+    graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.6))?;
+    graphics::rectangle(ctx, DrawMode::Fill, Rect::new(w / 2.0, h / 2.0, w, h))?;
+
+    let white = Color::new(1.0, 1.0, 1.0, 1.0);
+    let gray = Color::new(0.6, 0.6, 0.6, 1.0);
+    let left = (w - WIDTH) / 2.0;
+    let mut y = h / 8.0;
+    let title = match outcome.winner {
+        Some(team) if team == us => "Victory!",
+        Some(_) => "Defeat",
+        None => "Game over",
+    };
+    draw_text(ctx, font, title, left, y, white)?;
+    y += LINE_HEIGHT;
+    draw_text(ctx, font, &format!("The game lasted {} turns", outcome.ticks), left, y, gray)?;
+    y += LINE_HEIGHT * 1.5;
+
+    let mut scores = outcome.scores.clone();
+    scores.sort_by_key(|s| (Some(s.team) != outcome.winner, ::std::usize::MAX - s.land));
+    for score in scores.iter() {
+        let name = if score.team == us {
+            "You".to_string()
+        } else {
+            format!("Player {}", score.team + 1)
+        };
+        let text = format!("{}: {} army, {} land, {} cities",
+                           name,
+                           score.army,
+                           score.land,
+                           score.cities);
+        draw_text(ctx, font, &text, left, y, white)?;
+        y += LINE_HEIGHT;
+        let totals = outcome.history.totals(score.team);
+        let text = format!("took {} cities and {} Kings, made {} moves",
+                           totals.cities_taken,
+                           totals.kills,
+                           totals.moves);
+        draw_text(ctx, font, &text, left + 20.0, y, gray)?;
+        y += LINE_HEIGHT;
+    }
+    y += LINE_HEIGHT / 2.0;
+    let graph_width = (WIDTH - GRAPH_GAP) / 2.0;
+    for (i, &(title, army)) in [("Army", true), ("Land", false)].iter().enumerate() {
+        let lines = scores.iter()
+            .map(|s| {
+                let values = if army {
+                    outcome.history.army(s.team)
+                } else {
+                    outcome.history.land(s.team)
+                };
+                (team_color(s.team), values)
+            })
+            .collect::<Vec<_>>();
+        let x = left + i as f32 * (graph_width + GRAPH_GAP) + graph_width / 2.0;
+        let area = Rect::new(x, y + GRAPH_HEIGHT / 2.0, graph_width, GRAPH_HEIGHT);
+        graph::draw(ctx, font, title, area, &lines)?;
+    }
+    y += GRAPH_HEIGHT;
+    y += LINE_HEIGHT;
+    draw_text(ctx, font, "R: rematch   M: menu   Enter: review the game", left, y, gray)?;
+    y += LINE_HEIGHT;
+    draw_text(ctx, font, "S: same map again   X: same map, swapped spawns", left, y, gray)
+}
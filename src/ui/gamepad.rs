@@ -0,0 +1,114 @@
+//! Controller input. The d-pad and left stick move the focus like a cursor and turn it to face
+//! that way, A queues a move in the faced direction, B undoes, and the triggers change the speed.
+//! Everything else maps onto the same actions as the keyboard.
+
+use std::time::Duration;
+
+use ggez::event::{Axis, Button};
+
+use generals::Direction;
+
+use super::keymap::Action;
+
+/// Stick positions closer to the middle than this are ignored.
+const DEAD_ZONE: i16 = 12000;
+/// How far a trigger is pulled before it counts as pressed.
+const TRIGGER_THRESHOLD: i16 = 16000;
+/// How often the focus moves while the stick is held.
+const REPEAT: Duration = Duration::from_millis(150);
+
+/// What a controller asks the game to do.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input {
+    /// Move the focus one cell, without moving any units.
+    Cursor(Direction),
+    /// Queue a move from the focus in `Direction`.
+    Queue(Direction),
+    Action(Action),
+}
+
+pub struct Gamepad {
+    facing: Direction,
+    /// The direction the stick is pushed in, and how long until it moves the focus again.
+    stick: Option<(Direction, Duration)>,
+    axes: (i16, i16),
+    triggers: (bool, bool),
+}
+
+impl Gamepad {
+    pub fn new() -> Self {
+        Gamepad {
+            facing: Direction::Up,
+            stick: None,
+            axes: (0, 0),
+            triggers: (false, false),
+        }
+    }
+
+    fn cursor(&mut self, dir: Direction) -> Input {
+        self.facing = dir;
+        Input::Cursor(dir)
+    }
+
+    pub fn button_down(&mut self, button: Button) -> Option<Input> {
+        Some(match button {
+            Button::DPadUp => self.cursor(Direction::Up),
+            Button::DPadDown => self.cursor(Direction::Down),
+            Button::DPadLeft => self.cursor(Direction::Left),
+            Button::DPadRight => self.cursor(Direction::Right),
+            Button::A => Input::Queue(self.facing),
+            Button::B => Input::Action(Action::Undo),
+            Button::X => Input::Action(Action::ClearMoves),
+            Button::Start => Input::Action(Action::Pause),
+            Button::LeftShoulder => Input::Action(Action::ZoomOut),
+            Button::RightShoulder => Input::Action(Action::ZoomIn),
+            _ => return None,
+        })
+    }
+
+    /// Track the stick and triggers. Pulling a trigger gives an input right away, while the
+    /// stick is handled in `update`.
+    pub fn axis(&mut self, axis: Axis, value: i16) -> Option<Input> {
+        match axis {
+            Axis::LeftX => self.axes.0 = value,
+            Axis::LeftY => self.axes.1 = value,
+            Axis::TriggerLeft | Axis::TriggerRight => {
+                let pulled = value > TRIGGER_THRESHOLD;
+                let (held, action) = if axis == Axis::TriggerLeft {
+                    (&mut self.triggers.0, Action::Slower)
+                } else {
+                    (&mut self.triggers.1, Action::Faster)
+                };
+                let was_held = *held;
+                *held = pulled;
+                return if pulled && !was_held { Some(Input::Action(action)) } else { None };
+            }
+            _ => return None,
+        }
+        let (x, y) = self.axes;
+        let dir = if x.abs().max(y.abs()) < DEAD_ZONE {
+            None
+        } else if x.abs() > y.abs() {
+            Some(if x > 0 { Direction::Right } else { Direction::Left })
+        } else {
+            Some(if y > 0 { Direction::Down } else { Direction::Up })
+        };
+        match (dir, self.stick) {
+            (Some(dir), Some((held, _))) if dir == held => {}
+            (Some(dir), _) => self.stick = Some((dir, Duration::new(0, 0))),
+            (None, _) => self.stick = None,
+        }
+        None
+    }
+
+    /// Move the focus while the stick is held: once right away, then every `REPEAT`.
+    pub fn update(&mut self, dt: Duration) -> Option<Input> {
+        let (dir, wait) = self.stick?;
+        if wait > dt {
+            self.stick = Some((dir, wait - dt));
+            return None;
+        }
+        self.stick = Some((dir, REPEAT));
+        Some(self.cursor(dir))
+    }
+}
@@ -0,0 +1,90 @@
+//! Line graphs of a statistic over time, one line per team.
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawMode, Font, Point, Rect};
+
+use generals::Team;
+use generals::stats::History;
+
+use super::draw_text;
+
+/// The most points drawn per line. Longer series are thinned out to this many.
+const MAX_POINTS: usize = 200;
+const TITLE_HEIGHT: f32 = 24.0;
+/// How many of the last ticks the live graphs show.
+const LIVE_TICKS: usize = 100;
+const LIVE_WIDTH: f32 = 320.0;
+const LIVE_HEIGHT: f32 = 130.0;
+const MARGIN: f32 = 20.0;
+
+/// Draw `title` and a graph of `lines` below it in `area`, which is positioned by its center.
+/// Each line is a value per tick, and all of them share the scale of the largest value.
+pub fn draw(ctx: &mut Context, font: &Font, title: &str, area: Rect, lines: &[(Color, Vec<usize>)])
+    -> GameResult<()> {
+    let (left, top) = (area.x - area.w / 2.0, area.y - area.h / 2.0);
+    let (width, height) = (area.w, area.h);
+    draw_text(ctx, font, title, left, top, Color::new(0.6, 0.6, 0.6, 1.0))?;
+    let top = top + TITLE_HEIGHT;
+    let height = height - TITLE_HEIGHT;
+    graphics::set_color(ctx, Color::new(1.0, 1.0, 1.0, 0.1))?;
+    graphics::rectangle(ctx,
+                        DrawMode::Fill,
+                        Rect::new(left + width / 2.0, top + height / 2.0, width, height))?;
+
+    let len = lines.iter().map(|&(_, ref values)| values.len()).max().unwrap_or(0);
+    let max = lines.iter().flat_map(|&(_, ref values)| values.iter()).cloned().max();
+    let max = match max {
+        Some(max) if len >= 2 && max > 0 => max as f32,
+        _ => return Ok(()),
+    };
+    let step = (len + MAX_POINTS - 1) / MAX_POINTS;
+    for &(color, ref values) in lines {
+        let points = values.iter()
+            .enumerate()
+            .filter(|&(i, _)| i % step == 0 || i + 1 == values.len())
+            .map(|(i, &v)| {
+                Point::new(left + width * i as f32 / (len - 1) as f32,
+                           top + height - height * v as f32 / max)
+            })
+            .collect::<Vec<_>>();
+        if points.len() >= 2 {
+            graphics::set_color(ctx, color)?;
+            graphics::line(ctx, &points)?;
+        }
+    }
+    let label = format!("{}", max);
+    draw_text(ctx, font, &label, left + 4.0, top + 2.0, Color::new(0.6, 0.6, 0.6, 1.0))
+}
+
+/// Draw rolling graphs of the army and land of `teams` over the last ticks of `history`, in the
+/// bottom left corner. They are meant to be shown during a game, on top of the board.
+pub fn draw_live<F>(ctx: &mut Context,
+                    font: &Font,
+                    history: &History,
+                    teams: &[Team],
+                    team_color: F)
+                    -> GameResult<()>
+where
+    F: Fn(Team) -> Color,
+{
+    let h = ctx.conf.window_height as f32;
+    let height = 2.0 * LIVE_HEIGHT + 12.0;
+    graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.7))?;
+    graphics::rectangle(ctx,
+                        DrawMode::Fill,
+                        Rect::new(MARGIN + LIVE_WIDTH / 2.0,
+                                  h - MARGIN - height / 2.0,
+                                  LIVE_WIDTH + 12.0,
+                                  height + 12.0))?;
+    let army = teams.iter()
+        .map(|&t| (team_color(t), history.recent_army(t, LIVE_TICKS)))
+        .collect::<Vec<_>>();
+    let land = teams.iter()
+        .map(|&t| (team_color(t), history.recent_land(t, LIVE_TICKS)))
+        .collect::<Vec<_>>();
+    let x = MARGIN + LIVE_WIDTH / 2.0;
+    let land_y = h - MARGIN - LIVE_HEIGHT / 2.0;
+    let army_y = land_y - LIVE_HEIGHT - 12.0;
+    draw(ctx, font, "Army", Rect::new(x, army_y, LIVE_WIDTH, LIVE_HEIGHT), &army)?;
+    draw(ctx, font, "Land", Rect::new(x, land_y, LIVE_WIDTH, LIVE_HEIGHT), &land)
+}
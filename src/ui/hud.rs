@@ -0,0 +1,69 @@
+//! The turn counter and game clock in the top left corner, and below them the hill timer of
+//! king of the hill games or the countdown of blitz games.
+
+use std::time::Duration;
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawMode, Font, Rect};
+
+use generals::{Hill, Team};
+
+use super::draw_text;
+
+const MARGIN: f32 = 20.0;
+const WIDTH: f32 = 220.0;
+pub const HEIGHT: f32 = 36.0;
+
+/// The height taken up by the HUD, for anything drawn below it.
+pub const BOTTOM: f32 = MARGIN + HEIGHT;
+
+/// Draw the tick number, and how long the game has been going on as `m:ss`.
+pub fn draw(ctx: &mut Context, font: &Font, tick: usize, elapsed: Duration) -> GameResult<()> {
+    graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.7))?;
+    graphics::rectangle(ctx,
+                        DrawMode::Fill,
+                        Rect::new(MARGIN + WIDTH / 2.0, MARGIN + HEIGHT / 2.0, WIDTH, HEIGHT))?;
+    let secs = elapsed.as_secs();
+    let text = format!("Turn {}   {}:{:02}", tick, secs / 60, secs % 60);
+    draw_text(ctx, font, &text, MARGIN + 8.0, MARGIN + 6.0, Color::new(1.0, 1.0, 1.0, 1.0))
+}
+
+/// Draw who holds the hill, and how many more ticks they need to hold it, right below `draw`.
+pub fn draw_hill<F>(ctx: &mut Context, font: &Font, hill: &Hill, team_color: F) -> GameResult<()>
+where
+    F: Fn(Team) -> Color,
+{
+    let (text, color) = match hill.holder {
+        Some(team) => {
+            (format!("Hill: Player {}, {} to go", team + 1, hill.remaining()), team_color(team))
+        }
+        None => (format!("Hill: free, hold {}", hill.needed), Color::new(1.0, 1.0, 1.0, 1.0)),
+    };
+    draw_below(ctx, font, &text, color, WIDTH)
+}
+
+/// Draw how many ticks are left of a blitz game, right below `draw`.
+pub fn draw_countdown(ctx: &mut Context, font: &Font, remaining: usize) -> GameResult<()> {
+    let color = if remaining <= 20 {
+        Color::new(1.0, 0.3, 0.3, 1.0)
+    } else {
+        Color::new(1.0, 1.0, 1.0, 1.0)
+    };
+    draw_below(ctx, font, &format!("{} ticks left", remaining), color, WIDTH)
+}
+
+/// Draw the objective of a scenario, right below `draw`.
+pub fn draw_objective(ctx: &mut Context, font: &Font, objective: &str) -> GameResult<()> {
+    draw_below(ctx, font, objective, Color::new(1.0, 1.0, 1.0, 1.0), 2.0 * WIDTH)
+}
+
+/// Draw a line of text in a box `width` wide and as high as the one `draw` draws, right below
+/// it.
+fn draw_below(ctx: &mut Context, font: &Font, text: &str, color: Color, width: f32)
+    -> GameResult<()> {
+    graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.7))?;
+    graphics::rectangle(ctx,
+                        DrawMode::Fill,
+                        Rect::new(MARGIN + width / 2.0, BOTTOM + HEIGHT / 2.0, width, HEIGHT))?;
+    draw_text(ctx, font, text, MARGIN + 8.0, BOTTOM + 6.0, color)
+}
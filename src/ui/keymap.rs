@@ -0,0 +1,229 @@
+//! Which keys do what in a game. The bindings are kept in the config file, as a table that maps
+//! each action to the SDL name of a key, like `clear_moves = "Q"`. Actions missing from the
+//! table keep their default key.
+
+use std::collections::BTreeMap;
+
+use ggez::event::Keycode;
+
+use generals::Direction;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    ClearMoves,
+    /// Drop the last move in the queue.
+    Undo,
+    Pause,
+    /// Run one tick while paused.
+    Step,
+    Mute,
+    Faster,
+    Slower,
+    PanUp,
+    PanLeft,
+    PanDown,
+    PanRight,
+    ZoomIn,
+    ZoomOut,
+    /// Focus our King.
+    FocusKing,
+    /// Switch between arrows queueing moves and arrows only moving the focus.
+    ToggleQueueing,
+    /// Move the camera to our King, without changing the focus.
+    CenterKing,
+    /// Move the camera to where we were last attacked.
+    JumpToAlert,
+    /// Show or hide the graphs of army and land during the game.
+    ToggleGraph,
+}
+
+pub const NUM_ACTIONS: usize = 22;
+
+/// Every action, in the order of the enum.
+pub const ACTIONS: [Action; NUM_ACTIONS] = [Action::MoveUp,
+                                           Action::MoveDown,
+                                           Action::MoveLeft,
+                                           Action::MoveRight,
+                                           Action::ClearMoves,
+                                           Action::Undo,
+                                           Action::Pause,
+                                           Action::Step,
+                                           Action::Mute,
+                                           Action::Faster,
+                                           Action::Slower,
+                                           Action::PanUp,
+                                           Action::PanLeft,
+                                           Action::PanDown,
+                                           Action::PanRight,
+                                           Action::ZoomIn,
+                                           Action::ZoomOut,
+                                           Action::FocusKing,
+                                           Action::ToggleQueueing,
+                                           Action::CenterKing,
+                                           Action::JumpToAlert,
+                                           Action::ToggleGraph];
+
+impl Action {
+    /// The name of the action in the key file.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::ClearMoves => "clear_moves",
+            Action::Undo => "undo",
+            Action::Pause => "pause",
+            Action::Step => "step",
+            Action::Mute => "mute",
+            Action::Faster => "faster",
+            Action::Slower => "slower",
+            Action::PanUp => "pan_up",
+            Action::PanLeft => "pan_left",
+            Action::PanDown => "pan_down",
+            Action::PanRight => "pan_right",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::FocusKing => "focus_king",
+            Action::ToggleQueueing => "toggle_queueing",
+            Action::CenterKing => "center_king",
+            Action::JumpToAlert => "jump_to_alert",
+            Action::ToggleGraph => "toggle_graph",
+        }
+    }
+
+    /// The name of the action on the settings page.
+    pub fn label(&self) -> &'static str {
+        match *self {
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::ClearMoves => "Clear moves",
+            Action::Undo => "Undo move",
+            Action::Pause => "Pause",
+            Action::Step => "Step while paused",
+            Action::Mute => "Mute",
+            Action::Faster => "Faster",
+            Action::Slower => "Slower",
+            Action::PanUp => "Pan up",
+            Action::PanLeft => "Pan left",
+            Action::PanDown => "Pan down",
+            Action::PanRight => "Pan right",
+            Action::ZoomIn => "Zoom in",
+            Action::ZoomOut => "Zoom out",
+            Action::FocusKing => "Focus King",
+            Action::ToggleQueueing => "Arrows queue moves",
+            Action::CenterKing => "Show King",
+            Action::JumpToAlert => "Show last attack",
+            Action::ToggleGraph => "Show graphs",
+        }
+    }
+
+    fn default_key(&self) -> Keycode {
+        match *self {
+            Action::MoveUp => Keycode::Up,
+            Action::MoveDown => Keycode::Down,
+            Action::MoveLeft => Keycode::Left,
+            Action::MoveRight => Keycode::Right,
+            Action::ClearMoves => Keycode::Q,
+            Action::Undo => Keycode::Z,
+            Action::Pause => Keycode::P,
+            Action::Step => Keycode::N,
+            Action::Mute => Keycode::M,
+            Action::Faster => Keycode::Equals,
+            Action::Slower => Keycode::Minus,
+            Action::PanUp => Keycode::W,
+            Action::PanLeft => Keycode::A,
+            Action::PanDown => Keycode::S,
+            Action::PanRight => Keycode::D,
+            Action::ZoomIn => Keycode::PageUp,
+            Action::ZoomOut => Keycode::PageDown,
+            Action::FocusKing => Keycode::K,
+            Action::ToggleQueueing => Keycode::Space,
+            Action::CenterKing => Keycode::C,
+            Action::JumpToAlert => Keycode::X,
+            Action::ToggleGraph => Keycode::G,
+        }
+    }
+
+    /// The direction a move action queues a move in.
+    pub fn direction(&self) -> Option<Direction> {
+        match *self {
+            Action::MoveUp => Some(Direction::Up),
+            Action::MoveDown => Some(Direction::Down),
+            Action::MoveLeft => Some(Direction::Left),
+            Action::MoveRight => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    /// The direction a pan action moves the camera in.
+    pub fn pan(&self) -> Option<Direction> {
+        match *self {
+            Action::PanUp => Some(Direction::Up),
+            Action::PanDown => Some(Direction::Down),
+            Action::PanLeft => Some(Direction::Left),
+            Action::PanRight => Some(Direction::Right),
+            _ => None,
+        }
+    }
+}
+
+pub struct Keymap {
+    /// Indexed by `Action`.
+    keys: Vec<Keycode>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap { keys: ACTIONS.iter().map(|a| a.default_key()).collect() }
+    }
+}
+
+impl Keymap {
+    /// The keymap with `bindings` from action names to key names. Unknown actions or keys are
+    /// skipped with a warning.
+    pub fn from_bindings(bindings: &BTreeMap<String, String>) -> Self {
+        let mut keymap = Keymap::default();
+        for (name, key) in bindings.iter() {
+            let action = ACTIONS.iter().find(|a| a.name() == name);
+            match (action, Keycode::from_name(key)) {
+                (Some(&action), Some(keycode)) => keymap.keys[action as usize] = keycode,
+                (None, _) => println!("Unknown action in key bindings: {}", name),
+                (_, None) => println!("Unknown key in key bindings: {}", key),
+            }
+        }
+        keymap
+    }
+
+    /// The key of every action, by name.
+    pub fn bindings(&self) -> BTreeMap<String, String> {
+        ACTIONS.iter()
+            .map(|a| (a.name().to_string(), self.key(*a).name()))
+            .collect()
+    }
+
+    pub fn key(&self, action: Action) -> Keycode {
+        self.keys[action as usize]
+    }
+
+    /// Bind `action` to `keycode`. An action that already had the key gets the old key of
+    /// `action` instead, so that no key does two things.
+    pub fn set(&mut self, action: Action, keycode: Keycode) {
+        let old = self.key(action);
+        for key in self.keys.iter_mut().filter(|k| **k == keycode) {
+            *key = old;
+        }
+        self.keys[action as usize] = keycode;
+    }
+
+    /// The action bound to `keycode`, if any.
+    pub fn action(&self, keycode: Keycode) -> Option<Action> {
+        self.keys.iter().position(|&k| k == keycode).map(|i| ACTIONS[i])
+    }
+}
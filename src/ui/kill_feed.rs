@@ -0,0 +1,147 @@
+//! The feed of eliminations in the top right corner, below the scoreboard, and the banner
+//! across the window when we eliminate someone or are eliminated ourselves.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawMode, Drawable, Font, Point, Rect, Text};
+
+use generals::{GameEvent, Team};
+
+use super::draw_text;
+
+const MARGIN: f32 = 20.0;
+const LINE_HEIGHT: f32 = 26.0;
+const WIDTH: f32 = 330.0;
+const SWATCH: f32 = 12.0;
+/// How many eliminations the feed shows at once.
+const SHOWN: usize = 5;
+const FEED_FOR: Duration = Duration::from_secs(8);
+const BANNER_FOR: Duration = Duration::from_millis(2500);
+const BANNER_HEIGHT: f32 = 70.0;
+
+fn name(team: Team, us: Team) -> String {
+    if team == us {
+        "You".to_string()
+    } else {
+        format!("Player {}", team + 1)
+    }
+}
+
+/// The fraction of `duration` that is left since `at`, from 1 down to 0.
+fn left(at: Instant, duration: Duration) -> f32 {
+    let elapsed = at.elapsed();
+    let elapsed = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 * 1e-9;
+    let total = duration.as_secs() as f32 + duration.subsec_nanos() as f32 * 1e-9;
+    (1.0 - elapsed / total).max(0.0)
+}
+
+struct Banner {
+    text: String,
+    /// The team the banner is in the color of.
+    team: Team,
+    at: Instant,
+}
+
+pub struct KillFeed {
+    /// Who took whose King, and when.
+    kills: VecDeque<(Team, Team, Instant)>,
+    banner: Option<Banner>,
+}
+
+impl KillFeed {
+    pub fn new() -> Self {
+        KillFeed {
+            kills: VecDeque::new(),
+            banner: None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.kills.clear();
+        self.banner = None;
+    }
+
+    /// Add `event` to the feed if it is an elimination, which `us` sees a banner for if they
+    /// were in on it.
+    pub fn event(&mut self, event: &GameEvent, us: Team) {
+        let (team, victim) = match *event {
+            GameEvent::KingCaptured { team, victim, .. } => (team, victim),
+            _ => return,
+        };
+        self.kills.push_back((team, victim, Instant::now()));
+        if self.kills.len() > SHOWN {
+            self.kills.pop_front();
+        }
+        let text = if victim == us {
+            Some(format!("You were eliminated by {}", name(team, us)))
+        } else if team == us {
+            Some(format!("You eliminated {}", name(victim, us)))
+        } else {
+            None
+        };
+        if let Some(text) = text {
+            self.banner = Some(Banner {
+                text,
+                team,
+                at: Instant::now(),
+            });
+        }
+    }
+
+    /// Draw the feed with its top at `top`, and the banner if there is one.
+    pub fn draw<F>(&self, ctx: &mut Context, font: &Font, us: Team, top: f32, team_color: F)
+        -> GameResult<()>
+    where
+        F: Fn(Team) -> Color,
+    {
+        let (w, h) = (ctx.conf.window_width as f32, ctx.conf.window_height as f32);
+        let left_edge = w - MARGIN - WIDTH;
+        let shown = self.kills.iter().filter(|&&(_, _, at)| at.elapsed() < FEED_FOR);
+        for (i, &(team, victim, at)) in shown.enumerate() {
+            let alpha = (left(at, FEED_FOR) * 4.0).min(1.0);
+            let y = top + i as f32 * LINE_HEIGHT;
+            graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.6 * alpha))?;
+            graphics::rectangle(ctx,
+                                DrawMode::Fill,
+                                Rect::new(left_edge + WIDTH / 2.0,
+                                          y + LINE_HEIGHT / 2.0,
+                                          WIDTH,
+                                          LINE_HEIGHT - 2.0))?;
+            for &(team, x) in &[(team, 8.0), (victim, WIDTH - 8.0 - SWATCH)] {
+                let mut color = team_color(team);
+                color.a = alpha;
+                graphics::set_color(ctx, color)?;
+                graphics::rectangle(ctx,
+                                    DrawMode::Fill,
+                                    Rect::new(left_edge + x + SWATCH / 2.0,
+                                              y + LINE_HEIGHT / 2.0,
+                                              SWATCH,
+                                              SWATCH))?;
+            }
+            let text = format!("{} took {}'s King", name(team, us), name(victim, us));
+            draw_text(ctx,
+                      font,
+                      &text,
+                      left_edge + 16.0 + SWATCH,
+                      y + 2.0,
+                      Color::new(1.0, 1.0, 1.0, alpha))?;
+        }
+
+        let banner = match self.banner {
+            Some(ref banner) if banner.at.elapsed() < BANNER_FOR => banner,
+            _ => return Ok(()),
+        };
+        let alpha = (left(banner.at, BANNER_FOR) * 3.0).min(1.0);
+        let mut color = team_color(banner.team);
+        color.a = 0.8 * alpha;
+        graphics::set_color(ctx, color)?;
+        graphics::rectangle(ctx,
+                            DrawMode::Fill,
+                            Rect::new(w / 2.0, h / 3.0, w, BANNER_HEIGHT))?;
+        let text = Text::new(ctx, &banner.text, font)?;
+        graphics::set_color(ctx, Color::new(1.0, 1.0, 1.0, alpha))?;
+        text.draw(ctx, Point::new(w / 2.0, h / 3.0), 0.0)
+    }
+}
@@ -0,0 +1,347 @@
+use ggez::{Context, GameResult};
+use ggez::event::Keycode;
+use ggez::graphics::{self, Color, Font};
+
+use generals::{GameMode, MapSettings, DEFAULT_HOLD_TICKS, DEFAULT_TICK_LIMIT};
+use generals::bot;
+use generals::net::{Client, ClientMessage, LobbyInfo, ServerMessage};
+use generals::net::{CAP_BOTS, MAX_PLAYERS};
+use generals::rating::PlayerRating;
+use generals::replay::ReplayInfo;
+
+use super::draw_text;
+use super::theme::Theme;
+
+const LINE_HEIGHT: f32 = 28.0;
+const MARGIN: f32 = 40.0;
+/// Where the leaderboard is drawn.
+const LEADERBOARD_X: f32 = 900.0;
+
+fn gray() -> Color {
+    Color::new(0.6, 0.6, 0.6, 1.0)
+}
+
+/// The screens before a network game starts: browsing lobbies, and waiting in one.
+pub struct LobbyScreen {
+    lobbies: Vec<LobbyInfo>,
+    /// The lobby we are in.
+    current: Option<LobbyInfo>,
+    /// The selected lobby in the list.
+    selected: usize,
+    ready: bool,
+    /// The number of players of lobbies we create.
+    new_lobby_players: usize,
+    /// Whether lobbies we create are ranked. Ranked games are always 1v1.
+    new_lobby_ranked: bool,
+    /// The mode of lobbies we create.
+    new_lobby_mode: GameMode,
+    /// The replays on the server, while we are browsing them.
+    replays: Option<Vec<ReplayInfo>>,
+    /// The selected replay.
+    selected_replay: usize,
+    /// The last leaderboard we got, while it is shown.
+    leaderboard: Option<Vec<(String, PlayerRating)>>,
+    /// The last error from the server.
+    error: Option<String>,
+}
+
+impl LobbyScreen {
+    pub fn new() -> Self {
+        LobbyScreen {
+            lobbies: Vec::new(),
+            current: None,
+            selected: 0,
+            ready: false,
+            new_lobby_players: 2,
+            new_lobby_ranked: false,
+            new_lobby_mode: GameMode::Classic,
+            replays: None,
+            selected_replay: 0,
+            leaderboard: None,
+            error: None,
+        }
+    }
+
+    /// Update from a message from the server. Messages about the game itself are ignored.
+    pub fn handle(&mut self, msg: &ServerMessage) {
+        match *msg {
+            ServerMessage::Lobbies(ref lobbies) => {
+                self.lobbies = lobbies.clone();
+                self.selected = self.selected.min(self.lobbies.len().saturating_sub(1));
+            }
+            ServerMessage::Lobby(ref lobby) => {
+                self.current = Some(lobby.clone());
+                self.error = None;
+            }
+            ServerMessage::LeftLobby => {
+                self.current = None;
+                self.ready = false;
+            }
+            ServerMessage::GameOver { .. } => {
+                self.current = None;
+                self.ready = false;
+            }
+            ServerMessage::Error(ref e) => self.error = Some(e.clone()),
+            ServerMessage::Replays(ref replays) => {
+                self.replays = Some(replays.clone());
+                self.selected_replay = 0;
+            }
+            ServerMessage::ReplayData { .. } => self.replays = None,
+            ServerMessage::Leaderboard(ref players) => self.leaderboard = Some(players.clone()),
+            _ => {}
+        }
+    }
+
+    pub fn key_down(&mut self, keycode: Keycode, client: &mut Client) {
+        let msg = if let Some(ref lobby) = self.current {
+            match keycode {
+                Keycode::Space => {
+                    self.ready = !self.ready;
+                    Some(ClientMessage::Ready(self.ready))
+                }
+                Keycode::Escape => Some(ClientMessage::LeaveLobby),
+                Keycode::B | Keycode::N | Keycode::M if !client.has(CAP_BOTS) => {
+                    self.error = Some("This server has no bots".to_string());
+                    None
+                }
+                Keycode::B => {
+                    let mut bots = lobby.bots.clone();
+                    bots.push(bot::DEFAULT_BOT.to_string());
+                    Some(ClientMessage::SetBots(bots))
+                }
+                Keycode::N => {
+                    let mut bots = lobby.bots.clone();
+                    bots.pop();
+                    Some(ClientMessage::SetBots(bots))
+                }
+                Keycode::M if !lobby.bots.is_empty() => {
+                    // Switch the last bot to the next kind of bot.
+                    let mut bots = lobby.bots.clone();
+                    let last = bots.last_mut().unwrap();
+                    let i = bot::NAMES.iter().position(|&n| n == last.as_str()).unwrap_or(0);
+                    *last = bot::NAMES[(i + 1) % bot::NAMES.len()].to_string();
+                    Some(ClientMessage::SetBots(bots))
+                }
+                _ => None,
+            }
+        } else if let Some(ref replays) = self.replays {
+            match keycode {
+                Keycode::Up => {
+                    self.selected_replay = self.selected_replay.saturating_sub(1);
+                    None
+                }
+                Keycode::Down => {
+                    if self.selected_replay + 1 < replays.len() {
+                        self.selected_replay += 1;
+                    }
+                    None
+                }
+                Keycode::Return => {
+                    replays.get(self.selected_replay)
+                        .map(|r| ClientMessage::DownloadReplay(r.file.clone()))
+                }
+                Keycode::Escape | Keycode::P => {
+                    self.replays = None;
+                    None
+                }
+                _ => None,
+            }
+        } else {
+            match keycode {
+                Keycode::Up => {
+                    self.selected = self.selected.saturating_sub(1);
+                    None
+                }
+                Keycode::Down => {
+                    if self.selected + 1 < self.lobbies.len() {
+                        self.selected += 1;
+                    }
+                    None
+                }
+                Keycode::Return => self.lobbies.get(self.selected).map(|l| ClientMessage::JoinLobby(l.id)),
+                Keycode::V => {
+                    self.lobbies.get(self.selected).map(|l| {
+                        ClientMessage::Spectate {
+                            lobby: l.id,
+                            team: None,
+                        }
+                    })
+                }
+                Keycode::R => Some(ClientMessage::ListLobbies),
+                Keycode::P => Some(ClientMessage::ListReplays),
+                Keycode::Equals | Keycode::Plus => {
+                    self.new_lobby_players = (self.new_lobby_players + 1).min(MAX_PLAYERS);
+                    None
+                }
+                Keycode::Minus => {
+                    self.new_lobby_players = (self.new_lobby_players - 1).max(2);
+                    None
+                }
+                Keycode::T => {
+                    self.new_lobby_ranked = !self.new_lobby_ranked;
+                    None
+                }
+                Keycode::K => {
+                    self.new_lobby_mode = match self.new_lobby_mode {
+                        GameMode::Classic => {
+                            GameMode::KingOfTheHill { hold_ticks: DEFAULT_HOLD_TICKS }
+                        }
+                        GameMode::KingOfTheHill { .. } => {
+                            GameMode::Blitz { ticks: DEFAULT_TICK_LIMIT }
+                        }
+                        GameMode::Blitz { .. } => GameMode::Horde,
+                        GameMode::Horde => GameMode::Classic,
+                    };
+                    None
+                }
+                Keycode::L => {
+                    if self.leaderboard.take().is_none() {
+                        Some(ClientMessage::Leaderboard)
+                    } else {
+                        None
+                    }
+                }
+                Keycode::C => {
+                    let (name, num_players) = if self.new_lobby_ranked {
+                        ("Ranked 1v1".to_string(), 2)
+                    } else {
+                        (format!("{} player game", self.new_lobby_players), self.new_lobby_players)
+                    };
+                    Some(ClientMessage::CreateLobby {
+                        name,
+                        num_players,
+                        map: MapSettings::default(),
+                        ranked: self.new_lobby_ranked,
+                        mode: self.new_lobby_mode,
+                    })
+                }
+                _ => None,
+            }
+        };
+        if let Some(msg) = msg {
+            if let Err(e) = client.send(&msg) {
+                self.error = Some(format!("Failed to send: {}", e));
+            }
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut Context, font: &Font, theme: &Theme) -> GameResult<()> {
+        graphics::set_background_color(ctx, theme.background);
+        graphics::clear(ctx);
+        let mut y = MARGIN;
+        let mut line = |ctx: &mut Context, text: &str, color: Color| -> GameResult<()> {
+            draw_text(ctx, font, text, MARGIN, y, color)?;
+            y += LINE_HEIGHT;
+            Ok(())
+        };
+        match self.current {
+            None if self.replays.is_some() => {
+                let replays = self.replays.as_ref().unwrap();
+                line(ctx, "Replays", theme.text)?;
+                if replays.is_empty() {
+                    line(ctx, "  No games have been played yet", gray())?;
+                }
+                for (i, info) in replays.iter().enumerate() {
+                    let marker = if i == self.selected_replay { ">" } else { " " };
+                    let winner = info.winner
+                        .and_then(|team| info.players.get(team))
+                        .map(|name| format!(", {} won", name))
+                        .unwrap_or_default();
+                    let text = format!("{} {}: {} ({} ticks{})",
+                                       marker,
+                                       info.title,
+                                       info.players.join(" vs "),
+                                       info.ticks,
+                                       winner);
+                    line(ctx, &text, theme.text)?;
+                }
+                line(ctx, "", theme.text)?;
+                line(ctx, "Up/Down: select   Enter: watch   Esc: back", gray())?;
+            }
+            Some(ref lobby) => {
+                line(ctx, &format!("Lobby: {} ({})", lobby.name, lobby.mode.name()), theme.text)?;
+                line(ctx,
+                     &format!("{}/{} players",
+                              lobby.members.len() + lobby.bots.len(),
+                              lobby.num_players),
+                     gray())?;
+                for member in lobby.members.iter() {
+                    let status = if member.ready { "ready" } else { "not ready" };
+                    line(ctx, &format!("  {} ({})", member.name, status), theme.text)?;
+                }
+                for name in lobby.bots.iter() {
+                    line(ctx, &format!("  {} (bot)", name), gray())?;
+                }
+                line(ctx, "", theme.text)?;
+                line(ctx,
+                     "Space: toggle ready   B/N: add/remove a bot   M: change the last bot   \
+                      Esc: leave. The game starts when everyone is ready.",
+                     gray())?;
+            }
+            None => {
+                line(ctx, "Lobbies", theme.text)?;
+                if self.lobbies.is_empty() {
+                    line(ctx, "  No open lobbies", gray())?;
+                }
+                for (i, lobby) in self.lobbies.iter().enumerate() {
+                    let marker = if i == self.selected { ">" } else { " " };
+                    let status = match (lobby.ranked, lobby.started) {
+                        (true, true) => ", ranked, playing",
+                        (true, false) => ", ranked",
+                        (false, true) => ", playing",
+                        (false, false) => "",
+                    };
+                    let spectators = match lobby.spectators {
+                        0 => String::new(),
+                        n => format!(", {} watching", n),
+                    };
+                    let text = format!("{} {} ({}/{}{}{})",
+                                       marker,
+                                       lobby.name,
+                                       lobby.members.len() + lobby.bots.len(),
+                                       lobby.num_players,
+                                       status,
+                                       spectators);
+                    line(ctx, &text, if lobby.started { gray() } else { theme.text })?;
+                }
+                line(ctx, "", theme.text)?;
+                let create = if self.new_lobby_ranked {
+                    "a ranked 1v1 game".to_string()
+                } else {
+                    format!("a {} player game", self.new_lobby_players)
+                };
+                let create = match self.new_lobby_mode {
+                    GameMode::Classic => create,
+                    mode => format!("{} of {}", create, mode.name()),
+                };
+                line(ctx,
+                     "Up/Down: select   Enter: join   V: watch   R: refresh   L: leaderboard   \
+                      P: replays",
+                     gray())?;
+                line(ctx,
+                     &format!("C: create {}   +/-: change players   T: toggle ranked   \
+                              K: change the mode",
+                              create),
+                     gray())?;
+            }
+        }
+        if let Some(ref error) = self.error {
+            line(ctx, error, Color::new(1.0, 0.3, 0.3, 1.0))?;
+        }
+        if let Some(ref players) = self.leaderboard {
+            draw_text(ctx, font, "Leaderboard", LEADERBOARD_X, MARGIN, theme.text)?;
+            for (i, &(ref name, ref rating)) in players.iter().enumerate() {
+                let text = format!("{:>2}. {} {:.0} ({}-{}-{})",
+                                   i + 1,
+                                   name,
+                                   rating.rating,
+                                   rating.wins,
+                                   rating.losses,
+                                   rating.draws);
+                let y = MARGIN + (i + 1) as f32 * LINE_HEIGHT;
+                draw_text(ctx, font, &text, LEADERBOARD_X, y, gray())?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+//! Marks that make Kings easy to find: a crown on King cells, and an arrow at the edge of the
+//! window pointing at our King when it is off screen.
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawMode, Point, Rect};
+
+use generals::Position;
+
+use super::camera::Camera;
+
+/// How far the arrow stays from the edges of the window.
+const ARROW_MARGIN: f32 = 30.0;
+const ARROW_LENGTH: f32 = 26.0;
+const ARROW_WIDTH: f32 = 22.0;
+
+/// Draw a crown in the top part of `rect`, which is positioned by its center. It is drawn in
+/// the flat style, for when there is no crown sprite, and leaves room for the unit count.
+pub fn draw_crown(ctx: &mut Context, rect: Rect, color: Color) -> GameResult<()> {
+    let (left, top) = (rect.x - rect.w / 2.0, rect.y - rect.h / 2.0);
+    let at = |fx: f32, fy: f32| Point::new(left + rect.w * fx, top + rect.h * fy);
+    let crown = [at(0.3, 0.32),
+                 at(0.3, 0.14),
+                 at(0.4, 0.24),
+                 at(0.5, 0.1),
+                 at(0.6, 0.24),
+                 at(0.7, 0.14),
+                 at(0.7, 0.32)];
+    graphics::set_color(ctx, color)?;
+    graphics::polygon(ctx, DrawMode::Fill, &crown)
+}
+
+/// Draw an arrow at the edge of the window, pointing at the cell at `pos`, unless the cell is
+/// in view.
+pub fn draw_arrow_to(ctx: &mut Context, camera: &Camera, pos: Position, color: Color)
+    -> GameResult<()> {
+    if camera.is_visible(pos) {
+        return Ok(());
+    }
+    let (w, h) = (ctx.conf.window_width as f32, ctx.conf.window_height as f32);
+    let target = camera.cell_center(pos.0, pos.1);
+    let (dx, dy) = (target.x - w / 2.0, target.y - h / 2.0);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length < 1.0 {
+        return Ok(());
+    }
+    // Follow the line from the middle of the window to the cell until it meets the margin.
+    let reach = ((w / 2.0 - ARROW_MARGIN) / dx.abs()).min((h / 2.0 - ARROW_MARGIN) / dy.abs());
+    let tip = Point::new(w / 2.0 + dx * reach, h / 2.0 + dy * reach);
+    let (ux, uy) = (dx / length, dy / length);
+    let base = Point::new(tip.x - ux * ARROW_LENGTH, tip.y - uy * ARROW_LENGTH);
+    let (px, py) = (-uy * ARROW_WIDTH / 2.0, ux * ARROW_WIDTH / 2.0);
+    let arrow = [tip,
+                 Point::new(base.x + px, base.y + py),
+                 Point::new(base.x - px, base.y - py)];
+    graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.7))?;
+    graphics::circle(ctx, DrawMode::Fill, base, ARROW_WIDTH * 0.8, 0.5)?;
+    graphics::set_color(ctx, color)?;
+    graphics::polygon(ctx, DrawMode::Fill, &arrow)
+}
@@ -0,0 +1,93 @@
+//! The list of finished local games, opened from the menu.
+
+use ggez::{Context, GameResult};
+use ggez::event::Keycode;
+use ggez::graphics::{self, Color, Font};
+
+use generals::daily;
+use generals::matches::{MatchOutcome, MatchSummary};
+
+use super::draw_text;
+use super::theme::Theme;
+
+const LINE_HEIGHT: f32 = 28.0;
+const MARGIN: f32 = 40.0;
+/// The number of games shown at once.
+const PAGE: usize = 20;
+
+pub struct MatchHistory {
+    /// Newest first.
+    matches: Vec<MatchSummary>,
+    /// The index of the first game shown.
+    scroll: usize,
+    /// The name we play under, if we have one.
+    name: Option<String>,
+}
+
+impl MatchHistory {
+    /// The history of `matches`, which are oldest first like in the history file.
+    pub fn new(mut matches: Vec<MatchSummary>, name: Option<String>) -> Self {
+        matches.reverse();
+        MatchHistory {
+            matches,
+            scroll: 0,
+            name,
+        }
+    }
+
+    /// Returns `false` when the list is closed.
+    pub fn key_down(&mut self, keycode: Keycode) -> bool {
+        let last = self.matches.len().saturating_sub(PAGE);
+        match keycode {
+            Keycode::Up => self.scroll = self.scroll.saturating_sub(1),
+            Keycode::Down => self.scroll = (self.scroll + 1).min(last),
+            Keycode::PageUp => self.scroll = self.scroll.saturating_sub(PAGE),
+            Keycode::PageDown => self.scroll = (self.scroll + PAGE).min(last),
+            Keycode::Escape | Keycode::Return => return false,
+            _ => {}
+        }
+        true
+    }
+
+    fn count(&self, outcome: MatchOutcome) -> usize {
+        self.matches.iter().filter(|m| m.outcome == outcome).count()
+    }
+
+    pub fn draw(&self, ctx: &mut Context, font: &Font, theme: &Theme) -> GameResult<()> {
+        graphics::set_background_color(ctx, theme.background);
+        graphics::clear(ctx);
+        let gray = Color::new(0.6, 0.6, 0.6, 1.0);
+        let mut y = MARGIN;
+        let title = match self.name {
+            Some(ref name) => format!("Games of {}", name),
+            None => "Games".to_string(),
+        };
+        draw_text(ctx, font, &title, MARGIN, y, theme.text)?;
+        y += LINE_HEIGHT;
+        let record = format!("{} played: {} won, {} lost, {} drawn",
+                             self.matches.len(),
+                             self.count(MatchOutcome::Won),
+                             self.count(MatchOutcome::Lost),
+                             self.count(MatchOutcome::Draw));
+        draw_text(ctx, font, &record, MARGIN, y, gray)?;
+        y += LINE_HEIGHT * 1.5;
+        if self.matches.is_empty() {
+            draw_text(ctx, font, "No games finished yet", MARGIN, y, theme.text)?;
+            y += LINE_HEIGHT;
+        }
+        for m in self.matches.iter().skip(self.scroll).take(PAGE) {
+            let seed = m.seed.map(|s| format!(", seed {}", s)).unwrap_or_default();
+            let text = format!("{}  {:<5} {} against {}, {} ticks{}",
+                               daily::date(m.finished / (24 * 60 * 60)),
+                               m.outcome.name(),
+                               m.mode.name(),
+                               m.opponents.join(", "),
+                               m.ticks,
+                               seed);
+            draw_text(ctx, font, &text, MARGIN, y, theme.text)?;
+            y += LINE_HEIGHT;
+        }
+        y += LINE_HEIGHT;
+        draw_text(ctx, font, "Up/Down: scroll   Escape: back", MARGIN, y, gray)
+    }
+}
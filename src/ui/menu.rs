@@ -0,0 +1,232 @@
+use std::path::PathBuf;
+
+use ggez::{Context, GameResult};
+use ggez::event::Keycode;
+use ggez::graphics::{self, Color, Font};
+
+use generals::achievements::Unlocked;
+use generals::campaign::{Progress, MISSIONS};
+use generals::daily::{self, Challenge, DailyResult};
+use generals::matches::MatchSummary;
+use generals::net::MAX_PLAYERS;
+use generals::replay::SavedReplay;
+
+use super::achievements::Gallery;
+use super::draw_text;
+use super::match_history::MatchHistory;
+use super::replay_browser::{Browse, ReplayBrowser};
+use super::theme::Theme;
+
+const LINE_HEIGHT: f32 = 28.0;
+const MARGIN: f32 = 40.0;
+
+fn gray() -> Color {
+    Color::new(0.6, 0.6, 0.6, 1.0)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuChoice {
+    /// Play a new game against the bots, with this many players in all.
+    NewGame(usize),
+    /// Play this mission of the campaign.
+    Campaign(usize),
+    /// Play today's daily challenge.
+    Daily,
+    /// Play the game that teaches the controls.
+    Tutorial,
+    /// Look through the games played before.
+    History,
+    /// See which achievements are unlocked.
+    Achievements,
+    /// Browse the saved replays.
+    Replays,
+    /// Watch the replay at this path, which was picked in the browser.
+    WatchReplay(PathBuf),
+    /// Step through the game that was just played.
+    Review,
+}
+
+/// The menu shown between local games.
+pub struct Menu {
+    items: Vec<MenuChoice>,
+    selected: usize,
+    /// The number of players of new games, which is changed with Left and Right.
+    players: usize,
+    /// The mission of the campaign to play, which is also changed with Left and Right.
+    mission: usize,
+    progress: Progress,
+    /// The daily challenge of today, and our best result in it so far.
+    challenge: Challenge,
+    best: Option<DailyResult>,
+    /// `Some` while the list of earlier games is shown instead of the menu.
+    history: Option<MatchHistory>,
+    /// `Some` while the achievements are shown instead of the menu.
+    gallery: Option<Gallery>,
+    /// `Some` while the saved replays are shown instead of the menu.
+    replays: Option<ReplayBrowser>,
+}
+
+impl Menu {
+    /// `can_review` is whether there is a finished game to review, `players` how many players
+    /// the last game had, `progress` how far we got in the campaign, and `best` our best result
+    /// in the daily challenge of today.
+    pub fn new(can_review: bool, players: usize, progress: Progress, best: Option<DailyResult>)
+        -> Self {
+        let mission = progress.completed.min(MISSIONS.len() - 1);
+        let mut items = vec![MenuChoice::NewGame(players),
+                             MenuChoice::Campaign(mission),
+                             MenuChoice::Daily,
+                             MenuChoice::Tutorial,
+                             MenuChoice::History,
+                             MenuChoice::Achievements,
+                             MenuChoice::Replays];
+        if can_review {
+            items.push(MenuChoice::Review);
+        }
+        Menu {
+            items,
+            selected: 0,
+            players,
+            mission,
+            progress,
+            challenge: Challenge::today(),
+            best,
+            history: None,
+            gallery: None,
+            replays: None,
+        }
+    }
+
+    /// Show `matches`, the games played under `name`, until the list is closed.
+    pub fn show_history(&mut self, matches: Vec<MatchSummary>, name: Option<String>) {
+        self.history = Some(MatchHistory::new(matches, name));
+    }
+
+    /// Show the achievements, with the ones in `unlocked` marked, until the gallery is closed.
+    pub fn show_achievements(&mut self, unlocked: Unlocked) {
+        self.gallery = Some(Gallery::new(unlocked));
+    }
+
+    /// Show `replays` to pick one to watch.
+    pub fn show_replays(&mut self, replays: Vec<SavedReplay>) {
+        self.replays = Some(ReplayBrowser::new(replays));
+    }
+
+    /// Returns the chosen item when Enter is pressed.
+    pub fn key_down(&mut self, keycode: Keycode) -> Option<MenuChoice> {
+        if let Some(mut history) = self.history.take() {
+            if history.key_down(keycode) {
+                self.history = Some(history);
+            }
+            return None;
+        }
+        if let Some(mut browser) = self.replays.take() {
+            return match browser.key_down(keycode) {
+                Browse::Stay => {
+                    self.replays = Some(browser);
+                    None
+                }
+                Browse::Close => None,
+                Browse::Open(path) => Some(MenuChoice::WatchReplay(path)),
+            };
+        }
+        if let Some(mut gallery) = self.gallery.take() {
+            if gallery.key_down(keycode) {
+                self.gallery = Some(gallery);
+            }
+            return None;
+        }
+        match keycode {
+            Keycode::Up => self.selected = self.selected.saturating_sub(1),
+            Keycode::Down => self.selected = (self.selected + 1).min(self.items.len() - 1),
+            Keycode::Left | Keycode::Right => {
+                let right = keycode == Keycode::Right;
+                match self.items[self.selected] {
+                    MenuChoice::Campaign(_) if right => {
+                        if self.progress.is_unlocked(self.mission + 1) {
+                            self.mission += 1;
+                        }
+                    }
+                    MenuChoice::Campaign(_) => self.mission = self.mission.saturating_sub(1),
+                    _ if right => self.players = (self.players + 1).min(MAX_PLAYERS),
+                    _ => self.players = (self.players - 1).max(2),
+                }
+            }
+            Keycode::Return => {
+                return Some(match self.items[self.selected] {
+                    MenuChoice::NewGame(_) => MenuChoice::NewGame(self.players),
+                    MenuChoice::Campaign(_) => MenuChoice::Campaign(self.mission),
+                    ref choice => choice.clone(),
+                })
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn label(&self, choice: &MenuChoice) -> String {
+        match *choice {
+            MenuChoice::NewGame(_) if self.players == 2 => "New game: 1v1".to_string(),
+            MenuChoice::NewGame(_) => format!("New game: {} player free-for-all", self.players),
+            MenuChoice::Campaign(_) => {
+                let last = self.mission + 1 == MISSIONS.len();
+                let next = if last || self.progress.is_unlocked(self.mission + 1) {
+                    ""
+                } else {
+                    ", win it to unlock the next"
+                };
+                format!("Campaign: mission {} of {}, {}{}",
+                        self.mission + 1,
+                        MISSIONS.len(),
+                        MISSIONS[self.mission].scenario().title,
+                        next)
+            }
+            MenuChoice::Daily => {
+                let best = match self.best {
+                    Some(best) => format!("best: {}", best.describe()),
+                    None => "not played yet".to_string(),
+                };
+                format!("Daily challenge of {} against {}, {}",
+                        daily::date(self.challenge.day),
+                        self.challenge.bot,
+                        best)
+            }
+            MenuChoice::Tutorial => "Tutorial: learn to play".to_string(),
+            MenuChoice::History => "Earlier games".to_string(),
+            MenuChoice::Achievements => "Achievements".to_string(),
+            MenuChoice::Replays => "Saved replays".to_string(),
+            MenuChoice::WatchReplay(_) => "Watch a replay".to_string(),
+            MenuChoice::Review => "Review the last game".to_string(),
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut Context, font: &Font, theme: &Theme) -> GameResult<()> {
+        if let Some(ref history) = self.history {
+            return history.draw(ctx, font, theme);
+        }
+        if let Some(ref browser) = self.replays {
+            return browser.draw(ctx, font, theme);
+        }
+        if let Some(ref gallery) = self.gallery {
+            return gallery.draw(ctx, font, theme);
+        }
+        graphics::set_background_color(ctx, theme.background);
+        graphics::clear(ctx);
+        let mut y = MARGIN;
+        draw_text(ctx, font, "Generals", MARGIN, y, theme.text)?;
+        y += LINE_HEIGHT * 2.0;
+        for (i, choice) in self.items.iter().enumerate() {
+            let marker = if i == self.selected { ">" } else { " " };
+            let text = format!("{} {}", marker, self.label(choice));
+            draw_text(ctx, font, &text, MARGIN, y, theme.text)?;
+            y += LINE_HEIGHT;
+        }
+        y += LINE_HEIGHT;
+        draw_text(ctx,
+                  font,
+                  "Up/Down: select   Left/Right: number of players or mission   Enter: choose",
+                  MARGIN,
+                  y,
+                  gray())
+    }
+}
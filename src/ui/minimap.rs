@@ -0,0 +1,140 @@
+//! A small overview of the whole board in a corner of the window.
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Image, Point, Rect};
+
+use generals::{PlayerView, Position, Team, Tile};
+
+use super::camera::Camera;
+
+/// The longest side of the minimap, in pixels.
+const SIZE: f32 = 220.0;
+const MARGIN: f32 = 20.0;
+/// Boards larger than this many cells across are shrunk, so that every pixel of the minimap
+/// image covers a block of cells.
+const MAX_BLOCKS: i32 = 64;
+
+fn owner(tile: &Tile) -> Option<Team> {
+    match *tile {
+        Tile::Visible(cell) => cell.owner(),
+        _ => None,
+    }
+}
+
+fn rgba(color: Color) -> [u8; 4] {
+    [(color.r * 255.0) as u8, (color.g * 255.0) as u8, (color.b * 255.0) as u8, 255]
+}
+
+pub struct Minimap {
+    /// The tick the image was made for.
+    tick: Option<usize>,
+    dimens: (i32, i32),
+    /// The side length of the block of cells each pixel of the image covers.
+    block: i32,
+    image: Option<Image>,
+    /// Where the minimap was last drawn, with `x` and `y` at the top left corner.
+    area: Rect,
+}
+
+impl Minimap {
+    pub fn new() -> Self {
+        Minimap {
+            tick: None,
+            dimens: (0, 0),
+            block: 1,
+            image: None,
+            area: Rect::new(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Rebuild the image if `view` is of another tick than the one we have. Each block is
+    /// colored by whoever owns most of it, or by its top left tile if nobody owns any of it.
+    pub fn update<F>(&mut self, ctx: &mut Context, view: &PlayerView, color: F) -> GameResult<()>
+    where
+        F: Fn(&Tile) -> Color,
+    {
+        if self.tick == Some(view.tick_number) && self.dimens == view.dimens {
+            return Ok(());
+        }
+        let (w, h) = view.dimens;
+        let block = ((w.max(h) + MAX_BLOCKS - 1) / MAX_BLOCKS).max(1);
+        let (bw, bh) = ((w + block - 1) / block, (h + block - 1) / block);
+        let mut pixels = Vec::with_capacity((bw * bh * 4) as usize);
+        let mut owned = Vec::new();
+        for by in 0..bh {
+            for bx in 0..bw {
+                owned.clear();
+                let mut first = None;
+                for y in by * block..((by + 1) * block).min(h) {
+                    for x in bx * block..((bx + 1) * block).min(w) {
+                        let tile = &view.tiles[y as usize][x as usize];
+                        first = first.or(Some(tile));
+                        if let Some(team) = owner(tile) {
+                            owned.push((team, tile));
+                        }
+                    }
+                }
+                owned.sort_by_key(|&(team, _)| team);
+                let majority = owned.iter()
+                    .max_by_key(|&&(team, _)| owned.iter().filter(|o| o.0 == team).count())
+                    .map(|&(_, tile)| tile);
+                let tile = majority.or(first).unwrap();
+                pixels.extend_from_slice(&rgba(color(tile)));
+            }
+        }
+        self.image = Some(Image::from_rgba8(ctx, bw as u16, bh as u16, &pixels)?);
+        self.tick = Some(view.tick_number);
+        self.dimens = view.dimens;
+        self.block = block;
+        Ok(())
+    }
+
+    /// Draw the minimap in the bottom right corner of the window, with the part of the board
+    /// the camera shows outlined.
+    pub fn draw(&mut self, ctx: &mut Context, camera: &Camera) -> GameResult<()> {
+        let image = match self.image {
+            Some(ref image) => image,
+            None => return Ok(()),
+        };
+        let (w, h) = (self.dimens.0 as f32, self.dimens.1 as f32);
+        let scale = SIZE / w.max(h);
+        let (window_w, window_h) = (ctx.conf.window_width as f32, ctx.conf.window_height as f32);
+        self.area = Rect::new(window_w - MARGIN - w * scale,
+                              window_h - MARGIN - h * scale,
+                              w * scale,
+                              h * scale);
+        let center = Point::new(self.area.x + self.area.w / 2.0, self.area.y + self.area.h / 2.0);
+
+        graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 1.0))?;
+        graphics::rectangle(ctx,
+                            DrawMode::Fill,
+                            Rect::new(center.x, center.y, self.area.w + 4.0, self.area.h + 4.0))?;
+        graphics::set_color(ctx, Color::new(1.0, 1.0, 1.0, 1.0))?;
+        let pixel = scale * self.block as f32;
+        graphics::draw_ex(ctx,
+                          image,
+                          DrawParam {
+                              dest: center,
+                              scale: Point::new(pixel, pixel),
+                              ..Default::default()
+                          })?;
+
+        let ((x0, x1), (y0, y1)) = camera.visible_cells(self.dimens);
+        let view = Rect::new(self.area.x + (x0 + x1) as f32 / 2.0 * scale,
+                             self.area.y + (y0 + y1) as f32 / 2.0 * scale,
+                             (x1 - x0) as f32 * scale,
+                             (y1 - y0) as f32 * scale);
+        graphics::set_color(ctx, Color::new(1.0, 1.0, 0.3, 1.0))?;
+        graphics::rectangle(ctx, DrawMode::Line, view)
+    }
+
+    /// The cell under the window position `(x, y)`, if it is on the minimap.
+    pub fn cell_at(&self, x: i32, y: i32) -> Option<Position> {
+        let (x, y) = (x as f32 - self.area.x, y as f32 - self.area.y);
+        if self.image.is_none() || x < 0.0 || y < 0.0 || x >= self.area.w || y >= self.area.h {
+            return None;
+        }
+        let scale = self.area.w / self.dimens.0 as f32;
+        Some(Position((x / scale) as i32, (y / scale) as i32))
+    }
+}
@@ -0,0 +1,45 @@
+//! Screens and widgets of the windowed client.
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, Drawable, Font, Point, Text};
+
+pub mod achievements;
+pub mod animation;
+pub mod borders;
+pub mod camera;
+pub mod cell_text;
+pub mod chat;
+pub mod debug;
+pub mod game_over;
+pub mod gamepad;
+pub mod graph;
+pub mod hud;
+pub mod keymap;
+pub mod kill_feed;
+pub mod lobby;
+pub mod marker;
+pub mod match_history;
+pub mod menu;
+pub mod minimap;
+pub mod palette;
+pub mod player_list;
+pub mod replay_browser;
+pub mod sandbox;
+pub mod scoreboard;
+pub mod screenshot;
+pub mod settings;
+pub mod sprites;
+pub mod theme;
+pub mod threats;
+pub mod toasts;
+pub mod tutorial;
+
+/// Draw `text` with its top left corner at `(x, y)`.
+pub fn draw_text(ctx: &mut Context, font: &Font, text: &str, x: f32, y: f32, color: Color)
+    -> GameResult<()> {
+    let t = Text::new(ctx, text, font)?;
+    graphics::set_color(ctx, color)?;
+    // Drawables are positioned by their center.
+    let dest = Point::new(x + t.width() as f32 / 2.0, y + t.height() as f32 / 2.0);
+    t.draw(ctx, dest, 0.0)
+}
@@ -0,0 +1,140 @@
+//! Team colors, and patterns that tell teams apart without relying on color alone.
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawMode, Point, Rect};
+
+use generals::Team;
+
+/// The built-in palettes. `Custom` takes its colors from the settings file.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PaletteKind {
+    Classic,
+    /// The Okabe-Ito colors, which stay distinct with deuteranopia and protanopia.
+    ColorblindSafe,
+    Custom,
+}
+
+const KINDS: [PaletteKind; 3] =
+    [PaletteKind::Classic, PaletteKind::ColorblindSafe, PaletteKind::Custom];
+
+impl PaletteKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            PaletteKind::Classic => "Classic",
+            PaletteKind::ColorblindSafe => "Colorblind-safe",
+            PaletteKind::Custom => "Custom",
+        }
+    }
+
+    /// The next palette in the list, or the previous one for `step < 0`.
+    pub fn cycle(&self, step: i64) -> Self {
+        let i = KINDS.iter().position(|k| k == self).unwrap() as i64;
+        let n = KINDS.len() as i64;
+        KINDS[((i + step) % n + n) as usize % KINDS.len()]
+    }
+}
+
+const CLASSIC: [[u8; 3]; 8] = [[255, 25, 25],
+                               [25, 25, 255],
+                               [25, 170, 25],
+                               [150, 25, 200],
+                               [255, 140, 0],
+                               [0, 150, 150],
+                               [140, 75, 25],
+                               [255, 100, 180]];
+
+const COLORBLIND_SAFE: [[u8; 3]; 8] = [[230, 159, 0],
+                                       [86, 180, 233],
+                                       [0, 158, 115],
+                                       [240, 228, 66],
+                                       [0, 114, 178],
+                                       [213, 94, 0],
+                                       [204, 121, 167],
+                                       [120, 120, 120]];
+
+fn rgb(c: [u8; 3]) -> Color {
+    Color::new(c[0] as f32 / 255.0, c[1] as f32 / 255.0, c[2] as f32 / 255.0, 1.0)
+}
+
+pub struct Palette {
+    teams: Vec<Color>,
+    /// Our team, and the color we want it in instead of its own.
+    own: Option<(Team, usize)>,
+}
+
+impl Palette {
+    /// The colors of `kind`. For `Custom`, teams without a color in `custom` get their classic
+    /// color.
+    pub fn new(kind: PaletteKind, custom: &[[u8; 3]]) -> Self {
+        let base = match kind {
+            PaletteKind::ColorblindSafe => &COLORBLIND_SAFE,
+            PaletteKind::Classic | PaletteKind::Custom => &CLASSIC,
+        };
+        let teams = base.iter()
+            .enumerate()
+            .map(|(i, &c)| match kind {
+                PaletteKind::Custom => rgb(custom.get(i).cloned().unwrap_or(c)),
+                _ => rgb(c),
+            })
+            .collect();
+        Palette { teams, own: None }
+    }
+
+    /// Show `team` in the color at `color`. The team that has that color gets the one of `team`
+    /// instead.
+    pub fn set_own(&mut self, team: Team, color: usize) {
+        self.own = Some((team, color % self.teams.len()));
+    }
+
+    pub fn team(&self, team: Team) -> Color {
+        let n = self.teams.len();
+        match self.own {
+            Some((us, color)) if team == us => self.teams[color],
+            Some((us, color)) if team % n == color => self.teams[us % n],
+            _ => self.teams[team % n],
+        }
+    }
+}
+
+/// Marks drawn on top of a team's cells.
+#[derive(Debug, Clone, Copy)]
+pub enum Pattern {
+    Solid,
+    Stripes,
+    Dots,
+    Checks,
+}
+
+pub fn pattern(team: Team) -> Pattern {
+    [Pattern::Solid, Pattern::Stripes, Pattern::Dots, Pattern::Checks][team % 4]
+}
+
+/// Draw `pattern` over `rect`, which is positioned by its center.
+pub fn draw_pattern(ctx: &mut Context, pattern: Pattern, rect: Rect) -> GameResult<()> {
+    let (left, top) = (rect.x - rect.w / 2.0, rect.y - rect.h / 2.0);
+    let quarters = [0.25, 0.5, 0.75];
+    graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.35))?;
+    match pattern {
+        Pattern::Solid => {}
+        Pattern::Stripes => {
+            for &q in quarters.iter() {
+                let y = top + rect.h * q;
+                graphics::line(ctx, &[Point::new(left, y), Point::new(left + rect.w, y)])?;
+            }
+        }
+        Pattern::Dots => {
+            for &(qx, qy) in &[(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)] {
+                let center = Point::new(left + rect.w * qx, top + rect.h * qy);
+                graphics::circle(ctx, DrawMode::Fill, center, rect.w * 0.07, 0.5)?;
+            }
+        }
+        Pattern::Checks => {
+            let size = rect.w / 4.0;
+            for &(qx, qy) in &[(0.25, 0.25), (0.75, 0.75)] {
+                let square = Rect::new(left + rect.w * qx, top + rect.h * qy, size, size);
+                graphics::rectangle(ctx, DrawMode::Fill, square)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+//! The table of players in a network game, below the scoreboard: who they are, whether they
+//! are still in the game, and how good their connection is.
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawMode, Font, Rect};
+
+use generals::Team;
+use generals::net::{PlayerInfo, Presence};
+
+use super::draw_text;
+
+const MARGIN: f32 = 20.0;
+const ROW_HEIGHT: f32 = 26.0;
+const SWATCH: f32 = 14.0;
+/// The left edges of the columns, relative to the table.
+const COLUMNS: [f32; 4] = [0.0, 140.0, 190.0, 270.0];
+const WIDTH: f32 = 330.0;
+/// Pings slower than this are shown in red.
+const SLOW_PING_MS: u32 = 250;
+
+/// The height of the table, for `players` players.
+pub fn height(players: usize) -> f32 {
+    ROW_HEIGHT * (players + 1) as f32 + 8.0
+}
+
+/// Draw the players in team order with the top of the table at `top`.
+pub fn draw<F>(ctx: &mut Context, font: &Font, players: &[PlayerInfo], us: Team, top: f32,
+               team_color: F)
+    -> GameResult<()>
+where
+    F: Fn(Team) -> Color,
+{
+    if players.is_empty() {
+        return Ok(());
+    }
+    let left = ctx.conf.window_width as f32 - MARGIN - WIDTH;
+    let height = height(players.len());
+    graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.7))?;
+    graphics::rectangle(ctx,
+                        DrawMode::Fill,
+                        Rect::new(left + WIDTH / 2.0, top + height / 2.0, WIDTH, height))?;
+
+    let white = Color::new(1.0, 1.0, 1.0, 1.0);
+    let grey = Color::new(0.5, 0.5, 0.5, 1.0);
+    let red = Color::new(1.0, 0.4, 0.4, 1.0);
+    let mut y = top + 4.0;
+    for (column, title) in COLUMNS.iter().zip(["Name", "Team", "Status", "Ping"].iter()) {
+        draw_text(ctx, font, title, left + 8.0 + column, y, grey)?;
+    }
+    for player in players {
+        y += ROW_HEIGHT;
+        graphics::set_color(ctx, team_color(player.team))?;
+        graphics::rectangle(ctx,
+                            DrawMode::Fill,
+                            Rect::new(left + 8.0 + SWATCH / 2.0,
+                                      y + ROW_HEIGHT / 2.0 - 2.0,
+                                      SWATCH,
+                                      SWATCH))?;
+        let name = if player.team == us {
+            format!("{} (you)", player.name)
+        } else {
+            player.name.clone()
+        };
+        let (status, status_color) = match player.presence {
+            Presence::Alive => ("Alive", white),
+            Presence::Dead => ("Dead", grey),
+            Presence::Disconnected => ("Gone", red),
+        };
+        let (ping, ping_color) = match player.ping_ms {
+            _ if player.bot => ("bot".to_string(), grey),
+            Some(ms) if ms > SLOW_PING_MS => (format!("{} ms", ms), red),
+            Some(ms) => (format!("{} ms", ms), white),
+            None => ("-".to_string(), grey),
+        };
+        let name_color = if player.presence == Presence::Dead { grey } else { white };
+        let cells = [(name, name_color),
+                     ((player.team + 1).to_string(), name_color),
+                     (status.to_string(), status_color),
+                     (ping, ping_color)];
+        for (i, (column, &(ref text, color))) in COLUMNS.iter().zip(cells.iter()).enumerate() {
+            let indent = if i == 0 { SWATCH + 6.0 } else { 0.0 };
+            draw_text(ctx, font, text, left + 8.0 + column + indent, y, color)?;
+        }
+    }
+    Ok(())
+}
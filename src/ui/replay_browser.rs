@@ -0,0 +1,98 @@
+//! The list of saved replays, opened from the menu.
+
+use std::path::PathBuf;
+
+use ggez::{Context, GameResult};
+use ggez::event::Keycode;
+use ggez::graphics::{self, Color, Font};
+
+use generals::daily;
+use generals::replay::SavedReplay;
+
+use super::draw_text;
+use super::theme::Theme;
+
+const LINE_HEIGHT: f32 = 28.0;
+const MARGIN: f32 = 40.0;
+/// The number of replays shown at once.
+const PAGE: usize = 20;
+
+/// What to do after a key in the browser.
+pub enum Browse {
+    Stay,
+    Close,
+    /// Watch the replay at this path.
+    Open(PathBuf),
+}
+
+pub struct ReplayBrowser {
+    /// Most recent first.
+    replays: Vec<SavedReplay>,
+    selected: usize,
+}
+
+impl ReplayBrowser {
+    pub fn new(replays: Vec<SavedReplay>) -> Self {
+        ReplayBrowser {
+            replays,
+            selected: 0,
+        }
+    }
+
+    pub fn key_down(&mut self, keycode: Keycode) -> Browse {
+        let last = self.replays.len().saturating_sub(1);
+        match keycode {
+            Keycode::Up => self.selected = self.selected.saturating_sub(1),
+            Keycode::Down => self.selected = (self.selected + 1).min(last),
+            Keycode::PageUp => self.selected = self.selected.saturating_sub(PAGE),
+            Keycode::PageDown => self.selected = (self.selected + PAGE).min(last),
+            Keycode::Return => {
+                return match self.replays.get(self.selected) {
+                    Some(replay) => Browse::Open(replay.path.clone()),
+                    None => Browse::Close,
+                }
+            }
+            Keycode::Escape => return Browse::Close,
+            _ => {}
+        }
+        Browse::Stay
+    }
+
+    fn describe(replay: &SavedReplay) -> String {
+        let winner = match replay.winner {
+            Some(team) => format!("player {} won", team + 1),
+            None => "no winner".to_string(),
+        };
+        format!("{}  {} players, {} {}x{}, {}, {} ticks",
+                daily::date(replay.saved_at / (24 * 60 * 60)),
+                replay.num_players,
+                replay.mode.name(),
+                replay.dimens.0,
+                replay.dimens.1,
+                winner,
+                replay.ticks)
+    }
+
+    pub fn draw(&self, ctx: &mut Context, font: &Font, theme: &Theme) -> GameResult<()> {
+        graphics::set_background_color(ctx, theme.background);
+        graphics::clear(ctx);
+        let gray = Color::new(0.6, 0.6, 0.6, 1.0);
+        let mut y = MARGIN;
+        draw_text(ctx, font, "Replays", MARGIN, y, theme.text)?;
+        y += LINE_HEIGHT * 2.0;
+        if self.replays.is_empty() {
+            draw_text(ctx, font, "No replays saved yet", MARGIN, y, theme.text)?;
+            y += LINE_HEIGHT;
+        }
+        // Keep the selected replay on the page.
+        let first = self.selected.saturating_sub(PAGE - 1);
+        for (i, replay) in self.replays.iter().enumerate().skip(first).take(PAGE) {
+            let marker = if i == self.selected { ">" } else { " " };
+            let text = format!("{} {}", marker, ReplayBrowser::describe(replay));
+            draw_text(ctx, font, &text, MARGIN, y, theme.text)?;
+            y += LINE_HEIGHT;
+        }
+        y += LINE_HEIGHT;
+        draw_text(ctx, font, "Up/Down: select   Enter: watch   Escape: back", MARGIN, y, gray)
+    }
+}
@@ -0,0 +1,128 @@
+//! Editing the board of a local game while it runs, for trying out rules and setting up
+//! positions.
+
+use ggez::{Context, GameResult};
+use ggez::event::Keycode;
+use ggez::graphics::{self, Color, DrawMode, Font, Rect};
+
+use generals::{Cell, Team};
+
+use super::draw_text;
+
+const MARGIN: f32 = 20.0;
+const HEIGHT: f32 = 64.0;
+
+/// The kinds of cells the brush can paint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Mountain,
+    Open,
+    Captured,
+    Fortress,
+    King,
+}
+
+const KINDS: [Kind; 5] = [Kind::Mountain, Kind::Open, Kind::Captured, Kind::Fortress, Kind::King];
+
+/// The cell that clicking on the board paints, while editing.
+pub struct Sandbox {
+    kind: Kind,
+    owner: Option<Team>,
+    units: usize,
+    /// Whether clicks edit the board, rather than play the game.
+    pub editing: bool,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        Sandbox {
+            kind: Kind::Captured,
+            owner: Some(0),
+            units: 10,
+            editing: true,
+        }
+    }
+
+    /// The cell the brush paints. Captured cells and Kings need an owner; without one they
+    /// paint open land and neutral fortresses.
+    pub fn cell(&self) -> Cell {
+        match (self.kind, self.owner) {
+            (Kind::Mountain, _) => Cell::Mountain,
+            (Kind::Open, _) | (Kind::Captured, None) => Cell::Open,
+            (Kind::Captured, Some(team)) => Cell::Captured(team, self.units),
+            (Kind::Fortress, owner) | (Kind::King, owner @ None) => {
+                Cell::Fortress(owner, self.units)
+            }
+            (Kind::King, Some(team)) => Cell::King(team, self.units),
+        }
+    }
+
+    /// Make the brush paint `cell`, like an eyedropper.
+    pub fn pick(&mut self, cell: Cell) {
+        self.kind = match cell {
+            Cell::Mountain => Kind::Mountain,
+            Cell::Open => Kind::Open,
+            Cell::Captured(_, _) => Kind::Captured,
+            Cell::Fortress(_, _) => Kind::Fortress,
+            Cell::King(_, _) => Kind::King,
+        };
+        self.owner = cell.owner();
+        self.units = cell.units().unwrap_or(self.units);
+    }
+
+    /// Change the brush with the keyboard, in a game of `num_players`. Returns whether the key
+    /// was used.
+    pub fn key_down(&mut self, keycode: Keycode, num_players: usize) -> bool {
+        match keycode {
+            Keycode::E => self.editing = !self.editing,
+            Keycode::Tab if self.editing => {
+                let i = KINDS.iter().position(|&k| k == self.kind).unwrap_or(0);
+                self.kind = KINDS[(i + 1) % KINDS.len()];
+            }
+            Keycode::O if self.editing => {
+                self.owner = match self.owner {
+                    None => Some(0),
+                    Some(team) if team + 1 < num_players => Some(team + 1),
+                    Some(_) => None,
+                };
+            }
+            Keycode::RightBracket if self.editing => self.units += 10,
+            Keycode::LeftBracket if self.editing => self.units = self.units.saturating_sub(10),
+            Keycode::Period if self.editing => self.units += 1,
+            Keycode::Comma if self.editing => self.units = self.units.saturating_sub(1),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Draw what the brush paints, and the keys that change it, at `top`.
+    pub fn draw(&self, ctx: &mut Context, font: &Font, top: f32) -> GameResult<()> {
+        let width = ctx.conf.window_width as f32 - 2.0 * MARGIN;
+        graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.7))?;
+        graphics::rectangle(ctx,
+                            DrawMode::Fill,
+                            Rect::new(MARGIN + width / 2.0, top + HEIGHT / 2.0, width, HEIGHT))?;
+        let white = Color::new(1.0, 1.0, 1.0, 1.0);
+        let gray = Color::new(0.6, 0.6, 0.6, 1.0);
+        if !self.editing {
+            let text = "Sandbox: playing   E: edit";
+            return draw_text(ctx, font, text, MARGIN + 8.0, top + 6.0, white);
+        }
+        let owner = match self.owner {
+            Some(team) => format!("Player {}", team + 1),
+            None => "no one".to_string(),
+        };
+        let text = format!("Sandbox: painting {:?}, {} units, owned by {}",
+                           self.kind,
+                           self.units,
+                           owner);
+        draw_text(ctx, font, &text, MARGIN + 8.0, top + 6.0, white)?;
+        draw_text(ctx,
+                  font,
+                  "Click: paint   Right click: pick   Tab: kind   O: owner   ,/.: units   \
+                   [/]: units by 10   E: play",
+                  MARGIN + 8.0,
+                  top + 34.0,
+                  gray)
+    }
+}
@@ -0,0 +1,73 @@
+//! The table of player statistics in the top right corner.
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawMode, Font, Rect};
+
+use generals::{Score, Team};
+
+use super::draw_text;
+
+const MARGIN: f32 = 20.0;
+const ROW_HEIGHT: f32 = 26.0;
+const SWATCH: f32 = 14.0;
+/// The left edge of each column, relative to the left of the table.
+const COLUMNS: [f32; 4] = [0.0, 110.0, 190.0, 260.0];
+const WIDTH: f32 = 330.0;
+
+/// The bottom edge of the table, for `players` players.
+pub fn bottom(players: usize) -> f32 {
+    MARGIN + ROW_HEIGHT * (players + 1) as f32 + 8.0
+}
+
+/// Draw the scores of all players, the largest army first. Our own row is marked.
+pub fn draw<F>(ctx: &mut Context, font: &Font, scores: &[Score], us: Team, team_color: F)
+    -> GameResult<()>
+where
+    F: Fn(Team) -> Color,
+{
+    let mut scores = scores.to_vec();
+    scores.sort_by_key(|s| (s.dead, ::std::usize::MAX - s.army, s.team));
+
+    let left = ctx.conf.window_width as f32 - MARGIN - WIDTH;
+    let height = ROW_HEIGHT * (scores.len() + 1) as f32 + 8.0;
+    graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.7))?;
+    graphics::rectangle(ctx,
+                        DrawMode::Fill,
+                        Rect::new(left + WIDTH / 2.0, MARGIN + height / 2.0, WIDTH, height))?;
+
+    let white = Color::new(1.0, 1.0, 1.0, 1.0);
+    let grey = Color::new(0.5, 0.5, 0.5, 1.0);
+    let mut y = MARGIN + 4.0;
+    for (column, title) in COLUMNS.iter().zip(["Player", "Army", "Land", "Cities"].iter()) {
+        draw_text(ctx, font, title, left + 8.0 + column, y, grey)?;
+    }
+    for score in scores.iter() {
+        y += ROW_HEIGHT;
+        graphics::set_color(ctx, team_color(score.team))?;
+        graphics::rectangle(ctx,
+                            DrawMode::Fill,
+                            Rect::new(left + 8.0 + SWATCH / 2.0,
+                                      y + ROW_HEIGHT / 2.0 - 2.0,
+                                      SWATCH,
+                                      SWATCH))?;
+        let name = if score.team == us {
+            "You".to_string()
+        } else {
+            format!("Player {}", score.team + 1)
+        };
+        let (name, color) = if score.dead {
+            (format!("{} (dead)", name), grey)
+        } else {
+            (name, white)
+        };
+        let cells = [name,
+                     score.army.to_string(),
+                     score.land.to_string(),
+                     score.cities.to_string()];
+        for (i, (column, text)) in COLUMNS.iter().zip(cells.iter()).enumerate() {
+            let indent = if i == 0 { SWATCH + 6.0 } else { 0.0 };
+            draw_text(ctx, font, text, left + 8.0 + column + indent, y, color)?;
+        }
+    }
+    Ok(())
+}
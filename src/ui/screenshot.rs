@@ -0,0 +1,89 @@
+//! Saving the whole board as a PNG image, regardless of what the camera shows.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ggez::graphics::Color;
+use image::{self, ColorType};
+
+use generals::{Cell, PlayerView, Tile};
+
+pub const SCREENSHOT_DIR: &str = "screenshots";
+/// The side length of a cell in the image, in pixels.
+const CELL: u32 = 16;
+/// The gap between cells.
+const GAP: u32 = 1;
+
+fn rgba(color: Color) -> [u8; 4] {
+    [(color.r * 255.0) as u8, (color.g * 255.0) as u8, (color.b * 255.0) as u8, 255]
+}
+
+/// Draw `view` into an RGBA buffer of the returned size. Kings and cities get a dark square in
+/// the middle, since the image has no sprites.
+pub fn render<F>(view: &PlayerView, tile_color: F, background: Color) -> (Vec<u8>, u32, u32)
+where
+    F: Fn(&Tile) -> Color,
+{
+    let (w, h) = (view.dimens.0 as u32, view.dimens.1 as u32);
+    let stride = CELL + GAP;
+    let (width, height) = (w * stride + GAP, h * stride + GAP);
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for _ in 0..width * height {
+        pixels.extend_from_slice(&rgba(background));
+    }
+    let marker = rgba(Color::new(0.0, 0.0, 0.0, 1.0));
+    for y in 0..h {
+        for x in 0..w {
+            let tile = match view.try_get(x as i32, y as i32) {
+                Some(tile) => tile,
+                None => continue,
+            };
+            let fill = rgba(tile_color(&tile));
+            let marked = match tile {
+                Tile::Visible(Cell::King(..)) | Tile::Visible(Cell::Fortress(..)) => true,
+                _ => false,
+            };
+            for py in 0..CELL {
+                for px in 0..CELL {
+                    let inner = px >= CELL / 3 && px < CELL * 2 / 3 && py >= CELL / 3 &&
+                                py < CELL * 2 / 3;
+                    let color = if marked && inner { marker } else { fill };
+                    let (ix, iy) = (GAP + x * stride + px, GAP + y * stride + py);
+                    let i = ((iy * width + ix) * 4) as usize;
+                    pixels[i..i + 4].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+    (pixels, width, height)
+}
+
+/// Save `view` as a PNG in the screenshot directory, named by the current time.
+pub fn save<F>(view: &PlayerView, tile_color: F, background: Color) -> io::Result<PathBuf>
+where
+    F: Fn(&Tile) -> Color,
+{
+    let (pixels, width, height) = render(view, tile_color, background);
+    fs::create_dir_all(SCREENSHOT_DIR)?;
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let dir = PathBuf::from(SCREENSHOT_DIR);
+    let mut path = dir.join(format!("board-{}.png", secs));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("board-{}-{}.png", secs, n));
+        n += 1;
+    }
+    write_png(&path, &pixels, width, height)?;
+    Ok(path)
+}
+
+/// Write an image made by `render` to `path`.
+pub fn write_png<P: AsRef<Path>>(path: P, pixels: &[u8], width: u32, height: u32)
+    -> io::Result<()> {
+    image::save_buffer(path, pixels, width, height, ColorType::RGBA(8))
+}
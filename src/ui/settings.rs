@@ -0,0 +1,184 @@
+//! Client settings, and the page in the pause menu that edits them.
+
+use std::time::Duration;
+
+use ggez::{Context, GameResult};
+use ggez::event::Keycode;
+use ggez::graphics::{self, Color, DrawMode, Font, Rect};
+
+use super::draw_text;
+use super::keymap::{Action, Keymap, ACTIONS, NUM_ACTIONS};
+use super::palette::{Palette, PaletteKind};
+use super::theme::{Theme, ThemeKind};
+
+const LINE_HEIGHT: f32 = 28.0;
+const MARGIN: f32 = 40.0;
+const MIN_TICK_MS: u64 = 50;
+const MAX_TICK_MS: u64 = 2000;
+const TICK_STEP_MS: u64 = 50;
+const VOLUME_STEP: f32 = 0.1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// The time between ticks of local games, in milliseconds.
+    pub tick_ms: u64,
+    /// The volume of sound effects, from 0 to 1.
+    pub volume: f32,
+    /// The volume of the music, from 0 to 1.
+    pub music_volume: f32,
+    pub theme: ThemeKind,
+    pub palette: PaletteKind,
+    /// The colors of the `Custom` palette, as RGB, by team.
+    pub team_colors: Vec<[u8; 3]>,
+    /// Draw patterns on the cells of each team, in addition to the colors.
+    pub patterns: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            tick_ms: 500,
+            volume: 0.8,
+            music_volume: 0.5,
+            theme: ThemeKind::Light,
+            palette: PaletteKind::Classic,
+            team_colors: Vec::new(),
+            patterns: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn tick_interval(&self) -> Duration {
+        Duration::from_millis(self.tick_ms)
+    }
+
+    /// Make local games faster (`step < 0`) or slower.
+    pub fn step_tick(&mut self, step: i64) {
+        let ms = self.tick_ms as i64 + step * TICK_STEP_MS as i64;
+        self.tick_ms = (ms.max(0) as u64).max(MIN_TICK_MS).min(MAX_TICK_MS);
+    }
+
+    pub fn theme(&self) -> Theme {
+        Theme::new(self.theme)
+    }
+
+    pub fn palette(&self) -> Palette {
+        Palette::new(self.palette, &self.team_colors)
+    }
+}
+
+fn step_volume(volume: f32, step: i64) -> f32 {
+    let volume = volume + step as f32 * VOLUME_STEP;
+    // Round away the float error, so that the steps land on tenths.
+    ((volume * 10.0).round() / 10.0).max(0.0).min(1.0)
+}
+
+/// The rows of the settings page before the key bindings, which follow in the order of
+/// `ACTIONS`.
+const SETTING_ROWS: usize = 6;
+const ROWS: usize = SETTING_ROWS + NUM_ACTIONS;
+
+/// The settings page. Changes take effect right away, and the caller saves them when the page
+/// is closed.
+pub struct SettingsScreen {
+    selected: usize,
+    /// The action whose new key we are waiting for.
+    binding: Option<Action>,
+}
+
+impl SettingsScreen {
+    pub fn new() -> Self {
+        SettingsScreen {
+            selected: 0,
+            binding: None,
+        }
+    }
+
+    /// Returns `false` when the page is closed.
+    pub fn key_down(&mut self, keycode: Keycode, settings: &mut Settings, keymap: &mut Keymap)
+        -> bool {
+        if let Some(action) = self.binding.take() {
+            if keycode != Keycode::Escape {
+                keymap.set(action, keycode);
+            }
+            return true;
+        }
+        if self.selected >= SETTING_ROWS && keycode == Keycode::Return {
+            self.binding = Some(ACTIONS[self.selected - SETTING_ROWS]);
+            return true;
+        }
+        let step = match keycode {
+            Keycode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                return true;
+            }
+            Keycode::Down => {
+                self.selected = (self.selected + 1).min(ROWS - 1);
+                return true;
+            }
+            Keycode::Escape => return false,
+            Keycode::Left => -1,
+            Keycode::Right => 1,
+            _ => return true,
+        };
+        match self.selected {
+            0 => settings.step_tick(step),
+            1 => settings.volume = step_volume(settings.volume, step),
+            2 => settings.music_volume = step_volume(settings.music_volume, step),
+            3 => settings.theme = settings.theme.toggle(),
+            4 => settings.palette = settings.palette.cycle(step),
+            5 => settings.patterns = !settings.patterns,
+            _ => {}
+        }
+        true
+    }
+
+    /// Draw the page on top of the paused game.
+    pub fn draw(&self, ctx: &mut Context, font: &Font, settings: &Settings, keymap: &Keymap)
+        -> GameResult<()> {
+        let (w, h) = (ctx.conf.window_width as f32, ctx.conf.window_height as f32);
+        graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.8))?;
+        graphics::rectangle(ctx, DrawMode::Fill, Rect::new(w / 2.0, h / 2.0, w, h))?;
+
+        let white = Color::new(1.0, 1.0, 1.0, 1.0);
+        let gray = Color::new(0.6, 0.6, 0.6, 1.0);
+        let mut y = MARGIN;
+        draw_text(ctx, font, "Paused - Settings", MARGIN, y, white)?;
+        y += LINE_HEIGHT * 2.0;
+        let mut rows = vec![format!("Tick speed: {} ms", settings.tick_ms),
+                            format!("Sound volume: {}%", (settings.volume * 100.0).round()),
+                            format!("Music volume: {}%",
+                                    (settings.music_volume * 100.0).round()),
+                            format!("Theme: {}", settings.theme.name()),
+                            format!("Colors: {}", settings.palette.name()),
+                            format!("Team patterns: {}",
+                                    if settings.patterns { "on" } else { "off" })];
+        rows.extend(ACTIONS.iter().map(|&action| {
+            let key = if self.binding == Some(action) {
+                "press a key...".to_string()
+            } else {
+                keymap.key(action).name()
+            };
+            format!("{}: {}", action.label(), key)
+        }));
+        for (i, row) in rows.iter().enumerate() {
+            if i == SETTING_ROWS {
+                y += LINE_HEIGHT / 2.0;
+                draw_text(ctx, font, "Keys", MARGIN, y, gray)?;
+                y += LINE_HEIGHT;
+            }
+            let marker = if i == self.selected { ">" } else { " " };
+            draw_text(ctx, font, &format!("{} {}", marker, row), MARGIN, y, white)?;
+            y += LINE_HEIGHT;
+        }
+        y += LINE_HEIGHT;
+        let help = if self.selected >= SETTING_ROWS {
+            "Up/Down: select   Enter: change key   Esc: resume"
+        } else {
+            "Up/Down: select   Left/Right: change   Esc: resume"
+        };
+        draw_text(ctx, font, help, MARGIN, y, gray)
+    }
+}
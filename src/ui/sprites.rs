@@ -0,0 +1,87 @@
+//! Pictures drawn on top of the cell colors. They are loaded from the `tiles` folder of the
+//! ggez resources directory; any that are missing are drawn in the flat style instead.
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawParam, Image, Point, Rect};
+
+use generals::{Cell, Tile};
+
+const DIR: &str = "/tiles";
+
+#[derive(Debug, Clone, Copy)]
+pub enum Sprite {
+    Mountain,
+    City,
+    Crown,
+    /// A mountain or city in the fog.
+    Obstacle,
+}
+
+pub const ALL: [Sprite; 4] = [Sprite::Mountain, Sprite::City, Sprite::Crown, Sprite::Obstacle];
+
+impl Sprite {
+    fn file_name(&self) -> &'static str {
+        match *self {
+            Sprite::Mountain => "mountain.png",
+            Sprite::City => "city.png",
+            Sprite::Crown => "crown.png",
+            Sprite::Obstacle => "obstacle.png",
+        }
+    }
+
+    /// Where the sprite is in the resources directory.
+    pub fn path(&self) -> String {
+        format!("{}/{}", DIR, self.file_name())
+    }
+
+    /// The sprite drawn on `tile`, if any.
+    pub fn for_tile(tile: &Tile) -> Option<Sprite> {
+        match *tile {
+            Tile::Visible(Cell::Mountain) => Some(Sprite::Mountain),
+            Tile::Visible(Cell::Fortress(..)) => Some(Sprite::City),
+            Tile::Visible(Cell::King(..)) => Some(Sprite::Crown),
+            Tile::Obstacle => Some(Sprite::Obstacle),
+            _ => None,
+        }
+    }
+}
+
+pub struct Sprites {
+    /// Indexed by `Sprite`.
+    images: Vec<Option<Image>>,
+}
+
+impl Sprites {
+    /// Load every sprite that exists.
+    pub fn load(ctx: &mut Context) -> Self {
+        let images = ALL.iter()
+            .map(|sprite| Image::new(ctx, sprite.path()).ok())
+            .collect::<Vec<_>>();
+        let missing = images.iter().filter(|i| i.is_none()).count();
+        if missing > 0 {
+            println!("{} of {} tile sprites are missing, using flat tiles for those",
+                     missing,
+                     images.len());
+        }
+        Sprites { images }
+    }
+
+    /// Draw `sprite` to fill `rect`, which is positioned by its center. Returns `false` if the
+    /// sprite is missing, so that the caller can draw something else.
+    pub fn draw(&self, ctx: &mut Context, sprite: Sprite, rect: Rect) -> GameResult<bool> {
+        let image = match self.images[sprite as usize] {
+            Some(ref image) => image,
+            None => return Ok(false),
+        };
+        graphics::set_color(ctx, Color::new(1.0, 1.0, 1.0, 1.0))?;
+        graphics::draw_ex(ctx,
+                          image,
+                          DrawParam {
+                              dest: Point::new(rect.x, rect.y),
+                              scale: Point::new(rect.w / image.width() as f32,
+                                                rect.h / image.height() as f32),
+                              ..Default::default()
+                          })?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,85 @@
+//! The colors of everything that isn't a team.
+
+use ggez::graphics::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ThemeKind {
+    Light,
+    Dark,
+}
+
+impl ThemeKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ThemeKind::Light => "Light",
+            ThemeKind::Dark => "Dark",
+        }
+    }
+
+    pub fn toggle(&self) -> Self {
+        match *self {
+            ThemeKind::Light => ThemeKind::Dark,
+            ThemeKind::Dark => ThemeKind::Light,
+        }
+    }
+}
+
+pub struct Theme {
+    /// Behind the menus and lobby.
+    pub background: Color,
+    /// Behind the board, which shows between the cells.
+    pub grid: Color,
+    pub text: Color,
+    /// Shades the cells next to the focus.
+    pub overlay: Color,
+    pub focus: Color,
+    /// Our move queue.
+    pub moves: Color,
+    pub open: Color,
+    pub mountain: Color,
+    pub neutral_city: Color,
+    pub fog: Color,
+    /// The blobs on unseen mountains and cities.
+    pub obstacle: Color,
+}
+
+impl Theme {
+    pub fn new(kind: ThemeKind) -> Self {
+        match kind {
+            ThemeKind::Light => Theme::light(),
+            ThemeKind::Dark => Theme::dark(),
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            background: Color::new(0.0, 0.0, 0.0, 1.0),
+            grid: Color::new(0.0, 0.0, 0.0, 1.0),
+            text: Color::new(1.0, 1.0, 1.0, 1.0),
+            overlay: Color::new(0.0, 0.0, 0.0, 0.3),
+            focus: Color::new(1.0, 0.0, 0.0, 0.5),
+            moves: Color::new(0.0, 0.0, 0.0, 1.0),
+            open: Color::new(1.0, 1.0, 1.0, 1.0),
+            mountain: Color::new(0.2, 0.2, 0.2, 1.0),
+            neutral_city: Color::new(0.4, 0.4, 0.4, 1.0),
+            fog: Color::new(0.15, 0.15, 0.15, 1.0),
+            obstacle: Color::new(0.3, 0.3, 0.3, 1.0),
+        }
+    }
+
+    pub fn dark() -> Self {
+        Theme {
+            background: Color::new(0.08, 0.08, 0.1, 1.0),
+            grid: Color::new(0.05, 0.05, 0.06, 1.0),
+            text: Color::new(0.85, 0.85, 0.85, 1.0),
+            overlay: Color::new(1.0, 1.0, 1.0, 0.12),
+            focus: Color::new(1.0, 0.8, 0.2, 0.45),
+            moves: Color::new(0.9, 0.9, 0.9, 1.0),
+            open: Color::new(0.25, 0.25, 0.28, 1.0),
+            mountain: Color::new(0.1, 0.1, 0.1, 1.0),
+            neutral_city: Color::new(0.45, 0.45, 0.5, 1.0),
+            fog: Color::new(0.13, 0.13, 0.15, 1.0),
+            obstacle: Color::new(0.22, 0.22, 0.25, 1.0),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Marks on our cells that enemy armies came into or next to in the last few ticks, and a pulse
+//! on our King while enemy units are close to it. Both only use what our view shows, so they
+//! work the same in local and network games.
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::time::Instant;
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawMode};
+
+use generals::{PlayerView, Position, Tile};
+
+use super::camera::Camera;
+
+/// How many ticks a cell stays marked after an enemy army came near.
+const CONTESTED_TICKS: usize = 6;
+/// How close enemy units have to be to the King for it to pulse, in steps.
+const KING_DANGER: i32 = 4;
+/// Pulses per second.
+const PULSE_RATE: f32 = 1.5;
+
+const NEIGHBOURS: [(i32, i32); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+
+pub struct Threats {
+    /// The tiles of the last view, to tell where armies moved since.
+    previous: Option<(usize, Vec<Vec<Tile>>)>,
+    /// Our cells that were contested, and the last tick they were.
+    contested: HashMap<Position, usize>,
+    /// Where our King is, while enemy units are close to it.
+    king_in_danger: Option<Position>,
+    start: Instant,
+}
+
+impl Threats {
+    pub fn new() -> Self {
+        Threats {
+            previous: None,
+            contested: HashMap::new(),
+            king_in_danger: None,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.previous = None;
+        self.contested.clear();
+        self.king_in_danger = None;
+    }
+
+    /// Look at the latest `view`. Nothing is done until it is of a new tick.
+    pub fn update(&mut self, view: &PlayerView) {
+        let tick = view.tick_number;
+        let is_new = match self.previous {
+            Some((last, _)) => last != tick,
+            None => true,
+        };
+        if !is_new {
+            return;
+        }
+        let us = view.team;
+        let owned_by_us = |x: i32, y: i32| match view.try_get(x, y) {
+            Some(Tile::Visible(cell)) => cell.owner() == Some(us),
+            _ => false,
+        };
+        if let Some((_, ref before)) = self.previous {
+            let (w, h) = view.dimens;
+            for y in 0..h {
+                for x in 0..w {
+                    let cell = match view.tiles[y as usize][x as usize] {
+                        Tile::Visible(cell) => cell,
+                        _ => continue,
+                    };
+                    let enemy = match cell.owner() {
+                        Some(team) if team != us => team,
+                        _ => continue,
+                    };
+                    let was = match before.get(y as usize).and_then(|r| r.get(x as usize)) {
+                        Some(&Tile::Visible(was)) => Some(was),
+                        _ => None,
+                    };
+                    // An army came in if the enemy took the cell, or it grew by more than the
+                    // cells grow by themselves.
+                    let (arrived, taken_from_us) = match was {
+                        Some(was) if was.owner() == Some(enemy) => {
+                            (cell.units().unwrap_or(0) > was.units().unwrap_or(0) + 1, false)
+                        }
+                        Some(was) => (true, was.owner() == Some(us)),
+                        None => (false, false),
+                    };
+                    if !arrived {
+                        continue;
+                    }
+                    if taken_from_us {
+                        self.contested.insert(Position(x, y), tick);
+                    }
+                    for &(dx, dy) in NEIGHBOURS.iter() {
+                        if owned_by_us(x + dx, y + dy) {
+                            self.contested.insert(Position(x + dx, y + dy), tick);
+                        }
+                    }
+                }
+            }
+        }
+        self.contested.retain(|_, &mut at| at + CONTESTED_TICKS > tick);
+        self.king_in_danger = view.king().filter(|&king| enemy_near(view, king));
+        self.previous = Some((tick, view.tiles.clone()));
+    }
+
+    /// Outline the contested cells, fading as they get older, and pulse the King if it is in
+    /// danger.
+    pub fn draw(&self, ctx: &mut Context, camera: &Camera) -> GameResult<()> {
+        let tick = self.previous.as_ref().map(|&(tick, _)| tick).unwrap_or(0);
+        for (&Position(x, y), &at) in self.contested.iter() {
+            let age = tick.saturating_sub(at) as f32 / CONTESTED_TICKS as f32;
+            graphics::set_color(ctx, Color::new(1.0, 0.2, 0.1, 0.9 * (1.0 - age)))?;
+            graphics::rectangle(ctx, DrawMode::Line, camera.cell_rect(x, y))?;
+        }
+        if let Some(Position(x, y)) = self.king_in_danger {
+            let elapsed = self.start.elapsed();
+            let t = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 * 1e-9;
+            let pulse = 0.5 + 0.5 * (t * PULSE_RATE * 2.0 * PI).sin();
+            let mut rect = camera.cell_rect(x, y);
+            let grow = camera.cell_size() * 0.15 * pulse;
+            rect.w += grow;
+            rect.h += grow;
+            graphics::set_color(ctx, Color::new(1.0, 0.1, 0.1, 0.4 + 0.5 * pulse))?;
+            graphics::rectangle(ctx, DrawMode::Line, rect)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether there are enemy cells in sight within `KING_DANGER` steps of `pos`.
+fn enemy_near(view: &PlayerView, Position(x, y): Position) -> bool {
+    for dy in -KING_DANGER..KING_DANGER + 1 {
+        for dx in -KING_DANGER..KING_DANGER + 1 {
+            if dx.abs() + dy.abs() > KING_DANGER {
+                continue;
+            }
+            if let Some(Tile::Visible(cell)) = view.try_get(x + dx, y + dy) {
+                if cell.owner().map(|t| t != view.team).unwrap_or(false) {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
@@ -0,0 +1,145 @@
+//! Short messages at the top of the window about what happens in the game, like captured cities
+//! and unlocked achievements. The last ones can be shown in a panel with H. Eliminations have
+//! their own feed, in `kill_feed`.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, DrawMode, Font, Rect};
+
+use generals::{GameEvent, Team};
+
+use super::draw_text;
+
+const TOP: f32 = 20.0;
+const LINE_HEIGHT: f32 = 26.0;
+const WIDTH: f32 = 480.0;
+/// How many toasts are on screen at once.
+const SHOWN: usize = 4;
+/// How many messages the history panel keeps.
+const HISTORY: usize = 12;
+const SHOW_FOR: Duration = Duration::from_secs(4);
+/// Toasts fade out during the last part of `SHOW_FOR`.
+const FADE: f32 = 1.0;
+
+fn seconds(d: Duration) -> f32 {
+    d.as_secs() as f32 + d.subsec_nanos() as f32 * 1e-9
+}
+
+fn name(team: Team, us: Team) -> String {
+    if team == us {
+        "You".to_string()
+    } else {
+        format!("Player {}", team + 1)
+    }
+}
+
+/// The message for `event` as seen by `us`, and the team it is about. Captures of cities that
+/// `us` can't see are left out.
+fn message<F>(event: &GameEvent, us: Team, visible: F) -> Option<(String, Team)>
+where
+    F: Fn(i32, i32) -> bool,
+{
+    match *event {
+        GameEvent::CityCaptured { team, pos, from } if visible(pos.0, pos.1) => {
+            let text = match from {
+                Some(from) => format!("{} took a city from {}", name(team, us), name(from, us)),
+                None => format!("{} captured a city", name(team, us)),
+            };
+            Some((text, team))
+        }
+        _ => None,
+    }
+}
+
+pub struct Toasts {
+    messages: VecDeque<(String, Team, Instant)>,
+    show_history: bool,
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Toasts {
+            messages: VecDeque::new(),
+            show_history: false,
+        }
+    }
+
+    pub fn toggle_history(&mut self) {
+        self.show_history = !self.show_history;
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+
+    /// Add a toast for `event`, if it is worth one. `visible` tells whether `us` can see a cell.
+    pub fn event<F>(&mut self, event: &GameEvent, us: Team, visible: F)
+    where
+        F: Fn(i32, i32) -> bool,
+    {
+        if let Some((text, team)) = message(event, us, visible) {
+            self.push(text, team);
+        }
+    }
+
+    /// Add a toast with `text`, marked with the color of `team`.
+    pub fn push(&mut self, text: String, team: Team) {
+        self.messages.push_back((text, team, Instant::now()));
+        if self.messages.len() > HISTORY {
+            self.messages.pop_front();
+        }
+    }
+
+    /// Draw the recent toasts centered at the top of the window, or the history panel.
+    pub fn draw<F>(&self, ctx: &mut Context, font: &Font, team_color: F) -> GameResult<()>
+    where
+        F: Fn(Team) -> Color,
+    {
+        let shown = self.messages
+            .iter()
+            .filter(|&&(_, _, at)| self.show_history || at.elapsed() < SHOW_FOR)
+            .collect::<Vec<_>>();
+        let skip = if self.show_history { 0 } else { shown.len().saturating_sub(SHOWN) };
+        let shown = &shown[skip..];
+        if shown.is_empty() {
+            return Ok(());
+        }
+
+        let left = (ctx.conf.window_width as f32 - WIDTH) / 2.0;
+        if self.show_history {
+            let height = LINE_HEIGHT * (shown.len() + 1) as f32 + 8.0;
+            graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.75))?;
+            graphics::rectangle(ctx,
+                                DrawMode::Fill,
+                                Rect::new(left + WIDTH / 2.0, TOP + height / 2.0, WIDTH, height))?;
+            let grey = Color::new(0.5, 0.5, 0.5, 1.0);
+            draw_text(ctx, font, "Recent events (H to hide)", left + 8.0, TOP + 4.0, grey)?;
+        }
+
+        let first = if self.show_history { TOP + LINE_HEIGHT } else { TOP };
+        for (i, &&(ref text, team, at)) in shown.iter().enumerate() {
+            let y = first + 4.0 + i as f32 * LINE_HEIGHT;
+            let left_for = seconds(SHOW_FOR) - seconds(at.elapsed());
+            let alpha = if self.show_history { 1.0 } else { (left_for / FADE).min(1.0).max(0.0) };
+            if !self.show_history {
+                graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.6 * alpha))?;
+                graphics::rectangle(ctx,
+                                    DrawMode::Fill,
+                                    Rect::new(left + WIDTH / 2.0,
+                                              y + LINE_HEIGHT / 2.0 - 4.0,
+                                              WIDTH,
+                                              LINE_HEIGHT - 2.0))?;
+            }
+            let mut color = team_color(team);
+            color.a = alpha;
+            graphics::set_color(ctx, color)?;
+            graphics::rectangle(ctx,
+                                DrawMode::Fill,
+                                Rect::new(left + 10.0, y + LINE_HEIGHT / 2.0 - 4.0, 8.0, 8.0))?;
+            draw_text(ctx, font, text, left + 24.0, y, Color::new(1.0, 1.0, 1.0, alpha))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+//! The first-game tutorial: a script of hints shown one at a time at the bottom of the window,
+//! each pointing at a cell and waiting for the player to do what it says before the next one.
+//! Hints that only need a key press or a click hold the game until they are done.
+
+use std::f32::consts::PI;
+use std::time::Instant;
+
+use ggez::{Context, GameResult};
+use ggez::event::Keycode;
+use ggez::graphics::{self, Color, DrawMode, Font, Rect};
+
+use generals::{Cell, PlayerView, Position, Tile, LAND_GROWTH_INTERVAL};
+
+use super::camera::Camera;
+use super::draw_text;
+use super::marker;
+
+const LINE_HEIGHT: f32 = 24.0;
+const WIDTH: f32 = 640.0;
+const BOTTOM_MARGIN: f32 = 30.0;
+/// Pulses of the highlight per second.
+const PULSE_RATE: f32 = 1.2;
+
+/// What the player has to do to get to the next hint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    /// Press Enter.
+    Continue,
+    /// Focus our King.
+    FocusKing,
+    /// Queue at least one move.
+    QueueMove,
+    /// Own this many cells.
+    Land(usize),
+    /// Wait until all our land has grown once.
+    Growth,
+    /// Own a city besides our King.
+    TakeCity,
+    /// Take an enemy King, which ends the game.
+    TakeKing,
+}
+
+impl Goal {
+    /// Whether the game waits while the hint is shown. Goals that take ticks to reach let it
+    /// run.
+    fn holds_game(&self) -> bool {
+        match *self {
+            Goal::Continue | Goal::FocusKing | Goal::QueueMove => true,
+            Goal::Land(_) | Goal::Growth | Goal::TakeCity | Goal::TakeKing => false,
+        }
+    }
+}
+
+/// The cell a hint points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    Nothing,
+    King,
+    /// The neutral city closest to our King that we can see.
+    City,
+}
+
+pub struct Hint {
+    /// One line of the panel per line of text.
+    pub text: &'static str,
+    pub highlight: Highlight,
+    pub goal: Goal,
+}
+
+/// The hints of the tutorial game, in order.
+pub const SCRIPT: &[Hint] = &[
+    Hint {
+        text: "Welcome to Generals! The cell with the crown is your King.\n\
+               Lose it and you lose the game.",
+        highlight: Highlight::King,
+        goal: Goal::Continue,
+    },
+    Hint {
+        text: "Click on your King, or press K, to focus it.\n\
+               Moves start from the focused cell.",
+        highlight: Highlight::King,
+        goal: Goal::FocusKing,
+    },
+    Hint {
+        text: "Press an arrow key to queue a move from the focus.\n\
+               All units but one go along, and the focus follows them.",
+        highlight: Highlight::King,
+        goal: Goal::QueueMove,
+    },
+    Hint {
+        text: "Queued moves are done one per tick, in order.\n\
+               Keep moving, and own 6 cells of land.",
+        highlight: Highlight::Nothing,
+        goal: Goal::Land(6),
+    },
+    Hint {
+        text: "Your King gets one more unit every other tick,\n\
+               and all your other land every 32 ticks.",
+        highlight: Highlight::King,
+        goal: Goal::Continue,
+    },
+    Hint {
+        text: "Watch the units on your land go up by one at the next growth tick.",
+        highlight: Highlight::Nothing,
+        goal: Goal::Growth,
+    },
+    Hint {
+        text: "Cities are guarded, but once taken they grow as fast as your King.\n\
+               Move more units into one than it has to take it.",
+        highlight: Highlight::City,
+        goal: Goal::TakeCity,
+    },
+    Hint {
+        text: "Well done! The enemy King is somewhere in the fog to the east.\n\
+               Gather your army, find it and take it to win.",
+        highlight: Highlight::Nothing,
+        goal: Goal::TakeKing,
+    },
+];
+
+pub struct Tutorial {
+    step: usize,
+    /// The tick the current hint was first shown at.
+    shown_at: Option<usize>,
+    start: Instant,
+}
+
+impl Tutorial {
+    pub fn new() -> Self {
+        Tutorial {
+            step: 0,
+            shown_at: None,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn hint(&self) -> Option<&'static Hint> {
+        SCRIPT.get(self.step)
+    }
+
+    /// Whether the game should wait for the player to do what the hint says.
+    pub fn holds_game(&self) -> bool {
+        self.hint().map(|h| h.goal.holds_game()).unwrap_or(false)
+    }
+
+    fn next(&mut self) {
+        self.step += 1;
+        self.shown_at = None;
+    }
+
+    /// Go to the next hint on Enter, if the hint asks for it. Returns whether the key was used.
+    pub fn key_down(&mut self, keycode: Keycode) -> bool {
+        match self.hint() {
+            Some(hint) if hint.goal == Goal::Continue && keycode == Keycode::Return => {
+                self.next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Go to the next hint if `view` and `focus` show the goal of this one was reached.
+    pub fn update(&mut self, view: &PlayerView, focus: Option<Position>) {
+        let hint = match self.hint() {
+            Some(hint) => hint,
+            None => return,
+        };
+        let shown_at = *self.shown_at.get_or_insert(view.tick_number);
+        let score = view.scores.get(view.team);
+        let reached = match hint.goal {
+            Goal::Continue | Goal::TakeKing => false,
+            Goal::FocusKing => focus.is_some() && focus == view.king(),
+            Goal::QueueMove => !view.moves.is_empty(),
+            Goal::Land(cells) => score.map(|s| s.land >= cells).unwrap_or(false),
+            Goal::Growth => {
+                view.tick_number / LAND_GROWTH_INTERVAL > shown_at / LAND_GROWTH_INTERVAL
+            }
+            // The King counts as a city too.
+            Goal::TakeCity => score.map(|s| s.cities >= 2).unwrap_or(false),
+        };
+        if reached {
+            self.next();
+        }
+    }
+
+    fn highlighted(&self, view: &PlayerView) -> Option<Position> {
+        match self.hint()?.highlight {
+            Highlight::Nothing => None,
+            Highlight::King => view.king(),
+            Highlight::City => {
+                let king = view.king()?;
+                let (w, h) = view.dimens;
+                (0..h)
+                    .flat_map(|y| (0..w).map(move |x| Position(x, y)))
+                    .filter(|&Position(x, y)| match view.get(x, y) {
+                        Tile::Visible(Cell::Fortress(None, _)) => true,
+                        _ => false,
+                    })
+                    .min_by_key(|&pos| king.distance(pos))
+            }
+        }
+    }
+
+    /// Draw the hint at the bottom of the window, and mark the cell it points at.
+    pub fn draw(&self, ctx: &mut Context, font: &Font, camera: &Camera, view: &PlayerView)
+        -> GameResult<()> {
+        let hint = match self.hint() {
+            Some(hint) => hint,
+            None => return Ok(()),
+        };
+        let elapsed = self.start.elapsed();
+        let t = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 * 1e-9;
+        let pulse = 0.5 + 0.5 * (t * PULSE_RATE * 2.0 * PI).sin();
+        let yellow = Color::new(1.0, 0.9, 0.2, 1.0);
+        if let Some(pos) = self.highlighted(view) {
+            let mut rect = camera.cell_rect(pos.0, pos.1);
+            let grow = camera.cell_size() * 0.3 * pulse;
+            rect.w += grow;
+            rect.h += grow;
+            graphics::set_color(ctx, Color::new(1.0, 0.9, 0.2, 0.5 + 0.5 * pulse))?;
+            graphics::rectangle(ctx, DrawMode::Line, rect)?;
+            marker::draw_arrow_to(ctx, camera, pos, yellow)?;
+        }
+
+        let (w, h) = (ctx.conf.window_width as f32, ctx.conf.window_height as f32);
+        let footer = if hint.goal == Goal::Continue {
+            "Enter: continue   F1: skip the tutorial"
+        } else {
+            "F1: skip the tutorial"
+        };
+        let lines = hint.text.lines().collect::<Vec<_>>();
+        let height = LINE_HEIGHT * (lines.len() + 2) as f32 + 12.0;
+        let top = h - BOTTOM_MARGIN - height;
+        let left = w / 2.0 - WIDTH / 2.0;
+        graphics::set_color(ctx, Color::new(0.0, 0.0, 0.0, 0.8))?;
+        graphics::rectangle(ctx,
+                            DrawMode::Fill,
+                            Rect::new(w / 2.0, top + height / 2.0, WIDTH, height))?;
+        graphics::set_color(ctx, yellow)?;
+        graphics::rectangle(ctx,
+                            DrawMode::Line,
+                            Rect::new(w / 2.0, top + height / 2.0, WIDTH, height))?;
+        let grey = Color::new(0.6, 0.6, 0.6, 1.0);
+        let title = format!("Tutorial {}/{}", self.step + 1, SCRIPT.len());
+        draw_text(ctx, font, &title, left + 12.0, top + 6.0, yellow)?;
+        for (i, line) in lines.iter().enumerate() {
+            let y = top + 6.0 + LINE_HEIGHT * (i + 1) as f32;
+            draw_text(ctx, font, line.trim(), left + 12.0, y, Color::new(1.0, 1.0, 1.0, 1.0))?;
+        }
+        let y = top + 6.0 + LINE_HEIGHT * (lines.len() + 1) as f32;
+        draw_text(ctx, font, footer, left + 12.0, y, grey)
+    }
+}
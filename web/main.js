@@ -0,0 +1,138 @@
+// The browser client. The engine does the game; this draws views on a canvas and turns clicks
+// and keys into moves. Open `index.html?server=ws://host:4567` to play on a server, or without
+// the parameter to play against a bot in the page.
+
+import init, { LocalGame, Connection } from './pkg/generals_web.js';
+
+const CELL = 32;
+const TICK_MS = 500;
+const TEAM_COLORS = ['#ff1919', '#1919ff', '#19aa19', '#9619c8',
+                     '#ff8c00', '#009696', '#8c4b19', '#ff64b4'];
+// Clockwise from up, as the engine takes them.
+const KEYS = { ArrowUp: 0, ArrowRight: 1, ArrowDown: 2, ArrowLeft: 3 };
+const STEPS = [[0, -1], [1, 0], [0, 1], [-1, 0]];
+const ARROWS = { Up: '↑', Right: '→', Down: '↓', Left: '←' };
+
+const canvas = document.getElementById('board');
+const ctx = canvas.getContext('2d');
+const status = document.getElementById('status');
+
+let view = null;
+let focus = null;
+
+function cellStyle(tile) {
+  if (tile === 'Fog') return ['#3c3c3c', null];
+  if (tile === 'Obstacle') return ['#3c3c3c', '▒'];
+  const cell = tile.Visible;
+  if (cell === 'Mountain') return ['#646464', '▲'];
+  if (cell === 'Open') return ['#dcdcdc', null];
+  if (cell.Fortress) {
+    const [team, n] = cell.Fortress;
+    return [team === null ? '#6e6e6e' : TEAM_COLORS[team % 8], String(n)];
+  }
+  const [team, n] = cell.King || cell.Captured;
+  return [TEAM_COLORS[team % 8], cell.King ? '♚' + n : String(n)];
+}
+
+function draw() {
+  if (!view) return;
+  const [w, h] = view.dimens;
+  canvas.width = w * CELL;
+  canvas.height = h * CELL;
+  ctx.font = '12px sans-serif';
+  ctx.textAlign = 'center';
+  ctx.textBaseline = 'middle';
+  view.tiles.forEach((row, y) => row.forEach((tile, x) => {
+    const [color, text] = cellStyle(tile);
+    ctx.fillStyle = color;
+    ctx.fillRect(x * CELL, y * CELL, CELL - 1, CELL - 1);
+    if (text) {
+      ctx.fillStyle = '#000';
+      ctx.fillText(text, x * CELL + CELL / 2, y * CELL + CELL / 2);
+    }
+  }));
+  ctx.fillStyle = 'rgba(0, 0, 0, 0.6)';
+  for (const [[x, y], dir] of view.moves) {
+    ctx.fillText(ARROWS[dir], x * CELL + CELL / 2, y * CELL + 6);
+  }
+  if (focus) {
+    ctx.strokeStyle = '#fff';
+    ctx.lineWidth = 2;
+    ctx.strokeRect(focus[0] * CELL + 1, focus[1] * CELL + 1, CELL - 3, CELL - 3);
+  }
+}
+
+function setupInput(queueMove, clearMoves) {
+  canvas.addEventListener('mousedown', e => {
+    const rect = canvas.getBoundingClientRect();
+    focus = [Math.floor((e.clientX - rect.left) / CELL),
+             Math.floor((e.clientY - rect.top) / CELL)];
+    draw();
+  });
+  document.addEventListener('keydown', e => {
+    if (e.key === 'q' || e.key === 'Q') {
+      clearMoves();
+    } else if (e.key in KEYS && focus && view) {
+      e.preventDefault();
+      const dir = KEYS[e.key];
+      const [dx, dy] = STEPS[dir];
+      const next = [focus[0] + dx, focus[1] + dy];
+      const [w, h] = view.dimens;
+      if (next[0] < 0 || next[1] < 0 || next[0] >= w || next[1] >= h) return;
+      queueMove(focus[0], focus[1], dir);
+      focus = next;
+    } else {
+      return;
+    }
+    draw();
+  });
+}
+
+function playLocal() {
+  const game = new LocalGame(Math.floor(Math.random() * 0xffffffff), 0);
+  const refresh = () => { view = JSON.parse(game.view()); draw(); };
+  setupInput((x, y, dir) => { game.queue_move(x, y, dir); refresh(); },
+             () => { game.clear_moves(); refresh(); });
+  status.textContent = 'Playing against a bot';
+  refresh();
+  const timer = setInterval(() => {
+    game.tick();
+    refresh();
+    if (game.is_over()) {
+      clearInterval(timer);
+      status.textContent = 'Game over';
+    }
+  }, TICK_MS);
+}
+
+function playOnline(url) {
+  const conn = new Connection();
+  const socket = new WebSocket(url);
+  const refresh = () => {
+    const json = conn.view();
+    if (json) view = JSON.parse(json);
+    status.textContent = conn.status();
+    draw();
+  };
+  setupInput((x, y, dir) => {
+    const msg = conn.move_message(x, y, dir);
+    if (msg) socket.send(msg);
+    refresh();
+  }, () => { socket.send(conn.clear_message()); refresh(); });
+  socket.onopen = () => socket.send(conn.hello());
+  socket.onmessage = e => {
+    const reply = conn.receive(e.data);
+    if (reply) socket.send(reply);
+    refresh();
+  };
+  socket.onclose = () => { status.textContent = 'Disconnected'; };
+  refresh();
+}
+
+await init();
+const server = new URLSearchParams(location.search).get('server');
+if (server) {
+  playOnline(server);
+} else {
+  playLocal();
+}
@@ -0,0 +1,227 @@
+//! The engine compiled to WebAssembly, for the browser client in `index.html`. JavaScript draws
+//! the board from views serialized as JSON, and either plays a local game against a bot through
+//! `LocalGame`, or talks to a server over its own WebSocket through `Connection`.
+
+extern crate generals;
+extern crate serde_json;
+extern crate wasm_bindgen;
+
+use wasm_bindgen::prelude::*;
+
+use generals::*;
+use generals::bot::{self, BotPlayer};
+use generals::frontend::{self, Command};
+use generals::net::{ClientMessage, LobbyInfo, ServerMessage, CAP_DELTAS, PROTOCOL_VERSION};
+
+/// Directions are passed from JavaScript as 0 to 3, clockwise from up.
+fn direction(dir: u8) -> Option<Direction> {
+    match dir {
+        0 => Some(Direction::Up),
+        1 => Some(Direction::Right),
+        2 => Some(Direction::Down),
+        3 => Some(Direction::Left),
+        _ => None,
+    }
+}
+
+fn to_json(msg: &ClientMessage) -> String {
+    serde_json::to_string(msg).unwrap()
+}
+
+/// A game against a bot, run in the browser.
+#[wasm_bindgen]
+pub struct LocalGame {
+    game: GameState,
+    bots: Vec<BotPlayer>,
+    team: Team,
+}
+
+#[wasm_bindgen]
+impl LocalGame {
+    /// A new game on a board of side `size`, or a size that fits two players for 0.
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: u32, size: usize) -> LocalGame {
+        let settings = MapSettings {
+            size: if size == 0 { None } else { Some(size) },
+            ..MapSettings::default()
+        };
+        let board = Board::generate(&settings, 2, &mut seeded_rng(seed));
+        let bot = bot::seeded(bot::DEFAULT_BOT, seed).unwrap();
+        LocalGame {
+            game: GameState::new(board, 2),
+            bots: vec![BotPlayer::new(1, bot)],
+            team: 0,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        for bot in self.bots.iter_mut() {
+            bot.act(&mut self.game);
+        }
+        self.game.tick();
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.game.is_over()
+    }
+
+    /// The game as we see it, as JSON.
+    pub fn view(&self) -> String {
+        serde_json::to_string(&self.game.player_view(self.team)).unwrap()
+    }
+
+    /// Returns `false` if the move is off the board.
+    pub fn queue_move(&mut self, x: i32, y: i32, dir: u8) -> bool {
+        let command = match direction(dir) {
+            Some(dir) => Command::Move((Position(x, y), dir)),
+            None => return false,
+        };
+        frontend::apply(&mut self.game, self.team, command).is_ok()
+    }
+
+    pub fn clear_moves(&mut self) {
+        let _ = frontend::apply(&mut self.game, self.team, Command::ClearMoves);
+    }
+}
+
+/// The state of a connection to a server, which JavaScript feeds with the messages from its
+/// WebSocket. It finds a game on its own: it joins the first open lobby, or makes one.
+#[wasm_bindgen]
+pub struct Connection {
+    view: Option<PlayerView>,
+    ready: bool,
+    status: String,
+}
+
+#[wasm_bindgen]
+impl Connection {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Connection {
+        Connection {
+            view: None,
+            ready: false,
+            status: "Connecting".to_string(),
+        }
+    }
+
+    /// The first message to send.
+    pub fn hello(&self) -> String {
+        to_json(&ClientMessage::Hello {
+            version: PROTOCOL_VERSION,
+            capabilities: vec![CAP_DELTAS.to_string()],
+        })
+    }
+
+    /// Handle a message from the server, and return the answer to send, if any.
+    pub fn receive(&mut self, text: &str) -> Option<String> {
+        let msg = match serde_json::from_str(text) {
+            Ok(msg) => msg,
+            Err(e) => {
+                self.status = format!("Bad message from the server: {}", e);
+                return None;
+            }
+        };
+        let reply = match msg {
+            ServerMessage::Hello { .. } => {
+                self.status = "Looking for a game".to_string();
+                ClientMessage::ListLobbies
+            }
+            ServerMessage::Incompatible { reason, .. } => {
+                self.status = reason;
+                return None;
+            }
+            ServerMessage::Lobbies(lobbies) => {
+                let open = lobbies.iter()
+                    .find(|l| !l.started && l.members.len() < l.num_players);
+                match open {
+                    Some(lobby) => ClientMessage::JoinLobby(lobby.id),
+                    None => {
+                        ClientMessage::CreateLobby {
+                            name: "Browser game".to_string(),
+                            num_players: 2,
+                            map: MapSettings::default(),
+                            ranked: false,
+                            mode: GameMode::Classic,
+                        }
+                    }
+                }
+            }
+            ServerMessage::Lobby(lobby) => {
+                self.status = waiting(&lobby);
+                if self.ready {
+                    return None;
+                }
+                self.ready = true;
+                ClientMessage::Ready(true)
+            }
+            ServerMessage::Welcome { .. } => {
+                self.status = "Playing".to_string();
+                return None;
+            }
+            ServerMessage::State(view) => {
+                self.view = Some(view);
+                return None;
+            }
+            ServerMessage::Delta(delta) => {
+                let applied = self.view.as_mut().map(|v| v.apply(&delta)).unwrap_or(true);
+                if applied {
+                    return None;
+                }
+                ClientMessage::Resync
+            }
+            ServerMessage::GameOver { winner } => {
+                let us = self.view.as_ref().map(|v| v.team);
+                self.status = if winner.is_some() && winner == us {
+                    "You won".to_string()
+                } else {
+                    "You lost".to_string()
+                };
+                return None;
+            }
+            ServerMessage::Error(e) => {
+                self.status = e;
+                return None;
+            }
+            _ => return None,
+        };
+        Some(to_json(&reply))
+    }
+
+    /// The game as we see it, as JSON, once it has started.
+    pub fn view(&self) -> Option<String> {
+        self.view.as_ref().map(|v| serde_json::to_string(v).unwrap())
+    }
+
+    pub fn status(&self) -> String {
+        self.status.clone()
+    }
+
+    /// The message that queues a move, which also shows up in our view right away.
+    pub fn move_message(&mut self, x: i32, y: i32, dir: u8) -> Option<String> {
+        let mv = (Position(x, y), direction(dir)?);
+        if let Some(ref mut view) = self.view {
+            view.moves.push_back(mv);
+        }
+        Some(to_json(&ClientMessage::Move(mv)))
+    }
+
+    pub fn clear_message(&mut self) -> String {
+        if let Some(ref mut view) = self.view {
+            view.moves.clear();
+        }
+        to_json(&ClientMessage::ClearMoves)
+    }
+}
+
+impl Default for Connection {
+    fn default() -> Self {
+        Connection::new()
+    }
+}
+
+fn waiting(lobby: &LobbyInfo) -> String {
+    format!("Waiting for players in '{}' ({}/{})",
+            lobby.name,
+            lobby.members.len(),
+            lobby.num_players)
+}